@@ -52,6 +52,7 @@ fn code() -> Result<(), Box<Error>> {
 
     let future = client
         .create_database("something")
+        .execute()
         .and_then(|db| {
             println!("created database = {:?}", db);
             client.delete_database("something")
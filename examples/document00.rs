@@ -69,7 +69,7 @@ fn code() -> Result<(), Box<Error>> {
     // is not found we create it.
     let database = match core.run(future)? {
         Some(db) => db,
-        None => core.run(client.create_database(DATABASE))?,
+        None => core.run(client.create_database(DATABASE).execute())?,
     };
     println!("database == {:?}", database);
 
@@ -105,7 +105,7 @@ fn code() -> Result<(), Box<Error>> {
             // Performance levels have price impact. Also, higher
             // performance levels force you to specify an indexing
             // strategy. Consult the documentation for more details.
-            core.run(client.create_collection(&database.id, 400, &coll))?
+            core.run(client.create_collection(&database.id, &coll).throughput(400).execute())?
         }
     };
 
@@ -0,0 +1,357 @@
+use azure::core::errors::AzureError;
+use futures::Future;
+use hyper::client::connect::{Connect, Connected, Destination};
+use hyper::{self, client::HttpConnector};
+use hyper_tls::{HttpsConnector, MaybeHttpsStream};
+use native_tls;
+use std::io;
+use std::time::Duration;
+
+/// The hyper client shared by the requests of a client. It used to be
+/// `hyper::Client<HttpsConnector<HttpConnector>>`: code naming that type
+/// has to switch to this alias.
+pub type HyperClient = hyper::Client<AzureConnector>;
+
+const DEFAULT_DNS_THREADS: usize = 4;
+
+/// The Azure cloud hosting the accounts. Each cloud has its own host names.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AzureCloud {
+    Public,
+    China,
+    USGovernment,
+    Germany,
+}
+
+impl AzureCloud {
+    /// Suffix of the storage hosts, as in `{account}.blob.{suffix}`.
+    pub fn storage_suffix(self) -> &'static str {
+        match self {
+            AzureCloud::Public => "core.windows.net",
+            AzureCloud::China => "core.chinacloudapi.cn",
+            AzureCloud::USGovernment => "core.usgovcloudapi.net",
+            AzureCloud::Germany => "core.cloudapi.de",
+        }
+    }
+
+    /// Suffix of the Cosmos hosts, as in `{account}.{suffix}`.
+    pub fn cosmos_suffix(self) -> &'static str {
+        match self {
+            AzureCloud::Public => "documents.azure.com",
+            AzureCloud::China => "documents.azure.cn",
+            AzureCloud::USGovernment => "documents.azure.us",
+            AzureCloud::Germany => "documents.microsoftazure.de",
+        }
+    }
+
+    /// Suffix of the Service Bus hosts, as in `{namespace}.{suffix}`.
+    pub fn service_bus_suffix(self) -> &'static str {
+        match self {
+            AzureCloud::Public => "servicebus.windows.net",
+            AzureCloud::China => "servicebus.chinacloudapi.cn",
+            AzureCloud::USGovernment => "servicebus.usgovcloudapi.net",
+            AzureCloud::Germany => "servicebus.cloudapi.de",
+        }
+    }
+}
+
+/// Tunes the hyper connection pool shared by the requests of a client.
+/// Unspecified options keep the hyper defaults.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    dns_threads: usize,
+    max_idle_per_host: Option<usize>,
+    keep_alive: Option<bool>,
+    keep_alive_timeout: Option<Option<Duration>>,
+    http2_only: Option<bool>,
+    prefer_http2: bool,
+    cloud: AzureCloud,
+    endpoint_suffix: Option<String>,
+    #[cfg(feature = "insecure-tls")]
+    accept_invalid_certs: bool,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> ClientBuilder {
+        ClientBuilder {
+            dns_threads: DEFAULT_DNS_THREADS,
+            max_idle_per_host: None,
+            keep_alive: None,
+            keep_alive_timeout: None,
+            http2_only: None,
+            prefer_http2: false,
+            cloud: AzureCloud::Public,
+            endpoint_suffix: None,
+            #[cfg(feature = "insecure-tls")]
+            accept_invalid_certs: false,
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn new() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    pub fn with_dns_threads(mut self, dns_threads: usize) -> ClientBuilder {
+        self.dns_threads = dns_threads;
+        self
+    }
+
+    pub fn with_max_idle_per_host(mut self, max_idle_per_host: usize) -> ClientBuilder {
+        self.max_idle_per_host = Some(max_idle_per_host);
+        self
+    }
+
+    pub fn with_keep_alive(mut self, keep_alive: bool) -> ClientBuilder {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// `None` keeps idle connections open indefinitely.
+    pub fn with_keep_alive_timeout(mut self, keep_alive_timeout: Option<Duration>) -> ClientBuilder {
+        self.keep_alive_timeout = Some(keep_alive_timeout);
+        self
+    }
+
+    pub fn with_http2_only(mut self, http2_only: bool) -> ClientBuilder {
+        self.http2_only = Some(http2_only);
+        self
+    }
+
+    /// Offers HTTP/2 during the TLS handshake, falling back to HTTP/1.1
+    /// with the servers not supporting it. Over HTTP/2 the concurrent
+    /// requests to a host share a single connection.
+    pub fn with_prefer_http2(mut self, prefer_http2: bool) -> ClientBuilder {
+        self.prefer_http2 = prefer_http2;
+        self
+    }
+
+    /// Sends the requests to the hosts of `cloud` instead of the public
+    /// Azure ones.
+    pub fn with_cloud(mut self, cloud: AzureCloud) -> ClientBuilder {
+        self.cloud = cloud;
+        self
+    }
+
+    /// Overrides the storage host suffix of the cloud, for example
+    /// `core.chinacloudapi.cn` or the suffix of an Azure Stack deployment.
+    pub fn with_endpoint_suffix(mut self, endpoint_suffix: &str) -> ClientBuilder {
+        self.endpoint_suffix = Some(endpoint_suffix.trim_matches('.').to_owned());
+        self
+    }
+
+    /// Accepts any certificate from the servers, be it self-signed,
+    /// expired or issued for another host, for example to reach the
+    /// storage emulator over https.
+    ///
+    /// **Danger**: this removes the protection of TLS against anyone
+    /// between the client and the service, who can then read and alter
+    /// the requests, credentials included. Never enable it outside of tests.
+    #[cfg(feature = "insecure-tls")]
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> ClientBuilder {
+        self.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    pub fn dns_threads(&self) -> usize {
+        self.dns_threads
+    }
+
+    pub fn max_idle_per_host(&self) -> Option<usize> {
+        self.max_idle_per_host
+    }
+
+    pub fn keep_alive(&self) -> Option<bool> {
+        self.keep_alive
+    }
+
+    pub fn keep_alive_timeout(&self) -> Option<Option<Duration>> {
+        self.keep_alive_timeout
+    }
+
+    pub fn http2_only(&self) -> Option<bool> {
+        self.http2_only
+    }
+
+    pub fn prefer_http2(&self) -> bool {
+        self.prefer_http2
+    }
+
+    pub fn cloud(&self) -> AzureCloud {
+        self.cloud
+    }
+
+    #[cfg(feature = "insecure-tls")]
+    pub fn accept_invalid_certs(&self) -> bool {
+        self.accept_invalid_certs
+    }
+
+    /// The storage host suffix: the one set with `with_endpoint_suffix` or
+    /// else the one of the cloud.
+    pub fn endpoint_suffix(&self) -> &str {
+        match self.endpoint_suffix {
+            Some(ref endpoint_suffix) => endpoint_suffix,
+            None => self.cloud.storage_suffix(),
+        }
+    }
+
+    pub(crate) fn build_hyper_client(&self) -> Result<HyperClient, AzureError> {
+        let mut builder = hyper::Client::builder();
+
+        if let Some(max_idle_per_host) = self.max_idle_per_host {
+            builder.max_idle_per_host(max_idle_per_host);
+        }
+        if let Some(keep_alive) = self.keep_alive {
+            builder.keep_alive(keep_alive);
+        }
+        if let Some(keep_alive_timeout) = self.keep_alive_timeout {
+            builder.keep_alive_timeout(keep_alive_timeout);
+        }
+        if let Some(http2_only) = self.http2_only {
+            builder.http2_only(http2_only);
+        }
+
+        trace!("build_hyper_client(builder == {:?})", self);
+        Ok(builder.build(self.build_connector()?))
+    }
+
+    fn build_connector(&self) -> Result<AzureConnector, AzureError> {
+        let mut http = HttpConnector::new(self.dns_threads);
+        http.enforce_http(false);
+
+        let mut tls = native_tls::TlsConnector::builder();
+        if self.prefer_http2 {
+            tls.request_alpns(&["h2", "http/1.1"]);
+        }
+        #[cfg(feature = "insecure-tls")]
+        {
+            if self.accept_invalid_certs {
+                warn!("the certificates of the servers will not be verified");
+                tls.danger_accept_invalid_certs(true);
+            }
+        }
+
+        Ok(AzureConnector(HttpsConnector::from((http, tls.build()?))))
+    }
+}
+
+type Transport = MaybeHttpsStream<<HttpConnector as Connect>::Transport>;
+
+/// The https connector of the clients. It tells hyper when the server
+/// chose HTTP/2 during the TLS handshake, which `HttpsConnector` alone
+/// does not: hyper would otherwise speak HTTP/1.1 to it.
+#[derive(Clone)]
+pub struct AzureConnector(HttpsConnector<HttpConnector>);
+
+impl Connect for AzureConnector {
+    type Transport = Transport;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (Transport, Connected), Error = io::Error> + Send>;
+
+    fn connect(&self, dst: Destination) -> Self::Future {
+        Box::new(self.0.connect(dst).map(|(stream, connected)| {
+            if negotiated_h2(&stream) {
+                (stream, connected.negotiated_h2())
+            } else {
+                (stream, connected)
+            }
+        }))
+    }
+}
+
+fn negotiated_h2(stream: &Transport) -> bool {
+    match *stream {
+        MaybeHttpsStream::Https(ref tls) => match tls.get_ref().negotiated_alpn() {
+            Ok(Some(ref protocol)) => protocol == b"h2",
+            _ => false,
+        },
+        MaybeHttpsStream::Http(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_builder() {
+        let builder = ClientBuilder::new();
+
+        assert_eq!(builder.dns_threads(), DEFAULT_DNS_THREADS);
+        assert_eq!(builder.max_idle_per_host(), None);
+        assert_eq!(builder.keep_alive(), None);
+        assert_eq!(builder.http2_only(), None);
+        builder.build_hyper_client().unwrap();
+    }
+
+    #[cfg(feature = "insecure-tls")]
+    #[test]
+    fn insecure_builder() {
+        let builder = ClientBuilder::new();
+        assert!(!builder.accept_invalid_certs());
+
+        let builder = builder.danger_accept_invalid_certs(true);
+        assert!(builder.accept_invalid_certs());
+        builder.build_hyper_client().unwrap();
+    }
+
+    #[test]
+    fn configured_builder() {
+        let builder = ClientBuilder::new()
+            .with_max_idle_per_host(32)
+            .with_keep_alive(true)
+            .with_keep_alive_timeout(Some(Duration::from_secs(30)))
+            .with_http2_only(false);
+
+        assert_eq!(builder.max_idle_per_host(), Some(32));
+        assert_eq!(builder.keep_alive(), Some(true));
+        assert_eq!(builder.keep_alive_timeout(), Some(Some(Duration::from_secs(30))));
+        assert_eq!(builder.http2_only(), Some(false));
+        builder.build_hyper_client().unwrap();
+    }
+
+    // Without TLS there is no protocol negotiation: the servers keep being
+    // spoken to in HTTP/1.1. The ALPN negotiation itself needs a TLS server
+    // offering h2 and is not covered here.
+    #[test]
+    fn prefer_http2() {
+        use azure::core::test_server::serve_raw;
+
+        assert!(!ClientBuilder::new().prefer_http2());
+        let builder = ClientBuilder::new().with_prefer_http2(true);
+        assert!(builder.prefer_http2());
+        let client = builder.build_hyper_client().unwrap();
+
+        let uri = serve_raw("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_owned());
+
+        let mut core = ::tokio_core::reactor::Core::new().unwrap();
+        let response = core.run(client.get(uri.parse().unwrap())).unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::OK);
+        assert_eq!(response.version(), hyper::Version::HTTP_11);
+    }
+
+    #[test]
+    fn cloud_hosts() {
+        let builder = ClientBuilder::new();
+        assert_eq!(builder.cloud(), AzureCloud::Public);
+        assert_eq!(builder.endpoint_suffix(), "core.windows.net");
+
+        let builder = builder.with_cloud(AzureCloud::China);
+        assert_eq!(builder.endpoint_suffix(), "core.chinacloudapi.cn");
+        assert_eq!(builder.cloud().cosmos_suffix(), "documents.azure.cn");
+        assert_eq!(builder.cloud().service_bus_suffix(), "servicebus.chinacloudapi.cn");
+
+        let builder = builder.with_cloud(AzureCloud::USGovernment);
+        assert_eq!(builder.endpoint_suffix(), "core.usgovcloudapi.net");
+        assert_eq!(builder.cloud().cosmos_suffix(), "documents.azure.us");
+
+        let builder = builder.with_cloud(AzureCloud::Germany);
+        assert_eq!(builder.endpoint_suffix(), "core.cloudapi.de");
+        assert_eq!(builder.cloud().cosmos_suffix(), "documents.microsoftazure.de");
+
+        let builder = builder.with_endpoint_suffix(".local.azurestack.external");
+        assert_eq!(builder.endpoint_suffix(), "local.azurestack.external");
+        assert_eq!(builder.cloud(), AzureCloud::Germany);
+    }
+}
@@ -0,0 +1,92 @@
+use azure::core::date_from_headers;
+use chrono::{DateTime, Duration, Utc};
+use http::{HeaderMap, StatusCode};
+
+/// Largest difference between the local and the server clocks the services
+/// accept before rejecting the signed requests as expired.
+pub const MAX_CLOCK_SKEW_MINUTES: i64 = 15;
+
+/// The time of the server, from the `Date` header of its response.
+pub fn server_time_from_headers(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    date_from_headers(headers).ok()
+}
+
+/// How far ahead of `server_time` the local clock is, negative if it is
+/// behind.
+pub fn clock_skew(server_time: DateTime<Utc>) -> Duration {
+    Utc::now().signed_duration_since(server_time)
+}
+
+/// Logs a warning and returns `true` if the local clock is more than
+/// `MAX_CLOCK_SKEW_MINUTES` away from `server_time`: the authorization of
+/// the following requests would then fail.
+pub fn check_clock_skew(server_time: DateTime<Utc>) -> bool {
+    is_skewed(clock_skew(server_time))
+}
+
+// Called on the responses rejecting the authorization of a request, the
+// ones a skewed local clock explains, to warn about it.
+pub(crate) fn check_clock_skew_of_rejection(status: StatusCode, headers: &HeaderMap) -> bool {
+    if status != StatusCode::UNAUTHORIZED && status != StatusCode::FORBIDDEN {
+        return false;
+    }
+    match server_time_from_headers(headers) {
+        Some(server_time) => check_clock_skew(server_time),
+        None => false,
+    }
+}
+
+fn is_skewed(skew: Duration) -> bool {
+    if skew.num_seconds().abs() <= MAX_CLOCK_SKEW_MINUTES * 60 {
+        return false;
+    }
+
+    warn!(
+        "the local clock is {} seconds {} the one of the server, requests will fail to authenticate",
+        skew.num_seconds().abs(),
+        if skew > Duration::zero() { "ahead of" } else { "behind" }
+    );
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::header::{HeaderValue, DATE};
+
+    #[test]
+    fn parse_server_time() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(server_time_from_headers(&headers), None);
+
+        headers.insert(DATE, HeaderValue::from_static("Wed, 09 Mar 2011 01:42:34 GMT"));
+        let server_time = server_time_from_headers(&headers).unwrap();
+        assert_eq!(server_time.to_rfc3339(), "2011-03-09T01:42:34+00:00");
+
+        headers.insert(DATE, HeaderValue::from_static("yesterday"));
+        assert_eq!(server_time_from_headers(&headers), None);
+    }
+
+    #[test]
+    fn skew_warning() {
+        assert!(!check_clock_skew(Utc::now()));
+        assert!(!check_clock_skew(Utc::now() - Duration::minutes(14)));
+        assert!(!check_clock_skew(Utc::now() + Duration::minutes(14)));
+        assert!(check_clock_skew(Utc::now() - Duration::minutes(16)));
+        assert!(check_clock_skew(Utc::now() + Duration::minutes(16)));
+
+        assert!(!is_skewed(Duration::minutes(MAX_CLOCK_SKEW_MINUTES)));
+        assert!(is_skewed(Duration::minutes(-MAX_CLOCK_SKEW_MINUTES) - Duration::seconds(1)));
+    }
+
+    #[test]
+    fn skew_checked_on_rejections_only() {
+        let mut headers = HeaderMap::new();
+        headers.insert(DATE, HeaderValue::from_static("Wed, 09 Mar 2011 01:42:34 GMT"));
+
+        assert!(check_clock_skew_of_rejection(StatusCode::FORBIDDEN, &headers));
+        assert!(check_clock_skew_of_rejection(StatusCode::UNAUTHORIZED, &headers));
+        assert!(!check_clock_skew_of_rejection(StatusCode::NOT_FOUND, &headers));
+        assert!(!check_clock_skew_of_rejection(StatusCode::FORBIDDEN, &HeaderMap::new()));
+    }
+}
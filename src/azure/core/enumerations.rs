@@ -1,8 +1,21 @@
 #[derive(Debug)]
 pub enum ParsingError {
     ElementNotFound(String),
+    /// A Cosmos `_rid` that does not decode to a resource id.
+    MalformedResourceId(String),
 }
 
+impl ::std::fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            ParsingError::ElementNotFound(ref s) => write!(f, "element not found: {}", s),
+            ParsingError::MalformedResourceId(ref s) => write!(f, "malformed resource id: {}", s),
+        }
+    }
+}
+
+impl ::std::error::Error for ParsingError {}
+
 /// use as
 /// ```create_enum!(SecondCollection, (Pollo, "Pollo"), (Bianco, "Bianco"), (Giallo, "Giallo"));```
 macro_rules! create_enum {
@@ -55,6 +68,16 @@ macro_rules! create_enum {
                 }
             }
         }
+
+        impl ::azure::core::util::HeaderValueOf for $en {
+            fn header_value(&self) -> &'static str {
+                match *self {
+                    $(
+                        $en::$na => $x,
+                    )*
+                }
+            }
+        }
     )
 }
 
@@ -1,4 +1,4 @@
-use azure::core::{enumerations::ParsingError, range::ParseError};
+use azure::core::{clock_skew::check_clock_skew_of_rejection, enumerations::ParsingError, range::ParseError};
 use base64;
 use chrono;
 use futures::{Future, Stream};
@@ -9,12 +9,13 @@ use hyper_tls;
 use serde_json;
 use serde_xml_rs;
 use std;
-use std::io::Error as IOError;
+use std::io::{self, Error as IOError};
 use std::num;
 use std::num::ParseIntError;
 use std::str;
 use std::str::ParseBoolError;
 use std::string;
+use std::time::Duration;
 use url::ParseError as URLParseError;
 use uuid;
 use xml::BuilderError as XMLError;
@@ -41,21 +42,69 @@ quick_error! {
 pub struct UnexpectedHTTPResult {
     expected: StatusCode,
     received: StatusCode,
+    sub_status: Option<u32>,
     body: String,
 }
 
+// Cosmos substatus codes telling the request can succeed if sent again: the
+// write region moved, the session is not yet readable in this region, or
+// the partition moved or split.
+const RETRYABLE_SUB_STATUSES: [(StatusCode, u32); 6] = [
+    (StatusCode::FORBIDDEN, 3),
+    (StatusCode::NOT_FOUND, 1002),
+    (StatusCode::GONE, 1000),
+    (StatusCode::GONE, 1002),
+    (StatusCode::GONE, 1007),
+    (StatusCode::GONE, 1008),
+];
+
 impl UnexpectedHTTPResult {
     pub fn new(expected: StatusCode, received: StatusCode, body: &str) -> UnexpectedHTTPResult {
         UnexpectedHTTPResult {
             expected,
             received,
+            sub_status: None,
             body: body.to_owned(),
         }
     }
 
+    pub(crate) fn with_sub_status(mut self, sub_status: Option<u32>) -> UnexpectedHTTPResult {
+        self.sub_status = sub_status;
+        self
+    }
+
     pub fn status_code(&self) -> StatusCode {
         self.received
     }
+
+    /// The status the request expected, `status_code` being the one received.
+    pub fn expected_status_code(&self) -> StatusCode {
+        self.expected
+    }
+
+    /// The body of the response, usually the error the service described.
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// The `x-ms-substatus` Cosmos returned along with the status, if any.
+    pub fn sub_status(&self) -> Option<u32> {
+        self.sub_status
+    }
+
+    /// Whether the same request can succeed if sent again later: the
+    /// service was throttling, busy or failing over. A bad request, a
+    /// rejected authorization, a missing resource or a conflict are not
+    /// retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self.received.as_u16() {
+            408 | 429 | 449 | 500 | 502 | 503 | 504 => true,
+            _ => match self.sub_status {
+                Some(sub_status) => RETRYABLE_SUB_STATUSES.contains(&(self.received, sub_status)),
+                None => false,
+            },
+        }
+    }
 }
 
 impl std::fmt::Display for UnexpectedHTTPResult {
@@ -69,11 +118,7 @@ impl std::fmt::Display for UnexpectedHTTPResult {
 }
 
 impl std::error::Error for UnexpectedHTTPResult {
-    fn description(&self) -> &str {
-        "Unexpected HTTP result"
-    }
-
-    fn cause(&self) -> Option<&std::error::Error> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         None
     }
 }
@@ -99,12 +144,12 @@ quick_error! {
         ParseIntError(p :ParseIntError) {
             from()
             display("parse int error: {}", p)
-            cause(p)
+            source(p)
         }
         Not512ByteAlignedError(nb: Not512ByteAlignedError)  {
             from()
             display("not 512 byte aligned error: {}", nb)
-            cause(nb)
+            source(nb)
         }
     }
 }
@@ -121,7 +166,7 @@ quick_error! {
         Base64DecodeError(err: base64::DecodeError) {
             from()
             display("base64 decode error: {}", err)
-            cause(err)
+            source(err)
         }
         DigestNot16BytesLong(len : u64) {
             display("digest length {} bytes instead of 16", len)
@@ -129,32 +174,32 @@ quick_error! {
         ParseBoolError(err: ParseBoolError) {
             from()
             display("parse bool error: {}", err)
-            cause(err)
+            source(err)
         }
         ToStrError(err: ToStrError) {
             from()
             display("to str error: {}", err)
-            cause(err)
+            source(err)
         }
         JSONError(err: serde_json::Error) {
             from()
             display("json error: {}", err)
-            cause(err)
+            source(err)
         }
         HyperError(err: hyper::error::Error){
             from()
             display("Hyper error: {}", err)
-            cause(err)
+            source(err)
         }
         IOError(err: IOError){
             from()
             display("IO error: {}", err)
-            cause(err)
+            source(err)
         }
         XMLError(err: XMLError){
             from()
             display("XML error: {}", err)
-            cause(err)
+            source(err)
         }
         UnexpectedXMLError(err: String) {
             display("UnexpectedXMLError: {}", err)
@@ -162,11 +207,12 @@ quick_error! {
         AzurePathParseError(err: AzurePathParseError){
             from()
             display("Azure Path parse error: {}", err)
-            cause(err)
+            source(err)
         }
         UnexpectedHTTPResult(err: UnexpectedHTTPResult){
             from()
             display("UnexpectedHTTPResult error")
+            source(err)
         }
         HeaderNotFound(msg: String) {
             display("Header not found: {}", msg)
@@ -174,16 +220,17 @@ quick_error! {
         ResponseParsingError(err: TraversingError){
             from()
             display("Traversing error: {}", err)
-            cause(err)
+            source(err)
         }
         ParseIntError(err: num::ParseIntError){
             from()
             display("Parse int error: {}", err)
-            cause(err)
+            source(err)
         }
         ParseError(err: ParseError){
             from()
-            display("Parse error")
+            display("Parse error: {}", err)
+            source(err)
         }
         GenericError
         GenericErrorWithText(err: String) {
@@ -191,7 +238,8 @@ quick_error! {
         }
         ParsingError(err: ParsingError){
             from()
-            display("Parsing error")
+            display("Parsing error: {}", err)
+            source(err)
         }
         InputParametersError(msg: String) {
             display("Input parameters error: {}", msg)
@@ -199,51 +247,76 @@ quick_error! {
         URLParseError(err: URLParseError){
             from()
             display("URL parse error: {}", err)
-            cause(err)
+            source(err)
         }
         HttpPrepareError(err: http::Error) {
             from()
             display("Error preparing HTTP request: {}", err) // todo: revisit usages / message here
-            cause(err)
+            source(err)
         }
         ParseUuidError(err: uuid::ParseError){
             from()
             display("Parse uuid error: {}", err)
-            cause(err)
+            source(err)
         }
         // URIParseError(err: hyper::error::UriError) {
         //     from()
         //     display("URI parse error: {}", err)
-        //     cause(err)
+        //     source(err)
         // }
         ChronoParserError(err: chrono::ParseError) {
             from()
             display("Chrono parser error: {}", err)
-            cause(err)
+            source(err)
         }
         UTF8Error(err: str::Utf8Error) {
             from()
             display("UTF8 conversion error: {}", err)
-            cause(err)
+            source(err)
         }
         FromUtf8Error(err: string::FromUtf8Error) {
             from()
             display("FromUTF8 error: {}", err)
-            cause(err)
+            source(err)
         }
         TLSError(err: hyper_tls::Error) {
             from()
             display("Native TLS error: {}", err)
-            cause(err)
+            source(err)
         }
         SerdeXMLDeserializationError(err:serde_xml_rs::Error) {
             from()
             display("XML deserialization error: {}", err)
-            cause(err)
+            source(err)
         }
         MissingHeaderError(header: String) {
             display("A required header is missing: {}", header)
         }
+        PreconditionFailed(body: String) {
+            display("Precondition failed: {}", body)
+        }
+        NotModified {
+            display("Resource not modified")
+        }
+        ThrottledError { retry_after: Duration } {
+            display("Request throttled, retry after {:?}", retry_after)
+        }
+        BudgetExceeded { charge: f64, limit: f64 } {
+            display("Request charge of {} RU over the {} RU limit", charge, limit)
+        }
+        ThroughputTooLowError { minimum: u64 } {
+            display("Throughput below the minimum of {} RU/s", minimum)
+        }
+        RequestTooLarge { limit_bytes: u64 } {
+            display("Request body larger than the {} bytes limit", limit_bytes)
+        }
+        Unauthorized(body: String) {
+            display("The credentials were rejected: {}", body)
+        }
+        Operation { operation: &'static str, source: Box<dyn std::error::Error + Send + Sync> } {
+            display("{} failed: {}", operation, source)
+            source(&**source)
+        }
     }
 }
 
@@ -268,34 +341,128 @@ quick_error! {
         DateTimeParseError(err: chrono::format::ParseError){
             from()
             display("DateTime parse error: {}", err)
-            cause(err)
+            source(err)
         }
         TextNotFound
         ParseIntError(err: num::ParseIntError){
             from()
             display("Parse int error: {}", err)
-            cause(err)
+            source(err)
         }
         GenericParseError(msg: String) {
             display("Generic parse error: {}", msg)
         }
         ParsingError(err: ParsingError){
             from()
-            display("Parsing error: {:?}", err)
+            display("Parsing error: {}", err)
+            source(err)
         }
    }
 }
 
-impl From<()> for AzureError {
-    fn from(_: ()) -> AzureError {
-        AzureError::GenericError
+impl AzureError {
+    /// The error of `operation`, keeping the error that caused it rather
+    /// than turning it into text.
+    pub fn operation<E>(operation: &'static str, source: E) -> AzureError
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        AzureError::Operation {
+            operation,
+            source: source.into(),
+        }
+    }
+
+    /// Whether the request failed to reach the service or lost its
+    /// connection midway, as opposed to being answered with an error.
+    /// These failures are usually worth retrying.
+    pub fn is_connection_error(&self) -> bool {
+        match *self {
+            AzureError::HyperError(ref err) => {
+                err.is_connect() || err.is_closed() || err.is_incomplete_message() || is_connection_kind(io_error_kind(err))
+            }
+            AzureError::IOError(ref err) => is_connection_kind(Some(err.kind())),
+            AzureError::TLSError(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the service answered 409 Conflict: a resource with the same
+    /// id exists already or, in a Cosmos collection with a unique key
+    /// policy, a document with the same values on the paths of a unique key.
+    pub fn is_conflict(&self) -> bool {
+        match *self {
+            AzureError::UnexpectedHTTPResult(ref result) => result.status_code() == StatusCode::CONFLICT,
+            _ => false,
+        }
+    }
+
+    /// Whether sending the request again can succeed, that is the request
+    /// was throttled, the connection failed or timed out, or the service
+    /// answered with a retryable status (see
+    /// `UnexpectedHTTPResult::is_retryable`). Every other error is terminal.
+    pub fn is_retryable(&self) -> bool {
+        match *self {
+            AzureError::ThrottledError { .. } => true,
+            AzureError::UnexpectedHTTPResult(ref result) => result.is_retryable(),
+            _ => self.is_connection_error() || self.is_timeout(),
+        }
+    }
+
+    /// Whether an operation on the connection timed out.
+    pub fn is_timeout(&self) -> bool {
+        match *self {
+            AzureError::HyperError(ref err) => io_error_kind(err) == Some(io::ErrorKind::TimedOut),
+            AzureError::IOError(ref err) => err.kind() == io::ErrorKind::TimedOut,
+            _ => false,
+        }
+    }
+}
+
+const CONNECTION_ERROR_KINDS: [io::ErrorKind; 6] = [
+    io::ErrorKind::ConnectionRefused,
+    io::ErrorKind::ConnectionReset,
+    io::ErrorKind::ConnectionAborted,
+    io::ErrorKind::NotConnected,
+    io::ErrorKind::BrokenPipe,
+    io::ErrorKind::UnexpectedEof,
+];
+
+fn is_connection_kind(kind: Option<io::ErrorKind>) -> bool {
+    kind.map(|kind| CONNECTION_ERROR_KINDS.contains(&kind)) == Some(true)
+}
+
+// hyper wraps the io errors, possibly more than once.
+fn io_error_kind(err: &hyper::Error) -> Option<io::ErrorKind> {
+    let mut cause = std::error::Error::source(err);
+    while let Some(err) = cause {
+        if let Some(err) = err.downcast_ref::<IOError>() {
+            return Some(err.kind());
+        }
+        cause = err.source();
+    }
+    None
+}
+
+// A conditional request that does not apply is answered with 304 (reads)
+// or 412 (writes): surface them as their own errors.
+pub(crate) fn conditional_error(error: AzureError) -> AzureError {
+    match error {
+        AzureError::UnexpectedHTTPResult(ref result) if result.received == StatusCode::NOT_MODIFIED => AzureError::NotModified,
+        AzureError::UnexpectedHTTPResult(ref result) if result.received == StatusCode::PRECONDITION_FAILED => {
+            AzureError::PreconditionFailed(result.body.clone())
+        }
+        error => error,
     }
 }
 
 #[inline]
-pub(crate) fn extract_status_headers_and_body(
-    resp: hyper::client::ResponseFuture,
-) -> impl Future<Item = (hyper::StatusCode, hyper::HeaderMap, hyper::Chunk), Error = AzureError> {
+pub(crate) fn extract_status_headers_and_body<F>(
+    resp: F,
+) -> impl Future<Item = (hyper::StatusCode, hyper::HeaderMap, hyper::Chunk), Error = AzureError>
+where
+    F: Future<Item = hyper::Response<hyper::Body>, Error = hyper::Error>,
+{
     resp.from_err().and_then(|res| {
         let (head, body) = res.into_parts();
         let status = head.status;
@@ -305,28 +472,35 @@ pub(crate) fn extract_status_headers_and_body(
 }
 
 #[inline]
-pub(crate) fn check_status_extract_headers_and_body(
-    resp: hyper::client::ResponseFuture,
+pub(crate) fn check_status_extract_headers_and_body<F>(
+    resp: F,
     expected_status_code: hyper::StatusCode,
-) -> impl Future<Item = (hyper::HeaderMap, hyper::Chunk), Error = AzureError> {
+) -> impl Future<Item = (hyper::HeaderMap, hyper::Chunk), Error = AzureError>
+where
+    F: Future<Item = hyper::Response<hyper::Body>, Error = hyper::Error>,
+{
     extract_status_headers_and_body(resp).and_then(move |(status, headers, body)| {
         if status == expected_status_code {
             Ok((headers, body))
         } else {
-            Err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult {
-                expected: expected_status_code,
-                received: status,
-                body: str::from_utf8(&body)?.to_owned(),
-            }))
+            check_clock_skew_of_rejection(status, &headers);
+            Err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(
+                expected_status_code,
+                status,
+                str::from_utf8(&body)?,
+            )))
         }
     })
 }
 
 #[inline]
-pub(crate) fn check_status_extract_headers_and_body_as_string(
-    resp: hyper::client::ResponseFuture,
+pub(crate) fn check_status_extract_headers_and_body_as_string<F>(
+    resp: F,
     expected_status_code: hyper::StatusCode,
-) -> impl Future<Item = (hyper::HeaderMap, String), Error = AzureError> {
+) -> impl Future<Item = (hyper::HeaderMap, String), Error = AzureError>
+where
+    F: Future<Item = hyper::Response<hyper::Body>, Error = hyper::Error>,
+{
     check_status_extract_headers_and_body(resp, expected_status_code).and_then(move |(headers, body)| {
         let body = str::from_utf8(&body)?.to_owned();
         Ok((headers, body))
@@ -334,7 +508,10 @@ pub(crate) fn check_status_extract_headers_and_body_as_string(
 }
 
 #[inline]
-pub(crate) fn extract_status_and_body(resp: hyper::client::ResponseFuture) -> impl Future<Item = (StatusCode, String), Error = AzureError> {
+pub(crate) fn extract_status_and_body<F>(resp: F) -> impl Future<Item = (StatusCode, String), Error = AzureError>
+where
+    F: Future<Item = hyper::Response<hyper::Body>, Error = hyper::Error>,
+{
     resp.from_err().and_then(|res| {
         let status = res.status();
         res.into_body()
@@ -345,19 +522,222 @@ pub(crate) fn extract_status_and_body(resp: hyper::client::ResponseFuture) -> im
 }
 
 #[inline]
-pub(crate) fn check_status_extract_body(
-    resp: hyper::client::ResponseFuture,
+pub(crate) fn check_status_extract_body<F>(
+    resp: F,
     expected_status_code: hyper::StatusCode,
-) -> impl Future<Item = String, Error = AzureError> {
+) -> impl Future<Item = String, Error = AzureError>
+where
+    F: Future<Item = hyper::Response<hyper::Body>, Error = hyper::Error>,
+{
     extract_status_and_body(resp).and_then(move |(status, body)| {
         if status == expected_status_code {
             Ok(body)
         } else {
-            Err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult {
-                expected: expected_status_code,
-                received: status,
-                body,
-            }))
+            Err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(
+                expected_status_code,
+                status,
+                &body,
+            )))
         }
     })
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::stream;
+
+    fn body_error(kind: io::ErrorKind) -> AzureError {
+        let body = hyper::Body::wrap_stream(stream::once::<hyper::Chunk, _>(Err(IOError::new(kind, "synthetic"))));
+        AzureError::from(body.concat2().wait().unwrap_err())
+    }
+
+    #[test]
+    fn transport_errors() {
+        // nothing listens on the port 1
+        let refused = hyper::Client::new().get("http://127.0.0.1:1/".parse().unwrap()).wait().unwrap_err();
+        assert!(refused.is_connect());
+        let refused = AzureError::from(refused);
+        assert!(refused.is_connection_error());
+        assert!(!refused.is_timeout());
+
+        let reset = body_error(io::ErrorKind::ConnectionReset);
+        assert!(reset.is_connection_error());
+        assert!(!reset.is_timeout());
+
+        let timeout = body_error(io::ErrorKind::TimedOut);
+        assert!(timeout.is_timeout());
+        assert!(!timeout.is_connection_error());
+
+        let other = body_error(io::ErrorKind::InvalidData);
+        assert!(!other.is_connection_error());
+        assert!(!other.is_timeout());
+
+        assert!(AzureError::from(IOError::new(io::ErrorKind::TimedOut, "synthetic")).is_timeout());
+        assert!(AzureError::from(IOError::new(io::ErrorKind::BrokenPipe, "synthetic")).is_connection_error());
+        let application = unexpected(StatusCode::SERVICE_UNAVAILABLE, "");
+        assert!(!application.is_connection_error());
+        assert!(!application.is_timeout());
+    }
+
+    fn unexpected(received: StatusCode, body: &str) -> AzureError {
+        AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(StatusCode::OK, received, body))
+    }
+
+    #[test]
+    fn unexpected_http_result_accessors() {
+        let result = UnexpectedHTTPResult::new(StatusCode::CREATED, StatusCode::CONFLICT, r#"{"code":"Conflict"}"#);
+        assert_eq!(result.expected_status_code(), StatusCode::CREATED);
+        assert_eq!(result.status_code(), StatusCode::CONFLICT);
+        assert_eq!(result.body(), r#"{"code":"Conflict"}"#);
+        assert_eq!(result.sub_status(), None);
+    }
+
+    #[test]
+    fn conditional_errors() {
+        match conditional_error(unexpected(StatusCode::NOT_MODIFIED, "")) {
+            AzureError::NotModified => {}
+            e => panic!("unexpected error {:?}", e),
+        }
+        match conditional_error(unexpected(StatusCode::PRECONDITION_FAILED, "ConditionNotMet")) {
+            AzureError::PreconditionFailed(body) => assert_eq!(body, "ConditionNotMet"),
+            e => panic!("unexpected error {:?}", e),
+        }
+        match conditional_error(unexpected(StatusCode::NOT_FOUND, "")) {
+            AzureError::UnexpectedHTTPResult(result) => assert_eq!(result.status_code(), StatusCode::NOT_FOUND),
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn retryable_errors() {
+        let sub_status = |received, sub_status| {
+            AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(StatusCode::OK, received, "").with_sub_status(Some(sub_status)))
+        };
+        let table = vec![
+            (unexpected(StatusCode::TOO_MANY_REQUESTS, ""), true),
+            (unexpected(StatusCode::SERVICE_UNAVAILABLE, ""), true),
+            (unexpected(StatusCode::REQUEST_TIMEOUT, ""), true),
+            (unexpected(StatusCode::INTERNAL_SERVER_ERROR, ""), true),
+            (unexpected(StatusCode::GATEWAY_TIMEOUT, ""), true),
+            (unexpected(StatusCode::BAD_REQUEST, ""), false),
+            (unexpected(StatusCode::UNAUTHORIZED, ""), false),
+            (unexpected(StatusCode::FORBIDDEN, ""), false),
+            (unexpected(StatusCode::NOT_FOUND, ""), false),
+            (unexpected(StatusCode::CONFLICT, ""), false),
+            (unexpected(StatusCode::PRECONDITION_FAILED, ""), false),
+            (sub_status(StatusCode::NOT_FOUND, 1002), true),
+            (sub_status(StatusCode::GONE, 1002), true),
+            (sub_status(StatusCode::FORBIDDEN, 3), true),
+            (sub_status(StatusCode::FORBIDDEN, 5), false),
+            (sub_status(StatusCode::NOT_FOUND, 0), false),
+            (
+                AzureError::ThrottledError {
+                    retry_after: Duration::from_millis(100),
+                },
+                true,
+            ),
+            (body_error(io::ErrorKind::ConnectionReset), true),
+            (body_error(io::ErrorKind::TimedOut), true),
+            (body_error(io::ErrorKind::InvalidData), false),
+            (AzureError::Unauthorized("".to_owned()), false),
+            (AzureError::PreconditionFailed("".to_owned()), false),
+            (AzureError::NotModified, false),
+            (AzureError::InputParametersError("".to_owned()), false),
+            (AzureError::GenericError, false),
+        ];
+
+        for (error, retryable) in table {
+            assert_eq!(error.is_retryable(), retryable, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn operation_errors_keep_their_cause() {
+        let json = serde_json::from_str::<u64>("\"forty-two\"").unwrap_err();
+        let message = json.to_string();
+        let error = AzureError::operation("reduce_aggregate", json);
+        assert_eq!(error.to_string(), format!("reduce_aggregate failed: {}", message));
+
+        match error {
+            AzureError::Operation { operation, ref source } => {
+                assert_eq!(operation, "reduce_aggregate");
+                let json = source.downcast_ref::<serde_json::Error>().expect("the cause is kept");
+                assert_eq!(json.to_string(), message);
+            }
+            ref e => panic!("unexpected error {:?}", e),
+        }
+        let source = std::error::Error::source(&error).map(|source| source.to_string());
+        assert_eq!(source, Some(message));
+    }
+
+    #[test]
+    fn malformed_partial_aggregate() {
+        use azure::cosmos::cross_partition::{reduce_aggregate, AggregateOperator};
+
+        // the error of the partial that does not parse is the cause, not a
+        // GenericError
+        let malformed: Vec<serde_json::Value> = vec![serde_json::from_str(r#"[{"item":{"sum":6.0,"count":"three"}}]"#).unwrap()];
+        match reduce_aggregate(AggregateOperator::Average, &malformed) {
+            Err(AzureError::Operation { operation, source }) => {
+                assert_eq!(operation, "reduce_aggregate");
+                assert!(source.downcast_ref::<serde_json::Error>().is_some(), "{:?}", source);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn source_chain() {
+        use std::error::Error;
+
+        let error = AzureError::operation("delete_by_query", unexpected(StatusCode::NOT_FOUND, "NotFound"));
+        let mut chain = vec![error.to_string()];
+        let mut source = error.source();
+        while let Some(error) = source {
+            chain.push(error.to_string());
+            source = error.source();
+        }
+        assert_eq!(
+            chain,
+            vec![
+                "delete_by_query failed: UnexpectedHTTPResult error",
+                "UnexpectedHTTPResult error",
+                "Unexpected HTTP result (expected: 200 OK, received: 404 Not Found)",
+            ]
+        );
+
+        let io = AzureError::from(io::Error::new(io::ErrorKind::ConnectionReset, "reset by peer"));
+        assert_eq!(io.source().map(|source| source.to_string()), Some("reset by peer".to_owned()));
+        assert!(io.source().and_then(|source| source.downcast_ref::<io::Error>()).is_some());
+        assert!(AzureError::GenericError.source().is_none());
+        assert_eq!(AzureError::GenericError.to_string(), "GenericError");
+
+        let parse = "forty-two".parse::<u64>().unwrap_err();
+        let traversing = AzureError::ResponseParsingError(TraversingError::from(parse.clone()));
+        assert_eq!(
+            traversing
+                .source()
+                .and_then(|source| source.source())
+                .map(|source| source.to_string()),
+            Some(parse.to_string())
+        );
+
+        // a range that does not parse keeps its cause down to the integer
+        let range = AzureError::from("1000/many".parse::<::azure::core::range::Range>().unwrap_err());
+        let mut chain = vec![range.to_string()];
+        let mut source = range.source();
+        while let Some(error) = source {
+            chain.push(error.to_string());
+            source = error.source();
+        }
+        assert_eq!(
+            chain,
+            vec![
+                format!("Parse error: parse int error: {}", parse),
+                format!("parse int error: {}", parse),
+                parse.to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use azure::core::modify_conditions::IfMatchCondition;
+use std::fmt;
+
+/// An entity tag, kept quoted as `If-Match` expects it. The services send
+/// them quoted in the headers but not always in the bodies, the blob
+/// listings for example, and the quotes are easily lost along the way.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ETag(String);
+
+impl ETag {
+    /// The quoted ETag, for example `"0x8D5F4D6D8F2D1C0"`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The ETag without the quotes, nor the `W/` prefix of weak ETags.
+    pub fn unquoted(&self) -> &str {
+        let etag = if self.is_weak() { &self.0[2..] } else { &self.0 };
+        &etag[1..etag.len() - 1]
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.0.starts_with("W/")
+    }
+
+    /// Makes a request conditional on the resource still having this ETag.
+    pub fn if_match(&self) -> IfMatchCondition<'_> {
+        IfMatchCondition::Match(self.as_str())
+    }
+
+    /// Makes a request conditional on the resource having changed since it
+    /// had this ETag.
+    pub fn if_none_match(&self) -> IfMatchCondition<'_> {
+        IfMatchCondition::NotMatch(self.as_str())
+    }
+}
+
+impl<'a> From<&'a str> for ETag {
+    fn from(etag: &'a str) -> ETag {
+        let etag = etag.trim();
+        let value = etag.trim_start_matches("W/");
+        if value.len() > 1 && value.starts_with('"') && value.ends_with('"') {
+            ETag(etag.to_owned())
+        } else if value.len() < etag.len() {
+            ETag(format!("W/\"{}\"", value.trim_matches('"')))
+        } else {
+            ETag(format!("\"{}\"", value.trim_matches('"')))
+        }
+    }
+}
+
+impl From<String> for ETag {
+    fn from(etag: String) -> ETag {
+        ETag::from(etag.as_str())
+    }
+}
+
+impl From<ETag> for String {
+    fn from(etag: ETag) -> String {
+        etag.0
+    }
+}
+
+impl AsRef<str> for ETag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// Adds the `etag()` accessor to a type holding the ETag as a string.
+macro_rules! impl_etag {
+    ($t:ty, $field:ident) => {
+        impl $t {
+            pub fn etag(&self) -> $crate::core::etag::ETag {
+                $crate::core::etag::ETag::from(self.$field.as_str())
+            }
+        }
+    };
+    ($t:ty, $field:ident, optional) => {
+        impl $t {
+            pub fn etag(&self) -> Option<$crate::core::etag::ETag> {
+                self.$field.as_ref().map(|etag| $crate::core::etag::ETag::from(etag.as_str()))
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalization() {
+        for etag in &[
+            "\"0x8D5F4D6D8F2D1C0\"",
+            "0x8D5F4D6D8F2D1C0",
+            " \"0x8D5F4D6D8F2D1C0\"\n",
+            "\"0x8D5F4D6D8F2D1C0",
+        ] {
+            let etag = ETag::from(*etag);
+            assert_eq!(etag.as_str(), "\"0x8D5F4D6D8F2D1C0\"");
+            assert_eq!(etag.unquoted(), "0x8D5F4D6D8F2D1C0");
+            assert!(!etag.is_weak());
+        }
+
+        for etag in &["W/\"datetime'2018-07-29T12%3A15%3A23Z'\"", "W/datetime'2018-07-29T12%3A15%3A23Z'"] {
+            let etag = ETag::from(*etag);
+            assert_eq!(etag.as_str(), "W/\"datetime'2018-07-29T12%3A15%3A23Z'\"");
+            assert_eq!(etag.unquoted(), "datetime'2018-07-29T12%3A15%3A23Z'");
+            assert!(etag.is_weak());
+        }
+
+        assert_eq!(ETag::from("\"").as_str(), "\"\"");
+        assert_eq!(ETag::from("\"\"").unquoted(), "");
+        assert_eq!(ETag::from("0x8D5F4D6D8F2D1C0"), ETag::from("\"0x8D5F4D6D8F2D1C0\"".to_owned()));
+        assert_eq!(String::from(ETag::from("0x8D5F4D6D8F2D1C0")), "\"0x8D5F4D6D8F2D1C0\"");
+        assert_eq!(
+            ETag::from("0x8D5F4D6D8F2D1C0").if_match(),
+            IfMatchCondition::Match("\"0x8D5F4D6D8F2D1C0\"")
+        );
+    }
+}
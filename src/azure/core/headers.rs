@@ -24,6 +24,11 @@ pub const CONTENT_MD5: &str = "Content-MD5"; //=> [String] }
 pub const SERVER_ENCRYPTED: &str = "x-ms-server-encrypted";
 pub const BLOB_TYPE: &str = "x-ms-blob-type";
 pub const BLOB_CONTENT_LENGTH: &str = "x-ms-blob-content-length";
+pub const BLOB_CONTENT_TYPE: &str = "x-ms-blob-content-type";
+pub const BLOB_CONTENT_ENCODING: &str = "x-ms-blob-content-encoding";
+pub const BLOB_CONTENT_LANGUAGE: &str = "x-ms-blob-content-language";
+pub const BLOB_CACHE_CONTROL: &str = "x-ms-blob-cache-control";
+pub const BLOB_CONTENT_DISPOSITION: &str = "x-ms-blob-content-disposition";
 pub const BLOB_ACCESS_TIER: &str = "x-ms-access-tier";
 pub const BLOB_SEQUENCE_NUMBER: &str = "x-ms-blob-sequence-number";
 pub const IF_SEQUENCE_NUMBER_LE: &str = "x-ms-if-sequence-number-le";
@@ -31,3 +36,6 @@ pub const IF_SEQUENCE_NUMBER_LT: &str = "x-ms-if-sequence-number-lt";
 pub const IF_SEQUENCE_NUMBER_EQ: &str = "x-ms-if-sequence-number-eq";
 pub const PAGE_WRITE: &str = "x-ms-page-write";
 pub const REQUEST_SERVER_ENCRYPTED: &str = "x-ms-request-server-encrypted";
+pub const SNAPSHOT: &str = "x-ms-snapshot";
+pub const SKU_NAME: &str = "x-ms-sku-name"; // [SkuName]
+pub const ACCOUNT_KIND: &str = "x-ms-account-kind"; // [AccountKind]
@@ -1,6 +1,115 @@
+use azure::core::errors::AzureError;
+use azure::core::COMPLETE_ENCODE_SET;
+use bytes::Bytes;
+use serde::de::{Deserialize, Deserializer, Error};
+use serde::{Serialize, Serializer};
+use serde_json;
+use std::fmt;
 use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+use url::percent_encoding::{percent_decode, utf8_percent_encode};
 
-pub type ContinuationToken = String;
+/// The token Cosmos returns to resume an enumeration where the last page
+/// stopped. It is opaque but travels as a header value so it must be
+/// printable ASCII, and the JSON tokens of cross partition queries must be
+/// complete.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContinuationToken(String);
+
+impl ContinuationToken {
+    pub fn new<S: Into<String>>(token: S) -> Result<ContinuationToken, AzureError> {
+        let token = token.into();
+
+        if token.trim().is_empty() {
+            return Err(AzureError::InputParametersError(
+                "the continuation token cannot be empty".to_owned(),
+            ));
+        }
+        if !token.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+            return Err(AzureError::InputParametersError(format!(
+                "the continuation token {:?} contains characters other than printable ASCII",
+                token
+            )));
+        }
+        if (token.starts_with('[') || token.starts_with('{')) && serde_json::from_str::<serde_json::Value>(&token).is_err() {
+            return Err(AzureError::InputParametersError(format!(
+                "the continuation token {:?} is not valid JSON, has it been truncated?",
+                token
+            )));
+        }
+
+        Ok(ContinuationToken(token))
+    }
+
+    // The tokens returned by the service are valid by definition.
+    pub(crate) fn new_unchecked(token: String) -> ContinuationToken {
+        ContinuationToken(token)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The token percent encoded, to be passed in an URL. `from_str`
+    /// decodes it back.
+    pub fn url_encoded(&self) -> String {
+        utf8_percent_encode(&self.0, COMPLETE_ENCODE_SET).to_string()
+    }
+}
+
+/// Parses a token either as returned by the service or percent encoded.
+impl FromStr for ContinuationToken {
+    type Err = AzureError;
+
+    fn from_str(s: &str) -> Result<ContinuationToken, AzureError> {
+        // the raw tokens never contain a '%'
+        if s.contains('%') {
+            let decoded = percent_decode(s.as_bytes())
+                .decode_utf8()
+                .map_err(|_| AzureError::InputParametersError(format!("the continuation token {:?} is not correctly encoded", s)))?;
+            ContinuationToken::new(decoded.into_owned())
+        } else {
+            ContinuationToken::new(s)
+        }
+    }
+}
+
+impl fmt::Display for ContinuationToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AsRef<str> for ContinuationToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<ContinuationToken> for String {
+    fn from(token: ContinuationToken) -> String {
+        token.0
+    }
+}
+
+impl From<ContinuationToken> for Bytes {
+    fn from(token: ContinuationToken) -> Bytes {
+        Bytes::from(token.0)
+    }
+}
+
+impl Serialize for ContinuationToken {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+/// Checks the token as `new` does.
+impl<'de> Deserialize<'de> for ContinuationToken {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ContinuationToken, D::Error> {
+        ContinuationToken::new(String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct IncompleteVector<T> {
@@ -24,6 +133,10 @@ impl<T> IncompleteVector<T> {
             None
         }
     }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.vector
+    }
 }
 
 impl<T> DerefMut for IncompleteVector<T> {
@@ -43,7 +156,8 @@ impl<T> Deref for IncompleteVector<T> {
 #[cfg(test)]
 mod test {
     #[allow(unused_imports)]
-    use super::IncompleteVector;
+    use super::{ContinuationToken, IncompleteVector};
+    use azure::core::errors::AzureError;
 
     #[test]
     fn test_incomplete_vector_complete() {
@@ -67,4 +181,35 @@ mod test {
         let ic = IncompleteVector::new(None, v);
         assert_eq!(ic[0], 0);
     }
+
+    #[test]
+    fn valid_continuation_token() {
+        let token = "+RID:Sl8fALN4sw4CAAAAAAAAgA==#RT:1#TRC:1";
+        let ct = ContinuationToken::new(token).unwrap();
+        assert_eq!(ct.as_str(), token);
+        assert_eq!(ct.to_string(), token);
+        assert_eq!(ct.url_encoded(), "%2BRID%3ASl8fALN4sw4CAAAAAAAAgA%3D%3D%23RT%3A1%23TRC%3A1");
+        assert_eq!(ct.url_encoded().parse::<ContinuationToken>().unwrap(), ct);
+        assert_eq!(token.parse::<ContinuationToken>().unwrap(), ct);
+
+        let token = r#"[{"token":"+RID:Sl8fALN4sw4CAAAAAAAAgA==#RT:1","range":{"min":"","max":"FF"}}]"#;
+        assert_eq!(ContinuationToken::new(token).unwrap().as_str(), token);
+    }
+
+    #[test]
+    fn corrupt_continuation_token() {
+        for token in &[
+            "",
+            "  ",
+            "+RID:Sl8fALN4sw4CAAAAAAAAgA==\n#RT:1",
+            "+RID:Sl8fALN4sw4CAAAAAAAAgA==#RT:1\u{e9}",
+            r#"[{"token":"+RID:Sl8fALN4sw4CAAAAAAAAgA==#RT:1","range":{"min":"","#,
+            "%2BRID%3ASl8f%FF",
+        ] {
+            match token.parse::<ContinuationToken>() {
+                Err(AzureError::InputParametersError(_)) => {}
+                r => panic!("unexpected result {:?} for {:?}", r, token),
+            }
+        }
+    }
 }
@@ -1,6 +1,8 @@
 use azure::core::enumerations;
-use azure::core::errors::TraversingError;
+use azure::core::errors::{AzureError, TraversingError};
+use azure::core::headers::{LEASE_DURATION, LEASE_STATE, LEASE_STATUS};
 use azure::core::parsing::FromStringOptional;
+use http::HeaderMap;
 use std::fmt;
 use std::str::FromStr;
 use uuid::Uuid;
@@ -28,3 +30,75 @@ create_enum!(
 );
 
 pub type LeaseId = Uuid;
+
+/// Lease headers returned by blob and container operations.
+/// `duration` is only present while the resource is leased.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LeaseInfo {
+    pub state: LeaseState,
+    pub status: LeaseStatus,
+    pub duration: Option<LeaseDuration>,
+}
+
+impl LeaseInfo {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Result<LeaseInfo, AzureError> {
+        let state = match headers.get(LEASE_STATE) {
+            Some(state) => LeaseState::from_str(state.to_str()?)?,
+            None => return Err(AzureError::MissingHeaderError(LEASE_STATE.to_owned())),
+        };
+
+        let status = match headers.get(LEASE_STATUS) {
+            Some(status) => LeaseStatus::from_str(status.to_str()?)?,
+            None => return Err(AzureError::MissingHeaderError(LEASE_STATUS.to_owned())),
+        };
+
+        let duration = match headers.get(LEASE_DURATION) {
+            Some(duration) => Some(LeaseDuration::from_str(duration.to_str()?)?),
+            None => None,
+        };
+
+        Ok(LeaseInfo { state, status, duration })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::header::HeaderValue;
+
+    #[test]
+    fn lease_info_from_headers() {
+        let states = ["available", "leased", "expired", "breaking", "broken"];
+        let statuses = ["locked", "unlocked"];
+        let durations = [None, Some("infinite"), Some("fixed")];
+
+        for state in &states {
+            for status in &statuses {
+                for duration in &durations {
+                    let mut headers = HeaderMap::new();
+                    headers.insert(LEASE_STATE, HeaderValue::from_static(state));
+                    headers.insert(LEASE_STATUS, HeaderValue::from_static(status));
+                    if let Some(duration) = duration {
+                        headers.insert(LEASE_DURATION, HeaderValue::from_static(duration));
+                    }
+
+                    let lease_info = LeaseInfo::from_headers(&headers).unwrap();
+                    assert_eq!(lease_info.state.as_ref(), *state);
+                    assert_eq!(lease_info.status.as_ref(), *status);
+                    assert_eq!(lease_info.duration.as_ref().map(|d| d.as_ref()), *duration);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lease_info_missing_or_invalid() {
+        let mut headers = HeaderMap::new();
+        headers.insert(LEASE_STATE, HeaderValue::from_static("leased"));
+        assert!(LeaseInfo::from_headers(&headers).is_err());
+
+        headers.insert(LEASE_STATUS, HeaderValue::from_static("locked"));
+        headers.insert(LEASE_DURATION, HeaderValue::from_static("forever"));
+        assert!(LeaseInfo::from_headers(&headers).is_err());
+    }
+}
@@ -14,19 +14,27 @@ use azure::storage::client::Client;
 use base64;
 use std::fmt::Debug;
 pub mod ba512_range;
+pub mod client_builder;
+pub mod clock_skew;
+#[cfg(test)]
+pub(crate) mod test_server;
 use base64::encode;
 pub mod modify_conditions;
+#[macro_use]
+pub mod etag;
 use self::modify_conditions::{IfMatchCondition, IfSinceCondition, SequenceNumberCondition};
 pub mod range;
-use azure::storage::blob::{BlockList, BlockListType};
+use azure::storage::blob::{BlobBatch, BlockList, BlockListType, QuerySerialization};
 use std::borrow::Borrow;
 use url::percent_encoding;
 pub mod headers;
 use self::headers::{
     BLOB_ACCESS_TIER, BLOB_CONTENT_LENGTH, BLOB_SEQUENCE_NUMBER, CLIENT_REQUEST_ID, CONTENT_MD5, LEASE_BREAK_PERIOD, LEASE_DURATION,
-    LEASE_ID, PROPOSED_LEASE_ID, REQUEST_ID, REQUEST_SERVER_ENCRYPTED,
+    LEASE_ID, PROPOSED_LEASE_ID, REQUEST_ID, REQUEST_SERVER_ENCRYPTED, SNAPSHOT,
+};
+use hyper::header::{
+    CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_TYPE, DATE, ETAG, LAST_MODIFIED, RANGE,
 };
-use hyper::header::{CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_TYPE, DATE, ETAG, LAST_MODIFIED, RANGE};
 use uuid::Uuid;
 pub type RequestId = Uuid;
 use azure::core::errors::AzureError;
@@ -35,8 +43,9 @@ use http::request::Builder;
 use http::HeaderMap;
 use std::collections::HashMap;
 mod stored_access_policy;
+#[macro_use]
 pub(crate) mod util;
-pub use self::stored_access_policy::{StoredAccessPolicy, StoredAccessPolicyList};
+pub use self::stored_access_policy::{StoredAccessPolicy, StoredAccessPolicyList, MAX_STORED_ACCESS_POLICIES};
 use chrono::{DateTime, Utc};
 
 define_encode_set! {
@@ -106,7 +115,7 @@ pub trait ContentDispositionOption<'a> {
 
     fn add_header(&self, builder: &mut Builder) {
         if let Some(content_disposition) = self.content_disposition() {
-            builder.header(CACHE_CONTROL, content_disposition);
+            builder.header(CONTENT_DISPOSITION, content_disposition);
         }
     }
 }
@@ -256,7 +265,7 @@ pub trait SnapshotOption {
 
     fn to_uri_parameter(&self) -> Option<String> {
         if let Some(ref nm) = self.snapshot() {
-            Some(format!("snapshot={}", nm.to_rfc2822()))
+            Some(format!("snapshot={}", parsing::to_azure_snapshot_time(nm)))
         } else {
             None
         }
@@ -523,6 +532,67 @@ where
     }
 }
 
+pub trait BlobBatchSupport<'a> {
+    type O;
+    fn with_blob_batch(self, blob_batch: &'a BlobBatch) -> Self::O;
+}
+
+pub trait BlobBatchRequired<'a> {
+    fn blob_batch(&self) -> &'a BlobBatch;
+}
+
+pub trait TagsSupport<'a> {
+    type O;
+    fn with_tags(self, tags: &'a HashMap<String, String>) -> Self::O;
+}
+
+pub trait TagsRequired<'a> {
+    fn tags(&self) -> &'a HashMap<String, String>;
+}
+
+pub trait TagFilterSupport<'a> {
+    type O;
+    fn with_tag_filter(self, tag_filter: &'a str) -> Self::O;
+}
+
+pub trait TagFilterRequired<'a> {
+    fn tag_filter(&self) -> &'a str;
+
+    fn to_uri_parameter(&self) -> String {
+        format!(
+            "where={}",
+            percent_encoding::utf8_percent_encode(self.tag_filter(), COMPLETE_ENCODE_SET)
+        )
+    }
+}
+
+pub trait QueryExpressionSupport<'a> {
+    type O;
+    fn with_query_expression(self, query_expression: &'a str) -> Self::O;
+}
+
+pub trait QueryExpressionRequired<'a> {
+    fn query_expression(&self) -> &'a str;
+}
+
+pub trait InputSerializationSupport<'a> {
+    type O;
+    fn with_input_serialization(self, input_serialization: &'a QuerySerialization) -> Self::O;
+}
+
+pub trait InputSerializationOption<'a> {
+    fn input_serialization(&self) -> Option<&'a QuerySerialization>;
+}
+
+pub trait OutputSerializationSupport<'a> {
+    type O;
+    fn with_output_serialization(self, output_serialization: &'a QuerySerialization) -> Self::O;
+}
+
+pub trait OutputSerializationOption<'a> {
+    fn output_serialization(&self) -> Option<&'a QuerySerialization>;
+}
+
 pub trait LeaseIdSupport<'a> {
     type O;
     fn with_lease_id(self, &'a LeaseId) -> Self::O;
@@ -599,6 +669,30 @@ pub trait RangeRequired<'a> {
     }
 }
 
+pub trait RangeHeaderSupport<'a> {
+    type O;
+    fn with_range_header(self, range_header: &'a str) -> Self::O;
+}
+
+pub trait RangeHeaderOption<'a> {
+    fn range_header(&self) -> Option<&'a str>;
+
+    fn add_header(&self, builder: &mut Builder) {
+        if let Some(range_header) = self.range_header() {
+            builder.header(RANGE, range_header);
+        }
+    }
+}
+
+pub trait RangeContentMD5Support {
+    type O;
+    fn with_range_content_md5(self, range_content_md5: bool) -> Self::O;
+}
+
+pub trait RangeContentMD5Option {
+    fn range_content_md5(&self) -> Option<bool>;
+}
+
 pub trait BA512RangeSupport<'a> {
     type O;
     fn with_ba512_range(self, &'a ba512_range::BA512Range) -> Self::O;
@@ -767,6 +861,17 @@ pub(crate) fn etag_from_headers(headers: &HeaderMap) -> Result<String, AzureErro
     Ok(etag)
 }
 
+pub(crate) fn snapshot_from_headers(headers: &HeaderMap) -> Result<DateTime<Utc>, AzureError> {
+    let snapshot = headers
+        .get(SNAPSHOT)
+        .ok_or_else(|| AzureError::HeaderNotFound(SNAPSHOT.to_owned()))?
+        .to_str()?;
+    let snapshot = parsing::from_azure_snapshot_time(snapshot)?;
+
+    trace!("snapshot == {:?}", snapshot);
+    Ok(snapshot)
+}
+
 pub(crate) fn sequence_number_from_headers(headers: &HeaderMap) -> Result<u64, AzureError> {
     let sequence_number = headers
         .get(BLOB_SEQUENCE_NUMBER)
@@ -1,3 +1,5 @@
+use azure::core::etag::ETag;
+use azure::core::parsing::to_rfc1123;
 use chrono::{DateTime, Utc};
 use core::headers::{IF_SEQUENCE_NUMBER_EQ, IF_SEQUENCE_NUMBER_LE, IF_SEQUENCE_NUMBER_LT};
 use http::request::Builder;
@@ -12,14 +14,16 @@ pub enum IfSinceCondition {
 impl IfSinceCondition {
     pub(crate) fn add_header(&self, builder: &mut Builder) {
         match self {
-            IfSinceCondition::Modified(date) => builder.header(IF_MODIFIED_SINCE, &date.to_rfc2822() as &str),
-            IfSinceCondition::Unmodified(date) => builder.header(IF_UNMODIFIED_SINCE, &date.to_rfc2822() as &str),
+            IfSinceCondition::Modified(date) => builder.header(IF_MODIFIED_SINCE, &to_rfc1123(date) as &str),
+            IfSinceCondition::Unmodified(date) => builder.header(IF_UNMODIFIED_SINCE, &to_rfc1123(date) as &str),
         };
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum IfMatchCondition<'a> {
+    /// Only if the resource exists, whatever its ETag.
+    Any,
     Match(&'a str),
     NotMatch(&'a str),
 }
@@ -27,8 +31,9 @@ pub enum IfMatchCondition<'a> {
 impl<'a> IfMatchCondition<'a> {
     pub(crate) fn add_header(&self, builder: &mut Builder) {
         match self {
-            IfMatchCondition::Match(etag) => builder.header(IF_MATCH, *etag),
-            IfMatchCondition::NotMatch(etag) => builder.header(IF_NONE_MATCH, *etag),
+            IfMatchCondition::Any => builder.header(IF_MATCH, "*"),
+            IfMatchCondition::Match(etag) => builder.header(IF_MATCH, ETag::from(*etag).as_str()),
+            IfMatchCondition::NotMatch(etag) => builder.header(IF_NONE_MATCH, ETag::from(*etag).as_str()),
         };
     }
 }
@@ -49,3 +54,56 @@ impl SequenceNumberCondition {
         };
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::Request;
+
+    #[test]
+    fn if_since_condition_header() {
+        let date = "2011-03-09T01:42:34Z".parse::<DateTime<Utc>>().unwrap();
+
+        let mut builder = Request::builder();
+        IfSinceCondition::Modified(date).add_header(&mut builder);
+        IfSinceCondition::Unmodified(date).add_header(&mut builder);
+
+        let request = builder.body(()).unwrap();
+        assert_eq!(request.headers()[IF_MODIFIED_SINCE], "Wed, 09 Mar 2011 01:42:34 GMT");
+        assert_eq!(request.headers()[IF_UNMODIFIED_SINCE], "Wed, 09 Mar 2011 01:42:34 GMT");
+    }
+
+    #[test]
+    fn if_match_condition_header() {
+        let header_of = |condition: IfMatchCondition| {
+            let mut builder = Request::builder();
+            condition.add_header(&mut builder);
+            let request = builder.body(()).unwrap();
+            assert_eq!(request.headers().len(), 1);
+            request
+                .headers()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.to_str().unwrap().to_owned()))
+                .next()
+                .unwrap()
+        };
+
+        assert_eq!(header_of(IfMatchCondition::Any), (IF_MATCH, "*".to_owned()));
+        assert_eq!(
+            header_of(IfMatchCondition::Match("\"0x8D5F4D6D8F2D1C0\"")),
+            (IF_MATCH, "\"0x8D5F4D6D8F2D1C0\"".to_owned())
+        );
+        assert_eq!(
+            header_of(IfMatchCondition::Match("0x8D5F4D6D8F2D1C0")),
+            (IF_MATCH, "\"0x8D5F4D6D8F2D1C0\"".to_owned())
+        );
+        assert_eq!(
+            header_of(IfMatchCondition::NotMatch("00000b00-0000-0000-0000-5b5d3e9d0000")),
+            (IF_NONE_MATCH, "\"00000b00-0000-0000-0000-5b5d3e9d0000\"".to_owned())
+        );
+        assert_eq!(
+            header_of(IfMatchCondition::NotMatch("W/\"datetime'2018-07-29T12%3A15%3A23Z'\"")),
+            (IF_NONE_MATCH, "W/\"datetime'2018-07-29T12%3A15%3A23Z'\"".to_owned())
+        );
+    }
+}
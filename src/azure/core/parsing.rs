@@ -45,6 +45,27 @@ pub fn from_azure_time(s: &str) -> Result<chrono::DateTime<chrono::Utc>, chrono:
     Ok(dt_utc)
 }
 
+/// Parses a blob snapshot identifier, for example `2011-03-09T01:42:34.9360000Z`.
+#[inline]
+pub fn from_azure_snapshot_time(s: &str) -> Result<chrono::DateTime<chrono::Utc>, chrono::ParseError> {
+    let dt = chrono::DateTime::parse_from_rfc3339(s)?;
+    Ok(dt.with_timezone(&chrono::Utc))
+}
+
+/// Formats a blob snapshot identifier the way Azure returns it: the service
+/// matches the `snapshot` parameter against this exact representation.
+#[inline]
+pub fn to_azure_snapshot_time(dt: &chrono::DateTime<chrono::Utc>) -> String {
+    format!("{}.{:07}Z", dt.format("%Y-%m-%dT%H:%M:%S"), dt.timestamp_subsec_nanos() / 100)
+}
+
+/// Formats a date as RFC 1123 requires in HTTP headers, for example
+/// `Wed, 09 Mar 2011 01:42:34 GMT`.
+#[inline]
+pub fn to_rfc1123(dt: &chrono::DateTime<chrono::Utc>) -> String {
+    dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
 #[inline]
 pub fn traverse_single_must<'a>(node: &'a Element, path: &[&str]) -> Result<&'a Element, TraversingError> {
     let vec = traverse(node, path, false)?;
@@ -313,4 +334,13 @@ mod test {
         assert_eq!(t.hour(), 17);
         assert_eq!(t.second(), 40);
     }
+
+    #[test]
+    fn test_to_rfc1123() {
+        let t = super::from_azure_time("Sun, 27 Sep 2009 17:26:40 GMT").unwrap();
+        assert_eq!(super::to_rfc1123(&t), "Sun, 27 Sep 2009 17:26:40 GMT");
+
+        let t = super::from_azure_snapshot_time("2011-03-09T01:02:03.9360000Z").unwrap();
+        assert_eq!(super::to_rfc1123(&t), "Wed, 09 Mar 2011 01:02:03 GMT");
+    }
 }
@@ -1,5 +1,6 @@
 use azure::core::ba512_range::BA512Range;
 use std::convert::From;
+use std::error::Error;
 use std::fmt;
 use std::num::ParseIntError;
 use std::str::FromStr;
@@ -41,6 +42,24 @@ impl From<ParseIntError> for ParseError {
     }
 }
 
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::SplitNotFound => write!(f, "split not found"),
+            ParseError::ParseIntError(ref pie) => write!(f, "parse int error: {}", pie),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ParseError::SplitNotFound => None,
+            ParseError::ParseIntError(ref pie) => Some(pie),
+        }
+    }
+}
+
 impl FromStr for Range {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Range, ParseError> {
@@ -65,6 +84,49 @@ impl fmt::Display for Range {
     }
 }
 
+/// The part of the blob a ranged download returned, as told by the
+/// `Content-Range` header: `bytes <start>-<end>/<total length>`, the total
+/// length being `*` when unknown.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ContentRange {
+    pub start: u64,
+    pub end: u64,
+    pub total_length: Option<u64>,
+}
+
+impl FromStr for ContentRange {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<ContentRange, ParseError> {
+        let s = s.trim_start_matches("bytes ");
+        let mut v = s.split('/');
+        let (range, total_length) = match (v.next(), v.next(), v.next()) {
+            (Some(range), Some(total_length), None) => (range, total_length),
+            _ => return Err(ParseError::SplitNotFound),
+        };
+        let mut v = range.split('-');
+        let (start, end) = match (v.next(), v.next(), v.next()) {
+            (Some(start), Some(end), None) => (start.parse::<u64>()?, end.parse::<u64>()?),
+            _ => return Err(ParseError::SplitNotFound),
+        };
+        let total_length = if total_length == "*" {
+            None
+        } else {
+            Some(total_length.parse::<u64>()?)
+        };
+
+        Ok(ContentRange { start, end, total_length })
+    }
+}
+
+impl fmt::Display for ContentRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.total_length {
+            Some(total_length) => write!(f, "bytes {}-{}/{}", self.start, self.end, total_length),
+            None => write!(f, "bytes {}-{}/*", self.start, self.end),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -97,4 +159,25 @@ mod test {
 
         assert_eq!(txt, "bytes=100-500");
     }
+
+    #[test]
+    fn test_content_range_parse() {
+        let content_range = "bytes 1024-2047/4096".parse::<ContentRange>().unwrap();
+        assert_eq!(
+            content_range,
+            ContentRange {
+                start: 1024,
+                end: 2047,
+                total_length: Some(4096)
+            }
+        );
+        assert_eq!(content_range.to_string(), "bytes 1024-2047/4096");
+
+        let unknown_length = "bytes 0-99/*".parse::<ContentRange>().unwrap();
+        assert_eq!(unknown_length.total_length, None);
+        assert_eq!(unknown_length.to_string(), "bytes 0-99/*");
+
+        assert_eq!("bytes 0-99".parse::<ContentRange>(), Err(ParseError::SplitNotFound));
+        assert!("bytes a-99/100".parse::<ContentRange>().is_err());
+    }
 }
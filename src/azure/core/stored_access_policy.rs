@@ -2,6 +2,10 @@ use azure::core::errors::AzureError;
 use chrono::{DateTime, FixedOffset};
 use serde_xml_rs::deserialize;
 
+/// A container can have at most this many stored access policies.
+pub const MAX_STORED_ACCESS_POLICIES: usize = 5;
+const MAX_ID_LENGTH: usize = 64;
+
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct StoredAccessPolicyList {
     pub stored_access: Vec<StoredAccessPolicy>,
@@ -55,6 +59,29 @@ impl StoredAccessPolicyList {
         Ok(sal)
     }
 
+    /// Checks the list against the limits of the service: at most 5
+    /// policies, each with an id of at most 64 characters.
+    pub fn validate(&self) -> Result<(), AzureError> {
+        if self.stored_access.len() > MAX_STORED_ACCESS_POLICIES {
+            return Err(AzureError::InputParametersError(format!(
+                "a container can have at most {} stored access policies, {} given",
+                MAX_STORED_ACCESS_POLICIES,
+                self.stored_access.len()
+            )));
+        }
+
+        for sa in &self.stored_access {
+            if sa.id.is_empty() || sa.id.chars().count() > MAX_ID_LENGTH {
+                return Err(AzureError::InputParametersError(format!(
+                    "the stored access policy id {:?} must be between 1 and {} characters long",
+                    sa.id, MAX_ID_LENGTH
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn to_xml(&self) -> String {
         let mut s = String::new();
         s.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<SignedIdentifiers>\n");
@@ -129,4 +156,50 @@ mod test {
 
         let _sxml = sal.to_xml();
     }
+
+    fn policy(id: &str) -> StoredAccessPolicy {
+        StoredAccessPolicy::new(
+            id,
+            DateTime::parse_from_rfc3339("2018-09-28T08:49:37Z").unwrap(),
+            DateTime::parse_from_rfc3339("2020-09-29T08:49:37Z").unwrap(),
+            "rwdl",
+        )
+    }
+
+    #[test]
+    fn xml_round_trip() {
+        let mut sal = StoredAccessPolicyList::new();
+        sal.stored_access.push(policy("readers"));
+        sal.stored_access.push(StoredAccessPolicy {
+            permission: "r".to_owned(),
+            ..policy("MTIzNDU2Nzg5MDEyMzQ1Njc4OTAxMjM0NTY3ODkwMTI=")
+        });
+        sal.validate().unwrap();
+
+        let xml = sal.to_xml();
+        assert!(xml.contains("<Start>2018-09-28T08:49:37Z</Start>"));
+        assert_eq!(StoredAccessPolicyList::from_xml(&xml).unwrap(), sal);
+        let empty = StoredAccessPolicyList::new();
+        assert_eq!(StoredAccessPolicyList::from_xml(&empty.to_xml()).unwrap(), empty);
+    }
+
+    #[test]
+    fn five_policies_at_most() {
+        let mut sal = StoredAccessPolicyList::new();
+        for i in 0..MAX_STORED_ACCESS_POLICIES {
+            sal.stored_access.push(policy(&format!("policy{}", i)));
+        }
+        assert!(sal.validate().is_ok());
+
+        sal.stored_access.push(policy("policy5"));
+        match sal.validate() {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let sal = StoredAccessPolicyList {
+            stored_access: vec![policy(&"a".repeat(65))],
+        };
+        assert!(sal.validate().is_err());
+    }
 }
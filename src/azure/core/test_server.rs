@@ -0,0 +1,83 @@
+//! A local HTTP server the unit tests point the clients at.
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Answers every request with the status, the headers and the body `respond`
+// gives for it, the request being read as text, and returns the endpoint to
+// reach it. The headers are lines ending with `\r\n`.
+pub(crate) fn serve(respond: fn(&str) -> (&'static str, String, String)) -> String {
+    serve_with(move |request| response(respond(request)))
+}
+
+// Answers every request as `serve` does and returns, along with the
+// endpoint, the requests it got so far in the order they came.
+pub(crate) fn serve_recorded(respond: fn(&str) -> (&'static str, String, String)) -> (String, Arc<Mutex<Vec<String>>>) {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let endpoint = serve_with(move |request| {
+        recorded.lock().unwrap().push(request.to_owned());
+        response(respond(request))
+    });
+    (endpoint, requests)
+}
+
+// Answers every request with the raw `response` and returns the endpoint to
+// reach it.
+pub(crate) fn serve_raw(response: String) -> String {
+    serve_with(move |_| response.clone())
+}
+
+fn response((status, headers, body): (&'static str, String, String)) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nConnection: close\r\n{}Content-Length: {}\r\n\r\n{}",
+        status,
+        headers,
+        body.len(),
+        body
+    )
+}
+
+fn serve_with<F>(respond: F) -> String
+where
+    F: Fn(&str) -> String + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = String::new();
+            let mut buffer = [0; 1024];
+            while expected_length(&request).map(|expected| request.len() < expected) != Some(false) {
+                let read = stream.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.push_str(::std::str::from_utf8(&buffer[..read]).unwrap());
+            }
+            stream.write_all(respond(&request).as_bytes()).unwrap();
+        }
+    });
+    endpoint
+}
+
+// The length of the headers plus as many bytes as the content length, once
+// the headers are all read.
+fn expected_length(request: &str) -> Option<usize> {
+    request.find("\r\n\r\n").map(|end| {
+        let content_length = request[..end]
+            .lines()
+            .filter_map(|line| {
+                let mut header = line.splitn(2, ':');
+                match (header.next(), header.next()) {
+                    (Some(name), Some(value)) if name.eq_ignore_ascii_case("content-length") => value.trim().parse().ok(),
+                    _ => None,
+                }
+            })
+            .next();
+        end + 4 + content_length.unwrap_or(0)
+    })
+}
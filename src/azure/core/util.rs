@@ -1,11 +1,37 @@
+use azure::core::errors::AzureError;
 use bytes::{Bytes, BytesMut};
+use futures::future::{ok, Either};
+use futures::Future;
 use http::{self, request::Builder, HttpTryFrom};
 use hyper::header::{AsHeaderName, HeaderMap, HeaderName, HeaderValue};
 use std::{
+    env,
     fmt::Display,
     io::{self, Write},
     str::FromStr,
+    time::{Duration, Instant},
 };
+use tokio_timer::Delay;
+
+// Adds the `headers` accessor to a response keeping all the headers it was
+// read from in its `headers` field, so the headers this crate does not read
+// can still be looked at.
+macro_rules! impl_headers {
+    ($t:ident) => {
+        impl $t {
+            pub fn headers(&self) -> &::http::HeaderMap {
+                &self.headers
+            }
+        }
+    };
+    ($t:ident < $g:ident >) => {
+        impl<$g> $t<$g> {
+            pub fn headers(&self) -> &::http::HeaderMap {
+                &self.headers
+            }
+        }
+    };
+}
 
 struct Writer(BytesMut);
 impl io::Write for Writer {
@@ -25,6 +51,18 @@ fn format_as_bytes<D: Display>(value: D) -> Bytes {
     wrt.0.freeze()
 }
 
+/// Reads a setting from the environment. The error names the missing
+/// variable but never echoes the value.
+pub(crate) fn env_var(name: &str) -> Result<String, AzureError> {
+    lookup_var(&|name: &str| env::var(name).ok(), name)
+}
+
+// Like env_var, reading the variables with `lookup` so the tests do not
+// have to change the environment of the whole process.
+pub(crate) fn lookup_var<L: Fn(&str) -> Option<String>>(lookup: &L, name: &str) -> Result<String, AzureError> {
+    lookup(name).ok_or_else(|| AzureError::InputParametersError(format!("environment variable {} is not set", name)))
+}
+
 #[allow(dead_code)]
 pub fn into_header_value<B: Into<Bytes>>(value: B) -> Result<HeaderValue, http::Error> {
     let value = value.into();
@@ -65,6 +103,12 @@ impl HeaderMapExt for HeaderMap {
     }
 }
 
+/// The value sent in a header for an enumeration. `create_enum!`
+/// implements it with the strings of the enumeration.
+pub trait HeaderValueOf {
+    fn header_value(&self) -> &'static str;
+}
+
 pub trait RequestBuilderExt {
     fn header<K, V>(&mut self, key: K, value: V) -> &mut Self
     where
@@ -91,6 +135,13 @@ pub trait RequestBuilderExt {
     {
         self.header(key, value.into())
     }
+
+    fn header_value_of<K, V: HeaderValueOf>(&mut self, key: K, value: &V) -> &mut Self
+    where
+        HeaderName: HttpTryFrom<K>,
+    {
+        self.header_static(key, value.header_value())
+    }
 }
 
 impl RequestBuilderExt for Builder {
@@ -102,3 +153,13 @@ impl RequestBuilderExt for Builder {
         self.header(key, value)
     }
 }
+
+// Waits on the timer of the runtime or the `tokio_core` reactor the future
+// runs on, so it fails if polled outside of one.
+pub(crate) fn delay(duration: Duration) -> impl Future<Item = (), Error = AzureError> {
+    if duration == Duration::from_secs(0) {
+        return Either::A(ok(()));
+    }
+
+    Either::B(Delay::new(Instant::now() + duration).map_err(|error| AzureError::operation("delay", error)))
+}
@@ -0,0 +1,53 @@
+/// An attachment of a document: either a blob stored by Cosmos, whose `media`
+/// is a link below the account, or a reference to content stored elsewhere,
+/// whose `media` is the url of that content.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub id: String,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    pub media: String,
+    #[serde(rename = "_rid")]
+    pub rid: String,
+    #[serde(rename = "_ts")]
+    pub ts: u64,
+    #[serde(rename = "_self")]
+    pub _self: String,
+    #[serde(rename = "_etag")]
+    pub etag: String,
+}
+
+impl_etag!(Attachment, etag);
+
+impl Attachment {
+    /// Whether the content is stored by Cosmos rather than elsewhere.
+    pub fn is_managed(&self) -> bool {
+        self.media.starts_with("/media/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn parse_attachment() {
+        let body = r#"{
+            "id": "invoice", "contentType": "application/pdf", "media": "/media/XP0mAJ3H-AABAAAAAAAAAAOXH9Ys",
+            "_rid": "XP0mAJ3H-AABAAAAAAAAAAOXH9Ys=", "_ts": 1532866205,
+            "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/attachments/XP0mAJ3H-AABAAAAAAAAAAOXH9Ys=",
+            "_etag": "\"00002602-0000-0000-0000-5b5d3b5b0000\""
+        }"#;
+        let attachment: Attachment = serde_json::from_str(body).unwrap();
+        assert_eq!(attachment.id, "invoice");
+        assert_eq!(attachment.content_type, "application/pdf");
+        assert!(attachment.is_managed());
+
+        let external = Attachment {
+            media: "https://example.blob.core.windows.net/invoices/invoice.pdf".to_owned(),
+            ..attachment
+        };
+        assert!(!external.is_managed());
+    }
+}
@@ -0,0 +1,122 @@
+use azure::core::errors::AzureError;
+use azure::cosmos::partition_key::partition_key_value;
+use serde_json::{self, Value};
+use std::collections::HashMap;
+
+/// The most documents `Client::bulk_create` sends in one call of the bulk
+/// create stored procedure, which has to create them all within the few
+/// seconds a stored procedure is given.
+pub const MAX_BULK_CREATE_BATCH_LEN: usize = 100;
+
+// Documents sharing a partition key, created in one transaction. The
+// partition key is serialized as the header expects it, and missing when the
+// collection is not partitioned.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PartitionBatch {
+    pub partition_key: Option<String>,
+    pub documents: Vec<Value>,
+}
+
+// Groups the documents by the value found at the partition key `path` of the
+// collection, in the order the partition keys are first seen, a partition
+// being split in batches of at most `max_len` documents. A document without
+// a partition key is an error rather than being created in the partition of
+// the documents without one, which is rarely intended.
+pub(crate) fn batches_by_partition_key(
+    documents: Vec<Value>,
+    path: Option<&str>,
+    max_len: usize,
+) -> Result<Vec<PartitionBatch>, AzureError> {
+    let mut partitions: Vec<PartitionBatch> = Vec::new();
+    let mut positions = HashMap::new();
+
+    for (index, document) in documents.into_iter().enumerate() {
+        let partition_key = match path {
+            Some(path) => match partition_key_value(&document, path) {
+                Some(value) => Some(serde_json::to_string(&[value])?),
+                None => {
+                    let id = document
+                        .get("id")
+                        .map(|id| id.to_string())
+                        .unwrap_or_else(|| format!("at {}", index));
+                    return Err(AzureError::InputParametersError(format!(
+                        "document {} has no partition key at {}",
+                        id, path
+                    )));
+                }
+            },
+            None => None,
+        };
+
+        let position = *positions.entry(partition_key.clone()).or_insert_with(|| {
+            partitions.push(PartitionBatch {
+                partition_key,
+                documents: Vec::new(),
+            });
+            partitions.len() - 1
+        });
+        partitions[position].documents.push(document);
+    }
+
+    let max_len = ::std::cmp::max(max_len, 1);
+    let mut batches = Vec::new();
+    for partition in partitions {
+        let mut documents = partition.documents.into_iter().peekable();
+        while documents.peek().is_some() {
+            batches.push(PartitionBatch {
+                partition_key: partition.partition_key.clone(),
+                documents: documents.by_ref().take(max_len).collect(),
+            });
+        }
+    }
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn documents(json: &str) -> Vec<Value> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn documents_are_grouped_by_partition_key() {
+        let orders = documents(
+            r#"[{"id": "a", "customer": {"id": "c1"}}, {"id": "b", "customer": {"id": 7}}, {"id": "c", "customer": {"id": "c1"}}]"#,
+        );
+
+        let batches = batches_by_partition_key(orders.clone(), Some("/customer/id"), MAX_BULK_CREATE_BATCH_LEN).unwrap();
+        assert_eq!(
+            batches,
+            vec![
+                PartitionBatch {
+                    partition_key: Some("[\"c1\"]".to_owned()),
+                    documents: vec![orders[0].clone(), orders[2].clone()],
+                },
+                PartitionBatch {
+                    partition_key: Some("[7]".to_owned()),
+                    documents: vec![orders[1].clone()],
+                },
+            ]
+        );
+
+        let batches = batches_by_partition_key(orders.clone(), Some("/customer/id"), 1).unwrap();
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches[1].partition_key, Some("[\"c1\"]".to_owned()));
+
+        let batches = batches_by_partition_key(orders, None, 2).unwrap();
+        assert_eq!(batches.iter().map(|batch| batch.documents.len()).collect::<Vec<_>>(), vec![2, 1]);
+        assert!(batches.iter().all(|batch| batch.partition_key.is_none()));
+    }
+
+    #[test]
+    fn documents_without_partition_key_are_rejected() {
+        let orders = documents(r#"[{"id": "a", "customer": {"id": "c1"}}, {"id": "b", "customer": {}}]"#);
+
+        match batches_by_partition_key(orders, Some("/customer/id"), MAX_BULK_CREATE_BATCH_LEN) {
+            Err(AzureError::InputParametersError(message)) => assert_eq!(message, "document \"b\" has no partition key at /customer/id"),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use azure::core::errors::AzureError;
+use azure::core::util::delay;
+use azure::cosmos::partition_key::{partition_key_value, PartitionKey};
+use futures::future::{self, loop_fn, Either, Loop};
+use futures::Future;
+use serde_json::{self, Value};
+
+/// Attempts of a delete answered 429, each after waiting the time Cosmos
+/// asks for.
+pub(crate) const MAX_THROTTLED_ATTEMPTS: u32 = 10;
+
+/// What `Client::delete_by_query` and `Client::delete_by_query_via_sproc`
+/// did. A document is counted as not found when it was deleted between the
+/// query and its delete, by someone else.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DeleteByQueryResult {
+    pub deleted: u64,
+    pub not_found: u64,
+}
+
+// The body set by the bulk delete stored procedure.
+#[derive(Debug, Deserialize)]
+pub(crate) struct BulkDeleteReply {
+    pub deleted: u64,
+    pub continuation: bool,
+}
+
+// Sends the request again while Cosmos answers 429, waiting the time it asks
+// for, at most `attempts` times.
+pub(crate) fn retry_throttled<F, R>(attempts: u32, request: F) -> impl Future<Item = R::Item, Error = AzureError>
+where
+    F: Fn() -> R,
+    R: Future<Error = AzureError>,
+{
+    loop_fn(1, move |attempt: u32| {
+        request().then(move |result| match result {
+            Err(AzureError::ThrottledError { retry_after }) if attempt < attempts => {
+                debug!("request throttled, sending it again in {:?}", retry_after);
+                Either::A(delay(retry_after).map(move |_| Loop::Continue(attempt + 1)))
+            }
+            result => Either::B(future::result(result.map(Loop::Break))),
+        })
+    })
+}
+
+// The id of a document returned by the query, with its partition key as the
+// header expects it: the value found at the partition key `path` of the
+// collection, in an array, `[{}]` when the document has none. There is no
+// partition key when the collection is not partitioned.
+pub(crate) fn document_to_delete(document: &Value, path: Option<&str>) -> Result<(String, Option<String>), AzureError> {
+    let id = match document.get("id") {
+        Some(Value::String(id)) => id.to_owned(),
+        _ => {
+            return Err(AzureError::InputParametersError(
+                "the query must return the id of the documents to delete".to_owned(),
+            ))
+        }
+    };
+
+    let partition_key = match path {
+        Some(path) => match partition_key_value(document, path) {
+            Some(value) => Some(serde_json::to_string(&[value])?),
+            None => PartitionKey::undefined().to_json()?,
+        },
+        None => None,
+    };
+
+    Ok((id, partition_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    #[test]
+    fn documents_to_delete() {
+        let document: Value = serde_json::from_str(r#"{"id": "a", "customer": {"id": 42, "name": "c1"}}"#).unwrap();
+        assert_eq!(
+            document_to_delete(&document, Some("/customer/id")).unwrap(),
+            ("a".to_owned(), Some("[42]".to_owned()))
+        );
+        assert_eq!(
+            document_to_delete(&document, Some("/customer/\"name\"")).unwrap(),
+            ("a".to_owned(), Some("[\"c1\"]".to_owned()))
+        );
+        assert_eq!(
+            document_to_delete(&document, Some("/region")).unwrap(),
+            ("a".to_owned(), Some("[{}]".to_owned()))
+        );
+        assert_eq!(document_to_delete(&document, None).unwrap(), ("a".to_owned(), None));
+
+        let projected: Value = serde_json::from_str(r#"{"customer": {"id": 42}}"#).unwrap();
+        match document_to_delete(&projected, Some("/customer/id")) {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn throttled_requests_are_sent_again() {
+        let mut core = ::tokio_core::reactor::Core::new().unwrap();
+        let sent = Cell::new(0);
+        let throttled_twice = || {
+            sent.set(sent.get() + 1);
+            if sent.get() <= 2 {
+                future::err(AzureError::ThrottledError {
+                    retry_after: Duration::from_millis(1),
+                })
+            } else {
+                future::ok(sent.get())
+            }
+        };
+        assert_eq!(core.run(retry_throttled(3, &throttled_twice)).unwrap(), 3);
+
+        sent.set(0);
+        match core.run(retry_throttled(2, &throttled_twice)) {
+            Err(AzureError::ThrottledError { .. }) => assert_eq!(sent.get(), 2),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
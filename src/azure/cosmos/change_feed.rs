@@ -0,0 +1,239 @@
+use azure::core::errors::AzureError;
+use azure::cosmos::client::Client;
+use azure::cosmos::request_response::Document;
+use futures::future::{self, Either, IntoFuture};
+use futures::Future;
+use hyper::StatusCode;
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A physical partition of a collection, listed by
+/// `Client::list_partition_key_ranges`. The change feed is read per range,
+/// each with its own continuation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PartitionKeyRange {
+    pub id: String,
+    #[serde(rename = "minInclusive")]
+    pub min_inclusive: String,
+    #[serde(rename = "maxExclusive")]
+    pub max_exclusive: String,
+    #[serde(rename = "_rid")]
+    pub rid: String,
+    #[serde(rename = "_etag")]
+    pub etag: String,
+    /// The ranges this one was split from, the most recent last.
+    #[serde(default)]
+    pub parents: Vec<String>,
+}
+
+/// Where a `ChangeFeedProcessor` keeps the continuation of every partition
+/// key range between polls. Storing it outside of the process, in a blob or
+/// a document, lets another process resume where this one stopped.
+pub trait CheckpointStore {
+    fn load(&self, range_id: &str) -> Box<dyn Future<Item = Option<String>, Error = AzureError>>;
+    fn save(&self, range_id: &str, continuation: &str) -> Box<dyn Future<Item = (), Error = AzureError>>;
+}
+
+/// A `CheckpointStore` kept in memory, lost with the process. The clones
+/// share the continuations.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryCheckpointStore {
+    continuations: Rc<RefCell<HashMap<String, String>>>,
+}
+
+impl InMemoryCheckpointStore {
+    pub fn new() -> InMemoryCheckpointStore {
+        InMemoryCheckpointStore::default()
+    }
+
+    pub fn continuation(&self, range_id: &str) -> Option<String> {
+        self.continuations.borrow().get(range_id).cloned()
+    }
+}
+
+impl CheckpointStore for InMemoryCheckpointStore {
+    fn load(&self, range_id: &str) -> Box<dyn Future<Item = Option<String>, Error = AzureError>> {
+        Box::new(future::ok(self.continuation(range_id)))
+    }
+
+    fn save(&self, range_id: &str, continuation: &str) -> Box<dyn Future<Item = (), Error = AzureError>> {
+        self.continuations.borrow_mut().insert(range_id.to_owned(), continuation.to_owned());
+        Box::new(future::ok(()))
+    }
+}
+
+/// Handed along with every batch to the callback of
+/// `ChangeFeedProcessor::poll`: `save` stores the continuation following the
+/// batch. A batch that is not checkpointed is read again by the next poll.
+pub struct Checkpoint {
+    store: Rc<dyn CheckpointStore>,
+    range_id: String,
+    continuation: String,
+}
+
+impl Checkpoint {
+    pub fn range_id(&self) -> &str {
+        &self.range_id
+    }
+
+    pub fn continuation(&self) -> &str {
+        &self.continuation
+    }
+
+    pub fn save(&self) -> Box<dyn Future<Item = (), Error = AzureError>> {
+        self.store.save(&self.range_id, &self.continuation)
+    }
+}
+
+/// Reads the change feed of a collection range by range, so that every range
+/// progresses on its own and the work can be spread by range. The ranges are
+/// listed again on every poll. A range without a checkpoint resumes from the
+/// checkpoint of the range it was split from, if any, and is read from the
+/// beginning otherwise.
+pub struct ChangeFeedProcessor {
+    client: Client,
+    database: String,
+    collection: String,
+    store: Rc<dyn CheckpointStore>,
+    max_item_count: Option<u64>,
+}
+
+impl ChangeFeedProcessor {
+    pub fn new<S1, S2, C>(client: Client, database: S1, collection: S2, store: C) -> ChangeFeedProcessor
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+        C: CheckpointStore + 'static,
+    {
+        ChangeFeedProcessor {
+            client,
+            database: database.into(),
+            collection: collection.into(),
+            store: Rc::new(store),
+            max_item_count: None,
+        }
+    }
+
+    /// The most documents in a batch, Cosmos decides otherwise.
+    pub fn with_max_item_count(mut self, max_item_count: u64) -> ChangeFeedProcessor {
+        self.max_item_count = Some(max_item_count);
+        self
+    }
+
+    /// Reads one batch of changes from every range, the ranges in parallel,
+    /// and calls `handler` with each batch that is not empty. Resolves to
+    /// the number of documents handled.
+    pub fn poll<T, F, R>(&self, handler: F) -> impl Future<Item = usize, Error = AzureError>
+    where
+        T: DeserializeOwned,
+        F: Fn(Vec<Document<T>>, Checkpoint) -> R,
+        R: IntoFuture<Item = (), Error = AzureError>,
+    {
+        let handler = Rc::new(handler);
+        let client = self.client.clone();
+        let database = self.database.clone();
+        let collection = self.collection.clone();
+        let store = self.store.clone();
+        let max_item_count = self.max_item_count;
+
+        self.client
+            .list_partition_key_ranges(self.database.clone(), self.collection.clone())
+            .and_then(move |ranges| {
+                let polls = ranges
+                    .into_iter()
+                    .map(|range| {
+                        Self::poll_range(
+                            &client,
+                            &database,
+                            &collection,
+                            store.clone(),
+                            max_item_count,
+                            range,
+                            handler.clone(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                future::join_all(polls).map(|counts| counts.into_iter().sum())
+            })
+    }
+
+    fn poll_range<T, F, R>(
+        client: &Client,
+        database: &str,
+        collection: &str,
+        store: Rc<dyn CheckpointStore>,
+        max_item_count: Option<u64>,
+        range: PartitionKeyRange,
+        handler: Rc<F>,
+    ) -> impl Future<Item = usize, Error = AzureError>
+    where
+        T: DeserializeOwned,
+        F: Fn(Vec<Document<T>>, Checkpoint) -> R,
+        R: IntoFuture<Item = (), Error = AzureError>,
+    {
+        let range_id = range.id;
+        let mut request = client
+            .list_documents(database, collection)
+            .incremental_feed()
+            .partition_range_id(range_id.clone());
+        if let Some(max_item_count) = max_item_count {
+            request = request.max_item_count(max_item_count);
+        }
+
+        let mut lineage = range.parents;
+        lineage.push(range_id.clone());
+        load_continuation(store.clone(), lineage)
+            .and_then(move |continuation| {
+                let request = match continuation {
+                    Some(continuation) => request.if_none_match(continuation),
+                    None => request,
+                };
+                // 304 when nothing changed since the continuation
+                request.execute::<T>().then(|result| match result {
+                    Ok(response) => Ok(Some(response)),
+                    Err(AzureError::UnexpectedHTTPResult(ref e)) if e.status_code() == StatusCode::NOT_MODIFIED => Ok(None),
+                    Err(e) => Err(e),
+                })
+            })
+            .and_then(move |response| match response {
+                Some(ref response) if response.documents.is_empty() => Either::A(future::ok(0)),
+                Some(response) => {
+                    let continuation = match response.additional_headers.etag {
+                        Some(etag) => etag,
+                        None => {
+                            return Either::A(future::err(AzureError::GenericErrorWithText(format!(
+                                "no continuation returned with the changes of range {}",
+                                range_id
+                            ))))
+                        }
+                    };
+                    let count = response.documents.len();
+                    let checkpoint = Checkpoint {
+                        store,
+                        range_id,
+                        continuation,
+                    };
+                    Either::B(handler(response.documents, checkpoint).into_future().map(move |_| count))
+                }
+                None => Either::A(future::ok(0)),
+            })
+    }
+}
+
+// Looks for the checkpoint of the last range of `lineage`, then of the ranges
+// it was split from, the most recent first: the continuation of a parent is
+// valid for its children.
+fn load_continuation(
+    store: Rc<dyn CheckpointStore>,
+    mut lineage: Vec<String>,
+) -> Box<dyn Future<Item = Option<String>, Error = AzureError>> {
+    match lineage.pop() {
+        Some(range_id) => Box::new(store.load(&range_id).and_then(move |continuation| match continuation {
+            Some(continuation) => Either::A(future::ok(Some(continuation))),
+            None => Either::B(load_continuation(store, lineage)),
+        })),
+        None => Box::new(future::ok(None)),
+    }
+}
@@ -1,15 +1,35 @@
 use azure::core::{
-    errors::{check_status_extract_body, AzureError},
-    util::RequestBuilderExt,
+    client_builder::{ClientBuilder, HyperClient},
+    errors::{extract_status_and_body, extract_status_headers_and_body, AzureError, UnexpectedHTTPResult},
+    incompletevector::ContinuationToken,
+    parsing::to_rfc1123,
+    util::lookup_var,
     COMPLETE_ENCODE_SET,
 };
 
 use super::{
+    attachment::Attachment,
+    bulk_create::{batches_by_partition_key, PartitionBatch, MAX_BULK_CREATE_BATCH_LEN},
+    bulk_delete::{document_to_delete, retry_throttled, BulkDeleteReply, DeleteByQueryResult, MAX_THROTTLED_ATTEMPTS},
+    change_feed::PartitionKeyRange,
     collection::Collection,
+    conflict::Conflict,
+    cross_partition::{merge_with_query_plan, partition_query, CombinedQueryMetrics, CrossPartitionQueryResponse, QueryPlan},
     database::Database,
-    query::Query,
-    request_response::{Document, ListCollectionsResponse, ListDatabasesResponse},
+    database_account::DatabaseAccount,
+    failover::{follow_failover, FailoverPolicy},
+    navigation::DatabaseRef,
+    offer::{collections_throughput, offer_query, replaced_offer, Offer, Throughput},
+    partition_key::PartitionKey,
+    query::{select_projection, Query},
+    request_response::{
+        Document, ListAttachmentsResponse, ListCollectionsResponse, ListDatabasesResponse, ListOffersResponse,
+        ListPartitionKeyRangesResponse, QueryMetrics,
+    },
     requests::*,
+    ru_budget::{BudgetedHyperClient, BudgetedResponseFuture, RuBudget},
+    stored_procedure::{StoredProcedure, BULK_CREATE_SPROC_BODY, BULK_CREATE_SPROC_ID, BULK_DELETE_SPROC_BODY, BULK_DELETE_SPROC_ID},
+    uri_builder::{ResourceUri, UriBuilder},
     AuthorizationToken, TokenType,
 };
 
@@ -23,22 +43,28 @@ use hyper::{
 use ring::{digest::SHA256, hmac};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json;
-use std::sync::Arc;
+use std::env;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 
 use chrono;
-use hyper_tls::HttpsConnector;
 use url::percent_encoding::utf8_percent_encode;
 
 use futures::future::*;
+use futures::stream::{self, Stream};
 
 const AZURE_VERSION: &str = "2017-02-22";
+// The content type of every write, queries aside: see `set_query_headers`.
+const JSON_CONTENT_TYPE: &str = "application/json";
 const VERSION: &str = "1.0";
-const TIME_FORMAT: &str = "%a, %d %h %Y %T GMT";
 
 pub(crate) mod headers {
     pub const HEADER_VERSION: &str = "x-ms-version"; // Cow[str]
     pub const HEADER_DATE: &str = "x-ms-date"; // [String]
     pub const HEADER_OFFER_THROUGHPUT: &str = "x-ms-offer-throughput"; // [u64]
+    pub const HEADER_OFFER_AUTOPILOT_SETTINGS: &str = "x-ms-cosmos-offer-autopilot-settings"; // [json]
+    pub const HEADER_COSMOS_MIN_THROUGHPUT: &str = "x-ms-cosmos-min-throughput"; // [u64]
     pub const HEADER_DOCUMENTDB_IS_UPSERT: &str = "x-ms-documentdb-is-upsert"; // [bool]
     pub const HEADER_INDEXING_DIRECTIVE: &str = "x-ms-indexing-directive"; // [IndexingDirective]
     pub const HEADER_MAX_ITEM_COUNT: &str = "x-ms-max-item-count"; // [u64]
@@ -51,6 +77,19 @@ pub(crate) mod headers {
     pub const HEADER_DOCUMENTDB_PARTITIONKEY: &str = "x-ms-documentdb-partitionkey"; // [String]
     pub const HEADER_DOCUMENTDB_ISQUERY: &str = "x-ms-documentdb-isquery"; // [bool]
     pub const HEADER_DOCUMENTDB_QUERY_ENABLECROSSPARTITION: &str = "x-ms-documentdb-query-enablecrosspartition"; // [bool]
+    pub const HEADER_DOCUMENTDB_QUERY_ISCONTINUATIONEXPECTED: &str = "x-ms-documentdb-query-iscontinuationexpected"; // [bool]
+    pub const HEADER_PREFER: &str = "Prefer"; // [&str]
+    pub const HEADER_RETRY_AFTER_MS: &str = "x-ms-retry-after-ms"; // [u64]
+    pub const HEADER_SUB_STATUS: &str = "x-ms-substatus"; // [u32]
+    pub const HEADER_DOCUMENTDB_POPULATE_QUOTA_INFO: &str = "x-ms-documentdb-populatequotainfo"; // [bool]
+    pub const HEADER_ALLOW_TENTATIVE_WRITES: &str = "x-ms-cosmos-allow-tentative-writes"; // [bool]
+    pub const HEADER_DOCUMENTDB_POPULATE_QUERY_METRICS: &str = "x-ms-documentdb-populatequerymetrics"; // [bool]
+    pub const HEADER_DOCUMENTDB_QUERY_METRICS: &str = "x-ms-documentdb-query-metrics"; // [QueryMetrics]
+    pub const HEADER_SUPPORTED_QUERY_FEATURES: &str = "x-ms-cosmos-supported-query-features"; // [String]
+    pub const HEADER_QUERY_PLAN: &str = "x-ms-cosmos-query-plan"; // [QueryPlan]
+    pub const HEADER_RESOURCE_QUOTA: &str = "x-ms-resource-quota"; // [QuotaInfo]
+    pub const HEADER_RESOURCE_USAGE: &str = "x-ms-resource-usage"; // [QuotaInfo]
+    pub const HEADER_PRIORITY_LEVEL: &str = "x-ms-cosmos-priority-level"; // [PriorityLevel]
 }
 use self::headers::*;
 
@@ -60,33 +99,191 @@ enum ResourceType {
     Collections,
     Documents,
     StoredProcedures,
+    Conflicts,
+    Offers,
+    PartitionKeyRanges,
+    Attachments,
+    DatabaseAccount,
 }
 
+#[derive(Clone)]
 pub struct Client {
-    hyper_client: Arc<hyper::Client<HttpsConnector<hyper::client::HttpConnector>>>,
+    hyper_client: BudgetedHyperClient,
     auth_token: AuthorizationToken,
+    // shared by the clones, so a failover is seen by all of them
+    endpoint: Arc<RwLock<String>>,
+    multiple_write_locations: Arc<AtomicBool>,
+    throttle_retry: bool,
 }
 
 impl Client {
     pub fn new(auth_token: AuthorizationToken) -> Result<Client, AzureError> {
-        let client = hyper::Client::builder().build(HttpsConnector::new(4)?);
+        Client::with_builder(auth_token, &ClientBuilder::default())
+    }
+
+    pub fn with_builder(auth_token: AuthorizationToken, builder: &ClientBuilder) -> Result<Client, AzureError> {
+        let client = builder.build_hyper_client()?;
+        Ok(Client::with_hyper_client(auth_token, builder, client))
+    }
+
+    pub(crate) fn with_hyper_client(auth_token: AuthorizationToken, builder: &ClientBuilder, hyper_client: HyperClient) -> Client {
+        let endpoint = format!("https://{}.{}/", auth_token.account(), builder.cloud().cosmos_suffix());
 
-        Ok(Client {
-            hyper_client: Arc::new(client),
+        Client {
+            hyper_client: BudgetedHyperClient::new(hyper_client),
             auth_token,
-        })
+            endpoint: Arc::new(RwLock::new(endpoint)),
+            multiple_write_locations: Arc::new(AtomicBool::new(false)),
+            throttle_retry: true,
+        }
+    }
+
+    /// Builds a client with the master key of the account, reading them
+    /// from `COSMOS_ACCOUNT` and `COSMOS_MASTER_KEY`.
+    pub fn from_env() -> Result<Client, AzureError> {
+        Client::from_lookup(|name| env::var(name).ok())
+    }
+
+    // `from_env` reading the variables with `lookup`, see `lookup_var`.
+    fn from_lookup<L: Fn(&str) -> Option<String>>(lookup: L) -> Result<Client, AzureError> {
+        let account = lookup_var(&lookup, "COSMOS_ACCOUNT")?;
+        let master_key = lookup_var(&lookup, "COSMOS_MASTER_KEY")?;
+        Client::new(AuthorizationToken::new(account, TokenType::Master, &master_key)?)
     }
 
     pub fn set_auth_token(&mut self, at: AuthorizationToken) {
         self.auth_token = at;
     }
 
-    fn list_databases_create_request(&self) -> Result<hyper::client::ResponseFuture, AzureError> {
+    /// Adds the request charge of every following operation to `ru_budget`.
+    pub fn set_ru_budget(&mut self, ru_budget: Option<RuBudget>) {
+        self.hyper_client.set_ru_budget(ru_budget);
+    }
+
+    pub fn ru_budget(&self) -> Option<&RuBudget> {
+        self.hyper_client.ru_budget()
+    }
+
+    /// The endpoint the requests are sent to, by default the global
+    /// endpoint of the account.
+    pub fn endpoint(&self) -> String {
+        self.endpoint.read().unwrap().clone()
+    }
+
+    pub fn set_endpoint<S: Into<String>>(&self, endpoint: S) {
+        let mut endpoint = endpoint.into();
+        if !endpoint.ends_with('/') {
+            endpoint.push('/');
+        }
+        *self.endpoint.write().unwrap() = endpoint;
+    }
+
+    /// Whether the account accepts writes in every region. When set, the
+    /// writes are sent with `x-ms-cosmos-allow-tentative-writes`.
+    pub fn multiple_write_locations(&self) -> bool {
+        self.multiple_write_locations.load(Ordering::Relaxed)
+    }
+
+    pub fn set_multiple_write_locations(&self, value: bool) {
+        self.multiple_write_locations.store(value, Ordering::Relaxed);
+    }
+
+    /// Stops the bulk operations of the client from sending a throttled
+    /// request again after the `retry_after` Cosmos asks for: they fail with
+    /// the `ThrottledError` of the first 429 instead, leaving the retry
+    /// policy to the caller.
+    pub fn no_throttle_retry(mut self) -> Client {
+        self.throttle_retry = false;
+        self
+    }
+
+    fn throttled_attempts(&self) -> u32 {
+        if self.throttle_retry {
+            MAX_THROTTLED_ATTEMPTS
+        } else {
+            1
+        }
+    }
+
+    /// Reads the account properties, including its regional endpoints. It
+    /// also records whether the account has multiple write locations.
+    pub fn get_database_account(&self) -> impl Future<Item = DatabaseAccount, Error = AzureError> {
+        trace!("get_database_account called");
+
+        let req = UriBuilder::new().build().and_then(|uri| {
+            let request = self
+                .prepare_request(&uri, hyper::Method::GET, ResourceType::DatabaseAccount)
+                .body(hyper::Body::empty())?;
+            Ok(self.hyper_client.request(request))
+        });
+
+        let multiple_write_locations = self.multiple_write_locations.clone();
+        done(req)
+            .from_err()
+            .and_then(move |future_response| {
+                check_status_extract_body(future_response, StatusCode::OK)
+                    .and_then(move |body| done(serde_json::from_str::<DatabaseAccount>(&body)).from_err())
+            })
+            .map(move |account| {
+                multiple_write_locations.store(account.enable_multiple_write_locations, Ordering::Relaxed);
+                account
+            })
+    }
+
+    /// Reads the account properties as a cheap readiness probe. Rejected
+    /// credentials give `AzureError::Unauthorized`; a service that cannot be
+    /// reached gives an error for which `is_connection_error` holds.
+    pub fn health_check(&self) -> impl Future<Item = (), Error = AzureError> {
+        trace!("health_check called");
+
+        let req = UriBuilder::new().build().and_then(|uri| {
+            let request = self
+                .prepare_request(&uri, hyper::Method::GET, ResourceType::DatabaseAccount)
+                .body(hyper::Body::empty())?;
+            Ok(self.hyper_client.request(request))
+        });
+
+        done(req)
+            .from_err()
+            .and_then(extract_status_and_body)
+            .and_then(|(status, body)| match status {
+                StatusCode::OK => Ok(()),
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(AzureError::Unauthorized(body)),
+                _ => Err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(
+                    StatusCode::OK,
+                    status,
+                    &body,
+                ))),
+            })
+    }
+
+    /// Runs `operation` following the account failovers: after repeated
+    /// 503 responses the account locations are read again and the client
+    /// moves to the next regional endpoint, as configured by `policy`.
+    pub fn with_failover<F, R>(&self, policy: FailoverPolicy, operation: F) -> impl Future<Item = R::Item, Error = AzureError>
+    where
+        F: Fn(&Client) -> R + 'static,
+        R: IntoFuture<Error = AzureError> + 'static,
+        R::Future: 'static,
+    {
+        let client = self.clone();
+        let locate_client = self.clone();
+
+        follow_failover(
+            policy,
+            self.endpoint.clone(),
+            move || Box::new(operation(&client).into_future()),
+            move || Box::new(locate_client.get_database_account().map(|account| account.endpoints())),
+        )
+    }
+
+    fn list_databases_create_request(&self) -> Result<BudgetedResponseFuture, AzureError> {
         // No specific headers are required, list databases only needs standard headers
         // which will be provied by perform_request. This is handled by passing an
         // empty closure.
+        let uri = UriBuilder::new().feed("dbs").build()?;
         let request = self
-            .prepare_request("dbs", hyper::Method::GET, ResourceType::Databases)
+            .prepare_request(&uri, hyper::Method::GET, ResourceType::Databases)
             .body(hyper::Body::empty())?;
 
         trace!("request prepared");
@@ -112,16 +309,14 @@ impl Client {
     }
 
     #[inline]
-    fn list_collections_create_request(&self, database_name: &str) -> Result<hyper::client::ResponseFuture, AzureError> {
+    fn list_collections_create_request(&self, database_name: &str) -> Result<BudgetedResponseFuture, AzureError> {
         // No specific headers are required, list collections only needs standard headers
         // which will be provied by perform_request. This is handled by passing an
         // empty closure.
+        let uri = UriBuilder::new().database(database_name).feed("colls").build()?;
         let request = self
-            .prepare_request(
-                &format!("dbs/{}/colls", database_name),
-                hyper::Method::GET,
-                ResourceType::Collections,
-            ).body(hyper::Body::empty())?;
+            .prepare_request(&uri, hyper::Method::GET, ResourceType::Collections)
+            .body(hyper::Body::empty())?;
 
         trace!("request prepared");
 
@@ -142,41 +337,153 @@ impl Client {
         })
     }
 
-    #[inline]
-    fn create_database_create_request(&self, database_name: &str) -> Result<hyper::client::ResponseFuture, AzureError> {
-        #[derive(Serialize, Debug)]
-        pub struct CreateDatabaseRequest<'a> {
-            pub id: &'a str,
-        }
+    fn query_offer_create_request(&self, resource_rid: &str) -> Result<BudgetedResponseFuture, AzureError> {
+        let uri = UriBuilder::new().feed("offers").build()?;
+        let mut request = self.prepare_request(&uri, hyper::Method::POST, ResourceType::Offers);
+        set_query_headers(&mut request);
+        let request = request.body(offer_query(resource_rid)?.into())?;
 
-        let req = serde_json::to_string(&CreateDatabaseRequest { id: database_name })?;
+        Ok(self.hyper_client.request(request))
+    }
 
-        let request = self
-            .prepare_request("dbs", hyper::Method::POST, ResourceType::Databases)
-            .body(req.into())?; // todo: set content-length here and elsewhere without builders
+    /// Reads the offer of a database or a collection given its `_rid`.
+    /// Databases without shared throughput and the collections relying on
+    /// the throughput of their database have none.
+    pub fn get_offer(&self, resource_rid: &str) -> impl Future<Item = Option<Offer>, Error = AzureError> {
+        trace!("get_offer called");
 
-        trace!("request prepared");
+        let req = self.query_offer_create_request(resource_rid);
 
-        Ok(self.hyper_client.request(request))
+        done(req).and_then(move |future_response| {
+            check_status_extract_body(future_response, StatusCode::OK).and_then(move |body| {
+                done(serde_json::from_str::<ListOffersResponse>(&body))
+                    .from_err()
+                    .and_then(|offers_response| ok(offers_response.offers.into_iter().next()))
+            })
+        })
     }
 
-    pub fn create_database(&self, database_name: &str) -> impl Future<Item = Database, Error = AzureError> {
-        trace!("create_databases called (database_name == {})", database_name);
+    /// Changes the throughput of the database or collection of the offer.
+    /// Lowering it below the minimum Cosmos allows, which grows with the
+    /// storage used and the number of collections, fails with
+    /// `AzureError::ThroughputTooLowError` carrying that minimum.
+    pub fn replace_offer(&self, offer: &Offer, throughput: u64) -> impl Future<Item = Offer, Error = AzureError> {
+        trace!("replace_offer called(offer == {}, throughput == {})", offer.id, throughput);
 
-        let req = self.create_database_create_request(database_name);
+        let mut offer = offer.clone();
+        offer.content.offer_throughput = throughput;
+        self.send_offer(&offer, throughput)
+    }
 
-        done(req).from_err().and_then(move |future_response| {
-            check_status_extract_body(future_response, StatusCode::CREATED)
-                .and_then(move |body| done(serde_json::from_str::<Database>(&body)).from_err())
+    /// Changes the maximum throughput of an autoscale offer, such as the
+    /// one of a collection created with
+    /// `CreateCollectionRequest::autoscale_max_throughput`. `max_throughput`
+    /// must be a multiple of 1000 and the offer must already be an
+    /// autoscale one: Cosmos does not switch an offer between a fixed
+    /// throughput and autoscale on replace.
+    pub fn replace_offer_autoscale(&self, offer: &Offer, max_throughput: i32) -> impl Future<Item = Offer, Error = AzureError> {
+        trace!(
+            "replace_offer_autoscale called(offer == {}, max_throughput == {})",
+            offer.id,
+            max_throughput
+        );
+
+        let mut offer = offer.clone();
+        if let Err(error) = check_autoscale_max_throughput(max_throughput) {
+            return Either::A(err(error));
+        }
+        match offer.content.offer_autopilot_settings {
+            Some(ref mut settings) => settings.max_throughput = max_throughput,
+            None => {
+                return Either::A(err(AzureError::InputParametersError(format!(
+                    "the offer {} has a fixed throughput, not an autoscale one",
+                    offer.id
+                ))))
+            }
+        }
+        Either::B(self.send_offer(&offer, max_throughput as u64))
+    }
+
+    fn send_offer(&self, offer: &Offer, requested: u64) -> impl Future<Item = Offer, Error = AzureError> {
+        let req = serde_json::to_string(offer).map_err(AzureError::from).and_then(|payload| {
+            let request = self
+                .prepare_request_with_resource_link(
+                    &format!("offers/{}", offer.rid),
+                    hyper::Method::PUT,
+                    ResourceType::Offers,
+                    &offer.rid.to_lowercase(),
+                )
+                .body(payload.into())?;
+            Ok(self.hyper_client.request(request))
+        });
+
+        done(req)
+            .and_then(extract_status_headers_and_body)
+            .and_then(move |(status, headers, body)| replaced_offer(status, &headers, &body, requested))
+    }
+
+    /// Reads the throughput of every collection of the account, sorted by
+    /// database and collection, as needed to audit its cost. At most
+    /// `concurrency` requests are in flight at once.
+    pub fn list_throughput(&self, concurrency: usize) -> impl Future<Item = Vec<(String, String, Throughput)>, Error = AzureError> {
+        trace!("list_throughput called");
+
+        let client = self.clone();
+        self.list_databases().and_then(move |databases| {
+            let lister = client.clone();
+            stream::iter_ok(databases)
+                .map(move |database| lister.list_collections(&database.id).map(|collections| (database, collections)))
+                .buffer_unordered(concurrency)
+                .collect()
+                .and_then(move |databases| {
+                    // the databases have an offer too, when their throughput is shared
+                    let rids = databases
+                        .iter()
+                        .flat_map(|(database, collections): &(Database, Vec<Collection>)| {
+                            Some(database.rid.clone())
+                                .into_iter()
+                                .chain(collections.iter().map(|c| c.rid.clone()))
+                        })
+                        .collect::<Vec<_>>();
+
+                    stream::iter_ok(rids)
+                        .map(move |rid| client.get_offer(&rid).map(|offer| (rid, offer)))
+                        .buffer_unordered(concurrency)
+                        .collect()
+                        .map(move |offers| collections_throughput(&databases, &offers.into_iter().collect()))
+                })
         })
     }
 
+    /// The database with the given id, to navigate to its collections and
+    /// their documents.
+    pub fn database<S: Into<String>>(&self, database_name: S) -> DatabaseRef<'_> {
+        DatabaseRef::new(self, database_name)
+    }
+
+    pub fn create_database(&self, database_name: &str) -> CreateDatabaseRequest {
+        #[derive(Serialize, Debug)]
+        struct DatabaseBody<'a> {
+            id: &'a str,
+        }
+
+        trace!("create_databases called (database_name == {})", database_name);
+
+        let req = UriBuilder::new()
+            .feed("dbs")
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::POST, ResourceType::Databases));
+        let payload = serde_json::to_string(&DatabaseBody { id: database_name });
+        CreateDatabaseRequest::new(self.hyper_client.clone(), req, payload)
+    }
+
     #[inline]
-    fn get_database_create_request(&self, database_name: &str) -> Result<hyper::client::ResponseFuture, AzureError> {
+    fn get_database_create_request(&self, database_name: &str) -> Result<BudgetedResponseFuture, AzureError> {
         // No specific headers are required, get database only needs standard headers
         // which will be provied by perform_request
+        let uri = UriBuilder::new().database(database_name).build()?;
         let request = self
-            .prepare_request(&format!("dbs/{}", database_name), hyper::Method::GET, ResourceType::Databases)
+            .prepare_request(&uri, hyper::Method::GET, ResourceType::Databases)
             .body(hyper::Body::empty())?;
 
         trace!("request prepared");
@@ -196,11 +503,12 @@ impl Client {
     }
 
     #[inline]
-    fn delete_database_create_request(&self, database_name: &str) -> Result<hyper::client::ResponseFuture, AzureError> {
+    fn delete_database_create_request(&self, database_name: &str) -> Result<BudgetedResponseFuture, AzureError> {
         // No specific headers are required, delete database only needs standard headers
         // which will be provied by perform_request
+        let uri = UriBuilder::new().database(database_name).build()?;
         let request = self
-            .prepare_request(&format!("dbs/{}", database_name), hyper::Method::DELETE, ResourceType::Databases)
+            .prepare_request(&uri, hyper::Method::DELETE, ResourceType::Databases)
             .body(hyper::Body::empty())?;
 
         trace!("request prepared");
@@ -218,103 +526,83 @@ impl Client {
             .and_then(move |future_response| check_status_extract_body(future_response, StatusCode::NO_CONTENT).and_then(|_| ok(())))
     }
 
-    #[inline]
-    fn get_collection_create_request(
-        &self,
-        database_name: &str,
-        collection_name: &str,
-    ) -> Result<hyper::client::ResponseFuture, AzureError> {
-        // No specific headers are required, get database only needs standard headers
-        // which will be provied by perform_request
-        let request = self
-            .prepare_request(
-                &format!("dbs/{}/colls/{}", database_name, collection_name),
-                hyper::Method::GET,
-                ResourceType::Collections,
-            ).body(hyper::Body::empty())?;
-
-        trace!("request prepared");
-
-        Ok(self.hyper_client.request(request))
+    /// Creates the database unless one with the same id exists already,
+    /// returning the database either way. Meant for the start of an
+    /// application, which can then run many times.
+    pub fn create_database_if_not_exists(&self, database_name: &str) -> impl Future<Item = Database, Error = AzureError> {
+        let client = self.clone();
+        let database_name = database_name.to_owned();
+
+        self.create_database(&database_name).execute().or_else(move |error| {
+            if error.is_conflict() {
+                Either::A(client.get_database(&database_name))
+            } else {
+                Either::B(err(error))
+            }
+        })
     }
 
-    pub fn get_collection(&self, database_name: &str, collection_name: &str) -> impl Future<Item = Collection, Error = AzureError> {
+    pub fn get_collection(&self, database_name: &str, collection_name: &str) -> GetCollectionRequest {
         trace!(
             "get_collection called (database_name == {}, collection_name == {})",
             database_name,
             collection_name
         );
 
-        let req = self.get_collection_create_request(database_name, collection_name);
+        // No specific headers are required, get collection only needs standard headers
+        // which will be provied by perform_request
+        let req = UriBuilder::new()
+            .database(database_name)
+            .collection(collection_name)
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::GET, ResourceType::Collections));
 
-        done(req).from_err().and_then(move |future_response| {
-            check_status_extract_body(future_response, StatusCode::OK)
-                .and_then(move |body| done(serde_json::from_str::<Collection>(&body)).from_err())
-        })
+        GetCollectionRequest::new(self.hyper_client.clone(), req)
     }
 
-    #[inline]
-    fn create_collection_create_request(
-        &self,
-        database_name: &str,
-        required_throughput: u64,
-        collection: &Collection,
-    ) -> Result<hyper::client::ResponseFuture, AzureError> {
+    pub fn create_collection(&self, database_name: &str, collection: &Collection) -> CreateCollectionRequest {
+        trace!(
+            "create_collection(database_name == {:?}, collection == {:?} called",
+            database_name,
+            collection
+        );
+
         // Headers added as per
         // https://docs.microsoft.com/en-us/rest/api/documentdb/create-a-collection
         // Standard headers (auth and version) will be provied by perform_request
-        let collection_serialized = serde_json::to_string(collection)?;
-        trace!("collection_serialized == {}", collection_serialized);
+        let collection_serialized = serde_json::to_string(collection);
+        trace!("collection_serialized == {:?}", collection_serialized);
 
-        let mut request = self.prepare_request(
-            &format!("dbs/{}/colls", database_name),
-            hyper::Method::POST,
-            ResourceType::Collections,
-        );
-        request.header_formatted(HEADER_OFFER_THROUGHPUT, required_throughput);
-        let request = request.body(collection_serialized.into())?;
+        let req = UriBuilder::new()
+            .database(database_name)
+            .feed("colls")
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::POST, ResourceType::Collections));
 
-        trace!("request prepared");
-
-        Ok(self.hyper_client.request(request))
+        CreateCollectionRequest::new(self.hyper_client.clone(), req, collection_serialized)
     }
 
-    pub fn create_collection(
-        &self,
-        database_name: &str,
-        required_throughput: u64,
-        collection: &Collection,
-    ) -> impl Future<Item = Collection, Error = AzureError> {
-        trace!(
-            "create_collection(database_name == {:?}, \
-             required_throughput == {:?}, collection == {:?} called",
-            database_name,
-            required_throughput,
-            collection
-        );
-
-        let req = self.create_collection_create_request(database_name, required_throughput, collection);
-
-        done(req).from_err().and_then(move |future_response| {
-            check_status_extract_body(future_response, StatusCode::CREATED)
-                .and_then(move |body| done(serde_json::from_str::<Collection>(&body)).from_err())
-        })
+    /// Creates the collection unless one with the same id exists already in
+    /// the database, returning the collection either way, as
+    /// `create_database_if_not_exists` does. The existing collection must
+    /// have the partition key of `collection`, it cannot be changed once
+    /// the collection is created and the documents of the application would
+    /// land in the wrong partitions otherwise. The other options of the
+    /// request only apply when the collection is created.
+    pub fn create_collection_if_not_exists(&self, database_name: &str, collection: &Collection) -> CreateCollectionRequest {
+        let existing = self.get_collection(database_name, &collection.id);
+        self.create_collection(database_name, collection)
+            .if_not_exists(existing, collection.parition_key.clone())
     }
 
     #[inline]
-    fn delete_collection_create_request(
-        &self,
-        database_name: &str,
-        collection_name: &str,
-    ) -> Result<hyper::client::ResponseFuture, AzureError> {
+    fn delete_collection_create_request(&self, database_name: &str, collection_name: &str) -> Result<BudgetedResponseFuture, AzureError> {
         // No specific headers are required.
         // Standard headers (auth and version) will be provied by perform_request
+        let uri = UriBuilder::new().database(database_name).collection(collection_name).build()?;
         let request = self
-            .prepare_request(
-                &format!("dbs/{}/colls/{}", database_name, collection_name),
-                hyper::Method::DELETE,
-                ResourceType::Collections,
-            ).body(hyper::Body::empty())?;
+            .prepare_request(&uri, hyper::Method::DELETE, ResourceType::Collections)
+            .body(hyper::Body::empty())?;
 
         trace!("request prepared");
 
@@ -335,49 +623,32 @@ impl Client {
             .and_then(move |future_response| check_status_extract_body(future_response, StatusCode::NO_CONTENT).and_then(|_| ok(())))
     }
 
-    #[inline]
-    fn replace_collection_prepare_request(
-        &self,
-        database_name: &str,
-        collection: &str,
-    ) -> Result<hyper::client::ResponseFuture, AzureError> {
+    pub fn replace_collection(&self, database_name: &str, collection: &Collection) -> ReplaceCollectionRequest {
+        trace!("replace_collection called");
+
         // No specific headers are required.
         // Standard headers (auth and version) will be provied by perform_request
-        let collection_serialized = serde_json::to_string(collection)?;
-        trace!("collection_serialized == {}", collection_serialized);
-
-        let request = self
-            .prepare_request(
-                &format!("dbs/{}/colls", database_name),
-                hyper::Method::PUT,
-                ResourceType::Collections,
-            ).body(collection_serialized.into())?;
-
-        trace!("request prepared");
-
-        Ok(self.hyper_client.request(request))
-    }
+        let collection_serialized = serde_json::to_string(collection);
+        trace!("collection_serialized == {:?}", collection_serialized);
 
-    pub fn replace_collection(&self, database_name: &str, collection: &str) -> impl Future<Item = Collection, Error = AzureError> {
-        trace!("replace_collection called");
-
-        let req = self.replace_collection_prepare_request(database_name, collection);
+        let req = UriBuilder::new()
+            .database(database_name)
+            .collection(&collection.id)
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::PUT, ResourceType::Collections));
 
-        done(req).from_err().and_then(move |future_response| {
-            check_status_extract_body(future_response, StatusCode::CREATED)
-                .and_then(move |body| done(serde_json::from_str::<Collection>(&body)).from_err())
-        })
+        ReplaceCollectionRequest::new(self.hyper_client.clone(), req, collection_serialized)
     }
 
     #[inline]
-    fn create_document_as_str_create_request(&self, database: &str, collection: &str) -> RequestBuilder {
-        let uri = format!("dbs/{}/colls/{}/docs", database, collection);
+    fn create_document_as_str_create_request(&self, database: &str, collection: &str) -> Result<RequestBuilder, AzureError> {
+        let uri = UriBuilder::new().database(database).collection(collection).feed("docs").build()?;
 
         let request = self.prepare_request(&uri, hyper::Method::POST, ResourceType::Documents);
 
         trace!("request prepared");
 
-        request
+        Ok(request)
     }
 
     pub fn create_document_as_str<T, S1, S2, S3>(&self, database: S1, collection: S2, document: S3) -> CreateDocumentRequest
@@ -434,14 +705,12 @@ impl Client {
             document_id.as_ref()
         );
 
-        let uri = format!(
-            "dbs/{}/colls/{}/docs/{}",
-            database_id.as_ref(),
-            collection_id.as_ref(),
-            document_id.as_ref()
-        );
-
-        let req = self.prepare_request(&uri, hyper::Method::DELETE, ResourceType::Documents);
+        let req = UriBuilder::new()
+            .database(database_id.as_ref())
+            .collection(collection_id.as_ref())
+            .document(document_id.as_ref())
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::DELETE, ResourceType::Documents));
         DeleteDocumentRequest::new(self.hyper_client.clone(), req)
     }
 
@@ -460,12 +729,20 @@ impl Client {
             document_serialized,
         );
 
-        let req = self.prepare_request_with_resource_link(
-            &document.document_attributes._self,
-            hyper::Method::PUT,
-            ResourceType::Documents,
-            &document.document_attributes.rid.to_lowercase(),
-        );
+        // the document is addressed by its _self link, only known once read
+        // from Cosmos
+        let req = if document.document_attributes._self.is_empty() {
+            Err(AzureError::InputParametersError(
+                "the document to replace has no _self link, it must be read from Cosmos first".to_owned(),
+            ))
+        } else {
+            Ok(self.prepare_request_with_resource_link(
+                &document.document_attributes._self,
+                hyper::Method::PUT,
+                ResourceType::Documents,
+                &document.document_attributes.rid.to_lowercase(),
+            ))
+        };
 
         ReplaceDocumentRequest::new(self.hyper_client.clone(), req, document_serialized)
     }
@@ -476,15 +753,74 @@ impl Client {
 
         trace!("list_documents called(database == {}, collection == {}", database, collection);
 
-        let req = self.prepare_request(
-            &format!("dbs/{}/colls/{}/docs", database, collection),
-            hyper::Method::GET,
-            ResourceType::Documents,
-        );
+        let req = UriBuilder::new()
+            .database(database)
+            .collection(collection)
+            .feed("docs")
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::GET, ResourceType::Documents));
 
         ListDocumentsRequest::new(self.hyper_client.clone(), req)
     }
 
+    /// Lists the partition key ranges of the collection, one per physical
+    /// partition. A range splits when its partition grows, so the list
+    /// changes over time.
+    pub fn list_partition_key_ranges<S1: AsRef<str>, S2: AsRef<str>>(
+        &self,
+        database: S1,
+        collection: S2,
+    ) -> impl Future<Item = Vec<PartitionKeyRange>, Error = AzureError> {
+        trace!("list_partition_key_ranges called");
+
+        let req = UriBuilder::new()
+            .database(database.as_ref())
+            .collection(collection.as_ref())
+            .feed("pkranges")
+            .build()
+            .and_then(|uri| {
+                Ok(self
+                    .prepare_request(&uri, hyper::Method::GET, ResourceType::PartitionKeyRanges)
+                    .body(hyper::Body::empty())?)
+            })
+            .map(|request| self.hyper_client.request(request));
+
+        done(req).and_then(move |future_response| {
+            check_status_extract_body(future_response, StatusCode::OK).and_then(move |body| {
+                done(serde_json::from_str::<ListPartitionKeyRangesResponse>(&body))
+                    .from_err()
+                    .map(|response| response.partition_key_ranges)
+            })
+        })
+    }
+
+    /// Lists the attachments of `document`, following its `_attachments`
+    /// link. A document without attachments has an empty list.
+    pub fn list_attachments<T>(&self, document: &Document<T>) -> impl Future<Item = Vec<Attachment>, Error = AzureError> {
+        trace!("list_attachments called");
+
+        let attributes = &document.document_attributes;
+        // the link is made of resource ids: the signature is computed on the
+        // one of the document
+        let request = self
+            .prepare_request_with_resource_link(
+                attributes.attachments_link().trim_end_matches('/'),
+                hyper::Method::GET,
+                ResourceType::Attachments,
+                &attributes.rid.to_lowercase(),
+            )
+            .body(hyper::Body::empty())
+            .map(|request| self.hyper_client.request(request));
+
+        done(request).from_err().and_then(move |future_response| {
+            check_status_extract_body(future_response, StatusCode::OK).and_then(move |body| {
+                done(serde_json::from_str::<ListAttachmentsResponse>(&body))
+                    .from_err()
+                    .map(|response| response.attachments)
+            })
+        })
+    }
+
     pub fn get_document<S1, S2, S3>(&self, database: S1, collection: S2, document_id: S3) -> GetDocumentRequest
     where
         S1: AsRef<str>,
@@ -495,11 +831,12 @@ impl Client {
         let coll = collection.as_ref();
         let doc_id = document_id.as_ref();
 
-        let req = self.prepare_request(
-            &format!("dbs/{}/colls/{}/docs/{}", db, coll, doc_id),
-            hyper::Method::GET,
-            ResourceType::Documents,
-        );
+        let req = UriBuilder::new()
+            .database(db)
+            .collection(coll)
+            .document(doc_id)
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::GET, ResourceType::Documents));
 
         GetDocumentRequest::new(self.hyper_client.clone(), req)
     }
@@ -513,15 +850,393 @@ impl Client {
         let database = database.as_ref();
         let collection = collection.as_ref();
 
-        let req = self.prepare_request(
-            &format!("dbs/{}/colls/{}/docs", database, collection),
-            hyper::Method::POST,
-            ResourceType::Documents,
-        );
+        let req = UriBuilder::new()
+            .database(database)
+            .collection(collection)
+            .feed("docs")
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::POST, ResourceType::Documents));
         let query_json = serde_json::to_string(query);
         QueryDocumentRequest::new(self.hyper_client.clone(), req, query_json)
     }
 
+    /// Runs the query across partitions, following the continuation from
+    /// page to page, and yields the documents one at a time. Each page is
+    /// kept as the raw body returned by Cosmos and split into documents
+    /// lazily (see `PageDocuments`): at most one page is held in memory and
+    /// it is never turned into a vector of documents.
+    pub fn stream_query_documents<'b, S1: AsRef<str>, S2: AsRef<str>>(
+        &self,
+        database: S1,
+        collection: S2,
+        query: &Query<'b>,
+    ) -> impl Stream<Item = serde_json::Value, Error = AzureError> {
+        let uri = UriBuilder::new()
+            .database(database.as_ref())
+            .collection(collection.as_ref())
+            .feed("docs")
+            .build();
+        let query_json = serde_json::to_string(query).map_err(AzureError::from);
+        let client = self.clone();
+
+        stream::once(uri.and_then(|uri| query_json.map(|query_json| (uri, query_json))))
+            .map(move |(uri, query_json)| {
+                let client = client.clone();
+                continuation_pages(move |continuation| {
+                    let req = client.prepare_request(&uri, hyper::Method::POST, ResourceType::Documents);
+                    let mut request = QueryDocumentRequest::new(client.hyper_client.clone(), Ok(req), Ok(query_json.clone()))
+                        .enable_cross_partition(true);
+                    if let Some(continuation) = continuation {
+                        request = request.continuation_token(continuation);
+                    }
+
+                    Box::new(
+                        request
+                            .execute_raw()
+                            .map(|page| (page.body, page.additional_headers.continuation_token)),
+                    )
+                })
+            })
+            .flatten()
+            .map(|body| stream::iter_result(PageDocuments::new(body)))
+            .flatten()
+    }
+
+    /// Queries the documents created or modified after `since`, oldest
+    /// first, the usual query of an incremental sync. The query runs on
+    /// every partition key range and their pages are merged on `_ts` (see
+    /// `cross_partition::merge_with_query_plan`). `_ts` only counts whole
+    /// seconds: the documents modified during the second of `since` are not
+    /// returned, so pass the `_ts` of the last document synced rather than
+    /// the time of the sync.
+    pub fn changed_since<T, S1, S2>(
+        &self,
+        database: S1,
+        collection: S2,
+        since: &chrono::DateTime<chrono::Utc>,
+    ) -> impl Future<Item = CrossPartitionQueryResponse<Document<T>>, Error = AzureError>
+    where
+        T: DeserializeOwned,
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+    {
+        let query = changed_since_query(since).map_err(AzureError::from);
+        let query_plan = changed_since_query_plan();
+        let client = self.clone();
+        let database = database.as_ref().to_owned();
+        let collection = collection.as_ref().to_owned();
+
+        done(query.and_then(|query| query_plan.map(|query_plan| (query, query_plan))))
+            .and_then(move |(query, query_plan)| client.query_partitions(&database, &collection, &query, query_plan))
+            .and_then(|response| {
+                Ok(CrossPartitionQueryResponse {
+                    results: response
+                        .results
+                        .into_iter()
+                        .map(Document::from_value)
+                        .collect::<Result<Vec<_>, _>>()?,
+                    charge: response.charge,
+                    query_metrics: response.query_metrics,
+                })
+            })
+    }
+
+    // Runs the query on every partition key range of the collection, the
+    // rewritten query of the plan if it has one, and combines the results and
+    // the query metrics of the ranges as the plan describes.
+    fn query_partitions(
+        &self,
+        database: &str,
+        collection: &str,
+        query_json: &str,
+        query_plan: QueryPlan,
+    ) -> impl Future<Item = CrossPartitionQueryResponse<serde_json::Value>, Error = AzureError> {
+        let uri = UriBuilder::new().database(database).collection(collection).feed("docs").build();
+        let query_json = partition_query(query_json, &query_plan);
+        let ranges = self.list_partition_key_ranges(database.to_owned(), collection.to_owned());
+        let client = self.clone();
+
+        done(uri.and_then(|uri| query_json.map(|query_json| (uri, query_json))))
+            .and_then(move |(uri, query_json)| {
+                ranges.and_then(move |ranges| {
+                    let partitions = ranges
+                        .into_iter()
+                        .map(|range| client.query_partition_key_range(uri.clone(), query_json.clone(), range.id))
+                        .collect::<Vec<_>>();
+                    join_all(partitions)
+                })
+            })
+            .and_then(move |partitions| {
+                let charge = partitions.iter().map(|(_, charge, _)| charge).sum();
+                let query_metrics = partitions.iter().filter_map(|(_, _, metrics)| metrics.as_ref()).collect();
+                let partitions = partitions.into_iter().map(|(results, _, _)| results).collect();
+                Ok(CrossPartitionQueryResponse {
+                    results: merge_with_query_plan(partitions, &query_plan)?,
+                    charge,
+                    query_metrics,
+                })
+            })
+    }
+
+    // Reads every page of the query from one partition key range, along
+    // with their charge and their query metrics added up, if Cosmos sent
+    // any.
+    fn query_partition_key_range(
+        &self,
+        uri: ResourceUri,
+        query_json: String,
+        range_id: String,
+    ) -> impl Future<Item = (Vec<serde_json::Value>, f64, Option<QueryMetrics>), Error = AzureError> {
+        let client = self.clone();
+
+        loop_fn(
+            (Vec::new(), 0.0, CombinedQueryMetrics::new(), None),
+            move |(mut results, charge, mut metrics, continuation): (
+                Vec<serde_json::Value>,
+                f64,
+                CombinedQueryMetrics,
+                Option<ContinuationToken>,
+            )| {
+                let req = client.prepare_request(&uri, hyper::Method::POST, ResourceType::Documents);
+                let mut request = QueryDocumentRequest::new(client.hyper_client.clone(), Ok(req), Ok(query_json.clone()))
+                    .partition_range_id(range_id.as_str())
+                    .populate_query_metrics(true);
+                if let Some(continuation) = continuation {
+                    request = request.continuation_token(continuation);
+                }
+
+                request.execute_raw().and_then(move |page| {
+                    let charge = charge + page.additional_headers.charge;
+                    if let Some(ref page_metrics) = page.additional_headers.query_metrics {
+                        metrics.add(page_metrics);
+                    }
+                    let continuation = page.additional_headers.continuation_token.clone();
+                    for result in PageDocuments::new(page.body) {
+                        results.push(result?);
+                    }
+                    Ok(match continuation {
+                        Some(continuation) => Loop::Continue((results, charge, metrics, Some(continuation))),
+                        // the metrics of the pages, counted as partitions
+                        // by CombinedQueryMetrics
+                        None if metrics.partition_count > 0 => Loop::Break((results, charge, Some(metrics.total))),
+                        None => Loop::Break((results, charge, None)),
+                    })
+                })
+            },
+        )
+    }
+
+    /// Queries only the given fields of the documents, see
+    /// `select_projection`. The results are meant to be read into a struct
+    /// holding just those fields: less data is read and returned, so the
+    /// query costs fewer RUs.
+    pub fn select_documents<S1: AsRef<str>, S2: AsRef<str>>(&self, database: S1, collection: S2, fields: &[&str]) -> QueryDocumentRequest {
+        match select_projection(fields) {
+            Ok(sql) => self.query_document(database, collection, &Query::new(&sql)),
+            Err(error) => QueryDocumentRequest::new(self.hyper_client.clone(), Err(error), Ok(String::new())),
+        }
+    }
+
+    /// Reads the documents with the given ids from a single logical
+    /// partition. The ids are looked up with `IN` queries of at most
+    /// `READ_MANY_CHUNK_SIZE` ids each; missing ids are simply not returned.
+    pub fn read_many<'b, T, S1, S2, P>(
+        &self,
+        database: S1,
+        collection: S2,
+        ids: Vec<String>,
+        partition_key: P,
+    ) -> impl Future<Item = Vec<Document<T>>, Error = AzureError>
+    where
+        T: DeserializeOwned,
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+        P: Into<PartitionKey<'b>>,
+    {
+        let uri = UriBuilder::new()
+            .database(database.as_ref())
+            .collection(collection.as_ref())
+            .feed("docs")
+            .build();
+        let client = self.clone();
+
+        let partition_key = partition_key
+            .into()
+            .to_json()
+            .and_then(|pk| pk.ok_or_else(|| AzureError::InputParametersError("read_many requires a partition key".to_owned())));
+
+        done(uri.and_then(|uri| partition_key.map(|partition_key| (uri, partition_key)))).and_then(move |(uri, partition_key)| {
+            let chunks = ids
+                .chunks(READ_MANY_CHUNK_SIZE)
+                .map(|chunk| client.read_many_chunk(uri.clone(), partition_key.clone(), chunk))
+                .collect::<Vec<_>>();
+
+            join_all(chunks).map(|chunks| chunks.into_iter().flatten().collect())
+        })
+    }
+
+    // Follows the continuation until every document of the chunk is read.
+    fn read_many_chunk<T: DeserializeOwned>(
+        &self,
+        uri: ResourceUri,
+        partition_key: String,
+        ids: &[String],
+    ) -> impl Future<Item = Vec<Document<T>>, Error = AzureError> {
+        let client = self.clone();
+        let max_item_count = ids.len() as u64;
+
+        done(read_many_query(ids)).from_err().and_then(move |query_json| {
+            loop_fn(
+                (Vec::new(), None),
+                move |(mut documents, continuation): (Vec<Document<T>>, Option<ContinuationToken>)| {
+                    let req = client.prepare_request(&uri, hyper::Method::POST, ResourceType::Documents);
+                    let mut request = QueryDocumentRequest::new(client.hyper_client.clone(), Ok(req), Ok(query_json.clone()))
+                        .partition_key(partition_key.as_str())
+                        .max_item_count(max_item_count);
+                    if let Some(continuation) = continuation {
+                        request = request.continuation_token(continuation);
+                    }
+
+                    request.execute_json().and_then(move |response| {
+                        let continuation = response.additional_headers.continuation_token.clone();
+                        documents.extend(documents_from_query_response(response)?);
+                        Ok(match continuation {
+                            Some(continuation) => Loop::Continue((documents, Some(continuation))),
+                            None => Loop::Break(documents),
+                        })
+                    })
+                },
+            )
+        })
+    }
+
+    pub fn list_stored_procedures<S1: AsRef<str>, S2: AsRef<str>>(&self, database: S1, collection: S2) -> ListStoredProceduresRequest {
+        let req = UriBuilder::new()
+            .database(database.as_ref())
+            .collection(collection.as_ref())
+            .feed("sprocs")
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::GET, ResourceType::StoredProcedures));
+        ListStoredProceduresRequest::new(self.hyper_client.clone(), req)
+    }
+
+    /// Lists every stored procedure of the collection, following the
+    /// continuation tokens until the last page.
+    pub fn list_all_stored_procedures<S1, S2>(
+        &self,
+        database: S1,
+        collection: S2,
+    ) -> impl Future<Item = Vec<StoredProcedure>, Error = AzureError>
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+    {
+        let client = self.clone();
+        let database = database.as_ref().to_owned();
+        let collection = collection.as_ref().to_owned();
+
+        follow_continuation(move |continuation| {
+            let mut request = client.list_stored_procedures(&database, &collection);
+            if let Some(continuation) = continuation {
+                request = request.continuation_token(continuation);
+            }
+            Box::new(request.execute())
+        })
+    }
+
+    pub fn list_conflicts<S1: AsRef<str>, S2: AsRef<str>>(&self, database: S1, collection: S2) -> ListConflictsRequest {
+        let req = UriBuilder::new()
+            .database(database.as_ref())
+            .collection(collection.as_ref())
+            .feed("conflicts")
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::GET, ResourceType::Conflicts));
+        ListConflictsRequest::new(self.hyper_client.clone(), req)
+    }
+
+    /// Lists every conflict of the collection, following the continuation
+    /// tokens until the last page.
+    pub fn list_all_conflicts<S1, S2>(&self, database: S1, collection: S2) -> impl Future<Item = Vec<Conflict>, Error = AzureError>
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+    {
+        let client = self.clone();
+        let database = database.as_ref().to_owned();
+        let collection = collection.as_ref().to_owned();
+
+        follow_continuation(move |continuation| {
+            let mut request = client.list_conflicts(&database, &collection);
+            if let Some(continuation) = continuation {
+                request = request.continuation_token(continuation);
+            }
+            Box::new(request.execute())
+        })
+    }
+
+    /// Deletes a conflict once it has been resolved. Conflicts of a
+    /// partitioned collection also need the partition key of the resource.
+    pub fn delete_conflict<S1, S2, S3>(&self, database: S1, collection: S2, conflict_id: S3) -> DeleteConflictRequest
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+        S3: AsRef<str>,
+    {
+        let req = UriBuilder::new()
+            .database(database.as_ref())
+            .collection(collection.as_ref())
+            .conflict(conflict_id.as_ref())
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::DELETE, ResourceType::Conflicts));
+        DeleteConflictRequest::new(self.hyper_client.clone(), req)
+    }
+
+    pub fn create_stored_procedure<S1, S2, S3>(
+        &self,
+        database: S1,
+        collection: S2,
+        sproc_name: S3,
+        body: &str,
+    ) -> CreateStoredProcedureRequest
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+        S3: AsRef<str>,
+    {
+        let req = UriBuilder::new()
+            .database(database.as_ref())
+            .collection(collection.as_ref())
+            .feed("sprocs")
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::POST, ResourceType::StoredProcedures));
+        let payload = stored_procedure_payload(sproc_name.as_ref(), body);
+        CreateStoredProcedureRequest::new(self.hyper_client.clone(), req, payload)
+    }
+
+    /// Replaces the script of a stored procedure. `body` is the JavaScript
+    /// source of the new version.
+    pub fn replace_stored_procedure<S1, S2, S3>(
+        &self,
+        database: S1,
+        collection: S2,
+        sproc_name: S3,
+        body: &str,
+    ) -> ReplaceStoredProcedureRequest
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+        S3: AsRef<str>,
+    {
+        let req = UriBuilder::new()
+            .database(database.as_ref())
+            .collection(collection.as_ref())
+            .stored_procedure(sproc_name.as_ref())
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::PUT, ResourceType::StoredProcedures));
+        let payload = stored_procedure_payload(sproc_name.as_ref(), body);
+        ReplaceStoredProcedureRequest::new(self.hyper_client.clone(), req, payload)
+    }
+
     pub fn execute_stored_procedure<S1, S2, S3, I>(
         &self,
         database: S1,
@@ -535,29 +1250,258 @@ impl Client {
         S3: AsRef<str>,
         I: Serialize,
     {
-        let req = self.prepare_request(
-            &format!(
-                "dbs/{}/colls/{}/sprocs/{}",
-                database.as_ref(),
-                collection.as_ref(),
-                sproc_name.as_ref()
-            ),
-            hyper::Method::POST,
-            ResourceType::StoredProcedures,
-        );
+        let req = UriBuilder::new()
+            .database(database.as_ref())
+            .collection(collection.as_ref())
+            .stored_procedure(sproc_name.as_ref())
+            .build()
+            .map(|uri| self.prepare_request(&uri, hyper::Method::POST, ResourceType::StoredProcedures));
         let input_json = serde_json::to_string(&input);
         ExecuteStoredProcedureRequest::new(self.hyper_client.clone(), req, input_json)
     }
 
+    /// Creates the stored procedure used by `bulk_create_via_sproc` in the
+    /// collection. Registering it again is not an error, so this can be
+    /// called every time the application starts.
+    pub fn register_bulk_create_sproc<S1, S2>(&self, database: S1, collection: S2) -> impl Future<Item = (), Error = AzureError>
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+    {
+        self.register_sproc(database, collection, BULK_CREATE_SPROC_ID, BULK_CREATE_SPROC_BODY)
+    }
+
+    /// Creates the stored procedure used by `delete_by_query_via_sproc` in
+    /// the collection. As with `register_bulk_create_sproc` registering it
+    /// again is not an error.
+    pub fn register_bulk_delete_sproc<S1, S2>(&self, database: S1, collection: S2) -> impl Future<Item = (), Error = AzureError>
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+    {
+        self.register_sproc(database, collection, BULK_DELETE_SPROC_ID, BULK_DELETE_SPROC_BODY)
+    }
+
+    // A conflict means the stored procedure is already there.
+    fn register_sproc<S1, S2>(&self, database: S1, collection: S2, id: &str, body: &str) -> impl Future<Item = (), Error = AzureError>
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+    {
+        self.create_stored_procedure(database, collection, id, body)
+            .execute()
+            .map(|_| ())
+            .or_else(|error| match error {
+                AzureError::UnexpectedHTTPResult(ref r) if r.status_code() == StatusCode::CONFLICT => Ok(()),
+                error => Err(error),
+            })
+    }
+
+    /// Creates `documents` atomically: either all of them or none. A stored
+    /// procedure only runs within a partition so every document must have
+    /// `partition_key` as its partition key.
+    pub fn bulk_create_via_sproc<'a, S1, S2, T, P>(
+        &self,
+        database: S1,
+        collection: S2,
+        documents: &[T],
+        partition_key: P,
+    ) -> BulkCreateRequest
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+        T: Serialize,
+        P: Into<PartitionKey<'a>>,
+    {
+        // the arguments of a stored procedure are sent as an array
+        let request = self.execute_stored_procedure(database, collection, BULK_CREATE_SPROC_ID, (documents,));
+        BulkCreateRequest::new(request.partition_key(partition_key))
+    }
+
+    /// Creates the documents through the stored procedure registered by
+    /// `register_bulk_create_sproc`, whatever their partition: they are
+    /// grouped by partition key, the partition key path being read from the
+    /// collection first, and the documents of a partition are created in
+    /// transactions of at most `MAX_BULK_CREATE_BATCH_LEN` documents, at most
+    /// `concurrency` at once. A document without a partition key fails the
+    /// whole call before anything is sent, but a failed transaction does not
+    /// roll back the others. Returns the number of documents created.
+    pub fn bulk_create<S1, S2, T>(
+        &self,
+        database: S1,
+        collection: S2,
+        documents: &[T],
+        concurrency: usize,
+    ) -> impl Future<Item = u64, Error = AzureError>
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+        T: Serialize,
+    {
+        let database = database.as_ref().to_owned();
+        let collection = collection.as_ref().to_owned();
+        let documents = documents.iter().map(serde_json::to_value).collect::<Result<Vec<_>, _>>();
+        let client = self.clone();
+
+        done(documents)
+            .from_err()
+            .join(self.get_collection(&database, &collection).execute())
+            .and_then(move |(documents, response)| {
+                let path = response.collection.parition_key.and_then(|key| key.paths.into_iter().next());
+                done(batches_by_partition_key(documents, path.as_deref(), MAX_BULK_CREATE_BATCH_LEN)).and_then(move |batches| {
+                    stream::iter_ok(batches)
+                        .map(move |batch| client.create_batch(&database, &collection, batch))
+                        .buffer_unordered(::std::cmp::max(concurrency, 1))
+                        .fold(0, |created, batch_created| Ok::<_, AzureError>(created + batch_created))
+                })
+            })
+    }
+
+    fn create_batch(&self, database: &str, collection: &str, batch: PartitionBatch) -> impl Future<Item = u64, Error = AzureError> {
+        let client = self.clone();
+        let database = database.to_owned();
+        let collection = collection.to_owned();
+
+        retry_throttled(self.throttled_attempts(), move || {
+            let request = client.execute_stored_procedure(&database, &collection, BULK_CREATE_SPROC_ID, (&batch.documents,));
+            let request = match batch.partition_key {
+                Some(ref partition_key) => with_partition_key_json(request, partition_key),
+                None => request,
+            };
+            BulkCreateRequest::new(request).execute()
+        })
+    }
+
+    /// Deletes every document returned by the query, sending at most
+    /// `concurrency` deletes at once. The query must return the id of the
+    /// documents and their partition key, as `SELECT *` does: the partition
+    /// key path is read from the collection first. A delete answered 429 is
+    /// sent again once the time asked for by Cosmos has passed.
+    pub fn delete_by_query<'b, S1, S2>(
+        &self,
+        database: S1,
+        collection: S2,
+        query: &Query<'b>,
+        concurrency: usize,
+    ) -> impl Future<Item = DeleteByQueryResult, Error = AzureError>
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+    {
+        let database = database.as_ref().to_owned();
+        let collection = collection.as_ref().to_owned();
+        let documents = self.stream_query_documents(database.clone(), collection.clone(), query);
+        let client = self.clone();
+
+        self.get_collection(&database, &collection).execute().and_then(move |response| {
+            let path = response.collection.parition_key.and_then(|key| key.paths.into_iter().next());
+            documents
+                .map(move |document| client.delete_queried_document(&database, &collection, &document, path.as_deref()))
+                .buffer_unordered(::std::cmp::max(concurrency, 1))
+                .fold(DeleteByQueryResult::default(), |mut result, deleted| {
+                    if deleted {
+                        result.deleted += 1;
+                    } else {
+                        result.not_found += 1;
+                    }
+                    Ok::<_, AzureError>(result)
+                })
+        })
+    }
+
+    // Whether the document was deleted: it may have been deleted by someone
+    // else since it was queried.
+    fn delete_queried_document(
+        &self,
+        database: &str,
+        collection: &str,
+        document: &serde_json::Value,
+        path: Option<&str>,
+    ) -> impl Future<Item = bool, Error = AzureError> {
+        let client = self.clone();
+        let database = database.to_owned();
+        let collection = collection.to_owned();
+        let attempts = self.throttled_attempts();
+
+        done(document_to_delete(document, path))
+            .and_then(move |(id, partition_key)| {
+                retry_throttled(attempts, move || {
+                    let request = client.delete_document(&database, &collection, &id);
+                    match partition_key {
+                        Some(ref partition_key) => with_partition_key_json(request, partition_key),
+                        None => request,
+                    }
+                    .execute()
+                })
+            })
+            .then(|result| match result {
+                Ok(()) => Ok(true),
+                Err(AzureError::UnexpectedHTTPResult(ref r)) if r.status_code() == StatusCode::NOT_FOUND => Ok(false),
+                Err(error) => Err(error),
+            })
+    }
+
+    /// Deletes the documents returned by the query within `partition_key`
+    /// through the stored procedure registered by
+    /// `register_bulk_delete_sproc`, so they are never sent to the client.
+    /// The procedure is called again as long as it runs out of time before
+    /// the end. The query must return the `_self` of the documents, as
+    /// `SELECT *` does.
+    pub fn delete_by_query_via_sproc<'a, 'b, S1, S2, P>(
+        &self,
+        database: S1,
+        collection: S2,
+        query: &Query<'b>,
+        partition_key: P,
+    ) -> impl Future<Item = DeleteByQueryResult, Error = AzureError>
+    where
+        S1: AsRef<str>,
+        S2: AsRef<str>,
+        P: Into<PartitionKey<'a>>,
+    {
+        let database = database.as_ref().to_owned();
+        let collection = collection.as_ref().to_owned();
+        let client = self.clone();
+        let attempts = self.throttled_attempts();
+        let arguments = serde_json::to_value(query)
+            .map_err(AzureError::from)
+            .and_then(|query| partition_key.into().to_json().map(|partition_key| (query, partition_key)));
+
+        done(arguments).and_then(move |(query, partition_key)| {
+            let execute = Rc::new(move || {
+                // the arguments of a stored procedure are sent as an array
+                let request = client.execute_stored_procedure(&database, &collection, BULK_DELETE_SPROC_ID, (&query,));
+                match partition_key {
+                    Some(ref partition_key) => with_partition_key_json(request, partition_key),
+                    None => request,
+                }
+                .execute::<BulkDeleteReply>()
+            });
+
+            loop_fn(0, move |deleted: u64| {
+                let execute = execute.clone();
+                retry_throttled(attempts, move || execute()).map(move |response| {
+                    let deleted = deleted + response.result.deleted;
+                    if response.result.continuation {
+                        Loop::Continue(deleted)
+                    } else {
+                        Loop::Break(DeleteByQueryResult { deleted, not_found: 0 })
+                    }
+                })
+            })
+        })
+    }
+
     #[inline]
-    fn prepare_request(&self, uri_path: &str, http_method: hyper::Method, resource_type: ResourceType) -> RequestBuilder {
-        let time = format!("{}", chrono::Utc::now().format(TIME_FORMAT));
+    fn prepare_request(&self, uri: &ResourceUri, http_method: hyper::Method, resource_type: ResourceType) -> RequestBuilder {
+        let time = ms_date_now();
 
+        // the signature is computed on the unencoded link
         let auth = {
-            let resource_link = generate_resource_link(&uri_path);
+            let resource_link = generate_resource_link(uri.link());
             generate_authorization(&self.auth_token, &http_method, resource_type, resource_link, &time)
         };
-        self.prepare_request_with_signature(uri_path, http_method, time, auth)
+        self.prepare_request_with_signature(uri.path(), http_method, time, auth)
     }
 
     #[inline]
@@ -568,7 +1512,7 @@ impl Client {
         resource_type: ResourceType,
         resource_link: &str,
     ) -> RequestBuilder {
-        let time = format!("{}", chrono::Utc::now().format(TIME_FORMAT));
+        let time = ms_date_now();
 
         let sig = { generate_authorization(&self.auth_token, &http_method, resource_type, resource_link, &time) };
         self.prepare_request_with_signature(uri_path, http_method, time, sig)
@@ -583,7 +1527,9 @@ impl Client {
         signature: String,
     ) -> RequestBuilder {
         trace!("prepare_request::auth == {:?}", signature);
-        let uri = format!("https://{}.documents.azure.com/{}", self.auth_token.account(), uri_path);
+        let uri = format!("{}{}", self.endpoint.read().unwrap(), uri_path);
+        let tentative_writes = self.multiple_write_locations() && http_method != hyper::Method::GET && http_method != hyper::Method::HEAD;
+        let has_body = http_method == hyper::Method::POST || http_method == hyper::Method::PUT;
         let mut request = hyper::Request::builder();
         request
             .method(http_method)
@@ -591,10 +1537,27 @@ impl Client {
             .header(HEADER_DATE, time.as_str())
             .header(HEADER_VERSION, HeaderValue::from_static(AZURE_VERSION))
             .header(header::AUTHORIZATION, signature.as_str());
+        if has_body {
+            request.header(header::CONTENT_TYPE, HeaderValue::from_static(JSON_CONTENT_TYPE));
+        }
+        if tentative_writes {
+            request.header(HEADER_ALLOW_TENTATIVE_WRITES, HeaderValue::from_static("true"));
+        }
         request
     }
 }
 
+/// Formats `date` as Cosmos expects it in `x-ms-date`, for example
+/// `Thu, 27 Apr 2017 00:51:12 GMT`. The signature is computed on the same
+/// value lowercased: with any other format Cosmos answers 401.
+pub fn format_ms_date(date: &chrono::DateTime<chrono::Utc>) -> String {
+    to_rfc1123(date)
+}
+
+pub fn ms_date_now() -> String {
+    format_ms_date(&chrono::Utc::now())
+}
+
 fn generate_authorization(
     auth_token: &AuthorizationToken,
     http_method: &hyper::Method,
@@ -655,6 +1618,11 @@ fn string_to_sign(http_method: &hyper::Method, rt: ResourceType, resource_link:
             ResourceType::Collections => "colls",
             ResourceType::Documents => "docs",
             ResourceType::StoredProcedures => "sprocs",
+            ResourceType::Conflicts => "conflicts",
+            ResourceType::Offers => "offers",
+            ResourceType::PartitionKeyRanges => "pkranges",
+            ResourceType::Attachments => "attachments",
+            ResourceType::DatabaseAccount => "",
         },
         resource_link,
         time.to_lowercase()
@@ -662,9 +1630,9 @@ fn string_to_sign(http_method: &hyper::Method, rt: ResourceType, resource_link:
 }
 
 fn generate_resource_link(u: &str) -> &str {
-    static ENDING_STRINGS: &'static [&str] = &["dbs", "colls", "docs"];
+    static ENDING_STRINGS: &'static [&str] = &["dbs", "colls", "docs", "sprocs", "conflicts", "offers", "pkranges"];
 
-    // store the element only if it does not end with dbs, colls or docs
+    // store the element only if it does not end with a feed
     let p = u;
     let len = p.len();
     for str_to_match in ENDING_STRINGS {
@@ -689,14 +1657,87 @@ fn generate_resource_link(u: &str) -> &str {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use azure::core::test_server::{serve, serve_raw};
     use azure::cosmos::client::*;
+    use tokio_core::reactor::Core;
+
+    pub(crate) fn test_client() -> (Core, Client) {
+        let auth_token = AuthorizationToken::new("mindflavor".to_owned(), TokenType::Master, "ZHVtbXk=").unwrap();
+        (Core::new().unwrap(), Client::new(auth_token).unwrap())
+    }
+
+    #[test]
+    fn from_env() {
+        let lookup = |key: Option<&'static str>| {
+            move |name: &str| match name {
+                "COSMOS_ACCOUNT" => Some("mindflavor".to_owned()),
+                "COSMOS_MASTER_KEY" => key.map(str::to_owned),
+                _ => None,
+            }
+        };
+
+        match Client::from_lookup(lookup(None)) {
+            Err(AzureError::InputParametersError(msg)) => assert!(msg.contains("COSMOS_MASTER_KEY")),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("COSMOS_MASTER_KEY should be missing"),
+        }
+
+        let client = Client::from_lookup(lookup(Some("ZHVtbXk="))).unwrap();
+        assert_eq!(client.auth_token.account(), "mindflavor");
+    }
+
+    #[test]
+    fn regional_endpoint() {
+        let (_, client) = test_client();
+        assert_eq!(client.endpoint(), "https://mindflavor.documents.azure.com/");
+
+        let clone = client.clone();
+        client.set_endpoint("https://mindflavor-northeurope.documents.azure.com:443");
+        assert_eq!(clone.endpoint(), "https://mindflavor-northeurope.documents.azure.com:443/");
+
+        let uri = UriBuilder::new().database("db").build().unwrap();
+        let request = clone
+            .prepare_request(&uri, hyper::Method::GET, ResourceType::Databases)
+            .body(())
+            .unwrap();
+        assert_eq!(request.uri(), "https://mindflavor-northeurope.documents.azure.com:443/dbs/db");
+    }
+
+    #[test]
+    fn cloud_endpoint() {
+        use azure::core::client_builder::AzureCloud;
+
+        let auth_token = AuthorizationToken::new("mindflavor".to_owned(), TokenType::Master, "ZHVtbXk=").unwrap();
+        let builder = ClientBuilder::new().with_cloud(AzureCloud::USGovernment);
+        let client = Client::with_builder(auth_token, &builder).unwrap();
+        assert_eq!(client.endpoint(), "https://mindflavor.documents.azure.us/");
+    }
+
+    #[test]
+    fn ms_date() {
+        let time = chrono::DateTime::parse_from_rfc3339("2017-04-27T00:51:12.000000000+00:00").unwrap();
+        assert_eq!(format_ms_date(&time.with_timezone(&chrono::Utc)), "Thu, 27 Apr 2017 00:51:12 GMT");
+
+        let time = chrono::DateTime::parse_from_rfc3339("1900-01-01T01:00:00.000000000+00:00").unwrap();
+        assert_eq!(format_ms_date(&time.with_timezone(&chrono::Utc)), "Mon, 01 Jan 1900 01:00:00 GMT");
+
+        let (_, client) = test_client();
+        let uri = UriBuilder::new().database("db").build().unwrap();
+        let request = client
+            .prepare_request(&uri, hyper::Method::GET, ResourceType::Databases)
+            .body(())
+            .unwrap();
+        let date = request.headers()[HEADER_DATE].to_str().unwrap().to_owned();
+        let parsed = chrono::DateTime::parse_from_rfc2822(&date).unwrap();
+        assert_eq!(format_ms_date(&parsed.with_timezone(&chrono::Utc)), date);
+    }
 
     #[test]
     fn string_to_sign_00() {
         let time = chrono::DateTime::parse_from_rfc3339("1900-01-01T01:00:00.000000000+00:00").unwrap();
         let time = time.with_timezone(&chrono::Utc);
-        let time = format!("{}", time.format(TIME_FORMAT));
+        let time = format_ms_date(&time);
 
         let ret = string_to_sign(
             &hyper::Method::GET,
@@ -719,7 +1760,7 @@ mon, 01 jan 1900 01:00:00 gmt
     fn generate_authorization_00() {
         let time = chrono::DateTime::parse_from_rfc3339("1900-01-01T01:00:00.000000000+00:00").unwrap();
         let time = time.with_timezone(&chrono::Utc);
-        let time = format!("{}", time.format(TIME_FORMAT));
+        let time = format_ms_date(&time);
 
         let auth_token = AuthorizationToken::new(
             "mindflavor".to_owned(),
@@ -744,7 +1785,7 @@ mon, 01 jan 1900 01:00:00 gmt
     fn generate_authorization_01() {
         let time = chrono::DateTime::parse_from_rfc3339("2017-04-27T00:51:12.000000000+00:00").unwrap();
         let time = time.with_timezone(&chrono::Utc);
-        let time = format!("{}", time.format(TIME_FORMAT));
+        let time = format_ms_date(&time);
 
         let auth_token = AuthorizationToken::new(
             "mindflavor".to_owned(),
@@ -765,11 +1806,618 @@ mon, 01 jan 1900 01:00:00 gmt
         );
     }
 
+    #[test]
+    fn health_check() {
+        let (mut core, client) = test_client();
+
+        client.set_endpoint(serve_raw("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}".to_owned()));
+        core.run(client.health_check()).unwrap();
+
+        let body = "{\"code\":\"Unauthorized\",\"message\":\"The input authorization token can't serve the request.\"}";
+        client.set_endpoint(serve_raw(format!(
+            "HTTP/1.1 401 Unauthorized\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )));
+        match core.run(client.health_check()) {
+            Err(AzureError::Unauthorized(ref text)) => assert_eq!(text, body),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        client.set_endpoint("http://127.0.0.1:1/");
+        assert!(core.run(client.health_check()).unwrap_err().is_connection_error());
+    }
+
+    #[test]
+    fn failed_request_over_the_charge_ceiling() {
+        let (mut core, client) = test_client();
+
+        // the status of a failed request is reported, not the ceiling
+        client.set_endpoint(serve_raw(
+            "HTTP/1.1 409 Conflict\r\nx-ms-request-charge: 12.4\r\nContent-Length: 0\r\n\r\n".to_owned(),
+        ));
+        let document: serde_json::Value = serde_json::from_str(r#"{"id": "a"}"#).unwrap();
+        let create = client.create_document("shop", "orders", &document).max_request_charge(5.0);
+        match core.run(create.execute()) {
+            Err(AzureError::UnexpectedHTTPResult(ref result)) => assert_eq!(result.status_code(), StatusCode::CONFLICT),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        client.set_endpoint(serve_raw(
+            "HTTP/1.1 404 Not Found\r\nx-ms-request-charge: 12.4\r\nContent-Length: 0\r\n\r\n".to_owned(),
+        ));
+        let get = client.get_document("shop", "orders", "a").max_request_charge(5.0);
+        let response = core.run(get.execute::<serde_json::Value>()).unwrap();
+        assert!(response.document.is_none());
+    }
+
+    fn collection_json(id: &str, rid: &str) -> String {
+        format!(
+            r#"{{"id": "{}", "indexingPolicy": {{"automatic": true, "indexingMode": "consistent", "includedPaths": [], "excludedPaths": []}},
+                "_rid": "{}", "_ts": 1532866205, "_self": "dbs/XP0mAA==/colls/{}/", "_etag": "\"00000b00-0000-0000-0000-5b5d3e9d0000\"",
+                "_docs": "docs/", "_sprocs": "sprocs/", "_triggers": "triggers/", "_udfs": "udfs/", "_conflicts": "conflicts/"}}"#,
+            id, rid, rid
+        )
+    }
+
+    // A collection partitioned by customer.
+    fn partitioned_collection_json(id: &str, rid: &str) -> String {
+        collection_json(id, rid).replacen("{", r#"{"partitionKey": {"paths": ["/customer"], "kind": "Hash"}, "#, 1)
+    }
+
+    fn offer_json(resource_rid: &str, throughput: u64) -> String {
+        format!(
+            r#"{{"_rid": "", "Offers": [{{"id": "uRMe", "_rid": "uRMe", "offerVersion": "V2", "resource": "dbs/XP0mAA==/",
+                "offerResourceId": "{}", "content": {{"offerThroughput": {}}}}}], "_count": 1}}"#,
+            resource_rid, throughput
+        )
+    }
+
+    // An account with a database of shared throughput, where one of the two
+    // collections has a throughput of its own.
+    fn two_collections_account(request: &str) -> (&'static str, String, String) {
+        let body = if request.starts_with("GET /dbs/shop/colls ") {
+            format!(
+                r#"{{"_rid": "XP0mAA==", "DocumentCollections": [{}, {}], "_count": 2}}"#,
+                collection_json("orders", "XP0mAJ3H-AA="),
+                collection_json("carts", "XP0mAKd7-BA=")
+            )
+        } else if request.starts_with("GET /dbs ") {
+            r#"{"_rid": "", "Databases": [{"id": "shop", "_rid": "XP0mAA==", "_ts": 1532866205, "_self": "dbs/XP0mAA==/",
+                "_etag": "\"00000a00-0000-0000-0000-5b5d3e9d0000\"", "_colls": "colls/", "_users": "users/"}], "_count": 1}"#
+                .to_owned()
+        } else if request.starts_with("POST /offers ") && request.contains("\"XP0mAA==\"") {
+            offer_json("XP0mAA==", 400)
+        } else if request.starts_with("POST /offers ") && request.contains("\"XP0mAJ3H-AA=\"") {
+            offer_json("XP0mAJ3H-AA=", 1000)
+        } else {
+            r#"{"_rid": "", "Offers": [], "_count": 0}"#.to_owned()
+        };
+        ("200 OK", String::new(), body)
+    }
+
+    // A query answered in two pages, the second being asked for with the
+    // continuation of the first, with an empty page in between.
+    fn two_pages_query(request: &str) -> (&'static str, String, String) {
+        let headers = "x-ms-request-charge: 2.5\r\n".to_owned();
+        if request.contains("x-ms-continuation: empty") {
+            (
+                "200 OK",
+                headers + "x-ms-continuation: page2\r\n",
+                r#"{"_rid": "XP0mAJ3H-AA=", "Documents": [], "_count": 0}"#.to_owned(),
+            )
+        } else if request.contains("x-ms-continuation: page2") {
+            (
+                "200 OK",
+                headers,
+                r#"{"_rid": "XP0mAJ3H-AA=", "Documents": [{"id": "c", "text": "{\"nested\": [\"]\"]}"}], "_count": 1}"#.to_owned(),
+            )
+        } else {
+            (
+                "200 OK",
+                headers + "x-ms-continuation: empty\r\n",
+                r#"{"_rid": "XP0mAJ3H-AA=", "Documents": [{"id": "a", "tags": ["x", "y"]}, {"id": "b", "tags": []}], "_count": 2}"#
+                    .to_owned(),
+            )
+        }
+    }
+
+    #[test]
+    fn stream_query_documents() {
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(two_pages_query));
+
+        // the documents are counted as they come, without collecting them
+        let query = Query::new("SELECT * FROM c");
+        let (count, last_id) = core
+            .run(
+                client
+                    .stream_query_documents("shop", "orders", &query)
+                    .fold((0, None), |(count, _), document| {
+                        Ok::<_, AzureError>((count + 1, document["id"].as_str().map(str::to_owned)))
+                    }),
+            )
+            .unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(last_id.as_deref(), Some("c"));
+    }
+
+    // A collection partitioned by customer where the query matches two
+    // documents, the first delete of `a` being throttled.
+    fn two_documents_to_delete(request: &str) -> (&'static str, String, String) {
+        static THROTTLED: AtomicBool = AtomicBool::new(false);
+        let headers = "x-ms-request-charge: 1\r\n".to_owned();
+        if request.starts_with("GET /dbs/shop/colls/orders ") {
+            let collection = partitioned_collection_json("orders", "XP0mAJ3H-AA=");
+            ("200 OK", String::new(), collection)
+        } else if request.starts_with("POST /dbs/shop/colls/orders/docs ") {
+            (
+                "200 OK",
+                headers,
+                r#"{"_rid": "XP0mAJ3H-AA=", "Documents": [{"id": "a", "customer": "c1"}, {"id": "b", "customer": 7}], "_count": 2}"#
+                    .to_owned(),
+            )
+        } else if request.starts_with("DELETE /dbs/shop/colls/orders/docs/a ")
+            && request.contains("x-ms-documentdb-partitionkey: [\"c1\"]\r\n")
+        {
+            if THROTTLED.swap(true, Ordering::SeqCst) {
+                ("204 No Content", headers, String::new())
+            } else {
+                ("429 Too Many Requests", headers + "x-ms-retry-after-ms: 1\r\n", String::new())
+            }
+        } else if request.starts_with("DELETE /dbs/shop/colls/orders/docs/b ") && request.contains("x-ms-documentdb-partitionkey: [7]\r\n")
+        {
+            ("204 No Content", headers, String::new())
+        } else {
+            ("400 Bad Request", headers, String::new())
+        }
+    }
+
+    #[test]
+    fn delete_by_query() {
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(two_documents_to_delete));
+
+        let query = Query::new("SELECT * FROM c WHERE c.expired = true");
+        let result = core.run(client.delete_by_query("shop", "orders", &query, 2)).unwrap();
+        assert_eq!(result, DeleteByQueryResult { deleted: 2, not_found: 0 });
+    }
+
+    static THROTTLED_DELETES: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
+
+    // The delete of `a` is always throttled.
+    fn documents_to_delete_throttled(request: &str) -> (&'static str, String, String) {
+        if request.starts_with("DELETE /dbs/shop/colls/orders/docs/a ") {
+            THROTTLED_DELETES.fetch_add(1, Ordering::SeqCst);
+            ("429 Too Many Requests", "x-ms-retry-after-ms: 1\r\n".to_owned(), String::new())
+        } else {
+            two_documents_to_delete(request)
+        }
+    }
+
+    #[test]
+    fn no_throttle_retry() {
+        let (mut core, client) = test_client();
+        let client = client.no_throttle_retry();
+        client.set_endpoint(serve(documents_to_delete_throttled));
+
+        let query = Query::new("SELECT * FROM c WHERE c.expired = true");
+        match core.run(client.delete_by_query("shop", "orders", &query, 1)) {
+            Err(AzureError::ThrottledError { retry_after }) => assert_eq!(retry_after, ::std::time::Duration::from_millis(1)),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(THROTTLED_DELETES.load(Ordering::SeqCst), 1);
+    }
+
+    // Echoes the offers replaced with an autoscale maximum.
+    fn autoscale_offer_replaced(request: &str) -> (&'static str, String, String) {
+        if request.starts_with("PUT /offers/uRMe ") && request.contains(r#""offerAutopilotSettings":{"maxThroughput":6000}"#) {
+            ("200 OK", String::new(), request[request.find("\r\n\r\n").unwrap() + 4..].to_owned())
+        } else {
+            ("400 Bad Request", String::new(), String::new())
+        }
+    }
+
+    #[test]
+    fn replace_offer_autoscale() {
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(autoscale_offer_replaced));
+
+        let offers: ListOffersResponse = serde_json::from_str(&offer_json("XP0mAJ3H-AA=", 400)).unwrap();
+        let fixed = offers.offers[0].clone();
+        let mut autoscale = fixed.clone();
+        autoscale.content.offer_autopilot_settings = serde_json::from_str(r#"{"maxThroughput": 4000}"#).unwrap();
+
+        let replaced = core.run(client.replace_offer_autoscale(&autoscale, 6000)).unwrap();
+        assert_eq!(replaced.content.offer_autopilot_settings.unwrap().max_throughput, 6000);
+        assert_eq!(replaced.content.offer_throughput, 400);
+
+        for (offer, max_throughput) in &[(&autoscale, 1500), (&fixed, 6000)] {
+            match core.run(client.replace_offer_autoscale(offer, *max_throughput)) {
+                Err(AzureError::InputParametersError(_)) => {}
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    fn always_throttled(_: &str) -> (&'static str, String, String) {
+        ("429 Too Many Requests", "x-ms-retry-after-ms: 1500\r\n".to_owned(), String::new())
+    }
+
+    #[test]
+    fn throttled_database_collection_and_offer_calls() {
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(always_throttled));
+
+        let results = vec![
+            core.run(client.list_databases().map(|_| ())),
+            core.run(client.list_collections("shop").map(|_| ())),
+            core.run(client.get_offer("XP0mAA==").map(|_| ())),
+        ];
+        for result in results {
+            match result {
+                Err(AzureError::ThrottledError { retry_after }) => assert_eq!(retry_after, ::std::time::Duration::from_millis(1500)),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    fn changes_of_one_range(request: &str) -> (&'static str, String, String) {
+        let change = |id: &str, etag: &str| {
+            (
+                "200 OK",
+                format!("etag: {}\r\nx-ms-request-charge: 1\r\n", etag),
+                format!(
+                    r#"{{"_rid": "XP0mAJ3H-AA=", "Documents": [{{"id": "{}", "_rid": "XP0mAJ3H-AABAAAAAAAAAA==", "_ts": 1532866523,
+                        "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/", "_etag": "\"00002602-0000-0000-0000-5b5d3b5b0000\"",
+                        "_attachments": "attachments/"}}], "_count": 1}}"#,
+                    id
+                ),
+            )
+        };
+        if request.starts_with("GET /dbs/shop/colls/orders/pkranges ") {
+            (
+                "200 OK",
+                String::new(),
+                r#"{"_rid": "XP0mAJ3H-AA=", "PartitionKeyRanges": [{"id": "0", "minInclusive": "", "maxExclusive": "FF",
+                    "_rid": "XP0mAJ3H-AACAAAAAAAAUA==", "_etag": "\"00009e00-0000-0000-0000-5b5d3b5b0000\""}], "_count": 1}"#
+                    .to_owned(),
+            )
+        } else if !request.starts_with("GET /dbs/shop/colls/orders/docs ")
+            || !request.contains("a-im: Incremental feed\r\n")
+            || !request.contains("x-ms-documentdb-partitionkeyrangeid: 0\r\n")
+        {
+            ("400 Bad Request", String::new(), String::new())
+        } else if request.contains("if-none-match: \"2\"\r\n") {
+            ("304 Not Modified", String::new(), String::new())
+        } else if request.contains("if-none-match: \"1\"\r\n") {
+            change("b", "\"2\"")
+        } else {
+            change("a", "\"1\"")
+        }
+    }
+
+    #[test]
+    fn change_feed_processor() {
+        use azure::cosmos::change_feed::{ChangeFeedProcessor, InMemoryCheckpointStore};
+        use std::cell::RefCell;
+
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(changes_of_one_range));
+
+        let store = InMemoryCheckpointStore::new();
+        let processor = ChangeFeedProcessor::new(client, "shop", "orders", store.clone());
+        let handled = Rc::new(RefCell::new(Vec::new()));
+        let handler = |documents: Vec<Document<serde_json::Value>>, checkpoint: ::azure::cosmos::change_feed::Checkpoint| {
+            for document in documents {
+                handled
+                    .borrow_mut()
+                    .push((checkpoint.range_id().to_owned(), document.document_attributes.id().to_owned()));
+            }
+            checkpoint.save()
+        };
+
+        assert_eq!(core.run(processor.poll(&handler)).unwrap(), 1);
+        assert_eq!(store.continuation("0").as_deref(), Some("\"1\""));
+        assert_eq!(core.run(processor.poll(&handler)).unwrap(), 1);
+        assert_eq!(store.continuation("0").as_deref(), Some("\"2\""));
+        assert_eq!(
+            *handled.borrow(),
+            vec![("0".to_owned(), "a".to_owned()), ("0".to_owned(), "b".to_owned())]
+        );
+
+        // nothing changed since the last checkpoint
+        assert_eq!(core.run(processor.poll(&handler)).unwrap(), 0);
+        assert_eq!(handled.borrow().len(), 2);
+    }
+
+    fn changes_of_two_split_ranges(request: &str) -> (&'static str, String, String) {
+        let range = |id: &str| {
+            format!(
+                r#"{{"id": "{}", "minInclusive": "", "maxExclusive": "FF", "parents": ["0"],
+                    "_rid": "XP0mAJ3H-AACAAAAAAAAUA==", "_etag": "\"00009e00-0000-0000-0000-5b5d3b5b0000\""}}"#,
+                id
+            )
+        };
+        if request.starts_with("GET /dbs/shop/colls/orders/pkranges ") {
+            let ranges = format!(
+                r#"{{"_rid": "XP0mAJ3H-AA=", "PartitionKeyRanges": [{}, {}], "_count": 2}}"#,
+                range("1"),
+                range("2")
+            );
+            return ("200 OK", String::new(), ranges);
+        }
+        // the ranges split from 0 resume from its checkpoint
+        let etag = if request.contains("x-ms-documentdb-partitionkeyrangeid: 1\r\n") {
+            "\"3\""
+        } else {
+            "\"4\""
+        };
+        if request.contains("if-none-match: \"2\"\r\n") {
+            (
+                "200 OK",
+                format!("etag: {}\r\nx-ms-request-charge: 1\r\n", etag),
+                r#"{"_rid": "XP0mAJ3H-AA=", "Documents": [{"id": "c", "_rid": "XP0mAJ3H-AABAAAAAAAAAA==", "_ts": 1532866523,
+                    "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/", "_etag": "\"00002602-0000-0000-0000-5b5d3b5b0000\"",
+                    "_attachments": "attachments/"}], "_count": 1}"#
+                    .to_owned(),
+            )
+        } else {
+            ("400 Bad Request", String::new(), String::new())
+        }
+    }
+
+    #[test]
+    fn change_feed_processor_after_a_split() {
+        use azure::cosmos::change_feed::{ChangeFeedProcessor, CheckpointStore, InMemoryCheckpointStore};
+
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(changes_of_two_split_ranges));
+
+        let store = InMemoryCheckpointStore::new();
+        core.run(store.save("0", "\"2\"")).unwrap();
+        let processor = ChangeFeedProcessor::new(client, "shop", "orders", store.clone());
+        let handler = |_: Vec<Document<serde_json::Value>>, checkpoint: ::azure::cosmos::change_feed::Checkpoint| checkpoint.save();
+
+        assert_eq!(core.run(processor.poll(&handler)).unwrap(), 2);
+        assert_eq!(store.continuation("1").as_deref(), Some("\"3\""));
+        assert_eq!(store.continuation("2").as_deref(), Some("\"4\""));
+    }
+
+    fn changes_in_two_partitions(request: &str) -> (&'static str, String, String) {
+        let page = |ids_and_ts: &[(&str, u64)]| {
+            let documents = ids_and_ts
+                .iter()
+                .map(|(id, ts)| {
+                    format!(
+                        r#"{{"_rid": "XP0mAJ3H-AABAAAAAAAAAA==", "orderByItems": [{{"item": {1}}}], "payload": {{"id": "{0}",
+                            "_rid": "XP0mAJ3H-AABAAAAAAAAAA==", "_ts": {1}, "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/",
+                            "_etag": "\"00002602-0000-0000-0000-5b5d3b5b0000\"", "_attachments": "attachments/"}}}}"#,
+                        id, ts
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!(
+                r#"{{"_rid": "XP0mAJ3H-AA=", "Documents": [{}], "_count": {}}}"#,
+                documents.join(", "),
+                documents.len()
+            )
+        };
+        let headers = |execution_time: &str, retrieved_documents: u64| {
+            format!(
+                "x-ms-request-charge: 1\r\nx-ms-documentdb-query-metrics: totalExecutionTimeInMs={};retrievedDocumentCount={}\r\n",
+                execution_time, retrieved_documents
+            )
+        };
+        if request.starts_with("GET /dbs/shop/colls/orders/pkranges ") {
+            (
+                "200 OK",
+                String::new(),
+                r#"{"_rid": "XP0mAJ3H-AA=", "PartitionKeyRanges": [
+                    {"id": "0", "minInclusive": "", "maxExclusive": "7F", "_rid": "XP0mAJ3H-AACAAAAAAAAUA==", "_etag": "\"1\""},
+                    {"id": "1", "minInclusive": "7F", "maxExclusive": "FF", "_rid": "XP0mAJ3H-AACAAAAAAAAUB==", "_etag": "\"2\""}], "_count": 2}"#
+                    .to_owned(),
+            )
+        } else if !request.starts_with("POST /dbs/shop/colls/orders/docs ")
+            || !request.contains("x-ms-documentdb-populatequerymetrics: true\r\n")
+            || !request.contains("AS orderByItems, c AS payload FROM c WHERE c._ts > @since ORDER BY c._ts")
+            || !request.contains(r#""value":1532866500"#)
+        {
+            ("400 Bad Request", String::new(), String::new())
+        } else if request.contains("x-ms-documentdb-partitionkeyrangeid: 0\r\n") {
+            if request.contains("x-ms-continuation: next\r\n") {
+                ("200 OK", headers("0.25", 1), page(&[("c", 1532866530)]))
+            } else {
+                let headers = headers("0.25", 1) + "x-ms-continuation: next\r\n";
+                ("200 OK", headers, page(&[("a", 1532866510)]))
+            }
+        } else if request.contains("x-ms-documentdb-partitionkeyrangeid: 1\r\n") {
+            ("200 OK", headers("1.00", 2), page(&[("b", 1532866520), ("d", 1532866540)]))
+        } else {
+            ("400 Bad Request", String::new(), String::new())
+        }
+    }
+
+    #[test]
+    fn changed_since_merges_the_partitions() {
+        use chrono::TimeZone;
+
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(changes_in_two_partitions));
+
+        let since = chrono::Utc.timestamp(1_532_866_500, 0);
+        let response = core
+            .run(client.changed_since::<serde_json::Value, _, _>("shop", "orders", &since))
+            .unwrap();
+        let ids = response
+            .results
+            .iter()
+            .map(|document| document.document_attributes.id())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["a", "b", "c", "d"]);
+        assert_eq!(response.results[1].document_attributes.ts(), 1_532_866_520);
+        assert_eq!(response.charge, 3.0);
+        assert_eq!(response.query_metrics.partition_count, 2);
+        assert_eq!(response.query_metrics.total.retrieved_document_count, 4);
+        assert_eq!(
+            response.query_metrics.total.total_execution_time,
+            ::std::time::Duration::from_micros(1_500)
+        );
+    }
+
+    fn orders_of_two_customers(request: &str) -> (&'static str, String, String) {
+        let headers = "x-ms-request-charge: 1\r\n".to_owned();
+        if request.starts_with("GET /dbs/shop/colls/orders ") {
+            let collection = partitioned_collection_json("orders", "XP0mAJ3H-AA=");
+            ("200 OK", String::new(), collection)
+        } else if !request.starts_with("POST /dbs/shop/colls/orders/sprocs/azure_sdk_bulk_create ") {
+            ("400 Bad Request", headers, String::new())
+        } else if request.contains("x-ms-documentdb-partitionkey: [\"c1\"]\r\n")
+            && request.ends_with(r#"[[{"customer":"c1","id":"a"},{"customer":"c1","id":"c"}]]"#)
+        {
+            ("200 OK", headers, "2".to_owned())
+        } else if request.contains("x-ms-documentdb-partitionkey: [7]\r\n") && request.ends_with(r#"[[{"customer":7,"id":"b"}]]"#) {
+            ("200 OK", headers, "1".to_owned())
+        } else {
+            ("400 Bad Request", headers, String::new())
+        }
+    }
+
+    #[test]
+    fn bulk_create() {
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(orders_of_two_customers));
+
+        let orders: Vec<serde_json::Value> =
+            serde_json::from_str(r#"[{"id": "a", "customer": "c1"}, {"id": "b", "customer": 7}, {"id": "c", "customer": "c1"}]"#).unwrap();
+        assert_eq!(core.run(client.bulk_create("shop", "orders", &orders, 2)).unwrap(), 3);
+    }
+
+    // A shop database holding an orders collection partitioned by customer.
+    fn existing_orders(request: &str) -> (&'static str, String, String) {
+        let database = |id: &str| {
+            format!(
+                r#"{{"id": "{}", "_rid": "XP0mAA==", "_ts": 1532866205, "_self": "dbs/XP0mAA==/",
+                    "_etag": "\"00000a00-0000-0000-0000-5b5d3e9d0000\"", "_colls": "colls/", "_users": "users/"}}"#,
+                id
+            )
+        };
+
+        if request.starts_with("POST /dbs ") && request.ends_with(r#"{"id":"shop"}"#) {
+            ("409 Conflict", String::new(), String::new())
+        } else if request.starts_with("POST /dbs ") && request.ends_with(r#"{"id":"blog"}"#) {
+            ("201 Created", String::new(), database("blog"))
+        } else if request.starts_with("GET /dbs/shop ") {
+            ("200 OK", String::new(), database("shop"))
+        } else if request.starts_with("POST /dbs/shop/colls ") && request.contains(r#""id":"orders""#) {
+            ("409 Conflict", String::new(), String::new())
+        } else if request.starts_with("POST /dbs/shop/colls ")
+            && request.contains(r#""id":"carts""#)
+            && request.contains("x-ms-offer-throughput: 400\r\n")
+        {
+            ("201 Created", String::new(), partitioned_collection_json("carts", "XP0mAKd7-BA="))
+        } else if request.starts_with("GET /dbs/shop/colls/orders ") {
+            ("200 OK", String::new(), partitioned_collection_json("orders", "XP0mAJ3H-AA="))
+        } else {
+            ("400 Bad Request", String::new(), String::new())
+        }
+    }
+
+    #[test]
+    fn create_if_not_exists() {
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(existing_orders));
+
+        assert_eq!(core.run(client.create_database_if_not_exists("blog")).unwrap().id, "blog");
+        assert_eq!(core.run(client.create_database_if_not_exists("shop")).unwrap().id, "shop");
+
+        let by_customer = |id: &str| -> Collection { serde_json::from_str(&partitioned_collection_json(id, "")).unwrap() };
+        let carts = client
+            .create_collection_if_not_exists("shop", &by_customer("carts"))
+            .throughput(400);
+        let carts = core.run(carts.execute());
+        assert_eq!(carts.unwrap().id, "carts");
+        let orders = core.run(client.create_collection_if_not_exists("shop", &by_customer("orders")).execute());
+        assert_eq!(orders.unwrap().rid, "XP0mAJ3H-AA=");
+
+        let mut by_region = by_customer("orders");
+        by_region.parition_key.as_mut().unwrap().paths = vec!["/region".to_owned()];
+        match core.run(client.create_collection_if_not_exists("shop", &by_region).execute()) {
+            Err(AzureError::InputParametersError(message)) => assert!(message.contains("/region"), "{}", message),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    fn attachments_of_two_documents(request: &str) -> (&'static str, String, String) {
+        if request.starts_with("GET /dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/attachments ") {
+            let body = r#"{"_rid": "XP0mAJ3H-AABAAAAAAAAAA==", "Attachments": [{"id": "invoice", "contentType": "application/pdf",
+                "media": "/media/XP0mAJ3H-AABAAAAAAAAAAOXH9Ys", "_rid": "XP0mAJ3H-AABAAAAAAAAAAOXH9Ys=", "_ts": 1532866205,
+                "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/attachments/XP0mAJ3H-AABAAAAAAAAAAOXH9Ys=",
+                "_etag": "\"00002602-0000-0000-0000-5b5d3b5b0000\""}], "_count": 1}"#;
+            ("200 OK", String::new(), body.to_owned())
+        } else if request.starts_with("GET /dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AACAAAAAAAAAA==/attachments ") {
+            let body = r#"{"_rid": "XP0mAJ3H-AACAAAAAAAAAA==", "Attachments": [], "_count": 0}"#;
+            ("200 OK", String::new(), body.to_owned())
+        } else {
+            ("400 Bad Request", String::new(), String::new())
+        }
+    }
+
+    #[test]
+    fn list_attachments() {
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(attachments_of_two_documents));
+
+        let document = |id: &str, rid: &str| -> Document<serde_json::Value> {
+            Document::from_json(
+                format!(
+                    r#"{{"id": "{}", "_rid": "{}", "_ts": 1532866523, "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/{}/",
+                        "_etag": "\"00002602-0000-0000-0000-5b5d3b5b0000\"", "_attachments": "attachments/"}}"#,
+                    id, rid, rid
+                )
+                .as_bytes(),
+            )
+            .unwrap()
+        };
+
+        let with_invoice = document("a", "XP0mAJ3H-AABAAAAAAAAAA==");
+        let attachments = core.run(client.list_attachments(&with_invoice)).unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].id, "invoice");
+        assert_eq!(attachments[0].content_type, "application/pdf");
+        assert!(attachments[0].is_managed());
+
+        let without_attachments = document("b", "XP0mAJ3H-AACAAAAAAAAAA==");
+        let attachments = core.run(client.list_attachments(&without_attachments)).unwrap();
+        assert!(attachments.is_empty());
+    }
+
+    #[test]
+    fn list_throughput() {
+        use azure::cosmos::offer::Throughput;
+
+        let (mut core, client) = test_client();
+        client.set_endpoint(serve(two_collections_account));
+
+        let throughputs = core.run(client.list_throughput(2)).unwrap();
+        assert_eq!(
+            throughputs,
+            vec![
+                ("shop".to_owned(), "carts".to_owned(), Throughput::Shared(400)),
+                ("shop".to_owned(), "orders".to_owned(), Throughput::Dedicated(1000)),
+            ]
+        );
+    }
+
     #[test]
     fn generate_resource_link_00() {
         assert_eq!(generate_resource_link("dbs/second"), "dbs/second");
         assert_eq!(generate_resource_link("dbs"), "");
         assert_eq!(generate_resource_link("colls/second/third"), "colls/second/third");
         assert_eq!(generate_resource_link("dbs/test_db/colls"), "dbs/test_db");
+        assert_eq!(generate_resource_link("dbs/test_db/colls/c/sprocs"), "dbs/test_db/colls/c");
+        assert_eq!(generate_resource_link("dbs/test_db/colls/c/conflicts"), "dbs/test_db/colls/c");
+        assert_eq!(generate_resource_link("offers"), "");
+        assert_eq!(generate_resource_link("dbs/test_db/colls/c/pkranges"), "dbs/test_db/colls/c");
     }
 }
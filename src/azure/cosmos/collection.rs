@@ -1,11 +1,11 @@
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum KeyKind {
     Hash,
     Range,
     Spatial,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum DataType {
     String,
     Number,
@@ -14,7 +14,7 @@ pub enum DataType {
     LineString,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum IndexingMode {
     #[serde(rename = "consistent")]
     Consistent,
@@ -22,7 +22,7 @@ pub enum IndexingMode {
     Lazy,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct IncludedPath {
     #[serde(rename = "path")]
     pub path: String,
@@ -30,7 +30,7 @@ pub struct IncludedPath {
     pub indexes: Vec<IncludedPathIndex>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct IncludedPathIndex {
     #[serde(rename = "dataType")]
     pub data_type: DataType,
@@ -40,19 +40,47 @@ pub struct IncludedPathIndex {
     pub kind: KeyKind,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ExcludedPath {
     #[serde(rename = "path")]
     pub path: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PartitionKey {
     pub paths: Vec<String>,
     pub kind: KeyKind,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Paths whose values, taken together, cannot repeat within a logical
+/// partition.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UniqueKey {
+    pub paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct UniqueKeyPolicy {
+    #[serde(rename = "uniqueKeys")]
+    pub unique_keys: Vec<UniqueKey>,
+}
+
+/// How the spatial values of the documents are interpreted by the `ST_*`
+/// functions: as coordinates on the round earth or on a flat plane. A
+/// collection created without it uses `Geography`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum GeospatialType {
+    Geography,
+    Geometry,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GeospatialConfig {
+    #[serde(rename = "type")]
+    pub geospatial_type: GeospatialType,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct IndexingPolicy {
     #[serde(rename = "automatic")]
     pub automatic: bool,
@@ -64,7 +92,7 @@ pub struct IndexingPolicy {
     pub excluded_paths: Vec<ExcludedPath>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Collection {
     pub id: String,
     #[serde(rename = "indexingPolicy")]
@@ -72,6 +100,10 @@ pub struct Collection {
     #[serde(rename = "partitionKey")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parition_key: Option<PartitionKey>,
+    #[serde(rename = "uniqueKeyPolicy", default, skip_serializing_if = "Option::is_none")]
+    pub unique_key_policy: Option<UniqueKeyPolicy>,
+    #[serde(rename = "geospatialConfig", default, skip_serializing_if = "Option::is_none")]
+    pub geospatial_config: Option<GeospatialConfig>,
     #[serde(rename = "_rid")]
     pub rid: String,
     #[serde(rename = "_ts")]
@@ -92,12 +124,16 @@ pub struct Collection {
     pub conflicts: String,
 }
 
+impl_etag!(Collection, etag);
+
 impl Collection {
     pub fn new(id: &str, indexing_policy: IndexingPolicy) -> Collection {
         Collection {
             id: id.to_owned(),
             indexing_policy,
             parition_key: None,
+            unique_key_policy: None,
+            geospatial_config: None,
             rid: "".to_owned(),
             ts: 0,
             _self: "".to_owned(),
@@ -110,3 +146,72 @@ impl Collection {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    const COLLECTION: &str = r#"{
+        "id": "mycoll",
+        "indexingPolicy": {
+            "automatic": true,
+            "indexingMode": "consistent",
+            "includedPaths": [
+                {
+                    "path": "/*",
+                    "indexes": [
+                        { "dataType": "String", "precision": -1, "kind": "Range" },
+                        { "dataType": "Point", "precision": null, "kind": "Spatial" }
+                    ]
+                }
+            ],
+            "excludedPaths": [{ "path": "/\"_etag\"/?" }]
+        },
+        "partitionKey": { "paths": ["/customer"], "kind": "Hash" },
+        "uniqueKeyPolicy": { "uniqueKeys": [{ "paths": ["/email"] }] },
+        "geospatialConfig": { "type": "Geometry" },
+        "_rid": "XP0mAJ3H-AA=",
+        "_ts": 1532866523,
+        "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/",
+        "_etag": "\"00000800-0000-0000-0000-5b5d3b5b0000\"",
+        "_docs": "docs/",
+        "_sprocs": "sprocs/",
+        "_triggers": "triggers/",
+        "_udfs": "udfs/",
+        "_conflicts": "conflicts/"
+    }"#;
+
+    #[test]
+    fn round_trip() {
+        let collection: Collection = serde_json::from_str(COLLECTION).unwrap();
+        assert_eq!(collection.parition_key.as_ref().unwrap().paths, vec!["/customer".to_owned()]);
+        assert_eq!(
+            collection.geospatial_config,
+            Some(GeospatialConfig {
+                geospatial_type: GeospatialType::Geometry
+            })
+        );
+        let serialized = serde_json::to_string(&collection).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&serialized).unwrap(),
+            serde_json::from_str::<serde_json::Value>(COLLECTION).unwrap()
+        );
+        assert_eq!(serde_json::from_str::<Collection>(&serialized).unwrap(), collection);
+    }
+    #[test]
+    fn geospatial_config() {
+        let mut value: serde_json::Value = serde_json::from_str(COLLECTION).unwrap();
+        value.as_object_mut().unwrap().remove("geospatialConfig");
+        let collection: Collection = serde_json::from_value(value).unwrap();
+        assert_eq!(collection.geospatial_config, None);
+        assert!(!serde_json::to_string(&collection).unwrap().contains("geospatialConfig"));
+
+        let config = GeospatialConfig {
+            geospatial_type: GeospatialType::Geography,
+        };
+        assert_eq!(serde_json::to_string(&config).unwrap(), r#"{"type":"Geography"}"#);
+        assert!(serde_json::from_str::<GeospatialConfig>(r#"{"type":"Flat"}"#).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use serde::de::DeserializeOwned;
+use serde_json;
+
+/// The operation that wrote the losing version of the resource.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum OperationType {
+    #[serde(rename = "create")]
+    Create,
+    #[serde(rename = "replace")]
+    Replace,
+    #[serde(rename = "delete")]
+    Delete,
+}
+
+/// A write that lost against a concurrent write in another region of a
+/// multi-master account. It stays in the conflicts feed until deleted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub id: String,
+    #[serde(rename = "operationType")]
+    pub operation_type: OperationType,
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    #[serde(rename = "resourceId", default)]
+    pub resource_id: String,
+    /// The losing version of the resource, serialized as JSON.
+    #[serde(default)]
+    pub content: String,
+    #[serde(rename = "_rid")]
+    pub rid: String,
+    #[serde(rename = "_ts")]
+    pub ts: u64,
+    #[serde(rename = "_self")]
+    pub _self: String,
+    #[serde(rename = "_etag")]
+    pub etag: String,
+}
+
+impl_etag!(Conflict, etag);
+
+impl Conflict {
+    /// Deserializes the losing version of the resource.
+    pub fn parse_content<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(&self.content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug)]
+    struct MySampleStruct {
+        id: String,
+        a_number: u64,
+    }
+
+    #[test]
+    fn parse_conflict() {
+        let body = r#"{
+            "id": "k6d9ALgBmD8BAAAAAAAAQA==",
+            "_rid": "k6d9ALgBmD8BAAAAAAAAQA==",
+            "_self": "dbs/k6d9AA==/colls/k6d9ALgBmD8=/conflicts/k6d9ALgBmD8BAAAAAAAAQA==/",
+            "_etag": "\"00000200-0000-0000-0000-5b5d3e9d0000\"",
+            "_ts": 1532837533,
+            "resourceId": "k6d9ALgBmD8BAAAAAAAAAA==",
+            "resourceType": "document",
+            "operationType": "replace",
+            "content": "{\"id\":\"doc\",\"a_number\":42,\"_rid\":\"k6d9ALgBmD8BAAAAAAAAAA==\"}"
+        }"#;
+
+        let conflict: Conflict = serde_json::from_str(body).unwrap();
+        assert_eq!(conflict.id, "k6d9ALgBmD8BAAAAAAAAQA==");
+        assert_eq!(conflict.operation_type, OperationType::Replace);
+        assert_eq!(conflict.resource_type, "document");
+        assert_eq!(conflict.etag, "\"00000200-0000-0000-0000-5b5d3e9d0000\"");
+
+        let content: MySampleStruct = conflict.parse_content().unwrap();
+        assert_eq!(content.id, "doc");
+        assert_eq!(content.a_number, 42);
+
+        let delete: Conflict = serde_json::from_str(&body.replace("\"replace\"", "\"delete\"")).unwrap();
+        assert_eq!(delete.operation_type, OperationType::Delete);
+    }
+}
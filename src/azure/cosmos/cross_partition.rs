@@ -0,0 +1,1093 @@
+use azure::core::enumerations;
+use azure::core::errors::{AzureError, TraversingError};
+use azure::core::parsing::FromStringOptional;
+use azure::core::util::HeaderMapExt;
+use azure::cosmos::client::headers::HEADER_QUERY_PLAN;
+use azure::cosmos::request_response::{QueryDocumentResponse, QueryMetrics};
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+create_enum!(SortOrder, (Ascending, "Ascending"), (Descending, "Descending"));
+
+create_enum!(
+    AggregateOperator,
+    (Average, "Average"),
+    (Count, "Count"),
+    (Max, "Max"),
+    (Min, "Min"),
+    (Sum, "Sum")
+);
+
+const ORDER_BY_ITEMS: &str = "orderByItems";
+const ORDER_BY_ITEM: &str = "item";
+const PAYLOAD: &str = "payload";
+const QUERY_INFO: &str = "queryInfo";
+const AGGREGATES: &str = "aggregates";
+const GROUP_BY_ITEMS: &str = "groupByItems";
+
+/// The query features this client knows how to complete across
+/// partitions, sent in `x-ms-cosmos-supported-query-features`. Cosmos
+/// rejects the cross-partition queries using the others.
+pub const SUPPORTED_QUERY_FEATURES: &str =
+    "Aggregate, CompositeAggregate, Distinct, GroupBy, MultipleOrderBy, OffsetAndLimit, OrderBy, Top";
+
+create_enum!(DistinctType, (None, "None"), (Ordered, "Ordered"), (Unordered, "Unordered"));
+
+// Cosmos orders values of different types this way:
+// undefined < null < boolean < number < string.
+// Arrays and objects cannot be used in an ORDER BY clause.
+fn type_rank(value: Option<&Value>) -> u8 {
+    match value {
+        None => 0,
+        Some(Value::Null) => 1,
+        Some(Value::Bool(_)) => 2,
+        Some(Value::Number(_)) => 3,
+        Some(Value::String(_)) => 4,
+        Some(Value::Array(_)) => 5,
+        Some(Value::Object(_)) => 6,
+    }
+}
+
+pub(crate) fn compare_values(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    match (a, b) {
+        (Some(Value::Bool(a)), Some(Value::Bool(b))) => a.cmp(b),
+        (Some(Value::Number(a)), Some(Value::Number(b))) => {
+            let a = a.as_f64().unwrap_or(0.0);
+            let b = b.as_f64().unwrap_or(0.0);
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        }
+        (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+        _ => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+/// Extracts the sort keys Cosmos attaches to every document returned by
+/// a rewritten cross-partition ORDER BY query. A missing `item` means the
+/// document has no value for that expression (undefined).
+pub fn order_by_items(document: &Value) -> Result<Vec<Option<&Value>>, AzureError> {
+    let items = document
+        .get(ORDER_BY_ITEMS)
+        .and_then(|items| items.as_array())
+        .ok_or_else(|| AzureError::GenericErrorWithText(format!("{} array not found in {}", ORDER_BY_ITEMS, document)))?;
+
+    Ok(items.iter().map(|item| item.get(ORDER_BY_ITEM)).collect())
+}
+
+fn compare_order_by_items(a: &[Option<&Value>], b: &[Option<&Value>], sort_orders: &[SortOrder]) -> Ordering {
+    for (i, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+        let ordering = compare_values(*a, *b);
+        let ordering = match sort_orders.get(i) {
+            Some(SortOrder::Descending) => ordering.reverse(),
+            _ => ordering,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Merges the per-partition results of a cross-partition ORDER BY query.
+///
+/// Every partition must be sorted already (Cosmos does that); the merge
+/// keeps the global order honoring `sort_orders`, one entry per ORDER BY
+/// expression. Documents are expected in the rewritten shape
+/// (`{ "orderByItems": [...], "payload": {...} }`) and the payloads are
+/// returned. Ties are resolved by partition order so the merge is stable.
+pub fn merge_order_by(partitions: Vec<Vec<Value>>, sort_orders: &[SortOrder]) -> Result<Vec<Value>, AzureError> {
+    let total = partitions.iter().map(|p| p.len()).sum();
+
+    let mut keys = Vec::with_capacity(partitions.len());
+    for partition in &partitions {
+        let mut partition_keys = Vec::with_capacity(partition.len());
+        for document in partition {
+            partition_keys.push(order_by_items(document)?);
+        }
+        keys.push(partition_keys);
+    }
+
+    let mut positions = vec![0; partitions.len()];
+    let mut order = Vec::with_capacity(total);
+
+    for _ in 0..total {
+        let mut selected: Option<usize> = None;
+        for (partition, partition_keys) in keys.iter().enumerate() {
+            if positions[partition] >= partition_keys.len() {
+                continue;
+            }
+            selected = match selected {
+                Some(s)
+                    if compare_order_by_items(&partition_keys[positions[partition]], &keys[s][positions[s]], sort_orders)
+                        != Ordering::Less =>
+                {
+                    Some(s)
+                }
+                _ => Some(partition),
+            };
+        }
+
+        // total is the sum of all partition lengths so there is always a candidate
+        let selected = selected.unwrap();
+        order.push((selected, positions[selected]));
+        positions[selected] += 1;
+    }
+
+    let mut partitions: Vec<Vec<Option<Value>>> = partitions.into_iter().map(|p| p.into_iter().map(Some).collect()).collect();
+
+    Ok(order
+        .into_iter()
+        .map(|(partition, position)| {
+            let mut document = partitions[partition][position].take().unwrap();
+            match document.get_mut(PAYLOAD) {
+                Some(payload) => payload.take(),
+                None => document,
+            }
+        }).collect())
+}
+
+/// Global row limits of a cross-partition query: `TOP n` or `OFFSET m LIMIT n`.
+/// Every partition applies them on its own so they must be enforced again
+/// after the merge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryLimits {
+    pub top: Option<u64>,
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+impl QueryLimits {
+    /// Reads the limits from the `queryInfo` section of a query plan, as
+    /// returned by Cosmos.
+    pub fn from_query_plan(query_plan: &Value) -> QueryLimits {
+        let query_info = query_plan.get(QUERY_INFO).unwrap_or(query_plan);
+        let field = |name: &str| query_info.get(name).and_then(|v| v.as_u64());
+
+        QueryLimits {
+            top: field("top"),
+            offset: field("offset"),
+            limit: field("limit"),
+        }
+    }
+
+    /// Best effort extraction of the limits from the query text.
+    /// Used when Cosmos does not hand back a query plan.
+    pub fn from_query(query: &str) -> QueryLimits {
+        let upper = query.to_uppercase();
+        let tokens: Vec<&str> = upper.split_whitespace().collect();
+        let after = |keyword: &str| {
+            tokens
+                .iter()
+                .position(|token| *token == keyword)
+                .and_then(|pos| tokens.get(pos + 1))
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+
+        // TOP is only valid right after SELECT (and an optional DISTINCT)
+        let top = match tokens.first() {
+            Some(&"SELECT") => after("TOP"),
+            _ => None,
+        };
+
+        QueryLimits {
+            top,
+            offset: after("OFFSET"),
+            limit: after("LIMIT"),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.top.is_none() && self.offset.is_none() && self.limit.is_none()
+    }
+
+    fn take(&self) -> Option<u64> {
+        match (self.top, self.limit) {
+            (Some(top), Some(limit)) => Some(top.min(limit)),
+            (top, limit) => top.or(limit),
+        }
+    }
+
+    /// How many items a single partition can contribute at most to the
+    /// final result. `None` means unbounded.
+    pub fn partition_take(&self) -> Option<u64> {
+        self.take().map(|take| take + self.offset.unwrap_or(0))
+    }
+
+    /// Trims the merged results, skipping `offset` items and taking at most
+    /// `top`/`limit` of the remaining ones.
+    pub fn apply(&self, merged: Vec<Value>) -> Vec<Value> {
+        let skip = self.offset.unwrap_or(0) as usize;
+        let iter = merged.into_iter().skip(skip);
+        match self.take() {
+            Some(take) => iter.take(take as usize).collect(),
+            None => iter.collect(),
+        }
+    }
+}
+
+/// Merges the per-partition results of a cross-partition ORDER BY query
+/// and enforces the global `TOP`/`OFFSET LIMIT` of the query.
+pub fn merge_order_by_with_limits(
+    mut partitions: Vec<Vec<Value>>,
+    sort_orders: &[SortOrder],
+    limits: &QueryLimits,
+) -> Result<Vec<Value>, AzureError> {
+    if let Some(take) = limits.partition_take() {
+        for partition in &mut partitions {
+            partition.truncate(take as usize);
+        }
+    }
+
+    let merged = merge_order_by(partitions, sort_orders)?;
+    Ok(limits.apply(merged))
+}
+
+/// Partial `AVG` computed by a single partition. Cosmos cannot average
+/// averages so every partition returns its sum and count instead.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PartialAverage {
+    pub sum: Option<f64>,
+    pub count: u64,
+}
+
+/// Partial `MIN`/`MAX` computed by a single partition. Newer service
+/// versions return this shape, older ones just the value.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PartialMinMax {
+    pub min: Option<Value>,
+    pub max: Option<Value>,
+    pub count: u64,
+}
+
+impl AggregateOperator {
+    /// Reads the aggregate operators from the `queryInfo` section of a
+    /// query plan, as returned by Cosmos.
+    pub fn from_query_plan(query_plan: &Value) -> Result<Vec<AggregateOperator>, AzureError> {
+        let query_info = query_plan.get(QUERY_INFO).unwrap_or(query_plan);
+        match query_info.get(AGGREGATES).and_then(|a| a.as_array()) {
+            Some(aggregates) => aggregates
+                .iter()
+                .map(|a| {
+                    a.as_str()
+                        .and_then(|a| AggregateOperator::from_str(a).ok())
+                        .ok_or_else(|| AzureError::GenericErrorWithText(format!("unsupported aggregate {}", a)))
+                }).collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Best effort detection of a `SELECT VALUE` aggregate in the query
+    /// text. Used when Cosmos does not hand back a query plan.
+    pub fn from_query(query: &str) -> Option<AggregateOperator> {
+        let upper = query.to_uppercase();
+        let upper = upper.trim_start();
+        if !upper.starts_with("SELECT") {
+            return None;
+        }
+        let projection = &upper["SELECT".len()..upper.find(" FROM ").unwrap_or(upper.len())];
+        let projection = projection.trim_start();
+        let projection = match projection.strip_prefix("VALUE ") {
+            Some(projection) => projection.trim_start(),
+            None => projection,
+        };
+
+        [
+            ("AVG(", AggregateOperator::Average),
+            ("COUNT(", AggregateOperator::Count),
+            ("MAX(", AggregateOperator::Max),
+            ("MIN(", AggregateOperator::Min),
+            ("SUM(", AggregateOperator::Sum),
+        ]
+            .iter()
+            .find(|(function, _)| projection.starts_with(function))
+            .map(|(_, operator)| *operator)
+    }
+}
+
+// A partition returns a single document holding its partial aggregate,
+// either wrapped (`{"item": ...}`) or bare.
+fn partial_item(partial: &Value) -> Option<&Value> {
+    match partial {
+        Value::Object(o) if o.contains_key(ORDER_BY_ITEM) => o.get(ORDER_BY_ITEM),
+        Value::Array(a) => a.first().and_then(partial_item),
+        v => Some(v),
+    }
+}
+
+fn number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < 9_007_199_254_740_992.0 {
+        Value::from(value as i64)
+    } else {
+        Value::from(value)
+    }
+}
+
+fn read_partial<T: DeserializeOwned>(item: &Value) -> Result<T, AzureError> {
+    ::serde_json::from_value(item.clone()).map_err(|e| AzureError::operation("reduce_aggregate", e))
+}
+
+/// Combines the partial aggregates returned by every partition of a
+/// cross-partition aggregate query into the final value.
+/// Partitions without matching documents are skipped; if no partition
+/// has a value the result is `null` (`0` for `COUNT`).
+pub fn reduce_aggregate(operator: AggregateOperator, partials: &[Value]) -> Result<Value, AzureError> {
+    let invalid = |partial: &Value| AzureError::GenericErrorWithText(format!("invalid partial {} aggregate {}", operator, partial));
+    let items = partials.iter().filter_map(|partial| partial_item(partial).map(|item| (partial, item)));
+
+    match operator {
+        AggregateOperator::Count | AggregateOperator::Sum => {
+            let mut total = 0.0;
+            let mut found = false;
+            for (partial, item) in items {
+                match item {
+                    Value::Null => {}
+                    Value::Number(n) => {
+                        total += n.as_f64().ok_or_else(|| invalid(partial))?;
+                        found = true;
+                    }
+                    _ => return Err(invalid(partial)),
+                }
+            }
+            if found || operator == AggregateOperator::Count {
+                Ok(number(total))
+            } else {
+                Ok(Value::Null)
+            }
+        }
+        AggregateOperator::Average => {
+            let mut sum = 0.0;
+            let mut count = 0;
+            for (_, item) in items {
+                let average: PartialAverage = read_partial(item)?;
+                if let Some(partial_sum) = average.sum {
+                    sum += partial_sum;
+                    count += average.count;
+                }
+            }
+            if count == 0 {
+                Ok(Value::Null)
+            } else {
+                Ok(Value::from(sum / count as f64))
+            }
+        }
+        AggregateOperator::Min | AggregateOperator::Max => {
+            let mut result: Option<Value> = None;
+            for (_, item) in items {
+                let value = match item {
+                    Value::Object(o) if o.contains_key("count") => {
+                        let min_max: PartialMinMax = read_partial(item)?;
+                        if min_max.count == 0 {
+                            continue;
+                        }
+                        match operator {
+                            AggregateOperator::Min => min_max.min,
+                            _ => min_max.max,
+                        }
+                    }
+                    Value::Null => None,
+                    v => Some(v.clone()),
+                };
+
+                if let Some(value) = value {
+                    let replace = match result {
+                        None => true,
+                        Some(ref current) => {
+                            let ordering = compare_values(Some(&value), Some(current));
+                            (operator == AggregateOperator::Min && ordering == Ordering::Less)
+                                || (operator == AggregateOperator::Max && ordering == Ordering::Greater)
+                        }
+                    };
+                    if replace {
+                        result = Some(value);
+                    }
+                }
+            }
+            Ok(result.unwrap_or(Value::Null))
+        }
+    }
+}
+
+/// Best effort extraction of the ORDER BY directions from the query text.
+/// Used when Cosmos does not hand back a query plan.
+pub fn sort_orders_from_query(query: &str) -> Vec<SortOrder> {
+    let upper = query.to_uppercase();
+    let start = match upper.rfind("ORDER BY") {
+        Some(pos) => pos + "ORDER BY".len(),
+        None => return Vec::new(),
+    };
+
+    let clause = &upper[start..];
+    // the ORDER BY clause ends where OFFSET starts (if present)
+    let clause = match clause.find(" OFFSET ") {
+        Some(end) => &clause[..end],
+        None => clause,
+    };
+
+    clause
+        .split(',')
+        .filter(|expression| !expression.trim().is_empty())
+        .map(|expression| {
+            if expression.split_whitespace().last() == Some("DESC") {
+                SortOrder::Descending
+            } else {
+                SortOrder::Ascending
+            }
+        }).collect()
+}
+
+/// How Cosmos executes a cross-partition query and what the client has to
+/// do with the results of every partition, from the `queryInfo` section of
+/// the query plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryPlan {
+    pub distinct_type: DistinctType,
+    pub limits: QueryLimits,
+    pub sort_orders: Vec<SortOrder>,
+    pub order_by_expressions: Vec<String>,
+    pub group_by_expressions: Vec<String>,
+    pub group_by_aliases: Vec<String>,
+    /// The aggregate computing every alias of a GROUP BY query, `None`
+    /// for the aliases of the grouping expressions.
+    pub group_by_alias_to_aggregate_type: HashMap<String, Option<AggregateOperator>>,
+    pub aggregates: Vec<AggregateOperator>,
+    /// The query to send to every partition instead of the original one.
+    pub rewritten_query: Option<String>,
+    pub has_select_value: bool,
+}
+
+impl QueryPlan {
+    pub fn parse(s: &str) -> Result<QueryPlan, AzureError> {
+        QueryPlan::from_value(&serde_json::from_str::<Value>(s)?)
+    }
+
+    pub fn from_value(query_plan: &Value) -> Result<QueryPlan, AzureError> {
+        let query_info = query_plan.get(QUERY_INFO).unwrap_or(query_plan);
+        let strings = |name: &str| -> Vec<String> {
+            query_info
+                .get(name)
+                .and_then(|v| v.as_array())
+                .map(|v| v.iter().filter_map(|s| s.as_str()).map(|s| s.to_owned()).collect())
+                .unwrap_or_default()
+        };
+
+        let distinct_type = match query_info.get("distinctType").and_then(|v| v.as_str()) {
+            Some(distinct_type) => distinct_type.parse::<DistinctType>()?,
+            None => DistinctType::None,
+        };
+        let mut group_by_alias_to_aggregate_type = HashMap::new();
+        if let Some(aliases) = query_info.get("groupByAliasToAggregateType").and_then(|v| v.as_object()) {
+            for (alias, aggregate) in aliases {
+                let aggregate = match aggregate.as_str() {
+                    Some(aggregate) => Some(aggregate.parse::<AggregateOperator>()?),
+                    None => None,
+                };
+                group_by_alias_to_aggregate_type.insert(alias.to_owned(), aggregate);
+            }
+        }
+        let sort_orders = strings("orderBy")
+            .iter()
+            .map(|sort_order| sort_order.parse::<SortOrder>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(QueryPlan {
+            distinct_type,
+            limits: QueryLimits::from_query_plan(query_plan),
+            sort_orders,
+            order_by_expressions: strings("orderByExpressions"),
+            group_by_expressions: strings("groupByExpressions"),
+            group_by_aliases: strings("groupByAliases"),
+            group_by_alias_to_aggregate_type,
+            aggregates: AggregateOperator::from_query_plan(query_plan)?,
+            rewritten_query: query_info
+                .get("rewrittenQuery")
+                .and_then(|v| v.as_str())
+                .filter(|query| !query.is_empty())
+                .map(|query| query.to_owned()),
+            has_select_value: query_info.get("hasSelectValue").and_then(|v| v.as_bool()).unwrap_or(false),
+        })
+    }
+
+    pub(crate) fn from_headers(headers: &::hyper::HeaderMap) -> Result<Option<QueryPlan>, AzureError> {
+        match headers.get_as_str(HEADER_QUERY_PLAN) {
+            Some(s) => Ok(Some(QueryPlan::parse(s)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether the results of the partitions have to be merged in order or
+    /// trimmed to the limits of the query.
+    pub fn needs_client_side_merge(&self) -> bool {
+        !self.sort_orders.is_empty() || !self.limits.is_empty() || self.distinct_type != DistinctType::None
+    }
+
+    /// Whether the partitions return partial aggregates that have to be
+    /// combined.
+    pub fn needs_client_side_aggregation(&self) -> bool {
+        !self.aggregates.is_empty() || !self.group_by_expressions.is_empty()
+    }
+
+    /// Whether the results of the partitions cannot just be concatenated.
+    pub fn needs_client_side_processing(&self) -> bool {
+        self.needs_client_side_merge() || self.needs_client_side_aggregation()
+    }
+}
+
+// A representation of `value` equal for the values DISTINCT considers
+// equal: object keys are sorted and numbers compared as f64 (1 and 1.0
+// are the same value).
+fn distinct_key(value: &Value, key: &mut String) {
+    match value {
+        Value::Number(n) => key.push_str(&n.as_f64().unwrap_or(0.0).to_string()),
+        Value::Array(items) => {
+            key.push('[');
+            for item in items {
+                distinct_key(item, key);
+                key.push(',');
+            }
+            key.push(']');
+        }
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            key.push('{');
+            for k in keys {
+                key.push_str(&Value::String(k.to_owned()).to_string());
+                key.push(':');
+                distinct_key(&map[k], key);
+                key.push(',');
+            }
+            key.push('}');
+        }
+        _ => key.push_str(&value.to_string()),
+    }
+}
+
+/// Removes the duplicates every partition cannot see from the merged
+/// results of a `SELECT DISTINCT` query, keeping the first occurrence.
+/// Works on scalars (`SELECT DISTINCT VALUE`) as well as on documents.
+/// With `DistinctType::Ordered` the results are sorted so the duplicates
+/// are next to each other and only the previous result is remembered.
+pub fn distinct(merged: Vec<Value>, distinct_type: DistinctType) -> Vec<Value> {
+    match distinct_type {
+        DistinctType::None => merged,
+        DistinctType::Ordered => {
+            let mut last: Option<String> = None;
+            merged
+                .into_iter()
+                .filter(|value| {
+                    let mut key = String::new();
+                    distinct_key(value, &mut key);
+                    let duplicate = last.as_ref() == Some(&key);
+                    last = Some(key);
+                    !duplicate
+                }).collect()
+        }
+        DistinctType::Unordered => {
+            let mut seen = HashSet::new();
+            merged
+                .into_iter()
+                .filter(|value| {
+                    let mut key = String::new();
+                    distinct_key(value, &mut key);
+                    seen.insert(key)
+                }).collect()
+        }
+    }
+}
+
+/// Groups the per-partition results of a cross-partition GROUP BY query.
+///
+/// Every partition returns one document per group it has seen, in the
+/// rewritten shape (`{ "groupByItems": [...], "payload": {...} }`) with the
+/// partial aggregates of the group in the payload. The partials of the
+/// same group are combined with the aggregate of their alias, the other
+/// aliases are taken from the first partition. Groups are returned in the
+/// order they are first seen.
+pub fn group_by(partitions: Vec<Vec<Value>>, query_plan: &QueryPlan) -> Result<Vec<Value>, AzureError> {
+    let mut keys: HashMap<String, usize> = HashMap::new();
+    let mut groups: Vec<Vec<Value>> = Vec::new();
+
+    for document in partitions.into_iter().flat_map(|partition| partition.into_iter()) {
+        let items = document
+            .get(GROUP_BY_ITEMS)
+            .ok_or_else(|| AzureError::GenericErrorWithText(format!("{} array not found in {}", GROUP_BY_ITEMS, document)))?;
+        let mut key = String::new();
+        distinct_key(items, &mut key);
+
+        let payload = match document {
+            Value::Object(mut document) => document.remove(PAYLOAD).unwrap_or(Value::Null),
+            document => document,
+        };
+        let next = groups.len();
+        let group = *keys.entry(key).or_insert(next);
+        if group == next {
+            groups.push(Vec::new());
+        }
+        groups[group].push(payload);
+    }
+
+    groups
+        .into_iter()
+        .map(|payloads| {
+            let aliases: Vec<String> = if query_plan.group_by_aliases.is_empty() {
+                payloads[0].as_object().map(|o| o.keys().cloned().collect()).unwrap_or_default()
+            } else {
+                query_plan.group_by_aliases.clone()
+            };
+
+            let mut result = ::serde_json::Map::new();
+            for alias in aliases {
+                let aggregate = query_plan.group_by_alias_to_aggregate_type.get(&alias).and_then(|a| *a);
+                let value = match aggregate {
+                    Some(operator) => {
+                        let partials: Vec<Value> = payloads.iter().filter_map(|p| p.get(&alias)).cloned().collect();
+                        Some(reduce_aggregate(operator, &partials)?)
+                    }
+                    None => payloads[0].get(&alias).cloned(),
+                };
+                // an undefined value leaves the alias out
+                if let Some(value) = value {
+                    result.insert(alias, value);
+                }
+            }
+
+            // SELECT VALUE projects a single alias
+            if query_plan.has_select_value && result.len() == 1 {
+                Ok(result.into_iter().next().unwrap().1)
+            } else {
+                Ok(Value::Object(result))
+            }
+        }).collect()
+}
+
+/// Combines the per-partition results of a cross-partition query as
+/// described by its plan: grouped if the query has a GROUP BY, merged in
+/// order if it has an ORDER BY (concatenated otherwise), deduplicated if it is a DISTINCT and finally
+/// trimmed to its limits.
+pub fn merge_with_query_plan(mut partitions: Vec<Vec<Value>>, query_plan: &QueryPlan) -> Result<Vec<Value>, AzureError> {
+    // the duplicates and the partial groups do not count towards the limits
+    // so the partitions can only be trimmed in advance when there are none
+    if query_plan.distinct_type == DistinctType::None && query_plan.group_by_expressions.is_empty() {
+        if let Some(take) = query_plan.limits.partition_take() {
+            for partition in &mut partitions {
+                partition.truncate(take as usize);
+            }
+        }
+    }
+
+    let merged = if !query_plan.group_by_expressions.is_empty() {
+        group_by(partitions, query_plan)?
+    } else if query_plan.sort_orders.is_empty() {
+        partitions.into_iter().flat_map(|partition| partition.into_iter()).collect()
+    } else {
+        merge_order_by(partitions, &query_plan.sort_orders)?
+    };
+
+    Ok(query_plan.limits.apply(distinct(merged, query_plan.distinct_type)))
+}
+
+/// Query metrics of every partition of a cross-partition query added up in
+/// a single view. Times and document counts are summed, the index hit
+/// ratio is weighted by the retrieved documents of each partition.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CombinedQueryMetrics {
+    pub partition_count: usize,
+    pub total: QueryMetrics,
+}
+
+impl CombinedQueryMetrics {
+    pub fn new() -> CombinedQueryMetrics {
+        CombinedQueryMetrics::default()
+    }
+
+    pub fn add(&mut self, metrics: &QueryMetrics) {
+        let total = &mut self.total;
+
+        let retrieved = total.retrieved_document_count + metrics.retrieved_document_count;
+        if retrieved > 0 {
+            total.index_hit_ratio = (total.index_hit_ratio * total.retrieved_document_count as f64
+                + metrics.index_hit_ratio * metrics.retrieved_document_count as f64)
+                / retrieved as f64;
+        }
+
+        total.total_execution_time += metrics.total_execution_time;
+        total.query_compile_time += metrics.query_compile_time;
+        total.query_logical_plan_build_time += metrics.query_logical_plan_build_time;
+        total.query_physical_plan_build_time += metrics.query_physical_plan_build_time;
+        total.query_optimization_time += metrics.query_optimization_time;
+        total.index_lookup_time += metrics.index_lookup_time;
+        total.document_load_time += metrics.document_load_time;
+        total.vm_execution_time += metrics.vm_execution_time;
+        total.system_function_execution_time += metrics.system_function_execution_time;
+        total.user_function_execution_time += metrics.user_function_execution_time;
+        total.document_write_time += metrics.document_write_time;
+        total.retrieved_document_count = retrieved;
+        total.retrieved_document_size += metrics.retrieved_document_size;
+        total.output_document_count += metrics.output_document_count;
+        total.output_document_size += metrics.output_document_size;
+
+        self.partition_count += 1;
+    }
+
+    /// Combines the metrics of the responses of every partition, once they
+    /// have all been received. Responses without metrics are skipped.
+    pub fn from_responses<T>(responses: &[QueryDocumentResponse<T>]) -> CombinedQueryMetrics {
+        responses
+            .iter()
+            .filter_map(|response| response.additional_headers.query_metrics.as_ref())
+            .collect()
+    }
+}
+
+impl<'a> FromIterator<&'a QueryMetrics> for CombinedQueryMetrics {
+    fn from_iter<I: IntoIterator<Item = &'a QueryMetrics>>(iter: I) -> CombinedQueryMetrics {
+        let mut combined = CombinedQueryMetrics::new();
+        for metrics in iter {
+            combined.add(metrics);
+        }
+        combined
+    }
+}
+
+/// The results of a query run on every partition key range of a
+/// collection, combined as its query plan describes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CrossPartitionQueryResponse<T> {
+    pub results: Vec<T>,
+    /// The charge of every page of every range, added up.
+    pub charge: f64,
+    /// The query metrics of every range, its pages added up.
+    pub query_metrics: CombinedQueryMetrics,
+}
+
+// The query sent to every partition: the rewritten query of the plan, if
+// any, with the parameters of the original one.
+pub(crate) fn partition_query(query_json: &str, query_plan: &QueryPlan) -> Result<String, AzureError> {
+    match query_plan.rewritten_query {
+        Some(ref rewritten_query) => {
+            let mut query = serde_json::from_str::<Value>(query_json)?;
+            query["query"] = Value::String(rewritten_query.clone());
+            Ok(serde_json::to_string(&query)?)
+        }
+        None => Ok(query_json.to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn doc(value: i64) -> Value {
+        serde_json::from_str(&format!(
+            r#"{{"_rid":"rid{0}","orderByItems":[{{"item":{0}}}],"payload":{{"id":"{0}","value":{0}}}}}"#,
+            value
+        )).unwrap()
+    }
+
+    fn values(merged: &[Value]) -> Vec<i64> {
+        merged.iter().map(|v| v["value"].as_i64().unwrap()).collect()
+    }
+
+    #[test]
+    fn merge_three_partitions_ascending() {
+        let partitions = vec![
+            vec![doc(1), doc(4), doc(7)],
+            vec![doc(2), doc(5), doc(8), doc(9)],
+            vec![doc(0), doc(3), doc(6)],
+        ];
+
+        let merged = merge_order_by(partitions, &[SortOrder::Ascending]).unwrap();
+        assert_eq!(values(&merged), vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(merged[0]["id"], "0");
+    }
+
+    #[test]
+    fn merge_three_partitions_descending() {
+        let partitions = vec![vec![doc(7), doc(4), doc(1)], vec![doc(8), doc(2)], vec![doc(6), doc(3), doc(0)]];
+
+        let merged = merge_order_by(partitions, &[SortOrder::Descending]).unwrap();
+        assert_eq!(values(&merged), vec![8, 7, 6, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn merge_mixed_types() {
+        let undefined: Value = serde_json::from_str(r#"{"orderByItems":[{}],"payload":{"value":-1}}"#).unwrap();
+        let string: Value = serde_json::from_str(r#"{"orderByItems":[{"item":"a"}],"payload":{"value":100}}"#).unwrap();
+
+        let merged = merge_order_by(vec![vec![doc(1), string], vec![undefined]], &[SortOrder::Ascending]).unwrap();
+        assert_eq!(values(&merged), vec![-1, 1, 100]);
+    }
+
+    #[test]
+    fn merge_missing_order_by_items() {
+        let plain: Value = serde_json::from_str(r#"{"id":"a"}"#).unwrap();
+        assert!(merge_order_by(vec![vec![plain]], &[SortOrder::Ascending]).is_err());
+    }
+
+    #[test]
+    fn parse_sort_orders() {
+        assert_eq!(sort_orders_from_query("SELECT * FROM c"), vec![]);
+        assert_eq!(
+            sort_orders_from_query("SELECT * FROM c ORDER BY c.a DESC, c.b"),
+            vec![SortOrder::Descending, SortOrder::Ascending]
+        );
+        assert_eq!(
+            sort_orders_from_query("SELECT * FROM c order by c._ts asc OFFSET 1 LIMIT 2"),
+            vec![SortOrder::Ascending]
+        );
+    }
+
+    #[test]
+    fn top_across_partitions() {
+        let query = "SELECT TOP 5 * FROM c ORDER BY c.value";
+        let limits = QueryLimits::from_query(query);
+        assert_eq!(limits.top, Some(5));
+
+        let partitions = vec![
+            vec![doc(1), doc(4), doc(7)],
+            vec![doc(2), doc(5), doc(8), doc(9)],
+            vec![doc(0), doc(3), doc(6)],
+        ];
+
+        let merged = merge_order_by_with_limits(partitions, &sort_orders_from_query(query), &limits).unwrap();
+        assert_eq!(values(&merged), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn offset_limit_across_partitions() {
+        let query = "SELECT * FROM c ORDER BY c.value DESC OFFSET 2 LIMIT 3";
+        let limits = QueryLimits::from_query(query);
+        assert_eq!(limits.offset, Some(2));
+        assert_eq!(limits.limit, Some(3));
+        assert_eq!(limits.partition_take(), Some(5));
+
+        let partitions = vec![vec![doc(7), doc(4), doc(1)], vec![doc(8), doc(2)], vec![doc(6), doc(3), doc(0)]];
+
+        let merged = merge_order_by_with_limits(partitions, &sort_orders_from_query(query), &limits).unwrap();
+        assert_eq!(values(&merged), vec![6, 4, 3]);
+    }
+
+    #[test]
+    fn limits_from_query_plan() {
+        let plan: Value =
+            serde_json::from_str(r#"{"partitionedQueryExecutionInfoVersion":2,"queryInfo":{"top":null,"offset":2,"limit":3,"orderBy":["Descending"]}}"#)
+                .unwrap();
+
+        let limits = QueryLimits::from_query_plan(&plan);
+        assert_eq!(
+            limits,
+            QueryLimits {
+                top: None,
+                offset: Some(2),
+                limit: Some(3),
+            }
+        );
+        assert!(QueryLimits::from_query("SELECT * FROM c").is_empty());
+    }
+
+    #[test]
+    fn parse_query_plan() {
+        let plan = QueryPlan::parse(
+            r#"{
+                "partitionedQueryExecutionInfoVersion": 2,
+                "queryInfo": {
+                    "distinctType": "None",
+                    "top": 10,
+                    "offset": null,
+                    "limit": null,
+                    "orderBy": ["Descending"],
+                    "orderByExpressions": ["c.ts"],
+                    "groupByExpressions": [],
+                    "groupByAliases": [],
+                    "aggregates": [],
+                    "groupByAliasToAggregateType": {},
+                    "rewrittenQuery": "SELECT TOP 10 c._rid, [{\"item\": c.ts}] AS orderByItems, c AS payload FROM c ORDER BY c.ts DESC",
+                    "hasSelectValue": false
+                },
+                "queryRanges": [{"min": "", "max": "FF", "isMinInclusive": true, "isMaxInclusive": false}]
+            }"#,
+        ).unwrap();
+
+        assert_eq!(plan.distinct_type, DistinctType::None);
+        assert_eq!(plan.limits.top, Some(10));
+        assert_eq!(plan.sort_orders, vec![SortOrder::Descending]);
+        assert_eq!(plan.order_by_expressions, vec!["c.ts"]);
+        assert!(plan.rewritten_query.as_ref().unwrap().starts_with("SELECT TOP 10 c._rid"));
+        assert!(plan.needs_client_side_merge());
+        assert!(!plan.needs_client_side_aggregation());
+
+        let plan = QueryPlan::parse(
+            r#"{"queryInfo": {"distinctType": "None", "aggregates": ["Count"], "rewrittenQuery": "", "hasSelectValue": true}}"#,
+        ).unwrap();
+        assert_eq!(plan.aggregates, vec![AggregateOperator::Count]);
+        assert_eq!(plan.rewritten_query, None);
+        assert!(plan.has_select_value);
+        assert!(!plan.needs_client_side_merge());
+        assert!(plan.needs_client_side_aggregation());
+
+        let plan = QueryPlan::parse(r#"{"queryInfo": {"distinctType": "None", "rewrittenQuery": ""}}"#).unwrap();
+        assert!(!plan.needs_client_side_processing());
+        assert!(QueryPlan::parse(r#"{"queryInfo": {"distinctType": "Sometimes"}}"#).is_err());
+    }
+
+    fn plan(distinct_type: DistinctType, sort_orders: Vec<SortOrder>) -> QueryPlan {
+        QueryPlan {
+            distinct_type,
+            limits: QueryLimits::default(),
+            sort_orders,
+            order_by_expressions: Vec::new(),
+            group_by_expressions: Vec::new(),
+            group_by_aliases: Vec::new(),
+            group_by_alias_to_aggregate_type: HashMap::new(),
+            aggregates: Vec::new(),
+            rewritten_query: None,
+            has_select_value: false,
+        }
+    }
+
+    #[test]
+    fn distinct_scalars_across_partitions() {
+        let partitions = vec![
+            serde_json::from_str::<Vec<Value>>(r#"["red", 1, true, "blue"]"#).unwrap(),
+            serde_json::from_str::<Vec<Value>>(r#"["blue", 1.0, "green", "red"]"#).unwrap(),
+        ];
+
+        let merged = merge_with_query_plan(partitions, &plan(DistinctType::Unordered, Vec::new())).unwrap();
+        let expected: Vec<Value> = serde_json::from_str(r#"["red", 1, true, "blue", "green"]"#).unwrap();
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn distinct_documents_across_partitions() {
+        let partitions = vec![
+            serde_json::from_str::<Vec<Value>>(r#"[{"city": "Milan", "zip": 20100}, {"city": "Rome", "zip": 100}]"#).unwrap(),
+            serde_json::from_str::<Vec<Value>>(r#"[{"zip": 100, "city": "Rome"}, {"city": "Turin", "zip": 10100}]"#).unwrap(),
+        ];
+
+        let merged = merge_with_query_plan(partitions, &plan(DistinctType::Unordered, Vec::new())).unwrap();
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged.iter().filter(|d| d["city"] == "Rome").count(), 1);
+
+        // ordered: the duplicates are next to each other after the merge
+        let partitions = vec![vec![doc(1), doc(3), doc(5)], vec![doc(1), doc(2), doc(5)]];
+        let mut query_plan = plan(DistinctType::Ordered, vec![SortOrder::Ascending]);
+        query_plan.limits.limit = Some(3);
+        let merged = merge_with_query_plan(partitions, &query_plan).unwrap();
+        assert_eq!(values(&merged), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn group_by_count_across_partitions() {
+        let query_plan = QueryPlan::parse(
+            r#"{"queryInfo": {
+                "distinctType": "None",
+                "groupByExpressions": ["c.city"],
+                "groupByAliases": ["city", "count"],
+                "groupByAliasToAggregateType": {"city": null, "count": "Count"},
+                "aggregates": [],
+                "hasSelectValue": false
+            }}"#,
+        ).unwrap();
+        assert!(query_plan.needs_client_side_aggregation());
+        assert_eq!(query_plan.group_by_alias_to_aggregate_type["count"], Some(AggregateOperator::Count));
+
+        let group = |city: &str, count: u64| -> Value {
+            serde_json::from_str(&format!(
+                r#"{{"groupByItems": [{{"item": "{0}"}}], "payload": {{"city": "{0}", "count": {{"item": {1}}}}}}}"#,
+                city, count
+            )).unwrap()
+        };
+        let partitions = vec![
+            vec![group("Milan", 2), group("Rome", 1)],
+            vec![group("Rome", 4), group("Turin", 3)],
+        ];
+
+        let grouped = merge_with_query_plan(partitions, &query_plan).unwrap();
+        let expected: Vec<Value> = serde_json::from_str(
+            r#"[{"city": "Milan", "count": 2}, {"city": "Rome", "count": 5}, {"city": "Turin", "count": 3}]"#,
+        ).unwrap();
+        assert_eq!(grouped, expected);
+    }
+
+    #[test]
+    fn count_across_partitions() {
+        let query = "SELECT VALUE COUNT(1) FROM c";
+        let operator = AggregateOperator::from_query(query).unwrap();
+        assert_eq!(operator, AggregateOperator::Count);
+
+        let partials: Vec<Value> = vec![
+            serde_json::from_str(r#"[{"item":3}]"#).unwrap(),
+            serde_json::from_str(r#"[{"item":4}]"#).unwrap(),
+        ];
+        assert_eq!(reduce_aggregate(operator, &partials).unwrap(), Value::from(7));
+        assert_eq!(reduce_aggregate(operator, &[]).unwrap(), Value::from(0));
+    }
+
+    #[test]
+    fn average_across_partitions() {
+        let plan: Value = serde_json::from_str(r#"{"queryInfo":{"aggregates":["Average"]}}"#).unwrap();
+        let operators = AggregateOperator::from_query_plan(&plan).unwrap();
+        assert_eq!(operators, vec![AggregateOperator::Average]);
+        assert_eq!(AggregateOperator::from_query("SELECT AVG(c.age) FROM c"), Some(AggregateOperator::Average));
+
+        // an average of the per partition averages would be 3.5
+        let partials: Vec<Value> = vec![
+            serde_json::from_str(r#"[{"item":{"sum":6.0,"count":3}}]"#).unwrap(),
+            serde_json::from_str(r#"[{"item":{"sum":5.0,"count":1}}]"#).unwrap(),
+            serde_json::from_str(r#"[{"item":{"count":0}}]"#).unwrap(),
+        ];
+        assert_eq!(reduce_aggregate(operators[0], &partials).unwrap(), Value::from(2.75));
+    }
+
+    #[test]
+    fn min_max_across_partitions() {
+        let partials: Vec<Value> = vec![
+            serde_json::from_str(r#"[{"item":{"min":4,"max":9,"count":2}}]"#).unwrap(),
+            serde_json::from_str(r#"[{"item":1}]"#).unwrap(),
+            serde_json::from_str(r#"[{"item":{"count":0}}]"#).unwrap(),
+        ];
+        assert_eq!(reduce_aggregate(AggregateOperator::Min, &partials).unwrap(), Value::from(1));
+        assert_eq!(reduce_aggregate(AggregateOperator::Max, &partials).unwrap(), Value::from(9));
+        assert_eq!(AggregateOperator::from_query("SELECT * FROM c"), None);
+    }
+
+    #[test]
+    fn combine_metrics_of_two_partitions() {
+        let first = QueryMetrics::parse(
+            "totalExecutionTimeInMs=10.50;documentLoadTimeInMs=4.00;retrievedDocumentCount=300;\
+             outputDocumentCount=100;outputDocumentSize=2048;indexUtilizationRatio=1.00",
+        ).unwrap();
+        let second = QueryMetrics::parse(
+            "totalExecutionTimeInMs=2.25;documentLoadTimeInMs=1.00;retrievedDocumentCount=100;\
+             outputDocumentCount=50;outputDocumentSize=1024;indexUtilizationRatio=0.20",
+        ).unwrap();
+
+        let combined: CombinedQueryMetrics = [first, second].iter().collect();
+        assert_eq!(combined.partition_count, 2);
+        assert_eq!(combined.total.total_execution_time, ::std::time::Duration::from_micros(12_750));
+        assert_eq!(combined.total.document_load_time, ::std::time::Duration::from_millis(5));
+        assert_eq!(combined.total.retrieved_document_count, 400);
+        assert_eq!(combined.total.output_document_count, 150);
+        assert_eq!(combined.total.output_document_size, 3072);
+        assert!((combined.total.index_hit_ratio - 0.8).abs() < 1e-9);
+
+        assert_eq!(CombinedQueryMetrics::from_responses::<Value>(&[]), CombinedQueryMetrics::new());
+    }
+
+    #[test]
+    fn rewritten_partition_query() {
+        let query_json = r#"{"query":"SELECT * FROM c ORDER BY c.value","parameters":[{"name":"@a","value":1}]}"#;
+        let mut query_plan = plan(DistinctType::None, vec![SortOrder::Ascending]);
+        assert_eq!(partition_query(query_json, &query_plan).unwrap(), query_json);
+
+        query_plan.rewritten_query = Some("SELECT c._rid, [{\"item\": c.value}] AS orderByItems, c AS payload FROM c".to_owned());
+        let sent: Value = serde_json::from_str(&partition_query(query_json, &query_plan).unwrap()).unwrap();
+        assert_eq!(sent["query"], "SELECT c._rid, [{\"item\": c.value}] AS orderByItems, c AS payload FROM c");
+        assert_eq!(sent["parameters"][0]["name"], "@a");
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Database {
     pub id: String,
     #[serde(rename = "_rid")]
@@ -14,3 +14,5 @@ pub struct Database {
     #[serde(rename = "_users")]
     pub users: String,
 }
+
+impl_etag!(Database, etag);
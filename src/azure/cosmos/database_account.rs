@@ -0,0 +1,67 @@
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Location {
+    pub name: String,
+    #[serde(rename = "databaseAccountEndpoint")]
+    pub database_account_endpoint: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DatabaseAccount {
+    pub id: String,
+    #[serde(rename = "_rid")]
+    pub rid: String,
+    #[serde(rename = "writableLocations", default)]
+    pub writable_locations: Vec<Location>,
+    #[serde(rename = "readableLocations", default)]
+    pub readable_locations: Vec<Location>,
+    #[serde(rename = "enableMultipleWriteLocations", default)]
+    pub enable_multiple_write_locations: bool,
+}
+
+impl DatabaseAccount {
+    /// Endpoints of the account, the writable ones first, in the order of
+    /// preference returned by Cosmos.
+    pub fn endpoints(&self) -> Vec<String> {
+        let mut endpoints: Vec<String> = Vec::new();
+        for location in self.writable_locations.iter().chain(self.readable_locations.iter()) {
+            if !endpoints.contains(&location.database_account_endpoint) {
+                endpoints.push(location.database_account_endpoint.clone());
+            }
+        }
+        endpoints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn parse_locations() {
+        let body = r#"{
+            "_self": "",
+            "id": "mindflavor",
+            "_rid": "mindflavor.documents.azure.com",
+            "writableLocations": [
+                {"name": "West Europe", "databaseAccountEndpoint": "https://mindflavor-westeurope.documents.azure.com:443/"}
+            ],
+            "readableLocations": [
+                {"name": "West Europe", "databaseAccountEndpoint": "https://mindflavor-westeurope.documents.azure.com:443/"},
+                {"name": "North Europe", "databaseAccountEndpoint": "https://mindflavor-northeurope.documents.azure.com:443/"}
+            ]
+        }"#;
+
+        let account: DatabaseAccount = serde_json::from_str(body).unwrap();
+        assert_eq!(account.id, "mindflavor");
+        assert_eq!(account.readable_locations[1].name, "North Europe");
+        assert!(!account.enable_multiple_write_locations);
+        assert_eq!(
+            account.endpoints(),
+            vec![
+                "https://mindflavor-westeurope.documents.azure.com:443/",
+                "https://mindflavor-northeurope.documents.azure.com:443/",
+            ]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use azure::core::util::HeaderValueOf;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
@@ -10,9 +11,15 @@ pub enum IndexingDirective {
 
 impl Display for IndexingDirective {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.header_value())
+    }
+}
+
+impl HeaderValueOf for IndexingDirective {
+    fn header_value(&self) -> &'static str {
         match *self {
-            IndexingDirective::Include => write!(f, "Include"),
-            IndexingDirective::Exclude => write!(f, "Exclude"),
+            IndexingDirective::Include => "Include",
+            IndexingDirective::Exclude => "Exclude",
         }
     }
 }
@@ -29,7 +36,7 @@ impl FromStr for IndexingDirective {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct DocumentAttributes {
     id: String,
     #[serde(rename = "_rid")]
@@ -44,7 +51,35 @@ pub struct DocumentAttributes {
     pub attachments: String,
 }
 
+impl_etag!(DocumentAttributes, etag);
+
 impl DocumentAttributes {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn rid(&self) -> &str {
+        &self.rid
+    }
+
+    pub fn self_link(&self) -> &str {
+        &self._self
+    }
+
+    pub fn ts(&self) -> u64 {
+        self.ts
+    }
+
+    /// Link of a resource nested in the document, for example
+    /// `child_link("attachments/myattachment")`.
+    pub fn child_link(&self, child: &str) -> String {
+        format!("{}/{}", self._self.trim_end_matches('/'), child.trim_start_matches('/'))
+    }
+
+    pub fn attachments_link(&self) -> String {
+        self.child_link(&self.attachments)
+    }
+
     pub(crate) fn try_extract(from: &mut ::serde_json::Map<String, ::serde_json::Value>) -> Option<DocumentAttributes> {
         let id = from.get("id")?.as_str()?.to_owned();
         let rid = from.remove("_rid")?.as_str()?.to_owned();
@@ -63,3 +98,58 @@ impl DocumentAttributes {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    const DOCUMENT: &str = r#"{
+        "id": "mydoc",
+        "_rid": "XP0mAJ3H-AABAAAAAAAAAA==",
+        "_ts": 1532866523,
+        "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/",
+        "_etag": "\"00002602-0000-0000-0000-5b5d3b5b0000\"",
+        "_attachments": "attachments/"
+    }"#;
+
+    #[test]
+    fn accessors() {
+        let attributes: DocumentAttributes = serde_json::from_str(DOCUMENT).unwrap();
+
+        assert_eq!(attributes.id(), "mydoc");
+        assert_eq!(attributes.rid(), "XP0mAJ3H-AABAAAAAAAAAA==");
+        assert_eq!(
+            attributes.self_link(),
+            "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/"
+        );
+        assert_eq!(attributes.etag().as_str(), "\"00002602-0000-0000-0000-5b5d3b5b0000\"");
+        assert_eq!(attributes.ts(), 1_532_866_523);
+    }
+
+    #[test]
+    fn round_trip() {
+        let attributes: DocumentAttributes = serde_json::from_str(DOCUMENT).unwrap();
+        let serialized = serde_json::to_string(&attributes).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&serialized).unwrap(),
+            serde_json::from_str::<serde_json::Value>(DOCUMENT).unwrap()
+        );
+        assert_eq!(serde_json::from_str::<DocumentAttributes>(&serialized).unwrap(), attributes);
+    }
+
+    #[test]
+    fn links() {
+        let attributes: DocumentAttributes = serde_json::from_str(DOCUMENT).unwrap();
+
+        assert_eq!(
+            attributes.attachments_link(),
+            "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/attachments/"
+        );
+        assert_eq!(
+            attributes.child_link("attachments/myattachment"),
+            "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/attachments/myattachment"
+        );
+    }
+}
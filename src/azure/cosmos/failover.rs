@@ -0,0 +1,260 @@
+use azure::core::errors::AzureError;
+use azure::core::util::delay;
+use futures::future::{err, loop_fn, ok, Loop};
+use futures::Future;
+use hyper::StatusCode;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Decides when a Cosmos account is considered failed over. After
+/// `unavailable_threshold` consecutive 503 responses the location list of
+/// the account is read again and the next endpoint is used. Every retry
+/// waits `backoff` first and at most `max_attempts` requests are sent.
+#[derive(Debug, Clone)]
+pub struct FailoverPolicy {
+    unavailable_threshold: u32,
+    max_attempts: u32,
+    backoff: Duration,
+}
+
+impl Default for FailoverPolicy {
+    fn default() -> FailoverPolicy {
+        FailoverPolicy {
+            unavailable_threshold: 2,
+            max_attempts: 5,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl FailoverPolicy {
+    pub fn new() -> FailoverPolicy {
+        FailoverPolicy::default()
+    }
+
+    pub fn unavailable_threshold(mut self, value: u32) -> Self {
+        self.unavailable_threshold = ::std::cmp::max(value, 1);
+        self
+    }
+
+    pub fn max_attempts(mut self, value: u32) -> Self {
+        self.max_attempts = ::std::cmp::max(value, 1);
+        self
+    }
+
+    pub fn backoff(mut self, value: Duration) -> Self {
+        self.backoff = value;
+        self
+    }
+}
+
+// The `x-ms-substatus` values of a 503 telling the region itself is
+// unavailable: the service is offline or the region failed to serve the
+// request. Other 503s (a busy partition, for example) are not failovers.
+const REGIONAL_UNAVAILABILITY_SUB_STATUSES: [u32; 2] = [9002, 21008];
+
+/// Whether the error is the 503 Cosmos returns while a region is
+/// unavailable.
+pub fn is_service_unavailable(error: &AzureError) -> bool {
+    match error {
+        AzureError::UnexpectedHTTPResult(result) => match result.sub_status() {
+            Some(sub_status) => {
+                result.status_code() == StatusCode::SERVICE_UNAVAILABLE && REGIONAL_UNAVAILABILITY_SUB_STATUSES.contains(&sub_status)
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+type BoxedFuture<T> = Box<dyn Future<Item = T, Error = AzureError>>;
+
+// Runs `operation` against the current endpoint, switching to the next
+// endpoint returned by `locate` when the policy says so.
+pub(crate) fn follow_failover<T, O, L>(
+    policy: FailoverPolicy,
+    endpoint: Arc<RwLock<String>>,
+    operation: O,
+    locate: L,
+) -> impl Future<Item = T, Error = AzureError>
+where
+    T: 'static,
+    O: Fn() -> BoxedFuture<T> + 'static,
+    L: Fn() -> BoxedFuture<Vec<String>> + 'static,
+{
+    let locate = Rc::new(locate);
+
+    loop_fn((1, 0), move |(attempt, unavailable): (u32, u32)| {
+        let policy = policy.clone();
+        let endpoint = endpoint.clone();
+        let locate = locate.clone();
+
+        operation().then(move |result| -> BoxedFuture<Loop<T, (u32, u32)>> {
+            match result {
+                Ok(value) => Box::new(ok(Loop::Break(value))),
+                Err(error) => {
+                    if !is_service_unavailable(&error) || attempt >= policy.max_attempts {
+                        return Box::new(err(error));
+                    }
+
+                    let unavailable = unavailable + 1;
+                    let backoff = policy.backoff;
+                    if unavailable < policy.unavailable_threshold {
+                        return Box::new(delay(backoff).map(move |_| Loop::Continue((attempt + 1, unavailable))));
+                    }
+
+                    debug!("{} consecutive 503s, reading the account locations again", unavailable);
+                    Box::new(
+                        locate()
+                            .map(move |endpoints| switch_endpoint(&endpoint, &endpoints))
+                            .and_then(move |_| delay(backoff))
+                            .map(move |_| Loop::Continue((attempt + 1, 0))),
+                    )
+                }
+            }
+        })
+    })
+}
+
+// Moves to the endpoint following the current one, wrapping around and
+// never picking the current one again. The endpoint is kept when the
+// account lists no other.
+fn switch_endpoint(endpoint: &RwLock<String>, endpoints: &[String]) {
+    let mut endpoint = endpoint.write().unwrap();
+    let start = endpoints
+        .iter()
+        .position(|e| same_endpoint(e, &endpoint))
+        .map_or(0, |current| current + 1);
+    let next = endpoints
+        .iter()
+        .cycle()
+        .skip(start)
+        .take(endpoints.len())
+        .find(|e| !same_endpoint(e, &endpoint));
+
+    match next {
+        Some(next) => {
+            info!("failing over from {} to {}", *endpoint, next);
+            *endpoint = next.clone();
+        }
+        None => warn!("no endpoint other than {} to fail over to", *endpoint),
+    }
+}
+
+// The endpoints are compared ignoring the trailing slash `set_endpoint`
+// adds.
+fn same_endpoint(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure::core::errors::UnexpectedHTTPResult;
+    use std::cell::Cell;
+
+    const PRIMARY: &str = "https://mindflavor-westeurope.documents.azure.com:443/";
+    const SECONDARY: &str = "https://mindflavor-northeurope.documents.azure.com:443/";
+
+    fn unavailable() -> AzureError {
+        AzureError::UnexpectedHTTPResult(
+            UnexpectedHTTPResult::new(StatusCode::OK, StatusCode::SERVICE_UNAVAILABLE, "").with_sub_status(Some(21008)),
+        )
+    }
+
+    #[test]
+    fn fail_over_to_secondary() {
+        let endpoint = Arc::new(RwLock::new(PRIMARY.to_owned()));
+        let calls = Rc::new(Cell::new(0));
+        let locations_read = Rc::new(Cell::new(0));
+
+        let operation = {
+            let endpoint = endpoint.clone();
+            let calls = calls.clone();
+            move || -> BoxedFuture<String> {
+                calls.set(calls.get() + 1);
+                let endpoint = endpoint.read().unwrap().clone();
+                if endpoint == PRIMARY {
+                    Box::new(err(unavailable()))
+                } else {
+                    Box::new(ok(endpoint))
+                }
+            }
+        };
+        let locate = {
+            let locations_read = locations_read.clone();
+            move || -> BoxedFuture<Vec<String>> {
+                locations_read.set(locations_read.get() + 1);
+                Box::new(ok(vec![PRIMARY.to_owned(), SECONDARY.to_owned()]))
+            }
+        };
+
+        let policy = FailoverPolicy::new().unavailable_threshold(2).backoff(Duration::from_millis(1));
+        let mut core = ::tokio_core::reactor::Core::new().unwrap();
+        let result = core.run(follow_failover(policy, endpoint.clone(), operation, locate)).unwrap();
+
+        assert_eq!(result, SECONDARY);
+        assert_eq!(*endpoint.read().unwrap(), SECONDARY);
+        assert_eq!(calls.get(), 3);
+        assert_eq!(locations_read.get(), 1);
+    }
+
+    #[test]
+    fn give_up_after_max_attempts() {
+        let endpoint = Arc::new(RwLock::new(PRIMARY.to_owned()));
+        let calls = Rc::new(Cell::new(0));
+
+        let operation = {
+            let calls = calls.clone();
+            move || -> BoxedFuture<()> {
+                calls.set(calls.get() + 1);
+                Box::new(err(unavailable()))
+            }
+        };
+        let locate = || -> BoxedFuture<Vec<String>> { Box::new(ok(vec![PRIMARY.to_owned()])) };
+
+        let policy = FailoverPolicy::new().max_attempts(3).backoff(Duration::from_secs(0));
+        match follow_failover(policy, endpoint, operation, locate).wait() {
+            Err(ref e) if is_service_unavailable(e) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let endpoint = Arc::new(RwLock::new(PRIMARY.to_owned()));
+        let operation = || -> BoxedFuture<()> { Box::new(err(AzureError::GenericError)) };
+        let locate = || -> BoxedFuture<Vec<String>> { panic!("locations must not be read") };
+
+        match follow_failover(FailoverPolicy::new(), endpoint, operation, locate).wait() {
+            Err(AzureError::GenericError) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn only_regional_unavailability_fails_over() {
+        let busy = UnexpectedHTTPResult::new(StatusCode::OK, StatusCode::SERVICE_UNAVAILABLE, "");
+        assert!(!is_service_unavailable(&AzureError::UnexpectedHTTPResult(busy.clone())));
+        let busy = busy.with_sub_status(Some(1007));
+        assert!(!is_service_unavailable(&AzureError::UnexpectedHTTPResult(busy)));
+        assert!(is_service_unavailable(&unavailable()));
+    }
+
+    #[test]
+    fn switch_endpoint_skips_the_current_one() {
+        let endpoint = RwLock::new(SECONDARY.to_owned());
+        switch_endpoint(&endpoint, &[PRIMARY.to_owned(), SECONDARY.to_owned()]);
+        assert_eq!(*endpoint.read().unwrap(), PRIMARY);
+
+        let endpoint = RwLock::new(PRIMARY.trim_end_matches('/').to_owned());
+        switch_endpoint(&endpoint, &[PRIMARY.to_owned(), SECONDARY.to_owned()]);
+        assert_eq!(*endpoint.read().unwrap(), SECONDARY);
+
+        let endpoint = RwLock::new(PRIMARY.to_owned());
+        switch_endpoint(&endpoint, &[PRIMARY.to_owned()]);
+        assert_eq!(*endpoint.read().unwrap(), PRIMARY);
+    }
+}
@@ -1,19 +1,34 @@
+pub mod attachment;
 mod authorization_token;
+pub mod bulk_create;
+pub mod bulk_delete;
+pub mod change_feed;
 mod client;
 pub mod database;
+pub mod database_account;
+pub mod failover;
+pub mod navigation;
+pub mod offer;
 mod requests;
+pub mod ru_budget;
+pub mod stored_procedure;
+mod uri_builder;
 
 pub mod request_response;
 
 pub mod collection;
+pub mod conflict;
+pub mod cross_partition;
 pub mod document;
 mod partition_key;
 pub mod query;
+pub mod rid;
 
 pub use self::authorization_token::*;
 pub use self::client::*;
 pub use self::partition_key::*;
 pub use self::requests::*;
+pub use self::uri_builder::{ResourceUri, UriBuilder};
 
 use azure::core::enumerations;
 use azure::core::errors::TraversingError;
@@ -28,3 +43,6 @@ create_enum!(
     (Session, "Session"),
     (Eventual, "Eventual")
 );
+
+// Under throttling the requests of low priority are rejected first.
+create_enum!(PriorityLevel, (High, "High"), (Low, "Low"));
@@ -0,0 +1,177 @@
+use azure::core::errors::AzureError;
+use azure::cosmos::collection::Collection;
+use azure::cosmos::database::Database;
+use azure::cosmos::query::Query;
+use azure::cosmos::{
+    Client, CreateDocumentRequest, DeleteDocumentRequest, GetCollectionRequest, GetDocumentRequest, ListDocumentsRequest,
+    QueryDocumentRequest, ResourceUri, UriBuilder,
+};
+use futures::Future;
+use serde::Serialize;
+
+/// A database reached from a `Client`, to get to its collections without
+/// repeating the database id at every call, as in
+/// `client.database("shop").collection("orders").document("1").get()`.
+#[derive(Clone)]
+pub struct DatabaseRef<'a> {
+    client: &'a Client,
+    id: String,
+}
+
+impl<'a> DatabaseRef<'a> {
+    pub(crate) fn new<S: Into<String>>(client: &'a Client, id: S) -> DatabaseRef<'a> {
+        DatabaseRef { client, id: id.into() }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn uri(&self) -> Result<ResourceUri, AzureError> {
+        UriBuilder::new().database(&self.id).build()
+    }
+
+    pub fn collection<S: Into<String>>(&self, id: S) -> CollectionRef<'a> {
+        CollectionRef {
+            client: self.client,
+            database_id: self.id.clone(),
+            id: id.into(),
+        }
+    }
+
+    pub fn get(&self) -> impl Future<Item = Database, Error = AzureError> {
+        self.client.get_database(&self.id)
+    }
+
+    pub fn delete(&self) -> impl Future<Item = (), Error = AzureError> {
+        self.client.delete_database(&self.id)
+    }
+
+    pub fn list_collections(&self) -> impl Future<Item = Vec<Collection>, Error = AzureError> {
+        self.client.list_collections(&self.id)
+    }
+}
+
+/// A collection reached from a `DatabaseRef`.
+#[derive(Clone)]
+pub struct CollectionRef<'a> {
+    client: &'a Client,
+    database_id: String,
+    id: String,
+}
+
+impl<'a> CollectionRef<'a> {
+    pub fn database_id(&self) -> &str {
+        &self.database_id
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn uri(&self) -> Result<ResourceUri, AzureError> {
+        UriBuilder::new().database(&self.database_id).collection(&self.id).build()
+    }
+
+    pub fn document<S: Into<String>>(&self, id: S) -> DocumentRef<'a> {
+        DocumentRef {
+            client: self.client,
+            database_id: self.database_id.clone(),
+            collection_id: self.id.clone(),
+            id: id.into(),
+        }
+    }
+
+    pub fn get(&self) -> GetCollectionRequest {
+        self.client.get_collection(&self.database_id, &self.id)
+    }
+
+    pub fn delete(&self) -> impl Future<Item = (), Error = AzureError> {
+        self.client.delete_collection(&self.database_id, &self.id)
+    }
+
+    pub fn create_document<T: Serialize>(&self, document: &T) -> CreateDocumentRequest {
+        self.client.create_document(&self.database_id, &self.id, document)
+    }
+
+    pub fn list_documents(&self) -> ListDocumentsRequest {
+        self.client.list_documents(&self.database_id, &self.id)
+    }
+
+    pub fn query_document<'b>(&self, query: &Query<'b>) -> QueryDocumentRequest {
+        self.client.query_document(&self.database_id, &self.id, query)
+    }
+}
+
+/// A document reached from a `CollectionRef`.
+#[derive(Clone)]
+pub struct DocumentRef<'a> {
+    client: &'a Client,
+    database_id: String,
+    collection_id: String,
+    id: String,
+}
+
+impl<'a> DocumentRef<'a> {
+    pub fn database_id(&self) -> &str {
+        &self.database_id
+    }
+
+    pub fn collection_id(&self) -> &str {
+        &self.collection_id
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn uri(&self) -> Result<ResourceUri, AzureError> {
+        UriBuilder::new()
+            .database(&self.database_id)
+            .collection(&self.collection_id)
+            .document(&self.id)
+            .build()
+    }
+
+    pub fn get(&self) -> GetDocumentRequest {
+        self.client.get_document(&self.database_id, &self.collection_id, &self.id)
+    }
+
+    pub fn delete(&self) -> DeleteDocumentRequest {
+        self.client.delete_document(&self.database_id, &self.collection_id, &self.id)
+    }
+}
+
+impl Database {
+    /// Navigates from a database read from Cosmos, see `DatabaseRef`.
+    pub fn with_client<'a>(&self, client: &'a Client) -> DatabaseRef<'a> {
+        DatabaseRef::new(client, self.id.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure::cosmos::{AuthorizationToken, TokenType};
+
+    #[test]
+    fn navigate_to_document() {
+        let auth_token = AuthorizationToken::new("mindflavor".to_owned(), TokenType::Master, "ZHVtbXk=").unwrap();
+        let client = Client::new(auth_token).unwrap();
+
+        let database = client.database("my db");
+        assert_eq!(database.uri().unwrap().path(), "dbs/my%20db");
+
+        let collection = database.collection("orders");
+        assert_eq!(collection.uri().unwrap().path(), "dbs/my%20db/colls/orders");
+
+        let document = collection.document("città");
+        assert_eq!(document.database_id(), "my db");
+        assert_eq!(document.collection_id(), "orders");
+        let uri = document.uri().unwrap();
+        assert_eq!(uri.link(), "dbs/my db/colls/orders/docs/città");
+        assert_eq!(uri.path(), "dbs/my%20db/colls/orders/docs/citt%C3%A0");
+
+        assert!(database.collection("a/b").document("c").uri().is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use azure::core::errors::{AzureError, UnexpectedHTTPResult};
+use azure::cosmos::client::headers::HEADER_COSMOS_MIN_THROUGHPUT;
+use azure::cosmos::collection::Collection;
+use azure::cosmos::database::Database;
+use azure::cosmos::query::{Parameter, Query};
+use http::HeaderMap;
+use hyper::StatusCode;
+use serde_json::{self, Map, Value};
+use std::collections::HashMap;
+use std::str;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OfferContent {
+    #[serde(rename = "offerThroughput")]
+    pub offer_throughput: u64,
+    /// Set on the offers scaling their throughput on their own, see
+    /// `Client::replace_offer_autoscale`.
+    #[serde(rename = "offerAutopilotSettings", default, skip_serializing_if = "Option::is_none")]
+    pub offer_autopilot_settings: Option<AutopilotSettings>,
+    /// The other settings of the content, sent back as they are.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The autoscale settings of an offer: Cosmos scales its throughput
+/// between a tenth of `max_throughput` and `max_throughput`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AutopilotSettings {
+    #[serde(rename = "maxThroughput")]
+    pub max_throughput: i32,
+    /// The other settings, sent back as they are.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The throughput provisioned on a database or a collection.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Offer {
+    pub id: String,
+    #[serde(rename = "_rid")]
+    pub rid: String,
+    #[serde(rename = "offerVersion")]
+    pub offer_version: String,
+    /// The `_self` link of the database or collection.
+    pub resource: String,
+    /// The `_rid` of the database or collection.
+    #[serde(rename = "offerResourceId")]
+    pub offer_resource_id: String,
+    pub content: OfferContent,
+    /// The fields not modeled above, such as `offerType`, `_self` or
+    /// `_etag`: `Client::replace_offer` sends them back untouched.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Throughput of a collection, in RU/s.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Throughput {
+    /// Provisioned on the collection itself.
+    Dedicated(u64),
+    /// Provisioned on the database and shared by its collections without
+    /// a throughput of their own.
+    Shared(u64),
+}
+
+pub(crate) fn offer_query(resource_rid: &str) -> Result<String, serde_json::Error> {
+    let mut query = Query::new("SELECT * FROM root r WHERE r.offerResourceId = @rid");
+    query
+        .parameters_mut()
+        .push(Parameter::new().name("@rid").value(resource_rid).build());
+    serde_json::to_string(&query)
+}
+
+// The offers are keyed by the `_rid` of their resource. Serverless
+// accounts have no offers at all: their collections are left out.
+pub(crate) fn collections_throughput(
+    databases: &[(Database, Vec<Collection>)],
+    offers: &HashMap<String, Option<Offer>>,
+) -> Vec<(String, String, Throughput)> {
+    let throughput_of = |rid: &str| match offers.get(rid) {
+        Some(Some(offer)) => Some(offer.content.offer_throughput),
+        _ => None,
+    };
+
+    let mut throughputs = Vec::new();
+    for (database, collections) in databases {
+        let shared = throughput_of(&database.rid).map(Throughput::Shared);
+        for collection in collections {
+            if let Some(throughput) = throughput_of(&collection.rid).map(Throughput::Dedicated).or(shared) {
+                throughputs.push((database.id.clone(), collection.id.clone(), throughput));
+            }
+        }
+    }
+    throughputs.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+    throughputs
+}
+
+// The minimum comes in a header. Older accounts give it only in the text
+// of the error, such as "Requested throughput 300 is less than required
+// minimum throughput 400" or "... valid throughput values between 400
+// and 1000000 inclusive ...", which is parsed as a last resort.
+fn minimum_throughput(headers: &HeaderMap, body: &str, requested: u64) -> Option<u64> {
+    let from_header = headers
+        .get(HEADER_COSMOS_MIN_THROUGHPUT)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+
+    let number_after = |marker: &str| {
+        body.find(marker).and_then(|start| {
+            let digits = body[start + marker.len()..]
+                .chars()
+                .skip_while(|c| !c.is_ascii_digit())
+                .take_while(char::is_ascii_digit)
+                .collect::<String>();
+            digits.parse::<u64>().ok()
+        })
+    };
+
+    from_header
+        .or_else(|| number_after("minimum"))
+        .or_else(|| number_after("between"))
+        .filter(|minimum| *minimum > requested)
+}
+
+pub(crate) fn replaced_offer(status: StatusCode, headers: &HeaderMap, body: &[u8], requested: u64) -> Result<Offer, AzureError> {
+    let body = str::from_utf8(body)?;
+    if status == StatusCode::OK {
+        Ok(serde_json::from_str(body)?)
+    } else if let (StatusCode::BAD_REQUEST, Some(minimum)) = (status, minimum_throughput(headers, body, requested)) {
+        Err(AzureError::ThroughputTooLowError { minimum })
+    } else {
+        Err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(
+            StatusCode::OK,
+            status,
+            body,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_offer() {
+        let body = r#"{
+            "offerVersion": "V2",
+            "offerType": "Invalid",
+            "content": {"offerThroughput": 400, "offerIsRUPerMinuteThroughputEnabled": false},
+            "resource": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/",
+            "offerResourceId": "XP0mAJ3H-AA=",
+            "id": "uRMe",
+            "_rid": "uRMe",
+            "_self": "offers/uRMe/",
+            "_etag": "\"00000200-0000-0000-0000-5b5d3e9d0000\"",
+            "_ts": 1532866205
+        }"#;
+
+        let offer: Offer = serde_json::from_str(body).unwrap();
+        assert_eq!(offer.offer_resource_id, "XP0mAJ3H-AA=");
+        assert_eq!(offer.content.offer_throughput, 400);
+
+        assert_eq!(offer.extra["offerType"], "Invalid");
+        assert_eq!(offer.content.extra["offerIsRUPerMinuteThroughputEnabled"], false);
+        let sent: Value = serde_json::to_value(&offer).unwrap();
+        assert_eq!(sent, serde_json::from_str::<Value>(body).unwrap());
+
+        let query: serde_json::Value = serde_json::from_str(&offer_query("XP0mAJ3H-AA=").unwrap()).unwrap();
+        assert_eq!(query["parameters"][0]["value"], "XP0mAJ3H-AA=");
+    }
+
+    #[test]
+    fn throughput_too_low() {
+        let body = r#"{"code":"BadRequest","message":"Message: {\"Errors\":[\"Requested throughput 300 is less than required minimum throughput 1400\"]}\r\nActivityId: 2a5c0e09-3bd2-4a8a-a1a7-a3b0e77e8ee8, Request URI: /apps/9d0c/services/5f1e/partitions/2b9b/replicas/1326p/, RequestStats: , SDK: Microsoft.Azure.Documents.Common/2.14.0"}"#;
+        match replaced_offer(StatusCode::BAD_REQUEST, &HeaderMap::new(), body.as_bytes(), 300) {
+            Err(AzureError::ThroughputTooLowError { minimum }) => assert_eq!(minimum, 1400),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let body = r#"{"code":"BadRequest","message":"Message: {\"Errors\":[\"The offer should have valid throughput values between 400 and 1000000 inclusive in increments of 100.\"]}"}"#;
+        match replaced_offer(StatusCode::BAD_REQUEST, &HeaderMap::new(), body.as_bytes(), 200) {
+            Err(AzureError::ThroughputTooLowError { minimum }) => assert_eq!(minimum, 400),
+            r => panic!("unexpected result {:?}", r),
+        }
+        // too high rather than too low
+        match replaced_offer(StatusCode::BAD_REQUEST, &HeaderMap::new(), body.as_bytes(), 2_000_000) {
+            Err(AzureError::UnexpectedHTTPResult(r)) => assert_eq!(r.status_code(), StatusCode::BAD_REQUEST),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_COSMOS_MIN_THROUGHPUT, "600".parse().unwrap());
+        match replaced_offer(StatusCode::BAD_REQUEST, &headers, b"{}", 400) {
+            Err(AzureError::ThroughputTooLowError { minimum }) => assert_eq!(minimum, 600),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let offer = r#"{"id": "uRMe", "_rid": "uRMe", "offerVersion": "V2", "resource": "dbs/XP0mAA==/",
+            "offerResourceId": "XP0mAA==", "content": {"offerThroughput": 1400}}"#;
+        let offer = replaced_offer(StatusCode::OK, &HeaderMap::new(), offer.as_bytes(), 1400).unwrap();
+        assert_eq!(offer.content.offer_throughput, 1400);
+        assert_eq!(offer.content.offer_autopilot_settings, None);
+    }
+
+    #[test]
+    fn parse_autoscale_offer() {
+        let body = r#"{"id": "uRMe", "_rid": "uRMe", "offerVersion": "V2", "resource": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/",
+            "offerResourceId": "XP0mAJ3H-AA=", "content": {"offerThroughput": 400,
+            "offerAutopilotSettings": {"maxThroughput": 4000, "targetMaxThroughput": null}}}"#;
+
+        let offer: Offer = serde_json::from_str(body).unwrap();
+        let settings = offer.content.offer_autopilot_settings.as_ref().unwrap();
+        assert_eq!(settings.max_throughput, 4000);
+        assert_eq!(settings.extra["targetMaxThroughput"], Value::Null);
+        assert_eq!(serde_json::to_value(&offer).unwrap(), serde_json::from_str::<Value>(body).unwrap());
+    }
+}
@@ -1,23 +1,55 @@
 use azure::core::errors::AzureError;
-use serde_json;
+use serde_json::{self, Value};
 use smallvec::{IntoIter, SmallVec};
 use std::borrow::Cow;
 use std::iter::IntoIterator;
 
 #[derive(Debug, Clone)]
 pub struct PartitionKey<'a> {
-    pk: Option<SmallVec<[Cow<'a, str>; 2]>>,
+    pk: Key<'a>,
+}
+
+#[derive(Debug, Clone)]
+enum Key<'a> {
+    // no header is sent
+    Unset,
+    Values(SmallVec<[Cow<'a, str>; 2]>),
+    None,
+    Undefined,
+    Null,
 }
 
 impl<'a> PartitionKey<'a> {
+    /// The key of the documents of a collection without partition key,
+    /// sent as `[]`. Cosmos accepts the requests on such collections without
+    /// a key too.
+    pub fn none() -> Self {
+        PartitionKey { pk: Key::None }
+    }
+
+    /// The key of the documents of a partitioned collection without a value
+    /// at the partition key path, sent as `[{}]`. It is not the key of the
+    /// documents with a null value there, see `null`.
+    pub fn undefined() -> Self {
+        PartitionKey { pk: Key::Undefined }
+    }
+
+    /// The key of the documents with a null value at the partition key path,
+    /// sent as `[null]`.
+    pub fn null() -> Self {
+        PartitionKey { pk: Key::Null }
+    }
+
+    /// Adds a value to the key. Chained to `none`, `undefined` or `null` it
+    /// starts a key of its own.
     pub fn chain<S: Into<Cow<'a, str>>>(mut self, key: S) -> Self {
         match self.pk {
-            Some(ref mut p) => p.push(key.into()),
-            None => {
+            Key::Values(ref mut p) => p.push(key.into()),
+            _ => {
                 self.pk = {
                     let mut vec = SmallVec::new();
                     vec.push(key.into());
-                    Some(vec)
+                    Key::Values(vec)
                 }
             }
         }
@@ -27,15 +59,18 @@ impl<'a> PartitionKey<'a> {
     pub fn to_json(&self) -> Result<Option<String>, AzureError> {
         match self.pk {
             // the partition key should be a json formatted string list
-            Some(ref val) => Ok(Some(serde_json::to_string(val)?)),
-            None => Ok(None),
+            Key::Values(ref val) => Ok(Some(serde_json::to_string(val)?)),
+            Key::None => Ok(Some("[]".to_owned())),
+            Key::Undefined => Ok(Some("[{}]".to_owned())),
+            Key::Null => Ok(Some("[null]".to_owned())),
+            Key::Unset => Ok(None),
         }
     }
 }
 
 impl<'a> ::std::default::Default for PartitionKey<'a> {
     fn default() -> Self {
-        PartitionKey { pk: None }
+        PartitionKey { pk: Key::Unset }
     }
 }
 
@@ -43,10 +78,11 @@ impl<'a> IntoIterator for PartitionKey<'a> {
     type Item = Cow<'a, str>;
     type IntoIter = IntoIter<[Cow<'a, str>; 2]>;
 
+    /// The values of the key, none for `none`, `undefined` and `null`.
     fn into_iter(self) -> Self::IntoIter {
         match self.pk {
-            Some(p) => p.into_iter(),
-            None => SmallVec::new().into_iter(),
+            Key::Values(p) => p.into_iter(),
+            _ => SmallVec::new().into_iter(),
         }
     }
 }
@@ -56,3 +92,30 @@ impl<'a, S: Into<Cow<'a, str>>> From<S> for PartitionKey<'a> {
         PartitionKey::default().chain(v)
     }
 }
+
+// The value found at the partition key `path` of a collection, e.g.
+// `/customer/id`, in a document.
+pub(crate) fn partition_key_value<'v>(document: &'v Value, path: &str) -> Option<&'v Value> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(document, |value, segment| value.get(segment.trim_matches('"')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partition_key_json() {
+        assert_eq!(PartitionKey::default().to_json().unwrap(), None);
+        assert_eq!(PartitionKey::from("tenant").to_json().unwrap().as_deref(), Some("[\"tenant\"]"));
+        assert_eq!(PartitionKey::none().to_json().unwrap().as_deref(), Some("[]"));
+        assert_eq!(PartitionKey::undefined().to_json().unwrap().as_deref(), Some("[{}]"));
+        assert_eq!(
+            PartitionKey::undefined().chain("tenant").to_json().unwrap().as_deref(),
+            Some("[\"tenant\"]")
+        );
+        assert_eq!(PartitionKey::null().to_json().unwrap().as_deref(), Some("[null]"));
+        assert_eq!(PartitionKey::none().into_iter().count(), 0);
+    }
+}
@@ -1,3 +1,4 @@
+use azure::core::errors::AzureError;
 use std::marker::PhantomData;
 
 pub trait Complete {}
@@ -115,6 +116,84 @@ impl<'a> From<&'a str> for Query<'a> {
     }
 }
 
+// Keywords that cannot be used as a plain property name after a dot.
+const RESERVED_WORDS: &str = "and array as asc between by desc distinct escape exists false from group in join like limit not null \
+                              offset or order select top true udf undefined value where";
+
+fn is_identifier(segment: &str) -> bool {
+    let mut chars = segment.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RESERVED_WORDS.split_whitespace().any(|word| word.eq_ignore_ascii_case(segment))
+}
+
+// Writes a dotted path (`c.address.city`) the way Cosmos SQL accepts it:
+// properties that are not plain identifiers use the quoted form, so
+// `c.first-name` becomes `c["first-name"]`.
+fn escape_path(path: &str) -> Result<(&str, String), AzureError> {
+    let invalid = |reason: &str| AzureError::InputParametersError(format!("invalid field path {:?}: {}", path, reason));
+
+    let mut segments = path.split('.');
+    let root = segments.next().unwrap_or("");
+    if !is_identifier(root) {
+        return Err(invalid("it must start with the alias of the collection"));
+    }
+
+    let mut escaped = root.to_owned();
+    for segment in segments {
+        if segment.is_empty() {
+            return Err(invalid("empty property name"));
+        } else if is_identifier(segment) {
+            escaped.push('.');
+            escaped.push_str(segment);
+        } else {
+            escaped.push_str("[\"");
+            for c in segment.chars() {
+                match c {
+                    '"' => escaped.push_str("\\\""),
+                    '\\' => escaped.push_str("\\\\"),
+                    c => escaped.push(c),
+                }
+            }
+            escaped.push_str("\"]");
+        }
+    }
+
+    Ok((root, escaped))
+}
+
+/// Builds a `SELECT` projecting only the given fields, for example
+/// `select_projection(&["c.id", "c.name"])` gives `SELECT c.id, c.name FROM c`.
+/// Every field must start with the same alias, used in the `FROM` clause.
+/// Cosmos names the returned properties after the last segment of each
+/// path so the documents can be read into a struct with just those fields.
+pub fn select_projection(fields: &[&str]) -> Result<String, AzureError> {
+    if fields.is_empty() {
+        return Err(AzureError::InputParametersError("at least one field must be selected".to_owned()));
+    }
+
+    let mut alias = None;
+    let mut projection = Vec::with_capacity(fields.len());
+    for field in fields {
+        let (root, escaped) = escape_path(field)?;
+        match alias {
+            Some(alias) if alias != root => {
+                return Err(AzureError::InputParametersError(format!(
+                    "the field {:?} does not start with the alias {:?} of the other fields",
+                    field, alias
+                )))
+            }
+            _ => alias = Some(root),
+        }
+        projection.push(escaped);
+    }
+
+    Ok(format!("SELECT {} FROM {}", projection.join(", "), alias.unwrap()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -153,4 +232,22 @@ mod tests {
             r#"{"query":"SELECT * FROM Table","parameters":[{"name":"p1","value":"string"},{"name":"p2","value":"100"},{"name":"p3","value":"palazzo"}]}"#
         );
     }
+
+    #[test]
+    fn projection() {
+        assert_eq!(select_projection(&["c.id", "c.name"]).unwrap(), "SELECT c.id, c.name FROM c");
+        assert_eq!(
+            select_projection(&["doc.address.city", "doc.first-name", "doc.value", "doc.say \"hi\""]).unwrap(),
+            r#"SELECT doc.address.city, doc["first-name"], doc["value"], doc["say \"hi\""] FROM doc"#
+        );
+
+        assert_eq!(select_projection(&["c.id; DROP c"]).unwrap(), r#"SELECT c["id; DROP c"] FROM c"#);
+
+        for fields in &[&[][..], &["c.id", "d.name"], &["c..id"], &["1c.id"]] {
+            match select_projection(fields) {
+                Err(AzureError::InputParametersError(_)) => {}
+                r => panic!("unexpected result {:?} for {:?}", r, fields),
+            }
+        }
+    }
 }
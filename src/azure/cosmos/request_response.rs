@@ -1,8 +1,22 @@
-use azure::core::{errors::AzureError, util::HeaderMapExt};
-use azure::cosmos::{client::headers::HEADER_REQUEST_CHARGE, collection::Collection, database::Database, document::DocumentAttributes};
+use azure::core::{clock_skew::server_time_from_headers, errors::AzureError, incompletevector::ContinuationToken, util::HeaderMapExt};
+use azure::cosmos::{
+    attachment::Attachment,
+    change_feed::PartitionKeyRange,
+    client::headers::{HEADER_DOCUMENTDB_QUERY_METRICS, HEADER_REQUEST_CHARGE, HEADER_RESOURCE_QUOTA, HEADER_RESOURCE_USAGE},
+    collection::Collection,
+    conflict::Conflict,
+    cross_partition::QueryPlan,
+    database::Database,
+    document::DocumentAttributes,
+    offer::Offer,
+    stored_procedure::StoredProcedure,
+};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use serde::de::DeserializeOwned;
+use std::time::Duration;
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[allow(dead_code)]
 pub struct ListDatabasesResponse {
     _rid: String,
@@ -12,7 +26,7 @@ pub struct ListDatabasesResponse {
     pub count: u32,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[allow(dead_code)]
 pub struct ListCollectionsResponse {
     _rid: String,
@@ -22,6 +36,55 @@ pub struct ListCollectionsResponse {
     pub count: u32,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+#[allow(dead_code)]
+pub struct ListOffersResponse {
+    _rid: String,
+    #[serde(rename = "Offers")]
+    pub offers: Vec<Offer>,
+    #[serde(rename = "_count")]
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[allow(dead_code)]
+pub struct ListStoredProceduresResponse {
+    _rid: String,
+    #[serde(rename = "StoredProcedures")]
+    pub stored_procedures: Vec<StoredProcedure>,
+    #[serde(rename = "_count")]
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[allow(dead_code)]
+pub struct ListConflictsResponse {
+    _rid: String,
+    #[serde(rename = "Conflicts")]
+    pub conflicts: Vec<Conflict>,
+    #[serde(rename = "_count")]
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[allow(dead_code)]
+pub struct ListAttachmentsResponse {
+    _rid: String,
+    #[serde(rename = "Attachments")]
+    pub attachments: Vec<Attachment>,
+    #[serde(rename = "_count")]
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListPartitionKeyRangesResponse {
+    _rid: String,
+    #[serde(rename = "PartitionKeyRanges")]
+    pub partition_key_ranges: Vec<PartitionKeyRange>,
+    #[serde(rename = "_count")]
+    pub count: u32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListDocumentsResponseAttributes {
     #[serde(rename = "_rid")]
@@ -30,7 +93,7 @@ pub struct ListDocumentsResponseAttributes {
     pub documents: Vec<DocumentAttributes>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListDocumentsResponseEntities<T> {
     #[serde(rename = "_rid")]
     pub rid: String,
@@ -38,7 +101,7 @@ pub struct ListDocumentsResponseEntities<T> {
     pub entities: Vec<T>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Document<T> {
     pub document_attributes: DocumentAttributes,
     pub entity: T,
@@ -51,6 +114,13 @@ impl<T: DeserializeOwned> Document<T> {
             entity: ::serde_json::from_slice::<T>(json)?,
         })
     }
+
+    pub(crate) fn from_value(json: ::serde_json::Value) -> Result<Document<T>, AzureError> {
+        Ok(Document {
+            document_attributes: ::serde_json::from_value::<DocumentAttributes>(json.clone())?,
+            entity: ::serde_json::from_value::<T>(json)?,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,56 +129,326 @@ pub struct QueryResult<T> {
     pub result: T,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ListDocumentsResponseAdditionalHeaders {
-    pub continuation_token: Option<String>,
+    pub continuation_token: Option<ContinuationToken>,
     pub charge: f64,
     pub etag: Option<String>,
+    pub collection_usage: Option<DocumentCollectionUsage>,
+    pub server_time: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone)]
+impl_etag!(ListDocumentsResponseAdditionalHeaders, etag, optional);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct QueryDocumentResponseAdditonalHeaders {
-    pub continuation_token: Option<String>,
+    pub continuation_token: Option<ContinuationToken>,
     pub charge: f64,
+    pub query_metrics: Option<QueryMetrics>,
+    // only sent with the first page of a query, which reads it again
+    #[serde(skip)]
+    pub query_plan: Option<QueryPlan>,
+    pub server_time: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct QueryDocumentResponse<T> {
     pub query_response_meta: QueryResponseMeta,
     pub results: Vec<QueryResult<T>>,
     pub additional_headers: QueryDocumentResponseAdditonalHeaders,
+    #[serde(skip)]
+    pub(crate) headers: ::hyper::HeaderMap,
 }
 
-#[derive(Debug, Clone)]
+impl_headers!(QueryDocumentResponse<T>);
+
+impl<T> QueryDocumentResponse<T> {
+    /// Whether the page holds no results. It is not the last page as long as
+    /// `additional_headers.continuation_token` is set: Cosmos can answer an
+    /// empty page before the pages with results.
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ListDocumentsResponse<T> {
     pub rid: String,
     pub documents: Vec<Document<T>>,
     pub additional_headers: ListDocumentsResponseAdditionalHeaders,
+    #[serde(skip)]
+    pub(crate) headers: ::hyper::HeaderMap,
 }
 
-#[derive(Debug, Clone)]
+impl_headers!(ListDocumentsResponse<T>);
+
+impl<T> ListDocumentsResponse<T> {
+    /// Whether the page holds no documents, see `QueryDocumentResponse::is_empty`.
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DocumentAdditionalHeaders {
     pub charge: f64,
+    pub server_time: Option<DateTime<Utc>>,
 }
 
 impl DocumentAdditionalHeaders {
     pub(crate) fn derive_from(headers: &::hyper::HeaderMap) -> DocumentAdditionalHeaders {
         DocumentAdditionalHeaders {
             charge: headers.get_as_str(HEADER_REQUEST_CHARGE).unwrap().parse::<f64>().unwrap(),
+            server_time: server_time_from_headers(headers),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GetDocumentResponse<T> {
     pub document: Option<Document<T>>,
     pub additional_headers: DocumentAdditionalHeaders,
+    #[serde(skip)]
+    pub(crate) headers: ::hyper::HeaderMap,
 }
 
+impl_headers!(GetDocumentResponse<T>);
+
+/// A document read with `execute_raw`: its json exactly as returned by
+/// Cosmos, for the callers passing it through.
 #[derive(Debug, Clone)]
+pub struct GetDocumentRawResponse {
+    pub body: Option<Bytes>,
+    pub additional_headers: DocumentAdditionalHeaders,
+    pub(crate) headers: ::hyper::HeaderMap,
+}
+
+impl_headers!(GetDocumentRawResponse);
+
+/// A page of query results read with `execute_raw`: the json of the page,
+/// documents and metadata, exactly as returned by Cosmos.
+#[derive(Debug, Clone)]
+pub struct QueryDocumentRawResponse {
+    pub body: Bytes,
+    pub additional_headers: QueryDocumentResponseAdditonalHeaders,
+    pub(crate) headers: ::hyper::HeaderMap,
+}
+
+impl_headers!(QueryDocumentRawResponse);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ReplaceDocumentResponse<T> {
     pub document: Document<T>,
     pub additional_headers: DocumentAdditionalHeaders,
+    #[serde(skip)]
+    pub(crate) headers: ::hyper::HeaderMap,
+}
+
+impl_headers!(ReplaceDocumentResponse<T>);
+
+/// Response of a write sent with `Prefer: return=minimal`: Cosmos
+/// returns no body so only the headers are available.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MinimalDocumentResponse {
+    pub etag: Option<String>,
+    pub additional_headers: DocumentAdditionalHeaders,
+    #[serde(skip)]
+    pub(crate) headers: ::hyper::HeaderMap,
+}
+
+impl_etag!(MinimalDocumentResponse, etag, optional);
+impl_headers!(MinimalDocumentResponse);
+
+impl MinimalDocumentResponse {
+    pub(crate) fn from_headers(headers: &::hyper::HeaderMap) -> MinimalDocumentResponse {
+        MinimalDocumentResponse {
+            etag: headers.get_as_string(::hyper::header::ETAG),
+            additional_headers: DocumentAdditionalHeaders::derive_from(headers),
+            headers: headers.clone(),
+        }
+    }
+}
+
+/// Usage or quota of a collection, as returned in the `x-ms-resource-usage`
+/// and `x-ms-resource-quota` headers when the collection is read with
+/// `populate_quota_info(true)`. Sizes are in KB.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaInfo {
+    pub documents_count: Option<u64>,
+    pub documents_size: Option<u64>,
+    pub document_size: Option<u64>,
+    pub collection_size: Option<u64>,
+    pub stored_procedures: Option<u64>,
+    pub triggers: Option<u64>,
+    pub functions: Option<u64>,
+}
+
+impl QuotaInfo {
+    /// Parses the semicolon delimited `key=value` list. Unknown keys are ignored.
+    pub fn parse(s: &str) -> Result<QuotaInfo, AzureError> {
+        let mut quota_info = QuotaInfo::default();
+
+        for token in s.split(';').map(str::trim).filter(|t| !t.is_empty()) {
+            let mut kv = token.splitn(2, '=');
+            let key = kv.next().unwrap_or("");
+            let value = match kv.next() {
+                Some(value) => value.trim().parse::<u64>()?,
+                None => {
+                    return Err(AzureError::GenericErrorWithText(format!(
+                        "missing value for {} in quota info \"{}\"",
+                        key, s
+                    )))
+                }
+            };
+
+            match key {
+                "documentsCount" => quota_info.documents_count = Some(value),
+                "documentsSize" => quota_info.documents_size = Some(value),
+                "documentSize" => quota_info.document_size = Some(value),
+                "collectionSize" => quota_info.collection_size = Some(value),
+                "storedProcedures" => quota_info.stored_procedures = Some(value),
+                "triggers" => quota_info.triggers = Some(value),
+                "functions" => quota_info.functions = Some(value),
+                _ => trace!("ignoring unknown quota info key {}", key),
+            }
+        }
+
+        Ok(quota_info)
+    }
+
+    pub(crate) fn from_header(headers: &::hyper::HeaderMap, header: &str) -> Result<Option<QuotaInfo>, AzureError> {
+        match headers.get_as_str(header) {
+            Some(s) => Ok(Some(QuotaInfo::parse(s)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Usage and quota of the collection returned along with its documents
+/// when they are listed with `populate_quota_info(true)`, sparing a read of
+/// the collection.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DocumentCollectionUsage {
+    pub usage: QuotaInfo,
+    pub quota: Option<QuotaInfo>,
+}
+
+impl DocumentCollectionUsage {
+    pub fn documents_count(&self) -> Option<u64> {
+        self.usage.documents_count
+    }
+
+    /// Size of the collection in KB.
+    pub fn collection_size(&self) -> Option<u64> {
+        self.usage.collection_size
+    }
+
+    pub(crate) fn from_headers(headers: &::hyper::HeaderMap) -> Result<Option<DocumentCollectionUsage>, AzureError> {
+        let usage = match QuotaInfo::from_header(headers, HEADER_RESOURCE_USAGE)? {
+            Some(usage) => usage,
+            None => return Ok(None),
+        };
+
+        Ok(Some(DocumentCollectionUsage {
+            usage,
+            quota: QuotaInfo::from_header(headers, HEADER_RESOURCE_QUOTA)?,
+        }))
+    }
+}
+
+/// Execution statistics returned in the `x-ms-documentdb-query-metrics`
+/// header when a query is sent with `populate_query_metrics(true)`. A cross
+/// partition query gets one set of metrics per partition, see
+/// `CombinedQueryMetrics` to add them up.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct QueryMetrics {
+    pub total_execution_time: Duration,
+    pub query_compile_time: Duration,
+    pub query_logical_plan_build_time: Duration,
+    pub query_physical_plan_build_time: Duration,
+    pub query_optimization_time: Duration,
+    pub index_lookup_time: Duration,
+    pub document_load_time: Duration,
+    pub vm_execution_time: Duration,
+    pub system_function_execution_time: Duration,
+    pub user_function_execution_time: Duration,
+    pub document_write_time: Duration,
+    pub retrieved_document_count: u64,
+    pub retrieved_document_size: u64,
+    pub output_document_count: u64,
+    pub output_document_size: u64,
+    pub index_hit_ratio: f64,
+}
+
+impl QueryMetrics {
+    /// Parses the semicolon delimited `key=value` list. Times are in
+    /// milliseconds, sizes in bytes. Unknown keys are ignored.
+    pub fn parse(s: &str) -> Result<QueryMetrics, AzureError> {
+        let mut metrics = QueryMetrics::default();
+
+        for token in s.split(';').map(str::trim).filter(|t| !t.is_empty()) {
+            let mut kv = token.splitn(2, '=');
+            let key = kv.next().unwrap_or("");
+            let value = kv
+                .next()
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| *value >= 0.0)
+                .ok_or_else(|| AzureError::GenericErrorWithText(format!("invalid value for {} in query metrics \"{}\"", key, s)))?;
+            let time = Duration::from_micros((value * 1000.0).round() as u64);
+
+            match key {
+                "totalExecutionTimeInMs" => metrics.total_execution_time = time,
+                "queryCompileTimeInMs" => metrics.query_compile_time = time,
+                "queryLogicalPlanBuildTimeInMs" => metrics.query_logical_plan_build_time = time,
+                "queryPhysicalPlanBuildTimeInMs" => metrics.query_physical_plan_build_time = time,
+                "queryOptimizationTimeInMs" => metrics.query_optimization_time = time,
+                "indexLookupTimeInMs" => metrics.index_lookup_time = time,
+                "documentLoadTimeInMs" => metrics.document_load_time = time,
+                "VMExecutionTimeInMs" => metrics.vm_execution_time = time,
+                "systemFunctionExecuteTimeInMs" => metrics.system_function_execution_time = time,
+                "userFunctionExecuteTimeInMs" => metrics.user_function_execution_time = time,
+                "writeOutputTimeInMs" => metrics.document_write_time = time,
+                "retrievedDocumentCount" => metrics.retrieved_document_count = value as u64,
+                "retrievedDocumentSize" => metrics.retrieved_document_size = value as u64,
+                "outputDocumentCount" => metrics.output_document_count = value as u64,
+                "outputDocumentSize" => metrics.output_document_size = value as u64,
+                "indexUtilizationRatio" => metrics.index_hit_ratio = value,
+                _ => trace!("ignoring unknown query metrics key {}", key),
+            }
+        }
+
+        Ok(metrics)
+    }
+
+    pub(crate) fn from_headers(headers: &::hyper::HeaderMap) -> Result<Option<QueryMetrics>, AzureError> {
+        match headers.get_as_str(HEADER_DOCUMENTDB_QUERY_METRICS) {
+            Some(s) => Ok(Some(QueryMetrics::parse(s)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GetCollectionResponse {
+    pub collection: Collection,
+    pub resource_usage: Option<QuotaInfo>,
+    pub resource_quota: Option<QuotaInfo>,
+    #[serde(skip)]
+    pub(crate) headers: ::hyper::HeaderMap,
+}
+
+impl_headers!(GetCollectionResponse);
+
+impl GetCollectionResponse {
+    pub(crate) fn from_response(headers: &::hyper::HeaderMap, body: &[u8]) -> Result<GetCollectionResponse, AzureError> {
+        Ok(GetCollectionResponse {
+            collection: ::serde_json::from_slice::<Collection>(body)?,
+            resource_usage: QuotaInfo::from_header(headers, HEADER_RESOURCE_USAGE)?,
+            resource_quota: QuotaInfo::from_header(headers, HEADER_RESOURCE_QUOTA)?,
+            headers: headers.clone(),
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -119,8 +459,152 @@ pub struct QueryResponseMeta {
     pub count: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ExecuteStoredProcedureResponse<T> {
     pub result: T,
     pub additional_headers: DocumentAdditionalHeaders,
+    #[serde(skip)]
+    pub(crate) headers: ::hyper::HeaderMap,
+}
+
+impl_headers!(ExecuteStoredProcedureResponse<T>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::{HeaderMap, HeaderValue};
+
+    #[test]
+    fn parse_quota_info() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HEADER_RESOURCE_USAGE,
+            HeaderValue::from_static(
+                "functions=0;storedProcedures=2;triggers=0;documentSize=0;documentsSize=12;documentsCount=3;collectionSize=15;",
+            ),
+        );
+        headers.insert(
+            HEADER_RESOURCE_QUOTA,
+            HeaderValue::from_static("functions=25;storedProcedures=100;triggers=25;documentSize=10240;collectionSize=10485760"),
+        );
+
+        let usage = QuotaInfo::from_header(&headers, HEADER_RESOURCE_USAGE).unwrap().unwrap();
+        assert_eq!(usage.documents_count, Some(3));
+        assert_eq!(usage.documents_size, Some(12));
+        assert_eq!(usage.collection_size, Some(15));
+        assert_eq!(usage.stored_procedures, Some(2));
+
+        let quota = QuotaInfo::from_header(&headers, HEADER_RESOURCE_QUOTA).unwrap().unwrap();
+        assert_eq!(quota.documents_count, None);
+        assert_eq!(quota.document_size, Some(10240));
+        assert_eq!(quota.collection_size, Some(10_485_760));
+
+        assert_eq!(QuotaInfo::from_header(&HeaderMap::new(), HEADER_RESOURCE_USAGE).unwrap(), None);
+        assert!(QuotaInfo::parse("documentsCount=many").is_err());
+        assert!(QuotaInfo::parse("documentsCount").is_err());
+    }
+
+    #[test]
+    fn headers_not_read_are_kept() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_REQUEST_CHARGE, HeaderValue::from_static("5.71"));
+        headers.insert("x-ms-cosmos-llsn", HeaderValue::from_static("42"));
+
+        let response = MinimalDocumentResponse::from_headers(&headers);
+        assert_eq!(response.additional_headers.charge, 5.71);
+        assert_eq!(response.headers()["x-ms-cosmos-llsn"], "42");
+        assert_eq!(response.headers(), &headers);
+    }
+
+    #[test]
+    fn parse_document_collection_usage() {
+        assert_eq!(DocumentCollectionUsage::from_headers(&HeaderMap::new()).unwrap(), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HEADER_RESOURCE_USAGE,
+            HeaderValue::from_static("documentSize=0;documentsSize=1024;documentsCount=42;collectionSize=1056;"),
+        );
+        let usage = DocumentCollectionUsage::from_headers(&headers).unwrap().unwrap();
+        assert_eq!(usage.documents_count(), Some(42));
+        assert_eq!(usage.collection_size(), Some(1056));
+        assert_eq!(usage.usage.documents_size, Some(1024));
+        assert_eq!(usage.quota, None);
+
+        headers.insert(
+            HEADER_RESOURCE_QUOTA,
+            HeaderValue::from_static("documentSize=10240;collectionSize=10485760;"),
+        );
+        let usage = DocumentCollectionUsage::from_headers(&headers).unwrap().unwrap();
+        assert_eq!(usage.quota.unwrap().collection_size, Some(10_485_760));
+
+        headers.insert(
+            HEADER_RESOURCE_USAGE,
+            HeaderValue::from_static("documentsCount=;collectionSize=1056"),
+        );
+        assert!(DocumentCollectionUsage::from_headers(&headers).is_err());
+    }
+
+    #[test]
+    fn parse_query_metrics() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HEADER_DOCUMENTDB_QUERY_METRICS,
+            HeaderValue::from_static(
+                "totalExecutionTimeInMs=33.67;queryCompileTimeInMs=0.06;queryLogicalPlanBuildTimeInMs=0.02;\
+                 queryPhysicalPlanBuildTimeInMs=0.10;queryOptimizationTimeInMs=0.00;VMExecutionTimeInMs=32.56;\
+                 indexLookupTimeInMs=0.36;documentLoadTimeInMs=9.58;systemFunctionExecuteTimeInMs=0.00;\
+                 userFunctionExecuteTimeInMs=0.00;retrievedDocumentCount=2000;retrievedDocumentSize=1125600;\
+                 outputDocumentCount=2000;outputDocumentSize=1125600;writeOutputTimeInMs=18.10;indexUtilizationRatio=1.00",
+            ),
+        );
+
+        let metrics = QueryMetrics::from_headers(&headers).unwrap().unwrap();
+        assert_eq!(metrics.total_execution_time, Duration::from_micros(33_670));
+        assert_eq!(metrics.document_load_time, Duration::from_micros(9_580));
+        assert_eq!(metrics.retrieved_document_count, 2000);
+        assert_eq!(metrics.output_document_size, 1_125_600);
+        assert_eq!(metrics.index_hit_ratio, 1.0);
+
+        assert_eq!(QueryMetrics::from_headers(&HeaderMap::new()).unwrap(), None);
+        assert!(QueryMetrics::parse("totalExecutionTimeInMs=slow").is_err());
+        assert!(QueryMetrics::parse("retrievedDocumentCount").is_err());
+    }
+
+    #[test]
+    fn query_response_round_trip() {
+        let result: ::serde_json::Value = ::serde_json::from_str(r#"{"id": "a"}"#).unwrap();
+        let response = QueryDocumentResponse {
+            query_response_meta: QueryResponseMeta {
+                rid: "XP0mAJ3H-AA=".to_owned(),
+                count: 1,
+            },
+            results: vec![QueryResult {
+                document_attributes: None,
+                result,
+            }],
+            additional_headers: QueryDocumentResponseAdditonalHeaders {
+                continuation_token: Some(ContinuationToken::new("+RID:XP0mAJ3H-AACAAAAAAAAAA==#RT:1").unwrap()),
+                charge: 2.9,
+                query_metrics: Some(QueryMetrics::parse("totalExecutionTimeInMs=0.42;retrievedDocumentCount=1").unwrap()),
+                query_plan: None,
+                server_time: Some(Utc::now()),
+            },
+            headers: ::hyper::HeaderMap::new(),
+        };
+
+        let serialized = ::serde_json::to_string(&response).unwrap();
+        let cached: QueryDocumentResponse<::serde_json::Value> = ::serde_json::from_str(&serialized).unwrap();
+        assert_eq!(cached.results[0].result, response.results[0].result);
+        assert_eq!(
+            cached.additional_headers.continuation_token,
+            response.additional_headers.continuation_token
+        );
+        assert_eq!(cached.additional_headers.query_metrics, response.additional_headers.query_metrics);
+        assert_eq!(cached.additional_headers.server_time, response.additional_headers.server_time);
+        assert_eq!(::serde_json::to_string(&cached).unwrap(), serialized);
+
+        let truncated = r#""{\"token\":\"+RID:XP0mAJ3H""#;
+        assert!(::serde_json::from_str::<ContinuationToken>(truncated).is_err());
+    }
 }
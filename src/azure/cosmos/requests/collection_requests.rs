@@ -0,0 +1,422 @@
+use super::*;
+use azure::cosmos::collection::{Collection, GeospatialConfig, GeospatialType, PartitionKey, UniqueKey};
+
+pub struct GetCollectionRequest {
+    hyper_client: HyperClient,
+    request: RequestBuilder,
+    uri_error: Option<AzureError>,
+}
+
+impl GetCollectionRequest {
+    pub(crate) fn new(hyper_client: HyperClient, request: Result<RequestBuilder, AzureError>) -> GetCollectionRequest {
+        let (request, uri_error) = split_request(request);
+        GetCollectionRequest {
+            hyper_client,
+            request,
+            uri_error,
+        }
+    }
+
+    // Asks Cosmos to return the collection usage and quota headers.
+    request_option!(populate_quota_info, bool, HEADER_DOCUMENTDB_POPULATE_QUOTA_INFO);
+
+    pub fn execute(self) -> impl Future<Item = GetCollectionResponse, Error = AzureError> {
+        trace!("get_collection called(request == {:?}", self.request);
+        let hc = self.hyper_client;
+        let mut req = self.request;
+        future::result(check_uri(self.uri_error))
+            .and_then(move |_| Ok(req.body(hyper::Body::empty())?))
+            .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::OK))
+            .and_then(move |(headers, body)| GetCollectionResponse::from_response(&headers, &body))
+    }
+
+    execute_boxed!(GetCollectionResponse);
+}
+
+pub struct CreateCollectionRequest {
+    hyper_client: HyperClient,
+    request: RequestBuilder,
+    uri_error: Option<AzureError>,
+    payload: Result<String, serde_json::Error>,
+    throughput: Option<u64>,
+    autoscale_max_throughput: Option<i32>,
+    database_throughput: bool,
+    unique_keys: Vec<UniqueKey>,
+    geospatial_type: Option<GeospatialType>,
+    if_not_exists: Option<(GetCollectionRequest, Option<PartitionKey>)>,
+}
+
+impl CreateCollectionRequest {
+    pub(crate) fn new(
+        hyper_client: HyperClient,
+        request: Result<RequestBuilder, AzureError>,
+        payload: Result<String, serde_json::Error>,
+    ) -> CreateCollectionRequest {
+        let (request, uri_error) = split_request(request);
+        CreateCollectionRequest {
+            hyper_client,
+            request,
+            uri_error,
+            payload,
+            throughput: None,
+            autoscale_max_throughput: None,
+            database_throughput: false,
+            unique_keys: Vec::new(),
+            geospatial_type: None,
+            if_not_exists: None,
+        }
+    }
+
+    // On conflict `existing` reads the collection, which must have
+    // `partition_key`.
+    pub(crate) fn if_not_exists(mut self, existing: GetCollectionRequest, partition_key: Option<PartitionKey>) -> Self {
+        self.if_not_exists = Some((existing, partition_key));
+        self
+    }
+
+    /// Provisions a fixed throughput, in RU/s.
+    pub fn throughput(mut self, value: u64) -> Self {
+        self.throughput = Some(value);
+        self
+    }
+
+    /// Lets Cosmos scale the throughput between a tenth of `value` and
+    /// `value`. `value` must be a multiple of 1000 and cannot be combined
+    /// with a fixed `throughput`.
+    pub fn autoscale_max_throughput(mut self, value: i32) -> Self {
+        self.autoscale_max_throughput = Some(value);
+        self
+    }
+
+    /// Declares that the collection uses the throughput shared by its
+    /// database, in which case it cannot have a `throughput` or an
+    /// `autoscale_max_throughput` of its own.
+    pub fn use_database_throughput(mut self, value: bool) -> Self {
+        self.database_throughput = value;
+        self
+    }
+
+    /// Adds a unique key made of the given paths, e.g. `&["/email"]`, to
+    /// the unique key policy of the collection. Inserting a document with
+    /// the same values on those paths as another document of the same
+    /// logical partition then fails with 409 Conflict, the status of an id
+    /// conflict too: see `AzureError::is_conflict`.
+    pub fn unique_key(mut self, paths: &[&str]) -> Self {
+        self.unique_keys.push(UniqueKey {
+            paths: paths.iter().map(|path| (*path).to_owned()).collect(),
+        });
+        self
+    }
+
+    /// Sets the `geospatialConfig` of the collection, overriding the one of
+    /// the collection given. It cannot be changed once the collection is
+    /// created, and the `ST_*` queries silently return other results with
+    /// the wrong one.
+    pub fn geospatial_type(mut self, value: GeospatialType) -> Self {
+        self.geospatial_type = Some(value);
+        self
+    }
+
+    pub fn execute(self) -> impl Future<Item = Collection, Error = AzureError> {
+        trace!("create_collection called(request == {:?}", self.request);
+        let hc = self.hyper_client;
+        let mut req = self.request;
+        let (throughput, autoscale_max_throughput) = (self.throughput, self.autoscale_max_throughput);
+        let (database_throughput, unique_keys, geospatial_type) = (self.database_throughput, self.unique_keys, self.geospatial_type);
+        let payload = check_uri(self.uri_error)
+            .and_then(|_| check_database_throughput(database_throughput, throughput, autoscale_max_throughput))
+            .and_then(|_| set_offer_headers(&mut req, throughput, autoscale_max_throughput))
+            .and(self.payload.map_err(AzureError::from))
+            .and_then(|payload| add_unique_keys(payload, unique_keys))
+            .and_then(|payload| set_geospatial_type(payload, geospatial_type));
+        let if_not_exists = self.if_not_exists;
+        future::result(payload)
+            .and_then(move |payload| Ok(req.body(payload.into())?))
+            .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::CREATED))
+            .and_then(move |(_, body)| Ok(serde_json::from_slice::<Collection>(&body)?))
+            .or_else(move |error| match if_not_exists {
+                Some((existing, partition_key)) if error.is_conflict() => Either::A(existing.execute().and_then(move |response| {
+                    let existing = response.collection;
+                    if existing.parition_key == partition_key {
+                        Ok(existing)
+                    } else {
+                        Err(AzureError::InputParametersError(format!(
+                            "the collection {} exists with the partition key {:?} instead of {:?}",
+                            existing.id, existing.parition_key, partition_key
+                        )))
+                    }
+                })),
+                _ => Either::B(future::err(error)),
+            })
+    }
+
+    execute_boxed!(Collection);
+}
+
+// The keys are added to the ones of the policy of the collection, if any.
+fn add_unique_keys(payload: String, unique_keys: Vec<UniqueKey>) -> Result<String, AzureError> {
+    if unique_keys.is_empty() {
+        return Ok(payload);
+    }
+
+    let mut collection: Collection = serde_json::from_str(&payload)?;
+    collection
+        .unique_key_policy
+        .get_or_insert_with(Default::default)
+        .unique_keys
+        .extend(unique_keys);
+    Ok(serde_json::to_string(&collection)?)
+}
+
+fn set_geospatial_type(payload: String, geospatial_type: Option<GeospatialType>) -> Result<String, AzureError> {
+    match geospatial_type {
+        Some(geospatial_type) => {
+            let mut collection: Collection = serde_json::from_str(&payload)?;
+            collection.geospatial_config = Some(GeospatialConfig { geospatial_type });
+            Ok(serde_json::to_string(&collection)?)
+        }
+        None => Ok(payload),
+    }
+}
+
+fn check_database_throughput(
+    database_throughput: bool,
+    throughput: Option<u64>,
+    autoscale_max_throughput: Option<i32>,
+) -> Result<(), AzureError> {
+    if database_throughput && (throughput.is_some() || autoscale_max_throughput.is_some()) {
+        Err(AzureError::InputParametersError(
+            "a collection using the throughput of its database cannot have its own throughput".to_owned(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+pub(super) fn set_offer_headers(
+    request: &mut RequestBuilder,
+    throughput: Option<u64>,
+    autoscale_max_throughput: Option<i32>,
+) -> Result<(), AzureError> {
+    match (throughput, autoscale_max_throughput) {
+        (Some(_), Some(_)) => Err(AzureError::InputParametersError(
+            "throughput and autoscale_max_throughput cannot be both specified".to_owned(),
+        )),
+        (Some(throughput), None) => {
+            request.header_formatted(HEADER_OFFER_THROUGHPUT, throughput);
+            Ok(())
+        }
+        (None, Some(max_throughput)) => {
+            check_autoscale_max_throughput(max_throughput)?;
+            request.header_formatted(HEADER_OFFER_AUTOPILOT_SETTINGS, format!("{{\"maxThroughput\":{}}}", max_throughput));
+            Ok(())
+        }
+        (None, None) => Ok(()),
+    }
+}
+
+pub(crate) fn check_autoscale_max_throughput(max_throughput: i32) -> Result<(), AzureError> {
+    if max_throughput < 1000 || max_throughput % 1000 != 0 {
+        Err(AzureError::InputParametersError(format!(
+            "autoscale_max_throughput must be a multiple of 1000, {} given",
+            max_throughput
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+pub struct ReplaceCollectionRequest {
+    hyper_client: HyperClient,
+    request: RequestBuilder,
+    uri_error: Option<AzureError>,
+    payload: Result<String, serde_json::Error>,
+}
+
+impl ReplaceCollectionRequest {
+    pub(crate) fn new(
+        hyper_client: HyperClient,
+        request: Result<RequestBuilder, AzureError>,
+        payload: Result<String, serde_json::Error>,
+    ) -> ReplaceCollectionRequest {
+        let (request, uri_error) = split_request(request);
+        ReplaceCollectionRequest {
+            hyper_client,
+            request,
+            uri_error,
+            payload,
+        }
+    }
+
+    // Pass the `etag` of the collection read before to avoid overwriting
+    // concurrent changes. A mismatch fails with AzureError::PreconditionFailed.
+    request_bytes_option!(if_match, String, header::IF_MATCH);
+
+    pub fn execute(self) -> impl Future<Item = Collection, Error = AzureError> {
+        trace!("replace_collection called(request == {:?}", self.request);
+        let hc = self.hyper_client;
+        let mut req = self.request;
+        future::result(check_uri(self.uri_error).and(self.payload.map_err(AzureError::from)))
+            .and_then(move |payload| Ok(req.body(payload.into())?))
+            .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::OK))
+            .and_then(move |(_, body)| Ok(serde_json::from_slice::<Collection>(&body)?))
+    }
+
+    execute_boxed!(Collection);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure::cosmos::collection::{IndexingMode, IndexingPolicy};
+    use azure::cosmos::AuthorizationToken;
+    use azure::cosmos::Client;
+    use azure::cosmos::TokenType;
+
+    fn client() -> Client {
+        let auth_token = AuthorizationToken::new("mindflavor".to_owned(), TokenType::Master, "ZHVtbXk=").unwrap();
+        Client::new(auth_token).unwrap()
+    }
+
+    fn collection(id: &str) -> Collection {
+        Collection::new(
+            id,
+            IndexingPolicy {
+                automatic: true,
+                indexing_mode: IndexingMode::Consistent,
+                included_paths: vec![],
+                excluded_paths: vec![],
+            },
+        )
+    }
+
+    #[test]
+    fn populate_quota_info() {
+        let client = client();
+
+        let mut request = client.get_collection("db", "coll").populate_quota_info(true);
+        let request = request.request.body(()).unwrap();
+        assert_eq!(request.uri().path(), "/dbs/db/colls/coll");
+        assert_eq!(request.headers()[HEADER_DOCUMENTDB_POPULATE_QUOTA_INFO], "true");
+
+        let mut request = client.get_collection("db", "coll");
+        let request = request.request.body(()).unwrap();
+        assert!(request.headers().get(HEADER_DOCUMENTDB_POPULATE_QUOTA_INFO).is_none());
+    }
+
+    #[test]
+    fn conditional_replace() {
+        let client = client();
+
+        let mut collection = collection("coll");
+        collection.etag = "\"00000b00-0000-0000-0000-5b5d3e9d0000\"".to_owned();
+
+        let mut request = client.replace_collection("db", &collection).if_match(collection.etag.clone());
+        let request = request.request.body(()).unwrap();
+
+        assert_eq!(request.method(), hyper::Method::PUT);
+        assert_eq!(request.uri().path(), "/dbs/db/colls/coll");
+        assert_eq!(request.headers()[header::IF_MATCH], "\"00000b00-0000-0000-0000-5b5d3e9d0000\"");
+
+        // the quotes lost along the way are put back
+        collection.etag = "00000b00-0000-0000-0000-5b5d3e9d0000".to_owned();
+        let mut request = client.replace_collection("db", &collection).if_match(collection.etag());
+        let request = request.request.body(()).unwrap();
+        assert_eq!(request.headers()[header::IF_MATCH], "\"00000b00-0000-0000-0000-5b5d3e9d0000\"");
+    }
+
+    #[test]
+    fn encoded_collection_uri() {
+        let client = client();
+
+        let mut request = client.get_collection("my db", "città");
+        assert!(request.uri_error.is_none());
+        let request = request.request.body(()).unwrap();
+        assert_eq!(request.uri().path(), "/dbs/my%20db/colls/citt%C3%A0");
+
+        match client.get_collection("db", "my/coll").execute().wait() {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn unique_key_policy() {
+        let collection = collection("users");
+
+        let request = client()
+            .create_collection("db", &collection)
+            .unique_key(&["/email"])
+            .unique_key(&["/firstName", "/lastName"]);
+        let payload = add_unique_keys(request.payload.unwrap(), request.unique_keys).unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(
+            payload["uniqueKeyPolicy"].to_string(),
+            r#"{"uniqueKeys":[{"paths":["/email"]},{"paths":["/firstName","/lastName"]}]}"#
+        );
+
+        // without unique keys the payload is sent as is
+        let payload = serde_json::to_string(&collection).unwrap();
+        assert!(!payload.contains("uniqueKeyPolicy"));
+        assert_eq!(add_unique_keys(payload.clone(), Vec::new()).unwrap(), payload);
+
+        let violation = AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(StatusCode::CREATED, StatusCode::CONFLICT, ""));
+        assert!(violation.is_conflict());
+    }
+
+    #[test]
+    fn geospatial_type() {
+        let collection = collection("places");
+
+        let request = client()
+            .create_collection("db", &collection)
+            .geospatial_type(GeospatialType::Geometry);
+        let payload = set_geospatial_type(request.payload.unwrap(), request.geospatial_type).unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(payload["geospatialConfig"].to_string(), r#"{"type":"Geometry"}"#);
+
+        let payload = serde_json::to_string(&collection).unwrap();
+        assert_eq!(set_geospatial_type(payload.clone(), None).unwrap(), payload);
+    }
+
+    #[test]
+    fn autoscale_collection() {
+        let collection = collection("coll");
+
+        let mut request = client().create_collection("db", &collection).autoscale_max_throughput(4000);
+        set_offer_headers(&mut request.request, request.throughput, request.autoscale_max_throughput).unwrap();
+        let request = request.request.body(()).unwrap();
+        assert_eq!(request.uri().path(), "/dbs/db/colls");
+        assert_eq!(request.headers()[HEADER_OFFER_AUTOPILOT_SETTINGS], "{\"maxThroughput\":4000}");
+        assert!(request.headers().get(HEADER_OFFER_THROUGHPUT).is_none());
+
+        let mut request = hyper::Request::builder();
+        set_offer_headers(&mut request, Some(400), None).unwrap();
+        assert_eq!(request.headers_ref().unwrap()[HEADER_OFFER_THROUGHPUT], "400");
+
+        for (throughput, max_throughput) in &[(Some(400), Some(4000)), (None, Some(0)), (None, Some(500)), (None, Some(4500))] {
+            match set_offer_headers(&mut hyper::Request::builder(), *throughput, *max_throughput) {
+                Err(AzureError::InputParametersError(_)) => {}
+                r => panic!("unexpected result {:?} for {:?}", r, max_throughput),
+            }
+        }
+    }
+
+    #[test]
+    fn database_throughput_collection() {
+        let collection = collection("coll");
+
+        // rejected before anything is sent
+        let check_rejected = |request: CreateCollectionRequest| match request.execute().wait() {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        };
+        let request = client().create_collection("db", &collection).use_database_throughput(true);
+        check_rejected(request.throughput(400));
+        let request = client().create_collection("db", &collection).use_database_throughput(true);
+        check_rejected(request.autoscale_max_throughput(4000));
+
+        assert!(check_database_throughput(true, None, None).is_ok());
+        assert!(check_database_throughput(false, Some(400), None).is_ok());
+    }
+}
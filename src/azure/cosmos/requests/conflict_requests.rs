@@ -0,0 +1,115 @@
+use super::*;
+
+pub struct ListConflictsRequest {
+    hyper_client: HyperClient,
+    request: RequestBuilder,
+    uri_error: Option<AzureError>,
+}
+
+impl ListConflictsRequest {
+    pub(crate) fn new(hyper_client: HyperClient, request: Result<RequestBuilder, AzureError>) -> ListConflictsRequest {
+        let (request, uri_error) = split_request(request);
+        ListConflictsRequest {
+            hyper_client,
+            request,
+            uri_error,
+        }
+    }
+
+    request_option!(max_item_count, u64, HEADER_MAX_ITEM_COUNT);
+    request_bytes_option!(continuation_token, ContinuationToken, HEADER_CONTINUATION);
+
+    /// Returns a page of conflicts. The token of the returned vector, if
+    /// any, is the continuation token of the next page.
+    pub fn execute(mut self) -> impl Future<Item = IncompleteVector<Conflict>, Error = AzureError> {
+        trace!("list_conflicts called(request == {:?}", self.request);
+        future::result(check_uri(self.uri_error.take()).and_then(|_| Ok(self.request.body(hyper::Body::empty())?)))
+            .and_then(move |r| check_status_extract_headers_and_body(self.hyper_client.request(r), StatusCode::OK))
+            .and_then(|(headers, whole_body)| Self::extract_result(&whole_body, &headers))
+    }
+
+    execute_boxed!(IncompleteVector<Conflict>);
+
+    fn extract_result(body: &[u8], headers: &HeaderMap) -> Result<IncompleteVector<Conflict>, AzureError> {
+        let response = serde_json::from_slice::<ListConflictsResponse>(body)?;
+        Ok(IncompleteVector::new(
+            next_continuation(headers).map(String::from),
+            response.conflicts,
+        ))
+    }
+}
+
+pub struct DeleteConflictRequest {
+    hyper_client: HyperClient,
+    request: RequestBuilder,
+    uri_error: Option<AzureError>,
+}
+
+impl DocumentRequestExt for DeleteConflictRequest {
+    fn request(&mut self) -> &mut RequestBuilder {
+        &mut self.request
+    }
+}
+
+impl DeleteConflictRequest {
+    pub(crate) fn new(hyper_client: HyperClient, request: Result<RequestBuilder, AzureError>) -> DeleteConflictRequest {
+        let (request, uri_error) = split_request(request);
+        DeleteConflictRequest {
+            hyper_client,
+            request,
+            uri_error,
+        }
+    }
+
+    pub fn execute(mut self) -> impl Future<Item = (), Error = AzureError> {
+        trace!("delete_conflict called(request == {:?}", self.request);
+        future::result(check_uri(self.uri_error.take()).and_then(|_| Ok(self.request.body(hyper::Body::empty())?)))
+            .and_then(move |r| check_status_extract_body(self.hyper_client.request(r), StatusCode::NO_CONTENT))
+            .and_then(|_| Ok(()))
+    }
+
+    execute_boxed!(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure::cosmos::client::tests::test_client;
+
+    #[test]
+    fn conflicts_page() {
+        let body = br#"{
+            "_rid": "k6d9ALgBmD8=",
+            "Conflicts": [{
+                "id": "k6d9ALgBmD8BAAAAAAAAQA==",
+                "_rid": "k6d9ALgBmD8BAAAAAAAAQA==",
+                "_self": "dbs/k6d9AA==/colls/k6d9ALgBmD8=/conflicts/k6d9ALgBmD8BAAAAAAAAQA==/",
+                "_etag": "\"00000200-0000-0000-0000-5b5d3e9d0000\"",
+                "_ts": 1532837533,
+                "resourceType": "document",
+                "operationType": "create",
+                "content": "{\"id\":\"doc\"}"
+            }],
+            "_count": 1
+        }"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_CONTINUATION, HeaderValue::from_static("+RID:k6d9ALgBmD8BAAAAAAAAQA==#RT:1"));
+
+        let page = ListConflictsRequest::extract_result(body, &headers).unwrap();
+        assert_eq!(page.token(), Some("+RID:k6d9ALgBmD8BAAAAAAAAQA==#RT:1"));
+        assert_eq!(page[0].content, "{\"id\":\"doc\"}");
+    }
+
+    #[test]
+    fn delete_conflict_request() {
+        let (_, client) = test_client();
+
+        let mut request = client
+            .delete_conflict("db", "coll", "k6d9ALgBmD8BAAAAAAAAQA==")
+            .partition_key("tenant");
+        let request = request.request.body(()).unwrap();
+        assert_eq!(request.method(), hyper::Method::DELETE);
+        assert_eq!(request.uri().path(), "/dbs/db/colls/coll/conflicts/k6d9ALgBmD8BAAAAAAAAQA==");
+        assert_eq!(request.headers()[HEADER_DOCUMENTDB_PARTITIONKEY], "[\"tenant\"]");
+    }
+}
@@ -0,0 +1,75 @@
+use super::collection_requests::set_offer_headers;
+use super::*;
+use azure::cosmos::database::Database;
+
+pub struct CreateDatabaseRequest {
+    hyper_client: HyperClient,
+    request: RequestBuilder,
+    uri_error: Option<AzureError>,
+    payload: Result<String, serde_json::Error>,
+    throughput: Option<u64>,
+}
+
+impl CreateDatabaseRequest {
+    pub(crate) fn new(
+        hyper_client: HyperClient,
+        request: Result<RequestBuilder, AzureError>,
+        payload: Result<String, serde_json::Error>,
+    ) -> CreateDatabaseRequest {
+        let (request, uri_error) = split_request(request);
+        CreateDatabaseRequest {
+            hyper_client,
+            request,
+            uri_error,
+            payload,
+            throughput: None,
+        }
+    }
+
+    /// Provisions a throughput, in RU/s, shared by the collections of the
+    /// database. The collections meant to use it must be created without a
+    /// throughput of their own, see
+    /// `CreateCollectionRequest::use_database_throughput`.
+    pub fn throughput(mut self, value: u64) -> Self {
+        self.throughput = Some(value);
+        self
+    }
+
+    pub fn execute(self) -> impl Future<Item = Database, Error = AzureError> {
+        trace!("create_database called(request == {:?}", self.request);
+        let hc = self.hyper_client;
+        let mut req = self.request;
+        let throughput = self.throughput;
+        let payload = check_uri(self.uri_error)
+            .and_then(|_| set_offer_headers(&mut req, throughput, None))
+            .and(self.payload.map_err(AzureError::from));
+        future::result(payload)
+            .and_then(move |payload| Ok(req.body(payload.into())?))
+            .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::CREATED))
+            .and_then(move |(_, body)| Ok(serde_json::from_slice::<Database>(&body)?))
+    }
+
+    execute_boxed!(Database);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure::cosmos::client::tests::test_client;
+
+    #[test]
+    fn shared_throughput_header() {
+        let (_, client) = test_client();
+
+        let mut request = client.create_database("db").throughput(400);
+        set_offer_headers(&mut request.request, request.throughput, None).unwrap();
+        let request = request.request.body(()).unwrap();
+        assert_eq!(request.method(), hyper::Method::POST);
+        assert_eq!(request.uri().path(), "/dbs");
+        assert_eq!(request.headers()[HEADER_OFFER_THROUGHPUT], "400");
+
+        let mut request = client.create_database("db");
+        set_offer_headers(&mut request.request, request.throughput, None).unwrap();
+        assert!(request.request.headers_ref().unwrap().get(HEADER_OFFER_THROUGHPUT).is_none());
+    }
+}
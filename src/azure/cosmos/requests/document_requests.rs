@@ -1,9 +1,47 @@
 use super::*;
+use azure::cosmos::partition_key::partition_key_value;
+use flate2::{write::GzEncoder, Compression};
+use std::io::Write;
+
+// The options of the requests sending a whole document, kept in their
+// `return_minimal`, `compress_body` and `partition_key_path` fields.
+macro_rules! document_write_options {
+    () => {
+        /// Asks Cosmos not to send back the document. Use
+        /// `execute_minimal` to read the response.
+        pub fn return_minimal(mut self, value: bool) -> Self {
+            self.return_minimal = value;
+            set_prefer_header(&mut self.request, value);
+            self
+        }
+
+        /// Sends the document gzip compressed. Small documents are sent as
+        /// they are since compressing them would not save anything.
+        pub fn compress_body(mut self, value: bool) -> Self {
+            self.compress_body = value;
+            self
+        }
+
+        /// Checks, before sending the document, that it has the key given
+        /// with `partition_key` at `path`, the partition key path of the
+        /// collection such as `/customer/id`. Cosmos rejects a mismatch with
+        /// a 400 that does not say which key was wrong.
+        pub fn partition_key_path<S: Into<String>>(mut self, path: S) -> Self {
+            self.partition_key_path = Some(path.into());
+            self
+        }
+    };
+}
 
 pub struct CreateDocumentRequest {
     hyper_client: HyperClient,
     request: RequestBuilder,
+    uri_error: Option<AzureError>,
     payload: Result<String, serde_json::Error>,
+    return_minimal: bool,
+    compress_body: bool,
+    generated_id: Option<String>,
+    partition_key_path: Option<String>,
 }
 
 impl DocumentRequestExt for CreateDocumentRequest {
@@ -12,37 +50,103 @@ impl DocumentRequestExt for CreateDocumentRequest {
     }
 }
 
+impl WriteRequestExt for CreateDocumentRequest {}
+
 impl CreateDocumentRequest {
     pub(crate) fn new(
         hyper_client: HyperClient,
-        request: RequestBuilder,
+        request: Result<RequestBuilder, AzureError>,
         payload: Result<String, serde_json::Error>,
     ) -> CreateDocumentRequest {
+        let (request, uri_error) = split_request(request);
         CreateDocumentRequest {
             hyper_client,
             request,
+            uri_error,
             payload,
+            return_minimal: false,
+            compress_body: false,
+            generated_id: None,
+            partition_key_path: None,
         }
     }
 
     request_option!(is_upsert, bool, HEADER_DOCUMENTDB_IS_UPSERT);
-    request_option!(indexing_directive, IndexingDirective, HEADER_INDEXING_DIRECTIVE);
+    request_option!(indexing_directive, enum IndexingDirective, HEADER_INDEXING_DIRECTIVE);
+
+    document_write_options!();
+
+    /// Gives the document a random UUID as id when it has none, see
+    /// `generated_id`. An existing id is left as it is.
+    pub fn auto_id(mut self, value: bool) -> Self {
+        if value && self.generated_id.is_none() {
+            let generated_id = &mut self.generated_id;
+            self.payload = self.payload.and_then(|payload| {
+                let (payload, id) = inject_document_id(payload)?;
+                *generated_id = id;
+                Ok(payload)
+            });
+        }
+        self
+    }
+
+    /// The id given to the document by `auto_id`, `None` if the document
+    /// already had one.
+    pub fn generated_id(&self) -> Option<&str> {
+        self.generated_id.as_deref()
+    }
 
     pub fn execute(self) -> impl Future<Item = DocumentAttributes, Error = AzureError> {
         trace!("get_document called(request == {:?}", self.request);
         let hc = self.hyper_client;
         let mut req = self.request;
-        future::result(self.payload)
-            .from_err()
-            .and_then(move |payload| Ok(req.body(payload.into())?))
+        let (compress_body, partition_key_path) = (self.compress_body, self.partition_key_path);
+        let payload = check_uri(self.uri_error)
+            .and(check_not_minimal(self.return_minimal))
+            .and(self.payload.map_err(AzureError::from));
+        future::result(payload)
+            .and_then(|payload| validate_document_id(&payload).map(|_| payload))
+            .and_then(move |payload| write_request(&mut req, payload, compress_body, partition_key_path.as_deref()))
             .and_then(move |r| check_status_extract_body(hc.request(r), StatusCode::CREATED))
             .and_then(move |body| Ok(serde_json::from_str::<DocumentAttributes>(&body)?))
     }
+
+    execute_boxed!(DocumentAttributes);
+
+    /// The request `execute_minimal` or, without `return_minimal`,
+    /// `execute` would send, see `DryRunRequest`.
+    pub fn dry_run(self) -> Result<DryRunRequest, AzureError> {
+        let mut req = self.request;
+        check_uri(self.uri_error)?;
+        let payload = self.payload?;
+        validate_document_id(&payload)?;
+        let partition_key_path = self.partition_key_path.as_deref();
+        dry_run_request(write_request(&mut req, payload, self.compress_body, partition_key_path)?)
+    }
+
+    pub fn execute_minimal(self) -> impl Future<Item = MinimalDocumentResponse, Error = AzureError> {
+        trace!("create_document minimal called(request == {:?}", self.request);
+        let hc = self.hyper_client;
+        let mut req = self.request;
+        let (compress_body, partition_key_path) = (self.compress_body, self.partition_key_path);
+        set_prefer_header(&mut req, true);
+        future::result(check_uri(self.uri_error).and(self.payload.map_err(AzureError::from)))
+            .and_then(|payload| validate_document_id(&payload).map(|_| payload))
+            .and_then(move |payload| write_request(&mut req, payload, compress_body, partition_key_path.as_deref()))
+            .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::CREATED))
+            .and_then(move |(headers, _)| Ok(MinimalDocumentResponse::from_headers(&headers)))
+    }
 }
 
+// Wait before the first read of a document not found again, doubled before
+// each of the following ones.
+const NOT_FOUND_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
 pub struct GetDocumentRequest {
     hyper_client: HyperClient,
     request: RequestBuilder,
+    uri_error: Option<AzureError>,
+    not_found_retries: u32,
 }
 
 impl DocumentRequestExt for GetDocumentRequest {
@@ -52,19 +156,101 @@ impl DocumentRequestExt for GetDocumentRequest {
 }
 
 impl GetDocumentRequest {
-    pub(crate) fn new(hyper_client: HyperClient, request: RequestBuilder) -> GetDocumentRequest {
-        GetDocumentRequest { hyper_client, request }
+    pub(crate) fn new(hyper_client: HyperClient, request: Result<RequestBuilder, AzureError>) -> GetDocumentRequest {
+        let (request, uri_error) = split_request(request);
+        GetDocumentRequest {
+            hyper_client,
+            request,
+            uri_error,
+            not_found_retries: 0,
+        }
     }
 
     request_bytes_option!(if_none_match, String, header::IF_NONE_MATCH);
 
+    /// With Session consistency a replica lagging behind can answer
+    /// NotFound for a document just written in the session. When a session
+    /// token is set, reads the document again up to `attempts` times before
+    /// returning no document, waiting 50ms before the first retry and twice
+    /// as long before each of the following ones.
+    pub fn retry_on_not_found_with_session(mut self, attempts: u32) -> Self {
+        self.not_found_retries = attempts;
+        self
+    }
+
     pub fn execute<T: DeserializeOwned>(mut self) -> impl Future<Item = GetDocumentResponse<T>, Error = AzureError> {
         trace!("get_document called(request == {:?}", self.request);
 
-        future::result(self.request.body(hyper::Body::empty()))
-            .from_err()
-            .and_then(move |r| extract_status_headers_and_body(self.hyper_client.request(r)))
-            .and_then(move |(status, headers, body)| Self::extract_result(status, &headers, &body))
+        let retries = if self.has_session_token() { self.not_found_retries } else { 0 };
+        let request = check_uri(self.uri_error.take()).and_then(|_| Ok(self.request.body(())?));
+        let hyper_client = self.hyper_client;
+
+        future::result(request).and_then(move |r| {
+            retry_not_found(
+                retries,
+                NOT_FOUND_RETRY_BACKOFF,
+                move || {
+                    future::result(copy_request(&r).map(|r| hyper_client.request(r)))
+                        .and_then(extract_budgeted_status_headers_and_body)
+                        .and_then(move |(status, headers, body)| Self::extract_result(status, &headers, &body))
+                },
+                |response: &GetDocumentResponse<T>| response.document.is_some(),
+            )
+        })
+    }
+
+    execute_boxed!(<T> GetDocumentResponse<T>);
+
+    /// The request `execute` would send first, see `DryRunRequest`.
+    pub fn dry_run(mut self) -> Result<DryRunRequest, AzureError> {
+        check_uri(self.uri_error.take())?;
+        dry_run_request(self.request.body(hyper::Body::empty())?)
+    }
+
+    /// Reads the json of the document without deserializing it.
+    pub fn execute_raw(mut self) -> impl Future<Item = GetDocumentRawResponse, Error = AzureError> {
+        trace!("get_document called(request == {:?}", self.request);
+
+        let retries = if self.has_session_token() { self.not_found_retries } else { 0 };
+        let request = check_uri(self.uri_error.take()).and_then(|_| Ok(self.request.body(())?));
+        let hyper_client = self.hyper_client;
+
+        future::result(request).and_then(move |r| {
+            retry_not_found(
+                retries,
+                NOT_FOUND_RETRY_BACKOFF,
+                move || {
+                    future::result(copy_request(&r).map(|r| hyper_client.request(r)))
+                        .and_then(extract_budgeted_status_headers_and_body)
+                        .and_then(move |(status, headers, body)| Self::extract_raw_result(status, &headers, body))
+                },
+                |response: &GetDocumentRawResponse| response.body.is_some(),
+            )
+        })
+    }
+
+    fn has_session_token(&self) -> bool {
+        self.request.headers_ref().map(|headers| headers.contains_key(HEADER_SESSION_TOKEN)) == Some(true)
+    }
+
+    fn extract_raw_result(
+        status: hyper::StatusCode,
+        headers: &HeaderMap,
+        body: hyper::Chunk,
+    ) -> Result<GetDocumentRawResponse, AzureError> {
+        match status {
+            StatusCode::OK | StatusCode::NOT_FOUND => Ok(GetDocumentRawResponse {
+                body: if status == StatusCode::OK { Some(body.into_bytes()) } else { None },
+                additional_headers: DocumentAdditionalHeaders::derive_from(headers),
+                headers: headers.clone(),
+            }),
+            StatusCode::TOO_MANY_REQUESTS => Err(throttled_error(headers)),
+            _ => Err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(
+                StatusCode::OK,
+                status,
+                str::from_utf8(&body)?,
+            ))),
+        }
     }
 
     fn extract_result<R: DeserializeOwned>(
@@ -79,8 +265,10 @@ impl GetDocumentRequest {
                 Ok(GetDocumentResponse {
                     document: Some(document),
                     additional_headers,
+                    headers: headers.clone(),
                 })
             }
+            StatusCode::TOO_MANY_REQUESTS => Err(throttled_error(headers)),
             // NotFound is not an error so we return None along
             // with the additional headers.
             StatusCode::NOT_FOUND => {
@@ -88,6 +276,7 @@ impl GetDocumentRequest {
                 Ok(GetDocumentResponse {
                     document: None,
                     additional_headers,
+                    headers: headers.clone(),
                 })
             }
             _ => {
@@ -105,10 +294,49 @@ impl GetDocumentRequest {
     }
 }
 
+// A request can only be sent once, the reads of a document send copies of
+// the same one.
+fn copy_request(request: &hyper::Request<()>) -> Result<hyper::Request<hyper::Body>, AzureError> {
+    let mut copy = hyper::Request::builder();
+    copy.method(request.method().clone()).uri(request.uri().clone());
+    if let Some(headers) = copy.headers_mut() {
+        *headers = request.headers().clone();
+    }
+    if let Some(max_request_charge) = request.extensions().get::<MaxRequestCharge>() {
+        copy.extension(*max_request_charge);
+    }
+    Ok(copy.body(hyper::Body::empty())?)
+}
+
+// Reads the document again while it is not found, at most `retries` times.
+fn retry_not_found<F, R>(
+    retries: u32,
+    backoff: Duration,
+    read: F,
+    found: fn(&R::Item) -> bool,
+) -> impl Future<Item = R::Item, Error = AzureError>
+where
+    F: Fn() -> R,
+    R: Future<Error = AzureError>,
+{
+    loop_fn((0, backoff), move |(attempt, backoff): (u32, Duration)| {
+        read().and_then(move |response| {
+            if found(&response) || attempt >= retries {
+                return Either::A(future::ok(Loop::Break(response)));
+            }
+
+            debug!("document not found, reading it again in {:?}", backoff);
+            Either::B(delay(backoff).map(move |_| Loop::Continue((attempt + 1, backoff * 2))))
+        })
+    })
+}
+
 pub struct QueryDocumentRequest {
     hyper_client: HyperClient,
     request: RequestBuilder,
+    uri_error: Option<AzureError>,
     payload: Result<String, serde_json::Error>,
+    continuation_expected_set: bool,
 }
 
 impl DocumentRequestExt for QueryDocumentRequest {
@@ -117,61 +345,135 @@ impl DocumentRequestExt for QueryDocumentRequest {
     }
 }
 
-const QUERY_CONTENT_TYPE: &str = "application/query+json";
-
 impl QueryDocumentRequest {
     pub(crate) fn new(
         hyper_client: HyperClient,
-        mut request: RequestBuilder,
+        request: Result<RequestBuilder, AzureError>,
         payload: Result<String, serde_json::Error>,
     ) -> QueryDocumentRequest {
-        request
-            .header(HEADER_DOCUMENTDB_ISQUERY, HeaderValue::from_static("true"))
-            .header(header::CONTENT_TYPE, HeaderValue::from_static(QUERY_CONTENT_TYPE));
+        let (mut request, uri_error) = split_request(request);
+        set_query_headers(&mut request);
         QueryDocumentRequest {
             hyper_client,
             request,
+            uri_error,
             payload,
+            continuation_expected_set: false,
         }
     }
 
     request_option!(max_item_count, u64, HEADER_MAX_ITEM_COUNT);
     request_bytes_option!(continuation_token, ContinuationToken, HEADER_CONTINUATION);
-    request_option!(enable_cross_partition, bool, HEADER_DOCUMENTDB_QUERY_ENABLECROSSPARTITION);
-    request_option!(consistency_level, ConsistencyLevel, HEADER_CONSISTENCY_LEVEL);
+    request_bytes_option!(partition_range_id, String, HEADER_DOCUMENTDB_PARTITIONRANGEID);
+
+    /// Cross partition queries also ask for partial results with a
+    /// continuation, unless `continuation_expected` is set explicitly.
+    pub fn enable_cross_partition<V: Into<bool>>(mut self, value: V) -> Self {
+        let value = value.into();
+        set_bool_header(&mut self.request, HEADER_DOCUMENTDB_QUERY_ENABLECROSSPARTITION, Some(value));
+        if !self.continuation_expected_set {
+            set_bool_header(
+                &mut self.request,
+                HEADER_DOCUMENTDB_QUERY_ISCONTINUATIONEXPECTED,
+                if value { Some(true) } else { None },
+            );
+        }
+        self
+    }
+
+    /// Lets Cosmos return partial results with a continuation for the
+    /// query shapes it cannot answer in one page.
+    pub fn continuation_expected<V: Into<bool>>(mut self, value: V) -> Self {
+        let value = value.into();
+        set_bool_header(&mut self.request, HEADER_DOCUMENTDB_QUERY_ISCONTINUATIONEXPECTED, Some(value));
+        self.continuation_expected_set = true;
+        self
+    }
+
+    request_option!(consistency_level, enum ConsistencyLevel, HEADER_CONSISTENCY_LEVEL);
+    request_option!(populate_query_metrics, bool, HEADER_DOCUMENTDB_POPULATE_QUERY_METRICS);
+
+    /// Advertises the query features the client completes across
+    /// partitions, see `SUPPORTED_QUERY_FEATURES`. Cosmos then accepts
+    /// the queries using them and describes how to complete them in the
+    /// query plan of the response.
+    pub fn supported_query_features(mut self, value: bool) -> Self {
+        if let Some(headers) = self.request.headers_mut() {
+            if value {
+                headers.insert(HEADER_SUPPORTED_QUERY_FEATURES, HeaderValue::from_static(SUPPORTED_QUERY_FEATURES));
+            } else {
+                headers.remove(HEADER_SUPPORTED_QUERY_FEATURES);
+            }
+        }
+        self
+    }
 
     pub fn execute<T: DeserializeOwned>(self) -> impl Future<Item = QueryDocumentResponse<T>, Error = AzureError> {
         trace!("get_document called(request == {:?}", self.request);
         self.execute_json().and_then(Self::convert_query_document_type)
     }
 
+    execute_boxed!(<T> QueryDocumentResponse<T>);
+
     pub fn execute_json(self) -> impl Future<Item = QueryDocumentResponse<serde_json::Value>, Error = AzureError> {
         trace!("query_document called(request == {:?}", self.request);
         let hc = self.hyper_client;
         let mut req = self.request;
-        future::result(self.payload)
-            .from_err()
+        future::result(check_uri(self.uri_error).and(self.payload.map_err(AzureError::from)))
             .and_then(move |payload| Ok(req.body(payload.into())?))
             .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::OK))
             .and_then(move |(headers, body)| Self::extract_result_json(&body, &headers))
     }
 
-    fn extract_result_json(body: &[u8], headers: &HeaderMap) -> Result<QueryDocumentResponse<serde_json::Value>, AzureError> {
-        trace!("headers == {:?}", headers);
+    /// The request the `execute` methods would send, see `DryRunRequest`.
+    pub fn dry_run(self) -> Result<DryRunRequest, AzureError> {
+        let mut req = self.request;
+        check_uri(self.uri_error)?;
+        dry_run_request(req.body(self.payload?.into())?)
+    }
+
+    /// Reads the json of the page of results without deserializing it.
+    pub fn execute_raw(self) -> impl Future<Item = QueryDocumentRawResponse, Error = AzureError> {
+        trace!("query_document called(request == {:?}", self.request);
+        let hc = self.hyper_client;
+        let mut req = self.request;
+        future::result(check_uri(self.uri_error).and(self.payload.map_err(AzureError::from)))
+            .and_then(move |payload| Ok(req.body(payload.into())?))
+            .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::OK))
+            .and_then(move |(headers, body)| Self::extract_raw_result(&headers, body))
+    }
 
-        let additional_headers = QueryDocumentResponseAdditonalHeaders {
+    fn extract_raw_result(headers: &HeaderMap, body: hyper::Chunk) -> Result<QueryDocumentRawResponse, AzureError> {
+        Ok(QueryDocumentRawResponse {
+            body: body.into_bytes(),
+            additional_headers: Self::additional_headers(headers)?,
+            headers: headers.clone(),
+        })
+    }
+
+    fn additional_headers(headers: &HeaderMap) -> Result<QueryDocumentResponseAdditonalHeaders, AzureError> {
+        Ok(QueryDocumentResponseAdditonalHeaders {
             // This match just tries to extract the info and convert it
             // into the correct type. It is complicated because headers
             // can be missing and also because headers.get<T> will return
             // a T reference (&T) so we need to cast it into the
             // correct type and clone it (in this case into a &str that will
             // become a String using to_owned())
-            continuation_token: derive_continuation_token(headers),
+            continuation_token: next_continuation(headers),
             // Here we assume the Charge header to always be present.
             // If problems arise we
             // will change the field to be Option(al).
             charge: derive_request_charge(headers),
-        };
+            query_metrics: QueryMetrics::from_headers(headers)?,
+            query_plan: QueryPlan::from_headers(headers)?,
+            server_time: server_time_from_headers(headers),
+        })
+    }
+
+    fn extract_result_json(body: &[u8], headers: &HeaderMap) -> Result<QueryDocumentResponse<serde_json::Value>, AzureError> {
+        trace!("headers == {:?}", headers);
+
+        let additional_headers = Self::additional_headers(headers)?;
         debug!("additional_headers == {:?}", additional_headers);
 
         let query_response_meta = serde_json::from_slice::<QueryResponseMeta>(body)?;
@@ -211,6 +513,7 @@ impl QueryDocumentRequest {
             query_response_meta,
             additional_headers,
             results: docs.collect(),
+            headers: headers.clone(),
         })
     }
 
@@ -223,6 +526,7 @@ impl QueryDocumentRequest {
             query_response_meta: qdr.query_response_meta,
             results: Vec::new(),
             additional_headers: qdr.additional_headers,
+            headers: qdr.headers,
         };
 
         for res_json in qdr.results {
@@ -239,19 +543,32 @@ impl QueryDocumentRequest {
 pub struct ListDocumentsRequest {
     hyper_client: HyperClient,
     request: RequestBuilder,
+    uri_error: Option<AzureError>,
+}
+
+impl DocumentRequestExt for ListDocumentsRequest {
+    fn request(&mut self) -> &mut RequestBuilder {
+        &mut self.request
+    }
 }
 
 impl ListDocumentsRequest {
-    pub(crate) fn new(hyper_client: HyperClient, request: RequestBuilder) -> ListDocumentsRequest {
-        ListDocumentsRequest { hyper_client, request }
+    pub(crate) fn new(hyper_client: HyperClient, request: Result<RequestBuilder, AzureError>) -> ListDocumentsRequest {
+        let (request, uri_error) = split_request(request);
+        ListDocumentsRequest {
+            hyper_client,
+            request,
+            uri_error,
+        }
     }
 
     request_option!(max_item_count, u64, HEADER_MAX_ITEM_COUNT);
     request_bytes_option!(continuation_token, ContinuationToken, HEADER_CONTINUATION);
-    request_option!(consistency_level, ConsistencyLevel, HEADER_CONSISTENCY_LEVEL);
+    request_option!(consistency_level, enum ConsistencyLevel, HEADER_CONSISTENCY_LEVEL);
     request_bytes_option!(session_token, String, HEADER_SESSION_TOKEN);
     request_bytes_option!(if_none_match, String, header::IF_NONE_MATCH);
     request_bytes_option!(partition_range_id, String, HEADER_DOCUMENTDB_PARTITIONRANGEID);
+    request_option!(populate_quota_info, bool, HEADER_DOCUMENTDB_POPULATE_QUOTA_INFO);
 
     pub fn incremental_feed(mut self) -> Self {
         self.request.header(HEADER_A_IM, HeaderValue::from_static("Incremental feed"));
@@ -259,12 +576,21 @@ impl ListDocumentsRequest {
     }
 
     pub fn execute<T: DeserializeOwned>(mut self) -> impl Future<Item = ListDocumentsResponse<T>, Error = AzureError> {
-        future::result(self.request.body(hyper::Body::empty()))
-            .from_err()
+        let checked = check_uri(self.uri_error.take()).and_then(|_| check_feed_scope(&self.request));
+        future::result(checked.and_then(|_| Ok(self.request.body(hyper::Body::empty())?)))
             .and_then(move |r| check_status_extract_headers_and_body(self.hyper_client.request(r), StatusCode::OK))
             .and_then(|(headers, whole_body)| Self::extract_result::<T>(&whole_body, &headers))
     }
 
+    execute_boxed!(<T> ListDocumentsResponse<T>);
+
+    /// The request `execute` would send, see `DryRunRequest`.
+    pub fn dry_run(mut self) -> Result<DryRunRequest, AzureError> {
+        check_uri(self.uri_error.take())?;
+        check_feed_scope(&self.request)?;
+        dry_run_request(self.request.body(hyper::Body::empty())?)
+    }
+
     fn extract_result<T>(body: &[u8], headers: &HeaderMap) -> Result<ListDocumentsResponse<T>, AzureError>
     where
         T: DeserializeOwned,
@@ -278,12 +604,14 @@ impl ListDocumentsRequest {
             // a T reference (&T) so we need to cast it into the
             // correct type and clone it (in this case into a &str that will
             // become a String using to_owned())
-            continuation_token: derive_continuation_token(headers),
+            continuation_token: next_continuation(headers),
             // Here we assume the Charge header to always be present.
             // If problems arise we
             // will change the field to be Option(al).
             charge: derive_request_charge(headers),
             etag: headers.get(header::ETAG).and_then(|v| v.to_str().ok()).map(|s| s.to_owned()),
+            collection_usage: DocumentCollectionUsage::from_headers(headers)?,
+            server_time: server_time_from_headers(headers),
         };
         debug!("ado == {:?}", ado);
 
@@ -308,6 +636,7 @@ impl ListDocumentsRequest {
             rid: document_attributes.rid,
             documents,
             additional_headers: ado,
+            headers: headers.clone(),
         })
     }
 }
@@ -315,7 +644,11 @@ impl ListDocumentsRequest {
 pub struct ReplaceDocumentRequest<T> {
     hyper_client: HyperClient,
     request: RequestBuilder,
+    uri_error: Option<AzureError>,
     payload: Result<String, serde_json::Error>,
+    return_minimal: bool,
+    compress_body: bool,
+    partition_key_path: Option<String>,
     _t: PhantomData<T>,
 }
 
@@ -325,40 +658,81 @@ impl<T> DocumentRequestExt for ReplaceDocumentRequest<T> {
     }
 }
 
+impl<T> WriteRequestExt for ReplaceDocumentRequest<T> {}
+
 impl<T: DeserializeOwned> ReplaceDocumentRequest<T> {
     pub(crate) fn new(
         hyper_client: HyperClient,
-        request: RequestBuilder,
+        request: Result<RequestBuilder, AzureError>,
         payload: Result<String, serde_json::Error>,
     ) -> ReplaceDocumentRequest<T> {
+        let (request, uri_error) = split_request(request);
         ReplaceDocumentRequest {
             hyper_client,
             request,
+            uri_error,
             payload,
+            return_minimal: false,
+            compress_body: false,
+            partition_key_path: None,
             _t: PhantomData,
         }
     }
 
     request_bytes_option!(if_match, String, header::IF_MATCH);
-    request_option!(indexing_directive, IndexingDirective, HEADER_INDEXING_DIRECTIVE);
+    request_option!(indexing_directive, enum IndexingDirective, HEADER_INDEXING_DIRECTIVE);
+
+    document_write_options!();
 
     pub fn execute(self) -> impl Future<Item = ReplaceDocumentResponse<T>, Error = AzureError> {
         trace!("get_document called(request == {:?}", self.request);
         let hc = self.hyper_client;
         let mut req = self.request;
-        future::result(self.payload)
-            .from_err()
-            .and_then(move |payload| Ok(req.body(payload.into())?))
+        let (compress_body, partition_key_path) = (self.compress_body, self.partition_key_path);
+        let payload = check_uri(self.uri_error)
+            .and(check_not_minimal(self.return_minimal))
+            .and(self.payload.map_err(AzureError::from));
+        future::result(payload)
+            .and_then(move |payload| write_request(&mut req, payload, compress_body, partition_key_path.as_deref()))
             .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::OK))
             .and_then(move |(headers, body)| Self::extract_result(&headers, &body))
     }
 
+    pub fn execute_boxed(self) -> BoxedFuture<ReplaceDocumentResponse<T>>
+    where
+        T: Send + 'static,
+    {
+        Box::new(self.execute())
+    }
+
+    /// The request `execute_minimal` or, without `return_minimal`,
+    /// `execute` would send, see `DryRunRequest`.
+    pub fn dry_run(self) -> Result<DryRunRequest, AzureError> {
+        let mut req = self.request;
+        check_uri(self.uri_error)?;
+        let partition_key_path = self.partition_key_path.as_deref();
+        dry_run_request(write_request(&mut req, self.payload?, self.compress_body, partition_key_path)?)
+    }
+
+    pub fn execute_minimal(self) -> impl Future<Item = MinimalDocumentResponse, Error = AzureError> {
+        trace!("replace_document minimal called(request == {:?}", self.request);
+        let hc = self.hyper_client;
+        let mut req = self.request;
+        let (compress_body, partition_key_path) = (self.compress_body, self.partition_key_path);
+        set_prefer_header(&mut req, true);
+        future::result(check_uri(self.uri_error).and(self.payload.map_err(AzureError::from)))
+            .and_then(move |payload| write_request(&mut req, payload, compress_body, partition_key_path.as_deref()))
+            .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::OK))
+            .and_then(move |(headers, _)| Ok(MinimalDocumentResponse::from_headers(&headers)))
+    }
+
     fn extract_result<R: DeserializeOwned>(headers: &HeaderMap, body: &[u8]) -> Result<ReplaceDocumentResponse<R>, AzureError> {
         let additional_headers = DocumentAdditionalHeaders::derive_from(headers);
         let document = Document::from_json(body)?;
         Ok(ReplaceDocumentResponse {
             document,
             additional_headers,
+            headers: headers.clone(),
         })
     }
 }
@@ -366,6 +740,7 @@ impl<T: DeserializeOwned> ReplaceDocumentRequest<T> {
 pub struct DeleteDocumentRequest {
     hyper_client: HyperClient,
     request: RequestBuilder,
+    uri_error: Option<AzureError>,
 }
 
 impl DocumentRequestExt for DeleteDocumentRequest {
@@ -374,9 +749,16 @@ impl DocumentRequestExt for DeleteDocumentRequest {
     }
 }
 
+impl WriteRequestExt for DeleteDocumentRequest {}
+
 impl DeleteDocumentRequest {
-    pub(crate) fn new(hyper_client: HyperClient, request: RequestBuilder) -> DeleteDocumentRequest {
-        DeleteDocumentRequest { hyper_client, request }
+    pub(crate) fn new(hyper_client: HyperClient, request: Result<RequestBuilder, AzureError>) -> DeleteDocumentRequest {
+        let (request, uri_error) = split_request(request);
+        DeleteDocumentRequest {
+            hyper_client,
+            request,
+            uri_error,
+        }
     }
 
     request_bytes_option!(if_match, String, header::IF_MATCH);
@@ -384,11 +766,18 @@ impl DeleteDocumentRequest {
     pub fn execute(mut self) -> impl Future<Item = (), Error = AzureError> {
         trace!("get_document called(request == {:?}", self.request);
 
-        future::result(self.request.body(hyper::Body::empty()))
-            .from_err()
+        future::result(check_uri(self.uri_error.take()).and_then(|_| Ok(self.request.body(hyper::Body::empty())?)))
             .and_then(move |r| check_status_extract_body(self.hyper_client.request(r), StatusCode::NO_CONTENT))
             .and_then(|_| Ok(()))
     }
+
+    execute_boxed!(());
+
+    /// The request `execute` would send, see `DryRunRequest`.
+    pub fn dry_run(mut self) -> Result<DryRunRequest, AzureError> {
+        check_uri(self.uri_error.take())?;
+        dry_run_request(self.request.body(hyper::Body::empty())?)
+    }
 }
 
 pub trait DocumentRequestExt: Sized {
@@ -406,12 +795,822 @@ pub trait DocumentRequestExt: Sized {
         }
         self
     }
+
+    /// The priority of the request when the account is throttled: the
+    /// requests of `PriorityLevel::Low` are rejected before the others.
+    /// Unset by default, which the service treats as high priority.
+    fn priority_level(mut self, value: PriorityLevel) -> Self {
+        self.request().header_value_of(HEADER_PRIORITY_LEVEL, &value);
+        self
+    }
+
+    /// Fails the operation with `AzureError::BudgetExceeded` when its
+    /// request charge is over `value` RUs. The charge is only known from
+    /// the response so the operation is performed anyway: this is meant to
+    /// catch runaway queries, not to prevent them.
+    fn max_request_charge(mut self, value: f64) -> Self {
+        self.request().extension(MaxRequestCharge(value));
+        self
+    }
+}
+
+/// Options of the requests writing documents.
+pub trait WriteRequestExt: DocumentRequestExt {
+    /// Allows a region other than the hub of a multi-master account to
+    /// accept the write tentatively. Clients of accounts with multiple write
+    /// locations send it by default.
+    fn allow_tentative_writes(mut self, value: bool) -> Self {
+        set_bool_header(self.request(), HEADER_ALLOW_TENTATIVE_WRITES, Some(value));
+        self
+    }
+
+    /// Makes the write conditional on the ETag of the document. An upsert
+    /// with `IfMatchCondition::Any` only replaces an existing document.
+    fn if_match_condition(mut self, condition: IfMatchCondition) -> Self {
+        condition.add_header(self.request());
+        self
+    }
 }
 
-fn derive_continuation_token(headers: &HeaderMap) -> Option<String> {
-    headers.get(HEADER_CONTINUATION).and_then(|v| v.to_str().ok()).map(|v| v.to_owned())
+// Sets the partition key already serialized the way the header expects it,
+// which is how keys that are not strings are passed.
+pub(crate) fn with_partition_key_json<R: DocumentRequestExt>(mut request: R, partition_key: &str) -> R {
+    request.request().header_formatted(HEADER_DOCUMENTDB_PARTITIONKEY, partition_key);
+    request
+}
+
+// Cosmos rejects documents without a string id with a rather obscure
+// error, so we check the serialized document before sending it.
+fn validate_document_id(payload: &str) -> Result<(), AzureError> {
+    let document: serde_json::Value = serde_json::from_str(payload)?;
+
+    match document.get("id") {
+        Some(serde_json::Value::String(id)) => validate_id(id),
+        Some(_) => Err(AzureError::InputParametersError("the document id must be a string".to_owned())),
+        None => Err(AzureError::InputParametersError("the document must have an id field".to_owned())),
+    }
+}
+
+// Documents that are not objects are left to `validate_document_id`.
+fn inject_document_id(payload: String) -> Result<(String, Option<String>), serde_json::Error> {
+    let mut document: serde_json::Value = serde_json::from_str(&payload)?;
+
+    match document.as_object_mut() {
+        Some(fields) if !fields.contains_key("id") => {
+            let id = Uuid::new_v4().to_string();
+            fields.insert("id".to_owned(), serde_json::Value::String(id.clone()));
+            Ok((serde_json::to_string(&document)?, Some(id)))
+        }
+        _ => Ok((payload, None)),
+    }
+}
+
+// A change feed can be scoped either to a partition key range or to a
+// single logical partition, not both.
+fn check_feed_scope(request: &RequestBuilder) -> Result<(), AzureError> {
+    let has_header = |h| request.headers_ref().map(|headers| headers.contains_key(h)).unwrap_or(false);
+
+    if has_header(HEADER_DOCUMENTDB_PARTITIONRANGEID) && has_header(HEADER_DOCUMENTDB_PARTITIONKEY) {
+        Err(AzureError::InputParametersError(
+            "partition_range_id and partition_key cannot be both specified".to_owned(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+const PREFER_RETURN_MINIMAL: &str = "return=minimal";
+const PREFER_RETURN_REPRESENTATION: &str = "return=representation";
+
+fn set_prefer_header(request: &mut RequestBuilder, return_minimal: bool) {
+    let value = if return_minimal {
+        PREFER_RETURN_MINIMAL
+    } else {
+        PREFER_RETURN_REPRESENTATION
+    };
+    if let Some(headers) = request.headers_mut() {
+        headers.insert(HEADER_PREFER, HeaderValue::from_static(value));
+    }
+}
+
+// Below this size gzip does not pay off.
+const COMPRESSION_THRESHOLD: usize = 1024;
+
+fn request_with_body(
+    request: &mut RequestBuilder,
+    payload: String,
+    compress_body: bool,
+) -> Result<hyper::Request<hyper::Body>, AzureError> {
+    // the limit applies to the document, compressed or not, so there is no
+    // point in sending it: Cosmos would answer 413.
+    if payload.len() as u64 > MAX_REQUEST_BODY_SIZE {
+        return Err(AzureError::RequestTooLarge {
+            limit_bytes: MAX_REQUEST_BODY_SIZE,
+        });
+    }
+
+    if compress_body && payload.len() >= COMPRESSION_THRESHOLD {
+        let mut encoder = GzEncoder::new(Vec::with_capacity(payload.len() / 4), Compression::default());
+        encoder.write_all(payload.as_bytes())?;
+        request.header(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        Ok(request.body(encoder.finish()?.into())?)
+    } else {
+        Ok(request.body(payload.into())?)
+    }
+}
+
+fn write_request(
+    request: &mut RequestBuilder,
+    payload: String,
+    compress_body: bool,
+    partition_key_path: Option<&str>,
+) -> Result<hyper::Request<hyper::Body>, AzureError> {
+    if let Some(path) = partition_key_path {
+        check_partition_key(request, &payload, path)?;
+    }
+    request_with_body(request, payload, compress_body)
+}
+
+// The key at `path` in the document, `[{}]` when it has none, must be the
+// one of the header.
+fn check_partition_key(request: &RequestBuilder, payload: &str, path: &str) -> Result<(), AzureError> {
+    let document: serde_json::Value = serde_json::from_str(payload)?;
+    let expected = match partition_key_value(&document, path) {
+        Some(value) => serde_json::Value::Array(vec![value.clone()]),
+        None => serde_json::Value::Array(vec![serde_json::Value::Object(serde_json::Map::new())]),
+    };
+    let header = request
+        .headers_ref()
+        .and_then(|headers| headers.get(HEADER_DOCUMENTDB_PARTITIONKEY));
+    let given = match header {
+        Some(header) => Some(serde_json::from_slice::<serde_json::Value>(header.as_bytes())?),
+        None => None,
+    };
+
+    match given {
+        Some(ref given) if *given == expected => Ok(()),
+        given => Err(AzureError::InputParametersError(format!(
+            "the document has the partition key {} at {} but {} was given",
+            expected,
+            path,
+            given.map(|given| given.to_string()).unwrap_or_else(|| "none".to_owned())
+        ))),
+    }
+}
+
+// With return=minimal there is no body to parse.
+fn check_not_minimal(return_minimal: bool) -> Result<(), AzureError> {
+    if return_minimal {
+        Err(AzureError::InputParametersError(
+            "return_minimal was requested: use execute_minimal to read the response".to_owned(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Ids looked up by a single read many query. It keeps the `IN` clause (and
+/// the number of parameters) well below the query size limits.
+pub(crate) const READ_MANY_CHUNK_SIZE: usize = 100;
+
+// Ids are passed as parameters so they never need escaping.
+pub(crate) fn read_many_query(ids: &[String]) -> Result<String, serde_json::Error> {
+    let names = (0..ids.len()).map(|i| format!("@id{}", i)).collect::<Vec<_>>();
+    let query_text = format!("SELECT * FROM c WHERE c.id IN ({})", names.join(", "));
+
+    let mut query = Query::new(&query_text);
+    for (name, id) in names.iter().zip(ids) {
+        query.parameters_mut().push(Parameter::new().name(name).value(id).build());
+    }
+
+    serde_json::to_string(&query)
+}
+
+/// The query of `Client::changed_since`.
+pub const CHANGED_SINCE_QUERY: &str = "SELECT * FROM c WHERE c._ts > @since ORDER BY c._ts";
+
+#[derive(Serialize)]
+struct TimestampParameter {
+    name: &'static str,
+    value: i64,
+}
+
+#[derive(Serialize)]
+struct ChangedSinceQuery {
+    query: &'static str,
+    parameters: [TimestampParameter; 1],
+}
+
+// `_ts` holds the seconds since the epoch, a number the string parameters
+// of `Query` cannot express.
+pub(crate) fn changed_since_query(since: &DateTime<Utc>) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&ChangedSinceQuery {
+        query: CHANGED_SINCE_QUERY,
+        parameters: [TimestampParameter {
+            name: "@since",
+            value: since.timestamp(),
+        }],
+    })
+}
+
+// The plan Cosmos gives for `CHANGED_SINCE_QUERY`: every partition returns
+// its documents as the payload of their `_ts`, merged in order.
+const CHANGED_SINCE_QUERY_PLAN: &str = r#"{"queryInfo": {"orderBy": ["Ascending"], "orderByExpressions": ["c._ts"],
+    "rewrittenQuery": "SELECT c._rid, [{\"item\": c._ts}] AS orderByItems, c AS payload FROM c WHERE c._ts > @since ORDER BY c._ts"}}"#;
+
+pub(crate) fn changed_since_query_plan() -> Result<QueryPlan, AzureError> {
+    QueryPlan::parse(CHANGED_SINCE_QUERY_PLAN)
+}
+
+pub(crate) fn documents_from_query_response<T: DeserializeOwned>(
+    response: QueryDocumentResponse<serde_json::Value>,
+) -> Result<Vec<Document<T>>, AzureError> {
+    response
+        .results
+        .into_iter()
+        .map(|result| match result.document_attributes {
+            Some(document_attributes) => Ok(Document {
+                document_attributes,
+                entity: serde_json::from_value(result.result)?,
+            }),
+            None => Err(AzureError::GenericErrorWithText(
+                "read many returned an entry without the document attributes".to_owned(),
+            )),
+        }).collect()
 }
 
 fn derive_request_charge(headers: &HeaderMap) -> f64 {
     headers.get(HEADER_REQUEST_CHARGE).unwrap().to_str().unwrap().parse().unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure::cosmos::client::tests::test_client;
+    use azure::cosmos::requests::tests::hyper_client;
+    use chrono::TimeZone;
+
+    #[test]
+    fn prefer_return_minimal_header() {
+        let mut request = hyper::Request::builder();
+
+        set_prefer_header(&mut request, true);
+        assert_eq!(request.headers_ref().unwrap()[HEADER_PREFER], "return=minimal");
+
+        // setting it twice must not send the header twice
+        set_prefer_header(&mut request, false);
+        let headers = request.headers_ref().unwrap();
+        assert_eq!(headers.get_all(HEADER_PREFER).iter().count(), 1);
+        assert_eq!(headers[HEADER_PREFER], "return=representation");
+
+        assert!(check_not_minimal(true).is_err());
+        assert!(check_not_minimal(false).is_ok());
+    }
+
+    #[test]
+    fn content_types() {
+        let (_, client) = test_client();
+        let content_types = |request: DryRunRequest| {
+            request
+                .headers()
+                .get_all(header::CONTENT_TYPE)
+                .iter()
+                .map(|value| value.to_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let create = client.create_document_as_str::<(), _, _, _>("db", "orders", r#"{"id": "a"}"#);
+        assert_eq!(content_types(create.dry_run().unwrap()), vec!["application/json"]);
+
+        let query = Query::new("SELECT * FROM c");
+        let request = client.query_document("db", "orders", &query).dry_run().unwrap();
+        assert_eq!(request.headers()[HEADER_DOCUMENTDB_ISQUERY], "true");
+        assert_eq!(content_types(request), vec!["application/query+json"]);
+
+        let get = client.get_document("db", "orders", "a").dry_run().unwrap();
+        assert!(content_types(get).is_empty());
+    }
+
+    #[test]
+    fn partition_key_of_written_documents() {
+        let (_, client) = test_client();
+        let create = |document: &'static str| client.create_document_as_str::<(), _, _, _>("db", "orders", document);
+        let order = r#"{"id": "a", "customer": {"id": "c1"}}"#;
+
+        let request = create(order).partition_key("c1").partition_key_path("/customer/id");
+        assert_eq!(request.dry_run().unwrap().headers()[HEADER_DOCUMENTDB_PARTITIONKEY], "[\"c1\"]");
+        let request = create(r#"{"id": "a"}"#)
+            .partition_key(PartitionKey::undefined())
+            .partition_key_path("/customer/id");
+        assert!(request.dry_run().is_ok());
+
+        match create(order).partition_key("c2").partition_key_path("/customer/id").dry_run() {
+            Err(AzureError::InputParametersError(message)) => assert_eq!(
+                message,
+                "the document has the partition key [\"c1\"] at /customer/id but [\"c2\"] was given"
+            ),
+            r => panic!("unexpected result {:?}", r),
+        }
+        // a number is not the text of the number
+        let request = create(r#"{"id": "a", "customer": {"id": 7}}"#).partition_key("7");
+        assert!(request.partition_key_path("/customer/id").dry_run().is_err());
+        match create(order).partition_key_path("/customer/id").dry_run() {
+            Err(AzureError::InputParametersError(message)) => assert!(message.ends_with("but none was given")),
+            r => panic!("unexpected result {:?}", r),
+        }
+        // without a path nothing is checked
+        assert!(create(order).partition_key("c2").dry_run().is_ok());
+
+        let hyper_client = hyper_client();
+        let replace = |key: &'static str| {
+            ReplaceDocumentRequest::<()>::new(hyper_client.clone(), Ok(hyper::Request::builder()), Ok(order.to_owned()))
+                .partition_key(key)
+                .partition_key_path("/customer/id")
+        };
+        assert!(replace("c1").dry_run().is_ok());
+        match replace("c2").dry_run() {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn dry_run_create_document() {
+        #[derive(Serialize)]
+        struct Order<'a> {
+            id: &'a str,
+            total: u64,
+        }
+
+        let (_, client) = test_client();
+
+        let request = client
+            .create_document("my db", "orders", &Order { id: "order-1", total: 42 })
+            .is_upsert(true)
+            .partition_key("shop")
+            .dry_run()
+            .unwrap();
+
+        assert_eq!(request.method(), hyper::Method::POST);
+        assert_eq!(
+            request.uri().to_string(),
+            "https://mindflavor.documents.azure.com/dbs/my%20db/colls/orders/docs"
+        );
+        let headers = request.headers();
+        assert_eq!(headers[header::AUTHORIZATION], "type%3Dmaster%26ver%3D1.0%26sig%3D<redacted>");
+        assert_eq!(headers[HEADER_DOCUMENTDB_IS_UPSERT], "true");
+        assert_eq!(headers[HEADER_DOCUMENTDB_PARTITIONKEY], "[\"shop\"]");
+        assert!(headers.contains_key(HEADER_DATE));
+        assert_eq!(request.body(), b"{\"id\":\"order-1\",\"total\":42}");
+
+        // the errors execute would return come out of the dry run
+        let request = client.create_document_as_str::<(), _, _, _>("db", "orders", "{\"id\": \"a/b\"}");
+        match request.dry_run() {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn dry_run_replace_document() {
+        let (_, client) = test_client();
+        let document = |self_link: &str| {
+            Document::<serde_json::Value>::from_json(
+                format!(
+                    r#"{{"id": "order-1", "total": 42, "_rid": "XP0mAJ3H-AABAAAAAAAAAA==", "_ts": 1532866523, "_self": "{}",
+                        "_etag": "\"00002602-0000-0000-0000-5b5d3b5b0000\"", "_attachments": "attachments/"}}"#,
+                    self_link
+                )
+                .as_bytes(),
+            )
+            .unwrap()
+        };
+
+        let read = document("dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/");
+        let request = client.replace_document("db", "orders", &read).dry_run().unwrap();
+        assert_eq!(request.method(), hyper::Method::PUT);
+        assert_eq!(
+            request.uri().to_string(),
+            "https://mindflavor.documents.azure.com/dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/"
+        );
+
+        // a document not read from Cosmos has no link to replace
+        match client.replace_document("db", "orders", &document("")).dry_run() {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn empty_page_with_continuation() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_REQUEST_CHARGE, HeaderValue::from_static("2.5"));
+        headers.insert(HEADER_CONTINUATION, HeaderValue::from_static("+RID:XP0mAJ3H-AA=#RT:1"));
+        let body = br#"{"_rid": "XP0mAJ3H-AA=", "Documents": [], "_count": 0}"#;
+
+        let page = QueryDocumentRequest::extract_result_json(body, &headers).unwrap();
+        let page = QueryDocumentRequest::convert_query_document_type::<serde_json::Value>(page).unwrap();
+        assert!(page.is_empty());
+        assert_eq!(page.query_response_meta.count, 0);
+        assert_eq!(
+            page.additional_headers.continuation_token.as_ref().map(|token| token.as_str()),
+            Some("+RID:XP0mAJ3H-AA=#RT:1")
+        );
+
+        headers.remove(HEADER_CONTINUATION);
+        let page = QueryDocumentRequest::extract_result_json(body, &headers).unwrap();
+        assert!(page.is_empty());
+        assert!(page.additional_headers.continuation_token.is_none());
+    }
+
+    #[test]
+    fn tentative_writes_header() {
+        let (_, client) = test_client();
+
+        let request = client.delete_document("db", "coll", "doc").allow_tentative_writes(true);
+        assert_eq!(request.request.headers_ref().unwrap()[HEADER_ALLOW_TENTATIVE_WRITES], "true");
+        let request = client.delete_document("db", "coll", "doc");
+        assert!(request.request.headers_ref().unwrap().get(HEADER_ALLOW_TENTATIVE_WRITES).is_none());
+
+        // multi-master accounts send it on the writes only
+        client.set_multiple_write_locations(true);
+        let request = client.delete_document("db", "coll", "doc");
+        assert_eq!(request.request.headers_ref().unwrap()[HEADER_ALLOW_TENTATIVE_WRITES], "true");
+        let request = client.get_document("db", "coll", "doc");
+        assert!(request.request.headers_ref().unwrap().get(HEADER_ALLOW_TENTATIVE_WRITES).is_none());
+        let request = client.query_document("db", "coll", &Query::new("SELECT * FROM c"));
+        assert!(request.request.headers_ref().unwrap().get(HEADER_ALLOW_TENTATIVE_WRITES).is_none());
+
+        let request = client.delete_document("db", "coll", "doc").allow_tentative_writes(false);
+        let headers = request.request.headers_ref().unwrap();
+        assert_eq!(headers.get_all(HEADER_ALLOW_TENTATIVE_WRITES).iter().count(), 1);
+        assert_eq!(headers[HEADER_ALLOW_TENTATIVE_WRITES], "false");
+    }
+
+    #[test]
+    fn if_match_condition_header() {
+        let (_, client) = test_client();
+
+        let document: serde_json::Value = serde_json::from_str(r#"{"id": "doc"}"#).unwrap();
+        let request = client
+            .create_document("db", "coll", &document)
+            .is_upsert(true)
+            .if_match_condition(IfMatchCondition::Any);
+        assert_eq!(request.request.headers_ref().unwrap()[header::IF_MATCH], "*");
+
+        let request = client
+            .delete_document("db", "coll", "doc")
+            .if_match_condition(IfMatchCondition::Match("00000b00-0000-0000-0000-5b5d3e9d0000"));
+        assert_eq!(
+            request.request.headers_ref().unwrap()[header::IF_MATCH],
+            "\"00000b00-0000-0000-0000-5b5d3e9d0000\""
+        );
+    }
+
+    #[test]
+    fn cross_partition_query_expects_continuation() {
+        let request = QueryDocumentRequest::new(hyper_client(), Ok(hyper::Request::builder()), Ok(String::new()));
+        assert!(request
+            .request
+            .headers_ref()
+            .unwrap()
+            .get(HEADER_DOCUMENTDB_QUERY_ISCONTINUATIONEXPECTED)
+            .is_none());
+
+        let request = request.enable_cross_partition(true);
+        let headers = request.request.headers_ref().unwrap();
+        assert_eq!(headers[HEADER_DOCUMENTDB_QUERY_ENABLECROSSPARTITION], "true");
+        assert_eq!(headers[HEADER_DOCUMENTDB_QUERY_ISCONTINUATIONEXPECTED], "true");
+
+        let request = request.enable_cross_partition(false);
+        let headers = request.request.headers_ref().unwrap();
+        assert_eq!(headers[HEADER_DOCUMENTDB_QUERY_ENABLECROSSPARTITION], "false");
+        assert!(headers.get(HEADER_DOCUMENTDB_QUERY_ISCONTINUATIONEXPECTED).is_none());
+
+        // an explicit choice wins over the cross partition default
+        let request = QueryDocumentRequest::new(hyper_client(), Ok(hyper::Request::builder()), Ok(String::new()))
+            .continuation_expected(false)
+            .enable_cross_partition(true);
+        let headers = request.request.headers_ref().unwrap();
+        assert_eq!(headers.get_all(HEADER_DOCUMENTDB_QUERY_ISCONTINUATIONEXPECTED).iter().count(), 1);
+        assert_eq!(headers[HEADER_DOCUMENTDB_QUERY_ISCONTINUATIONEXPECTED], "false");
+    }
+
+    #[test]
+    fn enum_header_values() {
+        use azure::core::util::HeaderValueOf;
+
+        use std::fmt;
+        use std::str::FromStr;
+
+        fn round_trip<E: HeaderValueOf + fmt::Display + FromStr + fmt::Debug + PartialEq>(values: &[E])
+        where
+            E::Err: fmt::Debug,
+        {
+            for value in values {
+                assert_eq!(value.header_value(), value.to_string());
+                assert_eq!(&value.header_value().parse::<E>().unwrap(), value);
+            }
+        }
+        round_trip(&[
+            ConsistencyLevel::Strong,
+            ConsistencyLevel::Bounded,
+            ConsistencyLevel::Session,
+            ConsistencyLevel::Eventual,
+        ]);
+        round_trip(&[IndexingDirective::Include, IndexingDirective::Exclude]);
+        round_trip(&[PriorityLevel::High, PriorityLevel::Low]);
+
+        let request = QueryDocumentRequest::new(hyper_client(), Ok(hyper::Request::builder()), Ok(String::new()))
+            .consistency_level(ConsistencyLevel::Bounded);
+        assert_eq!(request.request.headers_ref().unwrap()[HEADER_CONSISTENCY_LEVEL], "Bounded");
+        let request = CreateDocumentRequest::new(hyper_client(), Ok(hyper::Request::builder()), Ok(String::new()))
+            .indexing_directive(IndexingDirective::Exclude);
+        assert_eq!(request.request.headers_ref().unwrap()[HEADER_INDEXING_DIRECTIVE], "Exclude");
+    }
+
+    #[test]
+    fn priority_level_header() {
+        let request = GetDocumentRequest::new(hyper_client(), Ok(hyper::Request::builder()));
+        assert!(request.request.headers_ref().unwrap().get(HEADER_PRIORITY_LEVEL).is_none());
+
+        for (priority_level, value) in &[(PriorityLevel::High, "High"), (PriorityLevel::Low, "Low")] {
+            let request = DeleteDocumentRequest::new(hyper_client(), Ok(hyper::Request::builder())).priority_level(*priority_level);
+            assert_eq!(request.request.headers_ref().unwrap()[HEADER_PRIORITY_LEVEL], *value);
+        }
+    }
+
+    #[test]
+    fn read_many_in_clause() {
+        let ids = vec!["a".to_owned(), "b\"c".to_owned()];
+        let query: serde_json::Value = serde_json::from_str(&read_many_query(&ids).unwrap()).unwrap();
+
+        assert_eq!(query["query"], "SELECT * FROM c WHERE c.id IN (@id0, @id1)");
+        assert_eq!(query["parameters"][0]["name"], "@id0");
+        assert_eq!(query["parameters"][0]["value"], "a");
+        assert_eq!(query["parameters"][1]["value"], "b\"c");
+    }
+
+    #[test]
+    fn read_many_results() {
+        #[derive(Deserialize)]
+        struct Entity {
+            id: String,
+            value: u64,
+        }
+
+        let body = br#"{
+            "_rid": "XP0mAJ3H-AA=",
+            "Documents": [
+                {"id": "a", "value": 1, "_rid": "XP0mAJ3H-AABAAAAAAAAAA==", "_ts": 1532866523,
+                 "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/",
+                 "_etag": "\"00002602-0000-0000-0000-5b5d3b5b0000\"", "_attachments": "attachments/"},
+                {"id": "b", "value": 2, "_rid": "XP0mAJ3H-AACAAAAAAAAAA==", "_ts": 1532866524,
+                 "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AACAAAAAAAAAA==/",
+                 "_etag": "\"00002702-0000-0000-0000-5b5d3b5c0000\"", "_attachments": "attachments/"}
+            ],
+            "_count": 2
+        }"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_REQUEST_CHARGE, HeaderValue::from_static("2.9"));
+
+        let response = QueryDocumentRequest::extract_result_json(body, &headers).unwrap();
+        let documents = documents_from_query_response::<Entity>(response).unwrap();
+
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].entity.id, "a");
+        assert_eq!(documents[1].entity.value, 2);
+        assert_eq!(documents[1].document_attributes.rid(), "XP0mAJ3H-AACAAAAAAAAAA==");
+    }
+
+    #[test]
+    fn raw_bodies() {
+        let body = r#"{"id": "a", "value": 1, "_rid": "XP0mAJ3H-AABAAAAAAAAAA==", "_ts": 1532866523}"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_REQUEST_CHARGE, HeaderValue::from_static("1"));
+
+        let response = GetDocumentRequest::extract_raw_result(StatusCode::OK, &headers, hyper::Chunk::from(body)).unwrap();
+        assert_eq!(response.body.unwrap(), body.as_bytes());
+        let response = GetDocumentRequest::extract_raw_result(StatusCode::NOT_FOUND, &headers, hyper::Chunk::from("")).unwrap();
+        assert!(response.body.is_none());
+
+        let body = r#"{"_rid": "XP0mAJ3H-AA=", "Documents": [{"id": "a", "value": 1}], "_count": 1}"#;
+        let response = QueryDocumentRequest::extract_raw_result(&headers, hyper::Chunk::from(body)).unwrap();
+        assert_eq!(response.body, body.as_bytes());
+        assert_eq!(response.additional_headers.charge, 1.0);
+    }
+
+    #[test]
+    fn changed_since_parameter() {
+        let since = Utc.ymd(2018, 7, 29).and_hms_milli(12, 15, 23, 999);
+        let query: serde_json::Value = serde_json::from_str(&changed_since_query(&since).unwrap()).unwrap();
+
+        assert_eq!(query["query"], "SELECT * FROM c WHERE c._ts > @since ORDER BY c._ts");
+        let parameters = query["parameters"].as_array().unwrap();
+        assert_eq!(parameters.len(), 1);
+        assert_eq!(parameters[0]["name"], "@since");
+        // a number, truncated to the second like `_ts`
+        assert_eq!(parameters[0]["value"].as_i64(), Some(1_532_866_523));
+    }
+
+    #[test]
+    fn auto_id() {
+        #[derive(Serialize)]
+        struct Entity {
+            value: u64,
+        }
+
+        let (_, client) = test_client();
+
+        let request = client.create_document("db", "coll", &Entity { value: 1 }).auto_id(true);
+        let id = request.generated_id().unwrap().to_owned();
+        assert!(Uuid::parse_str(&id).is_ok());
+        let document: serde_json::Value = serde_json::from_str(request.payload.as_ref().unwrap()).unwrap();
+        assert_eq!(document["id"], id.as_str());
+        assert_eq!(document["value"], 1);
+        assert!(validate_document_id(request.payload.as_ref().unwrap()).is_ok());
+
+        // calling it again keeps the id
+        let request = request.auto_id(true);
+        assert_eq!(request.generated_id(), Some(id.as_str()));
+
+        let payload = r#"{"id": "mydoc", "value": 1}"#;
+        let request = client
+            .create_document_as_str::<Entity, _, _, _>("db", "coll", payload)
+            .auto_id(true);
+        assert!(request.generated_id().is_none());
+        assert_eq!(request.payload.as_ref().unwrap(), payload);
+
+        let request = client.create_document("db", "coll", &Entity { value: 1 });
+        assert!(request.auto_id(false).generated_id().is_none());
+    }
+
+    #[test]
+    fn document_id_validation() {
+        assert!(validate_document_id(r#"{"id": "mydoc", "value": 1}"#).is_ok());
+
+        for payload in &[
+            r#"{"value": 1}"#,
+            r#"{"id": 1}"#,
+            r#"{"id": ""}"#,
+            r#"{"id": "my/doc"}"#,
+            r#"{"id": "my\\doc"}"#,
+            r#"{"id": "mydoc?"}"#,
+            r##"{"id": "#mydoc"}"##,
+        ] {
+            match validate_document_id(payload) {
+                Err(AzureError::InputParametersError(_)) => {}
+                r => panic!("unexpected result {:?} for {}", r, payload),
+            }
+        }
+    }
+
+    #[test]
+    fn compressed_body() {
+        use std::io::Read;
+
+        let payload = "{\"id\":\"mydoc\",\"values\":[".to_owned() + &"1,".repeat(1000) + "1]}";
+
+        let request = request_with_body(&mut hyper::Request::builder(), payload.clone(), true).unwrap();
+        assert_eq!(request.headers()[header::CONTENT_ENCODING], "gzip");
+
+        let body = request.into_body().concat2().wait().unwrap();
+        assert!(body.len() < payload.len());
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(&body[..]).read_to_string(&mut decompressed).unwrap();
+        assert_eq!(decompressed, payload);
+
+        // small payloads are sent as they are
+        let request = request_with_body(&mut hyper::Request::builder(), "{\"id\":\"mydoc\"}".to_owned(), true).unwrap();
+        assert!(request.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(&request.into_body().concat2().wait().unwrap()[..], b"{\"id\":\"mydoc\"}");
+    }
+
+    #[test]
+    fn read_again_when_not_found() {
+        use std::cell::Cell;
+
+        let mut core = ::tokio_core::reactor::Core::new().unwrap();
+        let body = br#"{"id": "doc", "_rid": "XP0mAJ3H-AABAAAAAAAAAA==", "_ts": 1532866523,
+            "_self": "dbs/XP0mAA==/colls/XP0mAJ3H-AA=/docs/XP0mAJ3H-AABAAAAAAAAAA==/",
+            "_etag": "\"00002602-0000-0000-0000-5b5d3b5b0000\"", "_attachments": "attachments/"}"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_REQUEST_CHARGE, HeaderValue::from_static("1"));
+        let reads = Cell::new(0);
+        let read = || {
+            reads.set(reads.get() + 1);
+            let status = if reads.get() == 1 { StatusCode::NOT_FOUND } else { StatusCode::OK };
+            future::result(GetDocumentRequest::extract_result::<serde_json::Value>(status, &headers, body))
+        };
+
+        let found = |response: &GetDocumentResponse<serde_json::Value>| response.document.is_some();
+        let response = core.run(retry_not_found(3, Duration::from_millis(1), &read, found)).unwrap();
+        assert_eq!(response.document.unwrap().document_attributes.rid(), "XP0mAJ3H-AABAAAAAAAAAA==");
+        assert_eq!(reads.get(), 2);
+
+        // a document still missing after the retries is not an error
+        let missing = || {
+            future::result(GetDocumentRequest::extract_result::<serde_json::Value>(
+                StatusCode::NOT_FOUND,
+                &headers,
+                b"",
+            ))
+        };
+        let response = core.run(retry_not_found(2, Duration::from_millis(1), missing, found)).unwrap();
+        assert!(response.document.is_none());
+    }
+
+    #[test]
+    fn copied_request() {
+        let hyper_client = hyper_client();
+        let mut request = GetDocumentRequest::new(hyper_client, Ok(hyper::Request::builder()))
+            .session_token("0:1#9#4=8")
+            .max_request_charge(5.0)
+            .retry_on_not_found_with_session(3);
+        assert_eq!(request.not_found_retries, 3);
+
+        let request = request.request.body(()).unwrap();
+        let copy = copy_request(&request).unwrap();
+        assert_eq!(copy.headers()[HEADER_SESSION_TOKEN], "0:1#9#4=8");
+        assert_eq!(copy.extensions().get::<MaxRequestCharge>(), Some(&MaxRequestCharge(5.0)));
+    }
+
+    #[test]
+    fn max_request_charge_extension() {
+        let hyper_client = hyper_client();
+        let mut request =
+            QueryDocumentRequest::new(hyper_client, Ok(hyper::Request::builder()), Ok(String::new())).max_request_charge(10.0);
+
+        let request = request.request.body(()).unwrap();
+        assert_eq!(request.extensions().get::<MaxRequestCharge>(), Some(&MaxRequestCharge(10.0)));
+    }
+
+    #[test]
+    fn oversized_body_is_not_sent() {
+        let payload = "{\"id\":\"mydoc\",\"blob\":\"".to_owned() + &"a".repeat(MAX_REQUEST_BODY_SIZE as usize) + "\"}";
+
+        for compress_body in &[false, true] {
+            match request_with_body(&mut hyper::Request::builder(), payload.clone(), *compress_body) {
+                Err(AzureError::RequestTooLarge { limit_bytes }) => assert_eq!(limit_bytes, MAX_REQUEST_BODY_SIZE),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn partition_key_header() {
+        use azure::cosmos::PartitionKey;
+
+        let (_, client) = test_client();
+        let header = |key: Option<PartitionKey<'static>>| {
+            let request = client.get_document("db", "coll", "doc");
+            let request = match key {
+                Some(key) => request.partition_key(key),
+                None => request,
+            };
+            let headers = request.request.headers_ref().unwrap();
+            headers
+                .get(HEADER_DOCUMENTDB_PARTITIONKEY)
+                .map(|value| value.to_str().unwrap().to_owned())
+        };
+
+        assert_eq!(header(None), None);
+        assert_eq!(header(Some(PartitionKey::default())), None);
+        assert_eq!(header(Some(PartitionKey::from("tenant"))).as_deref(), Some("[\"tenant\"]"));
+        assert_eq!(header(Some(PartitionKey::none())).as_deref(), Some("[]"));
+        assert_eq!(header(Some(PartitionKey::undefined())).as_deref(), Some("[{}]"));
+        assert_eq!(header(Some(PartitionKey::null())).as_deref(), Some("[null]"));
+        // a key with the text "null" is neither
+        assert_eq!(header(Some(PartitionKey::from("null"))).as_deref(), Some("[\"null\"]"));
+    }
+
+    #[test]
+    fn change_feed_partition_key_scope() {
+        let hyper_client = hyper_client();
+        let request = ListDocumentsRequest::new(hyper_client, Ok(hyper::Request::builder()))
+            .incremental_feed()
+            .partition_key("tenant");
+
+        let headers = request.request.headers_ref().unwrap();
+        assert_eq!(headers[HEADER_DOCUMENTDB_PARTITIONKEY], "[\"tenant\"]");
+        assert!(check_feed_scope(&request.request).is_ok());
+
+        let request = request.partition_range_id("0");
+        match check_feed_scope(&request.request) {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn minimal_response_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_REQUEST_CHARGE, HeaderValue::from_static("6.29"));
+        headers.insert(header::ETAG, HeaderValue::from_static("\"00000000-0000-0000-0000-000000000000\""));
+
+        // the body is never looked at
+        let response = MinimalDocumentResponse::from_headers(&headers);
+        assert_eq!(response.etag, Some("\"00000000-0000-0000-0000-000000000000\"".to_owned()));
+        assert_eq!(response.additional_headers.charge, 6.29);
+    }
+}
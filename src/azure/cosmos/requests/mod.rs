@@ -1,19 +1,30 @@
 #[allow(unused_imports)]
 use azure::core::{
-    errors::{
-        check_status_extract_body, check_status_extract_headers_and_body, extract_status_headers_and_body, AzureError, UnexpectedHTTPResult,
-    },
-    incompletevector::ContinuationToken,
-    util::RequestBuilderExt,
+    clock_skew::{check_clock_skew_of_rejection, server_time_from_headers},
+    errors::{extract_status_headers_and_body, AzureError, UnexpectedHTTPResult},
+    incompletevector::{ContinuationToken, IncompleteVector},
+    modify_conditions::IfMatchCondition,
+    util::{delay, RequestBuilderExt},
 };
 use azure::cosmos::{
     client::headers::*,
+    conflict::Conflict,
+    cross_partition::{QueryPlan, SUPPORTED_QUERY_FEATURES},
     document::{DocumentAttributes, IndexingDirective},
     partition_key::PartitionKey,
+    query::{Parameter, Query},
     request_response::*,
-    ConsistencyLevel,
+    ru_budget::{check_request_charge, BudgetedHyperClient, BudgetedResponseFuture, MaxRequestCharge},
+    stored_procedure::StoredProcedure,
+    uri_builder::validate_id,
+    ConsistencyLevel, PriorityLevel,
+};
+use chrono::{DateTime, Utc};
+use futures::{
+    future::{self, loop_fn, Either, Loop},
+    prelude::*,
+    stream,
 };
-use futures::{future, prelude::*};
 use http::request::Builder as RequestBuilder;
 use hyper::{
     self,
@@ -22,10 +33,11 @@ use hyper::{
 };
 use serde::de::DeserializeOwned;
 use serde_json;
-use std::sync::Arc;
+use std::time::Duration;
 use std::{marker::PhantomData, str};
+use uuid::Uuid;
 
-type HyperClient = Arc<hyper::Client<::hyper_tls::HttpsConnector<hyper::client::HttpConnector>>>;
+type HyperClient = BudgetedHyperClient;
 
 macro_rules! request_bytes_option {
     ($name:ident, $ty:ty, $h:path) => {
@@ -37,6 +49,12 @@ macro_rules! request_bytes_option {
 }
 
 macro_rules! request_option {
+    ($name:ident, enum $ty:ty, $h:path) => {
+        pub fn $name<V: Into<$ty>>(mut self, value: V) -> Self {
+            self.request.header_value_of($h, &value.into());
+            self
+        }
+    };
     ($name:ident, bool, $h:path) => {
         pub fn $name<V: Into<bool>>(mut self, value: V) -> Self {
             self.request.header($h, ::http::header::HeaderValue::from_static(
@@ -52,8 +70,316 @@ macro_rules! request_option {
     };
 }
 
+// Boxes the future of `execute`, for the callers who need to name its type.
+macro_rules! execute_boxed {
+    (<$t:ident> $item:ty) => {
+        pub fn execute_boxed<$t: DeserializeOwned + Send + 'static>(self) -> BoxedFuture<$item> {
+            Box::new(self.execute::<$t>())
+        }
+    };
+    ($item:ty) => {
+        pub fn execute_boxed(self) -> BoxedFuture<$item> {
+            Box::new(self.execute())
+        }
+    };
+}
+
+// Cosmos answers 429 when the provisioned RUs are exhausted. We surface it
+// as a ThrottledError so the caller can decide when to retry.
+fn throttled_error(headers: &HeaderMap) -> AzureError {
+    let retry_after = headers
+        .get(HEADER_RETRY_AFTER_MS)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+    AzureError::ThrottledError {
+        retry_after: Duration::from_millis(retry_after),
+    }
+}
+
+// Cosmos rejects documents larger than 2MB.
+pub(crate) const MAX_REQUEST_BODY_SIZE: u64 = 2 * 1024 * 1024;
+
+fn check_status(status: StatusCode, headers: &HeaderMap, body: &[u8], expected_status_code: StatusCode) -> Result<(), AzureError> {
+    if status == expected_status_code {
+        Ok(())
+    } else if status == StatusCode::TOO_MANY_REQUESTS {
+        Err(throttled_error(headers))
+    } else if status == StatusCode::PRECONDITION_FAILED {
+        Err(AzureError::PreconditionFailed(str::from_utf8(body)?.to_owned()))
+    } else if status == StatusCode::PAYLOAD_TOO_LARGE {
+        Err(AzureError::RequestTooLarge {
+            limit_bytes: MAX_REQUEST_BODY_SIZE,
+        })
+    } else {
+        check_clock_skew_of_rejection(status, headers);
+        let sub_status = headers
+            .get(HEADER_SUB_STATUS)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u32>().ok());
+        Err(AzureError::UnexpectedHTTPResult(
+            UnexpectedHTTPResult::new(expected_status_code, status, str::from_utf8(body)?).with_sub_status(sub_status),
+        ))
+    }
+}
+
+// A resource uri that cannot be built is only reported when the request
+// is executed, so the request builders can keep returning Self.
+fn split_request(request: Result<RequestBuilder, AzureError>) -> (RequestBuilder, Option<AzureError>) {
+    match request {
+        Ok(request) => (request, None),
+        Err(error) => (hyper::Request::builder(), Some(error)),
+    }
+}
+
+fn check_uri(uri_error: Option<AzureError>) -> Result<(), AzureError> {
+    match uri_error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// A request as the `dry_run` methods of the builders return it: what
+/// `execute` would send, body included, but with the signature in the
+/// `Authorization` header redacted.
+pub type DryRunRequest = http::Request<Vec<u8>>;
+
+const REDACTED_SIGNATURE: &str = "sig%3D<redacted>";
+
+fn dry_run_request(request: hyper::Request<hyper::Body>) -> Result<DryRunRequest, AzureError> {
+    let (mut parts, body) = request.into_parts();
+    if let Some(authorization) = parts.headers.remove(header::AUTHORIZATION) {
+        // the type and version of the token are kept, they are no secret
+        let authorization = authorization.to_str()?;
+        let redacted = match authorization.find("sig%3D") {
+            Some(position) => format!("{}{}", &authorization[..position], REDACTED_SIGNATURE),
+            None => REDACTED_SIGNATURE.to_owned(),
+        };
+        parts.headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&redacted).map_err(http::Error::from)?);
+    }
+    let body = body.concat2().wait()?.to_vec();
+    Ok(http::Request::from_parts(parts, body))
+}
+
+// Replaces (or removes, with None) a boolean header so it is never sent twice.
+fn set_bool_header(request: &mut RequestBuilder, name: &'static str, value: Option<bool>) {
+    if let Some(headers) = request.headers_mut() {
+        match value {
+            Some(value) => {
+                headers.insert(name, HeaderValue::from_static(if value { "true" } else { "false" }));
+            }
+            None => {
+                headers.remove(name);
+            }
+        }
+    }
+}
+
+const QUERY_CONTENT_TYPE: &str = "application/query+json";
+
+// Marks the request as a query. Its content type replaces the one of the
+// writes, which Cosmos answers with 400 on a query. A query is a POST but
+// not a write, so it does not allow tentative writes either.
+pub(crate) fn set_query_headers(request: &mut RequestBuilder) {
+    if let Some(headers) = request.headers_mut() {
+        headers.insert(HEADER_DOCUMENTDB_ISQUERY, HeaderValue::from_static("true"));
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(QUERY_CONTENT_TYPE));
+        headers.remove(HEADER_ALLOW_TENTATIVE_WRITES);
+    }
+}
+
+/// The token of the next page of results, if any. The service sends an
+/// empty `x-ms-continuation` on some last pages: it ends the pagination
+/// just like an absent one, otherwise the first page would be fetched
+/// again and again.
+pub fn next_continuation(headers: &HeaderMap) -> Option<ContinuationToken> {
+    headers
+        .get(HEADER_CONTINUATION)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| ContinuationToken::new_unchecked(v.to_owned()))
+}
+
+// Like extract_status_headers_and_body, enforcing the charge ceiling of
+// the request if it has one. The ceiling is only checked on a successful
+// response: a failed request reports its own status, not the budget.
+#[inline]
+fn extract_budgeted_status_headers_and_body(
+    resp: BudgetedResponseFuture,
+) -> impl Future<Item = (StatusCode, HeaderMap, hyper::Chunk), Error = AzureError> {
+    let max_request_charge = resp.max_request_charge();
+    extract_status_headers_and_body(resp).and_then(move |(status, headers, body)| {
+        if status.is_success() {
+            check_request_charge(max_request_charge, &headers)?;
+        }
+        Ok((status, headers, body))
+    })
+}
+
+#[inline]
+fn check_status_extract_headers_and_body(
+    resp: BudgetedResponseFuture,
+    expected_status_code: StatusCode,
+) -> impl Future<Item = (HeaderMap, hyper::Chunk), Error = AzureError> {
+    let max_request_charge = resp.max_request_charge();
+    extract_status_headers_and_body(resp).and_then(move |(status, headers, body)| {
+        check_status(status, &headers, &body, expected_status_code)?;
+        check_request_charge(max_request_charge, &headers)?;
+        Ok((headers, body))
+    })
+}
+
+#[inline]
+pub(crate) fn check_status_extract_body(
+    resp: BudgetedResponseFuture,
+    expected_status_code: StatusCode,
+) -> impl Future<Item = String, Error = AzureError> {
+    check_status_extract_headers_and_body(resp, expected_status_code).and_then(|(_, body)| Ok(str::from_utf8(&body)?.to_owned()))
+}
+
+type PageFuture<T> = Box<dyn Future<Item = IncompleteVector<T>, Error = AzureError>>;
+
+/// The future of a request type erased, to be stored in a struct or
+/// returned from a trait method. Every request has an `execute_boxed`
+/// returning one; `execute` remains the zero-cost alternative.
+pub type BoxedFuture<T> = Box<dyn Future<Item = T, Error = AzureError> + Send>;
+
+// The items of every page of `page`, see `continuation_pages`.
+pub(crate) fn follow_continuation<T, F>(page: F) -> impl Future<Item = Vec<T>, Error = AzureError>
+where
+    T: 'static,
+    F: Fn(Option<ContinuationToken>) -> PageFuture<T> + 'static,
+{
+    continuation_pages(move |continuation| {
+        Box::new(page(continuation).map(|page| {
+            let continuation = page.token().map(|token| ContinuationToken::new_unchecked(token.to_owned()));
+            (page.into_vec(), continuation)
+        }))
+    })
+    .concat2()
+}
+
+// Asks `page` for the first page and then for the following ones, passing
+// the continuation token returned with the last page, until a page comes
+// without one.
+pub(crate) fn continuation_pages<P, F>(page: F) -> impl Stream<Item = P, Error = AzureError>
+where
+    F: Fn(Option<ContinuationToken>) -> Box<dyn Future<Item = (P, Option<ContinuationToken>), Error = AzureError>>,
+{
+    // `None` once the last page is read
+    stream::unfold(Some(None), move |continuation: Option<Option<ContinuationToken>>| {
+        continuation.map(|continuation| page(continuation).map(|(page, continuation)| (page, continuation.map(Some))))
+    })
+}
+
+mod collection_requests;
+mod conflict_requests;
+mod database_requests;
 mod document_requests;
+mod page_documents;
 mod sproc_requests;
 
+pub use self::collection_requests::*;
+pub use self::conflict_requests::*;
+pub use self::database_requests::*;
 pub use self::document_requests::*;
+pub use self::page_documents::PageDocuments;
 pub use self::sproc_requests::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure::core::client_builder::ClientBuilder;
+
+    pub(crate) fn hyper_client() -> HyperClient {
+        BudgetedHyperClient::new(ClientBuilder::new().build_hyper_client().unwrap())
+    }
+
+    #[test]
+    fn sub_status_is_captured() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_SUB_STATUS, HeaderValue::from_static("1002"));
+
+        let error = check_status(StatusCode::GONE, &headers, b"", StatusCode::OK).unwrap_err();
+        match error {
+            AzureError::UnexpectedHTTPResult(ref result) => assert_eq!(result.sub_status(), Some(1002)),
+            ref e => panic!("unexpected error {:?}", e),
+        }
+        assert!(error.is_retryable());
+        assert!(!check_status(StatusCode::GONE, &HeaderMap::new(), b"", StatusCode::OK)
+            .unwrap_err()
+            .is_retryable());
+    }
+
+    #[test]
+    fn continuation_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(next_continuation(&headers), None);
+
+        headers.insert(HEADER_CONTINUATION, HeaderValue::from_static(""));
+        assert_eq!(next_continuation(&headers), None);
+        headers.insert(HEADER_CONTINUATION, HeaderValue::from_static("  "));
+        assert_eq!(next_continuation(&headers), None);
+
+        headers.insert(HEADER_CONTINUATION, HeaderValue::from_static("+RID:Sl8fALN4sw4CAAAAAAAAgA==#RT:1"));
+        assert_eq!(
+            next_continuation(&headers).as_ref().map(ContinuationToken::as_str),
+            Some("+RID:Sl8fALN4sw4CAAAAAAAAgA==#RT:1")
+        );
+    }
+
+    #[test]
+    fn throttled_error_carries_retry_after() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_RETRY_AFTER_MS, HeaderValue::from_static("1500"));
+
+        match check_status(StatusCode::TOO_MANY_REQUESTS, &headers, b"", StatusCode::OK) {
+            Err(AzureError::ThrottledError { retry_after }) => assert_eq!(retry_after, Duration::from_millis(1500)),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        assert!(check_status(StatusCode::OK, &headers, b"", StatusCode::OK).is_ok());
+        match check_status(StatusCode::CONFLICT, &headers, b"conflict", StatusCode::OK) {
+            Err(AzureError::UnexpectedHTTPResult(r)) => assert_eq!(r.status_code(), StatusCode::CONFLICT),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn precondition_failed() {
+        match check_status(StatusCode::PRECONDITION_FAILED, &HeaderMap::new(), b"etag mismatch", StatusCode::OK) {
+            Err(AzureError::PreconditionFailed(body)) => assert_eq!(body, "etag mismatch"),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn request_too_large() {
+        match check_status(StatusCode::PAYLOAD_TOO_LARGE, &HeaderMap::new(), b"", StatusCode::CREATED) {
+            Err(AzureError::RequestTooLarge { limit_bytes }) => assert_eq!(limit_bytes, 2 * 1024 * 1024),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn boxed_futures_in_a_vec() {
+        use azure::cosmos::client::tests::test_client;
+
+        let (_, client) = test_client();
+
+        // the invalid ids make the requests fail before reaching the network
+        let futures: Vec<BoxedFuture<()>> = vec![
+            client.delete_document("db", "coll", "a/b").execute_boxed(),
+            client.delete_conflict("db", "coll", "c#d").execute_boxed(),
+        ];
+        assert_eq!(futures.len(), 2);
+
+        for future in futures {
+            match future.wait() {
+                Err(AzureError::InputParametersError(_)) => {}
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+}
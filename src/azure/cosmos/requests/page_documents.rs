@@ -0,0 +1,167 @@
+use super::*;
+use bytes::Bytes;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::value::RawValue;
+use std::fmt;
+use std::vec;
+
+/// The documents of a page of query results, parsed one at a time out of
+/// the raw body of the page. The page is never turned into a vector of
+/// documents: a first pass only records where each one is, so that it can
+/// be parsed, processed then dropped before the next.
+#[derive(Debug)]
+pub struct PageDocuments {
+    body: Bytes,
+    ranges: vec::IntoIter<(usize, usize)>,
+    // the error that ended the first pass, returned after the documents
+    // found before it
+    error: Option<AzureError>,
+}
+
+impl PageDocuments {
+    pub fn new(body: Bytes) -> PageDocuments {
+        let mut ranges = Vec::new();
+        let error = document_ranges(&body, &mut ranges).err();
+        PageDocuments {
+            body,
+            ranges: ranges.into_iter(),
+            error,
+        }
+    }
+
+    /// The json of the next document, sharing the memory of the page.
+    pub fn next_raw(&mut self) -> Option<Result<Bytes, AzureError>> {
+        match self.ranges.next() {
+            Some((start, end)) => Some(Ok(self.body.slice(start, end))),
+            None => self.error.take().map(Err),
+        }
+    }
+}
+
+impl Iterator for PageDocuments {
+    type Item = Result<serde_json::Value, AzureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_raw()
+            .map(|document| document.and_then(|document| Ok(serde_json::from_slice(&document)?)))
+    }
+}
+
+fn document_ranges(body: &[u8], ranges: &mut Vec<(usize, usize)>) -> Result<(), AzureError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    PageSeed {
+        start: body.as_ptr() as usize,
+        ranges,
+    }
+    .deserialize(&mut deserializer)?;
+    Ok(deserializer.end()?)
+}
+
+// Skips the fields of the page but its `Documents` array.
+struct PageSeed<'a> {
+    // the address of the body, which the documents borrow from
+    start: usize,
+    ranges: &'a mut Vec<(usize, usize)>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for PageSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for PageSeed<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a page of query results")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(name) = map.next_key::<String>()? {
+            if name == "Documents" {
+                map.next_value_seed(DocumentsSeed {
+                    start: self.start,
+                    ranges: &mut *self.ranges,
+                })?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Reads the documents as raw json to record where each starts and ends.
+struct DocumentsSeed<'a> {
+    start: usize,
+    ranges: &'a mut Vec<(usize, usize)>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for DocumentsSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for DocumentsSeed<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of documents")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(document) = seq.next_element::<&RawValue>()? {
+            let start = document.get().as_ptr() as usize - self.start;
+            self.ranges.push((start, start + document.get().len()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_one_by_one() {
+        let body = br#"{"_rid": "XP0mAJ3H-AA=", "_attachments": {"nested": ["]", "}"]},
+            "Documents": [
+                {"id": "a", "text": "a \"quoted\" ] }", "values": [1, [2, 3]], "_ts": 1532866523},
+                {"id": "b", "empty": {}, "_ts": 1532866524} ,
+                "c", 4, null
+            ],
+            "_count": 5}"#;
+
+        let mut documents = PageDocuments::new(Bytes::from(&body[..]));
+        assert_eq!(
+            documents.next_raw().unwrap().unwrap(),
+            &br#"{"id": "a", "text": "a \"quoted\" ] }", "values": [1, [2, 3]], "_ts": 1532866523}"#[..]
+        );
+
+        let b = documents.next().unwrap().unwrap();
+        assert_eq!(b["id"], "b");
+        assert!(b["empty"].as_object().unwrap().is_empty());
+        let mut count = 0;
+        for document in documents {
+            document.unwrap();
+            count += 1;
+        }
+        assert_eq!(count, 3);
+
+        let empty = br#"{"_rid": "XP0mAJ3H-AA=", "Documents": [ ], "_count": 0}"#;
+        assert_eq!(PageDocuments::new(Bytes::from(&empty[..])).count(), 0);
+
+        // an error ends the page
+        let truncated = br#"{"_rid": "XP0mAJ3H-AA=", "Documents": [{"id": "a"}, {"id": "#;
+        let mut documents = PageDocuments::new(Bytes::from(&truncated[..]));
+        assert!(documents.next().unwrap().is_ok());
+        assert!(documents.next().unwrap().is_err());
+        assert!(documents.next().is_none());
+    }
+}
@@ -3,6 +3,7 @@ use super::*;
 pub struct ExecuteStoredProcedureRequest {
     hyper_client: HyperClient,
     request: RequestBuilder,
+    uri_error: Option<AzureError>,
     payload: Result<String, serde_json::Error>,
 }
 
@@ -12,15 +13,19 @@ impl DocumentRequestExt for ExecuteStoredProcedureRequest {
     }
 }
 
+impl WriteRequestExt for ExecuteStoredProcedureRequest {}
+
 impl ExecuteStoredProcedureRequest {
     pub(crate) fn new(
         hyper_client: HyperClient,
-        request: RequestBuilder,
+        request: Result<RequestBuilder, AzureError>,
         payload: Result<String, serde_json::Error>,
     ) -> ExecuteStoredProcedureRequest {
+        let (request, uri_error) = split_request(request);
         ExecuteStoredProcedureRequest {
             hyper_client,
             request,
+            uri_error,
             payload,
         }
     }
@@ -29,19 +34,304 @@ impl ExecuteStoredProcedureRequest {
         trace!("execute_stored_procedure called(request == {:?}", self.request);
         let hc = self.hyper_client;
         let mut req = self.request;
-        future::result(self.payload)
-            .from_err()
+        future::result(check_uri(self.uri_error).and(self.payload.map_err(AzureError::from)))
             .and_then(move |payload| future::result(req.body(payload.into())).from_err())
             .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::OK))
             .and_then(move |(headers, v_body)| Self::extract_result(&headers, &v_body))
     }
 
+    execute_boxed!(<R> ExecuteStoredProcedureResponse<R>);
+
     fn extract_result<R: DeserializeOwned>(headers: &HeaderMap, v_body: &[u8]) -> Result<ExecuteStoredProcedureResponse<R>, AzureError> {
         let additional_headers = DocumentAdditionalHeaders::derive_from(headers);
         let result = serde_json::from_slice(v_body)?;
         Ok(ExecuteStoredProcedureResponse {
             result,
             additional_headers,
+            headers: headers.clone(),
         })
     }
 }
+
+/// Creates documents of a single partition in one transaction through the
+/// stored procedure registered by `Client::register_bulk_create_sproc`.
+pub struct BulkCreateRequest {
+    inner: ExecuteStoredProcedureRequest,
+}
+
+impl DocumentRequestExt for BulkCreateRequest {
+    fn request(&mut self) -> &mut RequestBuilder {
+        self.inner.request()
+    }
+}
+
+impl BulkCreateRequest {
+    pub(crate) fn new(inner: ExecuteStoredProcedureRequest) -> BulkCreateRequest {
+        BulkCreateRequest { inner }
+    }
+
+    /// Returns the number of documents created, that is all of them: the
+    /// stored procedure fails, rolling back, if any of them cannot be created.
+    pub fn execute(self) -> impl Future<Item = u64, Error = AzureError> {
+        self.inner.execute::<u64>().map(|response| response.result)
+    }
+
+    execute_boxed!(u64);
+}
+
+pub struct ListStoredProceduresRequest {
+    hyper_client: HyperClient,
+    request: RequestBuilder,
+    uri_error: Option<AzureError>,
+}
+
+impl ListStoredProceduresRequest {
+    pub(crate) fn new(hyper_client: HyperClient, request: Result<RequestBuilder, AzureError>) -> ListStoredProceduresRequest {
+        let (request, uri_error) = split_request(request);
+        ListStoredProceduresRequest {
+            hyper_client,
+            request,
+            uri_error,
+        }
+    }
+
+    request_option!(max_item_count, u64, HEADER_MAX_ITEM_COUNT);
+    request_bytes_option!(continuation_token, ContinuationToken, HEADER_CONTINUATION);
+    request_option!(consistency_level, enum ConsistencyLevel, HEADER_CONSISTENCY_LEVEL);
+    request_bytes_option!(session_token, String, HEADER_SESSION_TOKEN);
+
+    /// Returns a page of stored procedures. The token of the returned
+    /// vector, if any, is the continuation token of the next page.
+    pub fn execute(mut self) -> impl Future<Item = IncompleteVector<StoredProcedure>, Error = AzureError> {
+        trace!("list_stored_procedures called(request == {:?}", self.request);
+        future::result(check_uri(self.uri_error.take()).and_then(|_| Ok(self.request.body(hyper::Body::empty())?)))
+            .and_then(move |r| check_status_extract_headers_and_body(self.hyper_client.request(r), StatusCode::OK))
+            .and_then(|(headers, whole_body)| Self::extract_result(&whole_body, &headers))
+    }
+
+    execute_boxed!(IncompleteVector<StoredProcedure>);
+
+    fn extract_result(body: &[u8], headers: &HeaderMap) -> Result<IncompleteVector<StoredProcedure>, AzureError> {
+        let response = serde_json::from_slice::<ListStoredProceduresResponse>(body)?;
+        Ok(IncompleteVector::new(
+            next_continuation(headers).map(String::from),
+            response.stored_procedures,
+        ))
+    }
+}
+
+pub struct ReplaceStoredProcedureRequest {
+    hyper_client: HyperClient,
+    request: RequestBuilder,
+    uri_error: Option<AzureError>,
+    payload: Result<String, AzureError>,
+}
+
+impl ReplaceStoredProcedureRequest {
+    pub(crate) fn new(
+        hyper_client: HyperClient,
+        request: Result<RequestBuilder, AzureError>,
+        payload: Result<String, AzureError>,
+    ) -> ReplaceStoredProcedureRequest {
+        let (request, uri_error) = split_request(request);
+        ReplaceStoredProcedureRequest {
+            hyper_client,
+            request,
+            uri_error,
+            payload,
+        }
+    }
+
+    // Pass the `etag` of the stored procedure read before so a deployment
+    // does not overwrite a newer script. A mismatch fails with
+    // AzureError::PreconditionFailed.
+    request_bytes_option!(if_match, String, header::IF_MATCH);
+
+    pub fn execute(self) -> impl Future<Item = StoredProcedure, Error = AzureError> {
+        trace!("replace_stored_procedure called(request == {:?}", self.request);
+        let hc = self.hyper_client;
+        let mut req = self.request;
+        future::result(check_uri(self.uri_error).and(self.payload))
+            .and_then(move |payload| Ok(req.body(payload.into())?))
+            .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::OK))
+            .and_then(move |(_, body)| Ok(serde_json::from_slice::<StoredProcedure>(&body)?))
+    }
+
+    execute_boxed!(StoredProcedure);
+}
+
+pub struct CreateStoredProcedureRequest {
+    hyper_client: HyperClient,
+    request: RequestBuilder,
+    uri_error: Option<AzureError>,
+    payload: Result<String, AzureError>,
+}
+
+impl CreateStoredProcedureRequest {
+    pub(crate) fn new(
+        hyper_client: HyperClient,
+        request: Result<RequestBuilder, AzureError>,
+        payload: Result<String, AzureError>,
+    ) -> CreateStoredProcedureRequest {
+        let (request, uri_error) = split_request(request);
+        CreateStoredProcedureRequest {
+            hyper_client,
+            request,
+            uri_error,
+            payload,
+        }
+    }
+
+    pub fn execute(self) -> impl Future<Item = StoredProcedure, Error = AzureError> {
+        trace!("create_stored_procedure called(request == {:?}", self.request);
+        let hc = self.hyper_client;
+        let mut req = self.request;
+        future::result(check_uri(self.uri_error).and(self.payload))
+            .and_then(move |payload| Ok(req.body(payload.into())?))
+            .and_then(move |r| check_status_extract_headers_and_body(hc.request(r), StatusCode::CREATED))
+            .and_then(move |(_, body)| Ok(serde_json::from_slice::<StoredProcedure>(&body)?))
+    }
+
+    execute_boxed!(StoredProcedure);
+}
+
+pub(crate) fn stored_procedure_payload(id: &str, body: &str) -> Result<String, AzureError> {
+    #[derive(Serialize)]
+    struct StoredProcedurePayload<'a> {
+        id: &'a str,
+        body: &'a str,
+    }
+
+    if body.trim().is_empty() {
+        return Err(AzureError::InputParametersError(format!(
+            "the body of the stored procedure {:?} cannot be empty",
+            id
+        )));
+    }
+    Ok(serde_json::to_string(&StoredProcedurePayload { id, body })?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure::cosmos::client::tests::test_client;
+    use azure::cosmos::stored_procedure::BULK_CREATE_SPROC_ID;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn sproc(id: &str) -> StoredProcedure {
+        StoredProcedure {
+            id: id.to_owned(),
+            body: "function () { getContext().getResponse().setBody(1); }".to_owned(),
+            rid: "Sl8fALN4sw4CAAAAAAAAgA==".to_owned(),
+            ts: 1_459_194_239,
+            _self: format!("dbs/Sl8fAA==/colls/Sl8fALN4sw4=/sprocs/{}/", id),
+            etag: "\"00003200-0000-0000-0000-56f9e27f0000\"".to_owned(),
+        }
+    }
+
+    #[test]
+    fn parse_sproc_listing() {
+        let body = br#"{
+            "_rid": "Sl8fALN4sw4=",
+            "StoredProcedures": [{
+                "body": "function () { var context = getContext(); }",
+                "id": "sproc_1",
+                "_rid": "Sl8fALN4sw4CAAAAAAAAgA==",
+                "_ts": 1459194239,
+                "_self": "dbs/Sl8fAA==/colls/Sl8fALN4sw4=/sprocs/Sl8fALN4sw4CAAAAAAAAgA==/",
+                "_etag": "\"00003200-0000-0000-0000-56f9e27f0000\""
+            }],
+            "_count": 1
+        }"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_CONTINUATION, HeaderValue::from_static("+RID:Sl8fALN4sw4CAAAAAAAAgA==#RT:1"));
+
+        let page = ListStoredProceduresRequest::extract_result(body, &headers).unwrap();
+        assert_eq!(page.token(), Some("+RID:Sl8fALN4sw4CAAAAAAAAgA==#RT:1"));
+        assert_eq!(page[0].id, "sproc_1");
+        assert_eq!(page[0].body, "function () { var context = getContext(); }");
+        assert_eq!(page[0].etag, "\"00003200-0000-0000-0000-56f9e27f0000\"");
+
+        let page = ListStoredProceduresRequest::extract_result(body, &HeaderMap::new()).unwrap();
+        assert!(page.is_complete());
+    }
+
+    #[test]
+    fn replace_body() {
+        let (_, client) = test_client();
+        let script = "function () { getContext().getResponse().setBody(\"hello\"); }";
+
+        let request = client
+            .replace_stored_procedure("db", "coll", "hello", script)
+            .if_match("\"00003200-0000-0000-0000-56f9e27f0000\"");
+        assert_eq!(
+            request.payload.as_ref().unwrap(),
+            r#"{"id":"hello","body":"function () { getContext().getResponse().setBody(\"hello\"); }"}"#
+        );
+
+        let mut request = request.request;
+        let request = request.body(()).unwrap();
+        assert_eq!(request.method(), hyper::Method::PUT);
+        assert_eq!(request.uri().path(), "/dbs/db/colls/coll/sprocs/hello");
+        assert_eq!(request.headers()[header::IF_MATCH], "\"00003200-0000-0000-0000-56f9e27f0000\"");
+
+        match client.replace_stored_procedure("db", "coll", "hello", " \n").execute().wait() {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn bulk_create() {
+        #[derive(Serialize)]
+        struct Item<'a> {
+            id: &'a str,
+            pk: &'a str,
+        }
+
+        let (_, client) = test_client();
+        let items = [Item { id: "1", pk: "a" }, Item { id: "2", pk: "a" }];
+
+        let request = client.bulk_create_via_sproc("db", "coll", &items, "a");
+        assert_eq!(
+            request.inner.payload.as_ref().unwrap(),
+            r#"[[{"id":"1","pk":"a"},{"id":"2","pk":"a"}]]"#
+        );
+
+        let mut request = request.inner.request;
+        let request = request.body(()).unwrap();
+        assert_eq!(request.method(), hyper::Method::POST);
+        assert_eq!(request.uri().path(), format!("/dbs/db/colls/coll/sprocs/{}", BULK_CREATE_SPROC_ID));
+        assert_eq!(request.headers()[HEADER_DOCUMENTDB_PARTITIONKEY], "[\"a\"]");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_REQUEST_CHARGE, HeaderValue::from_static("12.38"));
+        let response = ExecuteStoredProcedureRequest::extract_result::<u64>(&headers, b"2").unwrap();
+        assert_eq!(response.result, 2);
+    }
+
+    #[test]
+    fn one_continuation_hop() {
+        let tokens = Rc::new(RefCell::new(Vec::new()));
+
+        let page = {
+            let tokens = tokens.clone();
+            move |continuation: Option<ContinuationToken>| -> PageFuture<StoredProcedure> {
+                tokens.borrow_mut().push(continuation.clone());
+                let page = match continuation {
+                    None => IncompleteVector::new(Some("page2".to_owned()), vec![sproc("sproc_1"), sproc("sproc_2")]),
+                    Some(_) => IncompleteVector::new(None, vec![sproc("sproc_3")]),
+                };
+                Box::new(future::ok(page))
+            }
+        };
+
+        let sprocs = follow_continuation(page).wait().unwrap();
+        assert_eq!(
+            sprocs.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+            vec!["sproc_1", "sproc_2", "sproc_3"]
+        );
+        assert_eq!(*tokens.borrow(), vec![None, Some(ContinuationToken::new("page2").unwrap())]);
+    }
+}
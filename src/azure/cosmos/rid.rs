@@ -0,0 +1,117 @@
+use azure::core::enumerations::ParsingError;
+use base64;
+use std::fmt;
+use std::str::FromStr;
+
+// The kind of a resource below a collection is in the high nibble of the
+// last byte of its id.
+const DOCUMENT_KIND: u8 = 0x0;
+// Below a database the ids of collections have their high bit set, those of
+// users do not.
+const COLLECTION_FLAG: u8 = 0x80;
+
+/// A Cosmos resource id, the `_rid` of a resource, decoded into the ids of
+/// the resources it belongs to. The `_rid` of a document holds the ids of
+/// its database and collection, so it tells where the document lives
+/// without reading anything else.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rid {
+    bytes: Vec<u8>,
+}
+
+impl Rid {
+    pub fn database(&self) -> u32 {
+        read_u32(&self.bytes[0..4])
+    }
+
+    /// The collection id, `None` for a database or a resource that does
+    /// not belong to a collection, such as a user.
+    pub fn collection(&self) -> Option<u32> {
+        if self.is_in_collection() {
+            Some(read_u32(&self.bytes[4..8]))
+        } else {
+            None
+        }
+    }
+
+    /// The document id, for a document or an attachment.
+    pub fn document(&self) -> Option<u64> {
+        if self.is_in_collection() && self.bytes.len() >= 16 && self.bytes[15] >> 4 == DOCUMENT_KIND {
+            Some(read_u32(&self.bytes[8..12]) as u64 | (read_u32(&self.bytes[12..16]) as u64) << 32)
+        } else {
+            None
+        }
+    }
+
+    /// The `_rid` of the database, as found in `Database::rid`.
+    pub fn database_rid(&self) -> String {
+        encode(&self.bytes[0..4])
+    }
+
+    /// The `_rid` of the collection, as found in `Collection::rid`.
+    pub fn collection_rid(&self) -> Option<String> {
+        self.collection().map(|_| encode(&self.bytes[0..8]))
+    }
+
+    fn is_in_collection(&self) -> bool {
+        self.bytes.len() >= 8 && self.bytes[4] & COLLECTION_FLAG == COLLECTION_FLAG
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    bytes.iter().rev().fold(0, |value, byte| value << 8 | u32::from(*byte))
+}
+
+// `_rid`s are base64 where `/` is replaced with `-` so they fit in a path.
+fn encode(bytes: &[u8]) -> String {
+    base64::encode(bytes).replace('/', "-")
+}
+
+impl FromStr for Rid {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Rid, ParsingError> {
+        let bytes = base64::decode(&s.replace('-', "/")).map_err(|_| ParsingError::MalformedResourceId(s.to_owned()))?;
+        // database, collection or user, resource of a collection, attachment
+        match bytes.len() {
+            4 | 8 | 16 | 20 => Ok(Rid { bytes }),
+            _ => Err(ParsingError::MalformedResourceId(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Rid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", encode(&self.bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_document_rid() {
+        let rid = "Sl8fALN4sw4CAAAAAAAAAA==".parse::<Rid>().unwrap();
+        assert_eq!(rid.database(), 0x001f_5f4a);
+        assert_eq!(rid.collection(), Some(0x0eb3_78b3));
+        assert_eq!(rid.document(), Some(2));
+        assert_eq!(rid.database_rid(), "Sl8fAA==");
+        assert_eq!(rid.collection_rid().as_deref(), Some("Sl8fALN4sw4="));
+        assert_eq!(rid.to_string(), "Sl8fALN4sw4CAAAAAAAAAA==");
+
+        let collection = "XP0mAJ3H-AA=".parse::<Rid>().unwrap();
+        assert_eq!(collection.collection_rid().as_deref(), Some("XP0mAJ3H-AA="));
+        assert_eq!(collection.document(), None);
+        let database = "XP0mAA==".parse::<Rid>().unwrap();
+        assert_eq!(database.collection(), None);
+        assert_eq!(database.database_rid(), "XP0mAA==");
+
+        for malformed in &["", "XP0m", "not base64!", "XP0mAJ3H"] {
+            match malformed.parse::<Rid>() {
+                Err(ParsingError::MalformedResourceId(s)) => assert_eq!(&s, malformed),
+                r => panic!("unexpected result {:?} for {:?}", r, malformed),
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use azure::core::client_builder::HyperClient;
+use azure::core::errors::AzureError;
+use azure::cosmos::client::headers::HEADER_REQUEST_CHARGE;
+use futures::{Async, Future, Poll};
+use hyper::{self, header::HeaderMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// Charges are stored as thousandths of RU so they can be summed atomically.
+const FIXED_POINT_SCALE: f64 = 1000.0;
+
+/// Sums the request charges of every operation performed through the
+/// clients sharing it. Clones share the same counter so a handle can be
+/// kept to read the total.
+#[derive(Debug, Clone, Default)]
+pub struct RuBudget {
+    charge: Arc<AtomicU64>,
+}
+
+impl RuBudget {
+    pub fn new() -> RuBudget {
+        RuBudget::default()
+    }
+
+    pub fn add(&self, charge: f64) {
+        if charge > 0.0 {
+            let charge = (charge * FIXED_POINT_SCALE).round() as u64;
+            self.charge.fetch_add(charge, Ordering::Relaxed);
+        }
+    }
+
+    pub fn total(&self) -> f64 {
+        self.charge.load(Ordering::Relaxed) as f64 / FIXED_POINT_SCALE
+    }
+
+    pub fn reset(&self) {
+        self.charge.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_from_headers(&self, headers: &HeaderMap) {
+        if let Some(charge) = charge_from_headers(headers) {
+            trace!("adding {} RU to the budget", charge);
+            self.add(charge);
+        }
+    }
+}
+
+fn charge_from_headers(headers: &HeaderMap) -> Option<f64> {
+    headers
+        .get(HEADER_REQUEST_CHARGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<f64>().ok())
+}
+
+/// Ceiling of the charge of a single request, carried as an extension of
+/// the request. See `DocumentRequestExt::max_request_charge`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct MaxRequestCharge(pub f64);
+
+// The charge is only known once Cosmos answered so the operation has
+// already been performed (and paid) when the ceiling trips.
+pub(crate) fn check_request_charge(max_request_charge: Option<MaxRequestCharge>, headers: &HeaderMap) -> Result<(), AzureError> {
+    match (max_request_charge, charge_from_headers(headers)) {
+        (Some(MaxRequestCharge(limit)), Some(charge)) if charge > limit => {
+            warn!("request charge of {} RU over the {} RU limit", charge, limit);
+            Err(AzureError::BudgetExceeded { charge, limit })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The hyper client used by the Cosmos requests. It adds the charge of
+/// every response to the RU budget, if any.
+#[derive(Debug, Clone)]
+pub(crate) struct BudgetedHyperClient {
+    hyper_client: Arc<HyperClient>,
+    ru_budget: Option<RuBudget>,
+}
+
+impl BudgetedHyperClient {
+    pub(crate) fn new(hyper_client: HyperClient) -> BudgetedHyperClient {
+        BudgetedHyperClient {
+            hyper_client: Arc::new(hyper_client),
+            ru_budget: None,
+        }
+    }
+
+    pub(crate) fn set_ru_budget(&mut self, ru_budget: Option<RuBudget>) {
+        self.ru_budget = ru_budget;
+    }
+
+    pub(crate) fn ru_budget(&self) -> Option<&RuBudget> {
+        self.ru_budget.as_ref()
+    }
+
+    pub(crate) fn request(&self, request: hyper::Request<hyper::Body>) -> BudgetedResponseFuture {
+        let max_request_charge = request.extensions().get::<MaxRequestCharge>().cloned();
+        BudgetedResponseFuture {
+            inner: self.hyper_client.request(request),
+            ru_budget: self.ru_budget.clone(),
+            max_request_charge,
+        }
+    }
+}
+
+pub(crate) struct BudgetedResponseFuture {
+    inner: hyper::client::ResponseFuture,
+    ru_budget: Option<RuBudget>,
+    max_request_charge: Option<MaxRequestCharge>,
+}
+
+impl BudgetedResponseFuture {
+    pub(crate) fn max_request_charge(&self) -> Option<MaxRequestCharge> {
+        self.max_request_charge
+    }
+}
+
+impl Future for BudgetedResponseFuture {
+    type Item = hyper::Response<hyper::Body>;
+    type Error = hyper::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.inner.poll()? {
+            Async::Ready(response) => {
+                if let Some(ref ru_budget) = self.ru_budget {
+                    ru_budget.add_from_headers(response.headers());
+                }
+                Ok(Async::Ready(response))
+            }
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    #[test]
+    fn accumulate_charges() {
+        let budget = RuBudget::new();
+        let handle = budget.clone();
+
+        let mut first = HeaderMap::new();
+        first.insert(HEADER_REQUEST_CHARGE, HeaderValue::from_static("6.29"));
+        let mut second = HeaderMap::new();
+        second.insert(HEADER_REQUEST_CHARGE, HeaderValue::from_static("2.81"));
+
+        budget.add_from_headers(&first);
+        budget.add_from_headers(&second);
+        // responses without the charge are ignored
+        budget.add_from_headers(&HeaderMap::new());
+
+        assert!((handle.total() - 9.1).abs() < 0.0001);
+
+        handle.reset();
+        assert_eq!(budget.total(), 0.0);
+    }
+
+    #[test]
+    fn request_charge_ceiling() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_REQUEST_CHARGE, HeaderValue::from_static("125.38"));
+
+        match check_request_charge(Some(MaxRequestCharge(50.0)), &headers) {
+            Err(AzureError::BudgetExceeded { charge, limit }) => {
+                assert_eq!(charge, 125.38);
+                assert_eq!(limit, 50.0);
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        assert!(check_request_charge(Some(MaxRequestCharge(200.0)), &headers).is_ok());
+        assert!(check_request_charge(None, &headers).is_ok());
+        assert!(check_request_charge(Some(MaxRequestCharge(50.0)), &HeaderMap::new()).is_ok());
+    }
+}
@@ -0,0 +1,90 @@
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StoredProcedure {
+    pub id: String,
+    pub body: String,
+    #[serde(rename = "_rid")]
+    pub rid: String,
+    #[serde(rename = "_ts")]
+    pub ts: u64,
+    #[serde(rename = "_self")]
+    pub _self: String,
+    #[serde(rename = "_etag")]
+    pub etag: String,
+}
+
+impl_etag!(StoredProcedure, etag);
+
+/// Id under which `Client::register_bulk_create_sproc` stores the bulk
+/// create stored procedure.
+pub const BULK_CREATE_SPROC_ID: &str = "azure_sdk_bulk_create";
+
+// Creates every document of the array given as the only argument and
+// answers with their count. A stored procedure runs in a transaction so
+// throwing rolls back the documents created so far: either every document
+// is created or none is.
+pub(crate) const BULK_CREATE_SPROC_BODY: &str = r#"function bulkCreate(docs) {
+    var collection = getContext().getCollection();
+    var link = collection.getSelfLink();
+    var created = 0;
+
+    createNext();
+
+    function createNext() {
+        if (created >= docs.length) {
+            getContext().getResponse().setBody(created);
+            return;
+        }
+        var accepted = collection.createDocument(link, docs[created], function (err) {
+            if (err) throw err;
+            created++;
+            createNext();
+        });
+        if (!accepted) throw new Error("bulk create ran out of time after " + created + " documents");
+    }
+}"#;
+
+/// Id under which `Client::register_bulk_delete_sproc` stores the bulk
+/// delete stored procedure.
+pub const BULK_DELETE_SPROC_ID: &str = "azure_sdk_bulk_delete";
+
+// Deletes the documents returned by the query given as the only argument,
+// querying again after each page as the deleted documents are no longer
+// returned. When the procedure runs out of time it stops and keeps the
+// documents deleted so far, answering with `continuation` set so it is
+// called again.
+pub(crate) const BULK_DELETE_SPROC_BODY: &str = r#"function bulkDelete(query) {
+    var collection = getContext().getCollection();
+    var link = collection.getSelfLink();
+    var deleted = 0;
+
+    queryNext();
+
+    function queryNext() {
+        var accepted = collection.queryDocuments(link, query, {}, function (err, docs) {
+            if (err) throw err;
+            if (docs.length === 0) {
+                getContext().getResponse().setBody({ deleted: deleted, continuation: false });
+                return;
+            }
+            deleteNext(docs, 0);
+        });
+        if (!accepted) stop();
+    }
+
+    function deleteNext(docs, i) {
+        if (i >= docs.length) {
+            queryNext();
+            return;
+        }
+        var accepted = collection.deleteDocument(docs[i]._self, {}, function (err) {
+            if (err) throw err;
+            deleted++;
+            deleteNext(docs, i + 1);
+        });
+        if (!accepted) stop();
+    }
+
+    function stop() {
+        getContext().getResponse().setBody({ deleted: deleted, continuation: true });
+    }
+}"#;
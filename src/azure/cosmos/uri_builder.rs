@@ -0,0 +1,147 @@
+use azure::core::errors::AzureError;
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+
+// Characters Cosmos does not accept in a resource id.
+const ILLEGAL_ID_CHARS: &[char] = &['/', '\\', '?', '#'];
+
+pub(crate) fn validate_id(id: &str) -> Result<(), AzureError> {
+    if id.is_empty() {
+        Err(AzureError::InputParametersError("a resource id cannot be empty".to_owned()))
+    } else if id.contains(ILLEGAL_ID_CHARS) {
+        Err(AzureError::InputParametersError(format!(
+            "the id {:?} cannot contain any of '/', '\\', '?' or '#'",
+            id
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Uri of a Cosmos resource. The link is what gets signed, the path is the
+/// same link percent-encoded to be sent on the wire.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceUri {
+    link: String,
+    path: String,
+}
+
+impl ResourceUri {
+    pub fn link(&self) -> &str {
+        &self.link
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// Assembles resource uris such as `dbs/{db}/colls/{coll}/docs` from their
+/// ids, which can contain spaces and unicode characters. Ids Cosmos cannot
+/// address (empty or containing `/ \ ? #`) make `build` fail.
+#[derive(Debug, Clone, Default)]
+pub struct UriBuilder {
+    segments: Vec<String>,
+    invalid_id: Option<String>,
+}
+
+impl UriBuilder {
+    pub fn new() -> UriBuilder {
+        UriBuilder::default()
+    }
+
+    pub fn database(self, id: &str) -> UriBuilder {
+        self.resource("dbs", id)
+    }
+
+    pub fn collection(self, id: &str) -> UriBuilder {
+        self.resource("colls", id)
+    }
+
+    pub fn document(self, id: &str) -> UriBuilder {
+        self.resource("docs", id)
+    }
+
+    pub fn stored_procedure(self, id: &str) -> UriBuilder {
+        self.resource("sprocs", id)
+    }
+
+    pub fn conflict(self, id: &str) -> UriBuilder {
+        self.resource("conflicts", id)
+    }
+
+    /// Appends a resource type followed by the id of the resource.
+    pub fn resource(mut self, resource_type: &str, id: &str) -> UriBuilder {
+        if self.invalid_id.is_none() && validate_id(id).is_err() {
+            self.invalid_id = Some(id.to_owned());
+        }
+        self.segments.push(resource_type.to_owned());
+        self.segments.push(id.to_owned());
+        self
+    }
+
+    /// Appends a resource type alone, addressing the feed of the resources
+    /// of that type (for example `docs` to list or create documents).
+    pub fn feed(mut self, resource_type: &str) -> UriBuilder {
+        self.segments.push(resource_type.to_owned());
+        self
+    }
+
+    pub fn build(self) -> Result<ResourceUri, AzureError> {
+        if let Some(id) = self.invalid_id {
+            validate_id(&id)?;
+        }
+
+        let path = self
+            .segments
+            .iter()
+            .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT_ENCODE_SET).to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Ok(ResourceUri {
+            link: self.segments.join("/"),
+            path,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_ids() {
+        let uri = UriBuilder::new().database("db").collection("coll").feed("docs").build().unwrap();
+        assert_eq!(uri.link(), "dbs/db/colls/coll/docs");
+        assert_eq!(uri.path(), "dbs/db/colls/coll/docs");
+    }
+
+    #[test]
+    fn ids_with_spaces() {
+        let uri = UriBuilder::new()
+            .database("my db")
+            .collection("my coll")
+            .document("a b")
+            .build()
+            .unwrap();
+        assert_eq!(uri.link(), "dbs/my db/colls/my coll/docs/a b");
+        assert_eq!(uri.path(), "dbs/my%20db/colls/my%20coll/docs/a%20b");
+    }
+
+    #[test]
+    fn unicode_ids() {
+        let uri = UriBuilder::new().database("db").collection("città").build().unwrap();
+        assert_eq!(uri.link(), "dbs/db/colls/città");
+        assert_eq!(uri.path(), "dbs/db/colls/citt%C3%A0");
+    }
+
+    #[test]
+    fn slashes_are_rejected() {
+        for id in &["a/b", "a\\b", "a?b", "a#b", ""] {
+            match UriBuilder::new().database("db").collection(id).build() {
+                Err(AzureError::InputParametersError(_)) => {}
+                r => panic!("unexpected result {:?} for {:?}", r, id),
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 #[macro_use]
 pub mod core;
 pub mod cosmos;
+pub mod service_client;
 pub mod service_bus;
 pub mod storage;
@@ -1,25 +1,43 @@
 use futures::future::*;
 
+use azure::core::client_builder::{ClientBuilder, HyperClient};
 use azure::core::errors::AzureError;
 use azure::service_bus::event_hub::send_event;
-use hyper;
-
 use time::Duration;
 
 use ring::{digest::SHA256, hmac::SigningKey};
 
-type HttpClient = hyper::Client<::hyper_tls::HttpsConnector<hyper::client::HttpConnector>>;
+type HttpClient = HyperClient;
 
 pub struct Client {
     namespace: String,
     event_hub: String,
     policy_name: String,
+    host_suffix: String,
     signing_key: SigningKey,
     http_client: HttpClient,
 }
 
 impl Client {
     pub fn new<N, E, P, K>(namespace: N, event_hub: E, policy_name: P, key: K) -> Result<Client, AzureError>
+    where
+        N: Into<String>,
+        E: Into<String>,
+        P: Into<String>,
+        K: AsRef<str>,
+    {
+        Client::with_builder(namespace, event_hub, policy_name, key, &ClientBuilder::default())
+    }
+
+    /// Builds a client sending the events to the Service Bus hosts of the
+    /// cloud set on the builder.
+    pub fn with_builder<N, E, P, K>(
+        namespace: N,
+        event_hub: E,
+        policy_name: P,
+        key: K,
+        builder: &ClientBuilder,
+    ) -> Result<Client, AzureError>
     where
         N: Into<String>,
         E: Into<String>,
@@ -27,12 +45,13 @@ impl Client {
         K: AsRef<str>,
     {
         let signing_key = SigningKey::new(&SHA256, key.as_ref().as_bytes());
-        let http_client = hyper::Client::builder().build(::hyper_tls::HttpsConnector::new(4)?);
+        let http_client = builder.build_hyper_client()?;
 
         Ok(Client {
             namespace: namespace.into(),
             event_hub: event_hub.into(),
             policy_name: policy_name.into(),
+            host_suffix: builder.cloud().service_bus_suffix().to_owned(),
             signing_key,
             http_client,
         })
@@ -40,9 +59,10 @@ impl Client {
 
     pub fn send_event(&mut self, event_body: &str, duration: Duration) -> impl Future<Item = (), Error = AzureError> {
         {
+            let host = format!("{}.{}", self.namespace, self.host_suffix);
             send_event(
                 &self.http_client,
-                &self.namespace,
+                &host,
                 &self.event_hub,
                 &self.policy_name,
                 &self.signing_key,
@@ -70,4 +90,16 @@ mod test {
 
         assert_eq!(sig, "2UNXaoPpeJBAhh6qxmTqXyNzTpOflGO6IhxegeUQBcU=");
     }
+
+    #[test]
+    pub fn client_cloud() {
+        use azure::core::client_builder::{AzureCloud, ClientBuilder};
+
+        let c = Client::new("namespace", "event_hub", "policy", "key").unwrap();
+        assert_eq!(c.host_suffix, "servicebus.windows.net");
+
+        let builder = ClientBuilder::new().with_cloud(AzureCloud::China);
+        let c = Client::with_builder("namespace", "event_hub", "policy", "key", &builder).unwrap();
+        assert_eq!(c.host_suffix, "servicebus.chinacloudapi.cn");
+    }
 }
@@ -1,4 +1,5 @@
 use azure::core::{
+    client_builder::HyperClient,
     errors::{check_status_extract_body, AzureError},
     COMPLETE_ENCODE_SET,
 };
@@ -11,12 +12,12 @@ use time::Duration;
 mod client;
 pub use self::client::Client;
 
-type HttpClient = hyper::Client<::hyper_tls::HttpsConnector<hyper::client::HttpConnector>>;
+type HttpClient = HyperClient;
 
 #[inline]
 fn send_event_prepare<B: Into<String>>(
     http_client: &HttpClient,
-    namespace: &str,
+    host: &str,
     event_hub: &str,
     policy_name: &str,
     signing_key: &hmac::SigningKey,
@@ -24,7 +25,7 @@ fn send_event_prepare<B: Into<String>>(
     duration: Duration,
 ) -> Result<hyper::client::ResponseFuture, AzureError> {
     // prepare the url to call
-    let url = format!("https://{}.servicebus.windows.net/{}/messages", namespace, event_hub);
+    let url = format!("https://{}/{}/messages", host, event_hub);
     debug!("url == {:?}", url);
 
     // generate sas signature based on key name, key value, url and duration.
@@ -41,14 +42,14 @@ fn send_event_prepare<B: Into<String>>(
 
 fn send_event(
     http_client: &HttpClient,
-    namespace: &str,
+    host: &str,
     event_hub: &str,
     policy_name: &str,
     hmac: &hmac::SigningKey,
     event_body: &str,
     duration: Duration,
 ) -> impl Future<Item = (), Error = AzureError> {
-    let req = send_event_prepare(http_client, namespace, event_hub, policy_name, hmac, event_body, duration);
+    let req = send_event_prepare(http_client, host, event_hub, policy_name, hmac, event_body, duration);
 
     future::result(req)
         .from_err()
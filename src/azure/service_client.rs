@@ -0,0 +1,113 @@
+use azure::core::client_builder::{ClientBuilder, HyperClient};
+use azure::core::errors::AzureError;
+use azure::core::util::env_var;
+use azure::cosmos::{self, AuthorizationToken, TokenType};
+use azure::storage::client::Client as StorageClient;
+use azure::storage::table::TableService;
+
+/// Entry point to the Cosmos and storage services of a set of accounts.
+/// The sub-clients share a single connection pool, built once from the
+/// `ClientBuilder`.
+#[derive(Clone)]
+pub struct ServiceClient {
+    builder: ClientBuilder,
+    hyper_client: HyperClient,
+    storage: Option<StorageClient>,
+    cosmos: Option<cosmos::Client>,
+}
+
+impl ServiceClient {
+    /// Builds a client without credentials, see `with_storage_account` and
+    /// `with_cosmos_auth_token`.
+    pub fn new(builder: &ClientBuilder) -> Result<ServiceClient, AzureError> {
+        Ok(ServiceClient {
+            builder: builder.clone(),
+            hyper_client: builder.build_hyper_client()?,
+            storage: None,
+            cosmos: None,
+        })
+    }
+
+    /// Builds a client reading the credentials of the accounts from the
+    /// same variables as `storage::client::Client::from_env` and
+    /// `cosmos::Client::from_env`.
+    pub fn from_env() -> Result<ServiceClient, AzureError> {
+        let service_client = ServiceClient::new(&ClientBuilder::default())?;
+        let storage_account = env_var("STORAGE_ACCOUNT")?;
+        let storage_key = env_var("STORAGE_MASTER_KEY")?;
+        let cosmos_account = env_var("COSMOS_ACCOUNT")?;
+        let cosmos_master_key = env_var("COSMOS_MASTER_KEY")?;
+
+        Ok(service_client
+            .with_storage_account(&storage_account, &storage_key)
+            .with_cosmos_auth_token(AuthorizationToken::new(cosmos_account, TokenType::Master, &cosmos_master_key)?))
+    }
+
+    pub fn with_storage_account(mut self, account: &str, key: &str) -> ServiceClient {
+        self.storage = Some(StorageClient::with_hyper_client(
+            account,
+            key,
+            &self.builder,
+            self.hyper_client.clone(),
+        ));
+        self
+    }
+
+    pub fn with_cosmos_auth_token(mut self, auth_token: AuthorizationToken) -> ServiceClient {
+        self.cosmos = Some(cosmos::Client::with_hyper_client(
+            auth_token,
+            &self.builder,
+            self.hyper_client.clone(),
+        ));
+        self
+    }
+
+    pub fn cosmos(&self) -> Result<&cosmos::Client, AzureError> {
+        self.cosmos
+            .as_ref()
+            .ok_or_else(|| AzureError::InputParametersError("no Cosmos credentials were given".to_owned()))
+    }
+
+    /// The storage client, which implements the `Blob` and `Container`
+    /// traits.
+    pub fn blob(&self) -> Result<&StorageClient, AzureError> {
+        self.storage
+            .as_ref()
+            .ok_or_else(|| AzureError::InputParametersError("no storage account credentials were given".to_owned()))
+    }
+
+    pub fn table(&self) -> Result<TableService, AzureError> {
+        self.blob().map(|client| TableService::new(client.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use azure::core::client_builder::AzureCloud;
+
+    #[test]
+    fn sub_clients() {
+        let builder = ClientBuilder::new().with_cloud(AzureCloud::China);
+        let service_client = ServiceClient::new(&builder).unwrap();
+        match service_client.cosmos() {
+            Err(AzureError::InputParametersError(_)) => {}
+            _ => panic!("the Cosmos client should not be available"),
+        }
+        assert!(service_client.blob().is_err());
+        assert!(service_client.table().is_err());
+
+        let service_client = service_client
+            .with_storage_account("mindflavor", "ZHVtbXk=")
+            .with_cosmos_auth_token(AuthorizationToken::new("mindflavor".to_owned(), TokenType::Master, "ZHVtbXk=").unwrap());
+
+        assert_eq!(
+            service_client.cosmos().unwrap().endpoint(),
+            "https://mindflavor.documents.azure.cn/"
+        );
+        let blob = service_client.blob().unwrap();
+        assert_eq!(blob.account(), "mindflavor");
+        assert_eq!(blob.endpoint_suffix(), "core.chinacloudapi.cn");
+        service_client.table().unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+use azure::core::errors::AzureError;
+use azure::core::headers::{ACCOUNT_KIND, SKU_NAME};
+use azure::core::util::HeaderMapExt;
+use http::HeaderMap;
+
+/// Version of the API used by `Get Account Information`, the first one
+/// supporting it.
+pub(crate) const ACCOUNT_INFO_VERSION: &str = "2018-03-28";
+
+/// Replication and performance tier of a storage account. SKUs this
+/// version does not know of are kept as `Unknown`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkuName {
+    StandardLRS,
+    StandardGRS,
+    StandardRAGRS,
+    StandardZRS,
+    StandardGZRS,
+    StandardRAGZRS,
+    PremiumLRS,
+    PremiumZRS,
+    Unknown(String),
+}
+
+impl<'a> From<&'a str> for SkuName {
+    fn from(s: &'a str) -> SkuName {
+        match s {
+            "Standard_LRS" => SkuName::StandardLRS,
+            "Standard_GRS" => SkuName::StandardGRS,
+            "Standard_RAGRS" => SkuName::StandardRAGRS,
+            "Standard_ZRS" => SkuName::StandardZRS,
+            "Standard_GZRS" => SkuName::StandardGZRS,
+            "Standard_RAGZRS" => SkuName::StandardRAGZRS,
+            "Premium_LRS" => SkuName::PremiumLRS,
+            "Premium_ZRS" => SkuName::PremiumZRS,
+            _ => SkuName::Unknown(s.to_owned()),
+        }
+    }
+}
+
+/// Kind of a storage account, which decides the features available: for
+/// example access tiers need a `StorageV2` or `BlobStorage` account.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccountKind {
+    Storage,
+    StorageV2,
+    BlobStorage,
+    BlockBlobStorage,
+    FileStorage,
+    Unknown(String),
+}
+
+impl<'a> From<&'a str> for AccountKind {
+    fn from(s: &'a str) -> AccountKind {
+        match s {
+            "Storage" => AccountKind::Storage,
+            "StorageV2" => AccountKind::StorageV2,
+            "BlobStorage" => AccountKind::BlobStorage,
+            "BlockBlobStorage" => AccountKind::BlockBlobStorage,
+            "FileStorage" => AccountKind::FileStorage,
+            _ => AccountKind::Unknown(s.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountInfo {
+    pub sku_name: SkuName,
+    pub account_kind: AccountKind,
+}
+
+impl AccountInfo {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Result<AccountInfo, AzureError> {
+        let sku_name = headers
+            .get_as_str(SKU_NAME)
+            .ok_or_else(|| AzureError::HeaderNotFound(SKU_NAME.to_owned()))?;
+        let account_kind = headers
+            .get_as_str(ACCOUNT_KIND)
+            .ok_or_else(|| AzureError::HeaderNotFound(ACCOUNT_KIND.to_owned()))?;
+
+        Ok(AccountInfo {
+            sku_name: SkuName::from(sku_name),
+            account_kind: AccountKind::from(account_kind),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::header::HeaderValue;
+
+    #[test]
+    fn parse_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(SKU_NAME, HeaderValue::from_static("Standard_RAGRS"));
+        headers.insert(ACCOUNT_KIND, HeaderValue::from_static("StorageV2"));
+        assert_eq!(
+            AccountInfo::from_headers(&headers).unwrap(),
+            AccountInfo {
+                sku_name: SkuName::StandardRAGRS,
+                account_kind: AccountKind::StorageV2,
+            }
+        );
+
+        headers.insert(SKU_NAME, HeaderValue::from_static("Premium_GZRS"));
+        headers.insert(ACCOUNT_KIND, HeaderValue::from_static("DataLakeStorage"));
+        let account_info = AccountInfo::from_headers(&headers).unwrap();
+        assert_eq!(account_info.sku_name, SkuName::Unknown("Premium_GZRS".to_owned()));
+        assert_eq!(account_info.account_kind, AccountKind::Unknown("DataLakeStorage".to_owned()));
+
+        headers.remove(ACCOUNT_KIND);
+        match AccountInfo::from_headers(&headers) {
+            Err(AzureError::HeaderNotFound(ref header)) => assert_eq!(header, ACCOUNT_KIND),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use azure::core::enumerations;
+use azure::core::errors::{AzureError, TraversingError};
+use azure::core::parsing::FromStringOptional;
+use azure::core::util::{format_header_value, HeaderMapExt};
+use azure::storage::rest_client::{authorization, ServiceType, HEADER_DATE};
+use hyper::header::{self, HeaderName, HeaderValue};
+use hyper::{HeaderMap, Method, StatusCode};
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the API used by the blob batch operation, the first one
+/// supporting it.
+pub(crate) const BLOB_BATCH_VERSION: &str = "2018-11-09";
+
+/// The most operations the service accepts in a `BlobBatch`.
+pub const MAX_BATCH_SUB_REQUESTS: usize = 256;
+
+const ACCESS_TIER: &str = "x-ms-access-tier";
+const ERROR_CODE: &str = "x-ms-error-code";
+
+create_enum!(AccessTier, (Hot, "Hot"), (Cool, "Cool"), (Archive, "Archive"));
+
+#[derive(Debug, Clone, PartialEq)]
+enum SubRequest {
+    Delete {
+        container_name: String,
+        blob_name: String,
+    },
+    SetTier {
+        container_name: String,
+        blob_name: String,
+        tier: AccessTier,
+    },
+}
+
+impl SubRequest {
+    fn is_delete(&self) -> bool {
+        match *self {
+            SubRequest::Delete { .. } => true,
+            SubRequest::SetTier { .. } => false,
+        }
+    }
+}
+
+/// Blobs to delete, or to move to another access tier, in a single request
+/// sent with `Client::blob_batch`. A batch holds at most 256 operations, all
+/// of the same kind. Every operation succeeds or fails on its own, see
+/// `BlobBatchResponse::sub_responses`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BlobBatch {
+    sub_requests: Vec<SubRequest>,
+}
+
+impl BlobBatch {
+    pub fn new() -> BlobBatch {
+        BlobBatch::default()
+    }
+
+    pub fn delete<C: Into<String>, B: Into<String>>(mut self, container_name: C, blob_name: B) -> BlobBatch {
+        self.sub_requests.push(SubRequest::Delete {
+            container_name: container_name.into(),
+            blob_name: blob_name.into(),
+        });
+        self
+    }
+
+    pub fn set_tier<C: Into<String>, B: Into<String>>(mut self, container_name: C, blob_name: B, tier: AccessTier) -> BlobBatch {
+        self.sub_requests.push(SubRequest::SetTier {
+            container_name: container_name.into(),
+            blob_name: blob_name.into(),
+            tier,
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.sub_requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sub_requests.is_empty()
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), AzureError> {
+        if self.sub_requests.is_empty() || self.sub_requests.len() > MAX_BATCH_SUB_REQUESTS {
+            return Err(AzureError::InputParametersError(format!(
+                "a blob batch must hold between 1 and {} operations, {} given",
+                MAX_BATCH_SUB_REQUESTS,
+                self.sub_requests.len()
+            )));
+        }
+        let deletes = self.sub_requests.iter().filter(|sub_request| sub_request.is_delete()).count();
+        if deletes != 0 && deletes != self.sub_requests.len() {
+            return Err(AzureError::InputParametersError(
+                "the operations of a blob batch must be either all deletes or all set tiers".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    // The body of the batch request: every operation as an http request of
+    // its own, signed with `key`, in a part of a multipart/mixed body.
+    // `uri_prefix` is the uri of the blob service, ending with a slash.
+    pub(crate) fn to_multipart(&self, uri_prefix: &str, key: &str, boundary: &str, date: &str) -> Result<String, AzureError> {
+        let mut body = String::new();
+        for (content_id, sub_request) in self.sub_requests.iter().enumerate() {
+            let (method, path, tier) = match *sub_request {
+                SubRequest::Delete {
+                    ref container_name,
+                    ref blob_name,
+                } => (Method::DELETE, format!("{}/{}", container_name, blob_name), None),
+                SubRequest::SetTier {
+                    ref container_name,
+                    ref blob_name,
+                    tier,
+                } => (Method::PUT, format!("{}/{}?comp=tier", container_name, blob_name), Some(tier)),
+            };
+
+            let mut headers = HeaderMap::new();
+            headers.insert(HEADER_DATE, format_header_value(date)?);
+            if let Some(tier) = tier {
+                headers.insert(ACCESS_TIER, format_header_value(tier)?);
+            }
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from_static("0"));
+            let auth = authorization(&headers, &format!("{}{}", uri_prefix, path), method.clone(), key, ServiceType::Blob)?;
+
+            body.push_str(&format!(
+                "--{}\r\nContent-Type: application/http\r\nContent-Transfer-Encoding: binary\r\nContent-ID: {}\r\n\r\n",
+                boundary, content_id
+            ));
+            body.push_str(&format!("{} /{} HTTP/1.1\r\n", method, path));
+            body.push_str(&format!("{}: {}\r\n", HEADER_DATE, date));
+            if let Some(tier) = tier {
+                body.push_str(&format!("{}: {}\r\n", ACCESS_TIER, tier));
+            }
+            body.push_str(&format!("Authorization: {}\r\nContent-Length: 0\r\n\r\n", auth));
+        }
+        body.push_str(&format!("--{}--\r\n", boundary));
+        Ok(body)
+    }
+}
+
+/// The outcome of one operation of a `BlobBatch`.
+#[derive(Debug, Clone)]
+pub struct BatchSubResponse {
+    pub status_code: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl BatchSubResponse {
+    pub fn is_success(&self) -> bool {
+        self.status_code.is_success()
+    }
+
+    /// Why the operation failed, such as `BlobNotFound`.
+    pub fn error_code(&self) -> Option<&str> {
+        self.headers.get_as_str(ERROR_CODE)
+    }
+}
+
+// The responses of the operations, found in the parts of the multipart/mixed
+// body, in the order of the operations of the batch.
+pub(crate) fn batch_responses_from_multipart(content_type: &str, body: &str) -> Result<Vec<BatchSubResponse>, AzureError> {
+    let boundary = content_type
+        .split(';')
+        .map(|parameter| parameter.trim())
+        .find(|parameter| parameter.starts_with("boundary="))
+        .map(|parameter| parameter["boundary=".len()..].trim_matches('"'))
+        .ok_or_else(|| AzureError::GenericErrorWithText(format!("no boundary in the batch response content type {}", content_type)))?;
+
+    let mut responses = Vec::new();
+    // what comes before the first boundary is ignored, the last one ends
+    // with `--`
+    for (index, part) in body.split(&format!("--{}", boundary) as &str).skip(1).enumerate() {
+        if part.starts_with("--") {
+            break;
+        }
+        let (content_id, response) = sub_response_from_part(part)?;
+        responses.push((content_id.unwrap_or(index), response));
+    }
+    responses.sort_by_key(|&(content_id, _)| content_id);
+
+    Ok(responses.into_iter().map(|(_, response)| response).collect())
+}
+
+fn sub_response_from_part(part: &str) -> Result<(Option<usize>, BatchSubResponse), AzureError> {
+    let malformed = || AzureError::GenericErrorWithText(format!("malformed batch response part {:?}", part));
+    // the CRLF ending the boundary line belongs to the boundary
+    let part = part.strip_prefix("\r\n").unwrap_or(part);
+
+    // the headers of the part, then the response
+    let (part_headers, response) = split_head(part).ok_or_else(malformed)?;
+    let mut content_id = None;
+    for line in part_headers.split("\r\n") {
+        let (name, value) = split_header(line).ok_or_else(malformed)?;
+        if name.eq_ignore_ascii_case("Content-ID") {
+            content_id = value.parse().ok();
+        }
+    }
+
+    let (head, body) = split_head(response).ok_or_else(malformed)?;
+    let mut head = head.split("\r\n");
+    let status_code = head
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|status_code| status_code.parse::<u16>().ok())
+        .and_then(|status_code| StatusCode::from_u16(status_code).ok())
+        .ok_or_else(malformed)?;
+
+    let mut headers = HeaderMap::new();
+    for line in head {
+        let (name, value) = split_header(line).ok_or_else(malformed)?;
+        headers.append(
+            HeaderName::from_bytes(name.as_bytes()).map_err(|_| malformed())?,
+            HeaderValue::from_str(value).map_err(|_| malformed())?,
+        );
+    }
+
+    // so does the CRLF before the next boundary, the body is kept as it is
+    let body = body.strip_suffix("\r\n").unwrap_or(body);
+
+    Ok((
+        content_id,
+        BatchSubResponse {
+            status_code,
+            headers,
+            body: body.to_owned(),
+        },
+    ))
+}
+
+// The lines before the first empty one and what follows it.
+fn split_head(text: &str) -> Option<(&str, &str)> {
+    text.find("\r\n\r\n").map(|end| (&text[..end], &text[end + 4..]))
+}
+
+fn split_header(line: &str) -> Option<(&str, &str)> {
+    line.find(':').map(|colon| (line[..colon].trim(), line[colon + 1..].trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATE: &str = "Thu, 14 Jun 2018 16:46:54 GMT";
+
+    #[test]
+    fn multipart_body_of_two_deletes() {
+        let batch = BlobBatch::new().delete("logs", "2018/06.txt").delete("logs", "2018/07.txt");
+        assert!(batch.validate().is_ok());
+
+        let body = batch
+            .to_multipart("https://mindflavor.blob.core.windows.net/", "ZHVtbXk=", "batch_1234", DATE)
+            .unwrap();
+        assert_eq!(
+            body,
+            "--batch_1234\r\n\
+             Content-Type: application/http\r\n\
+             Content-Transfer-Encoding: binary\r\n\
+             Content-ID: 0\r\n\
+             \r\n\
+             DELETE /logs/2018/06.txt HTTP/1.1\r\n\
+             x-ms-date: Thu, 14 Jun 2018 16:46:54 GMT\r\n\
+             Authorization: SharedKey mindflavor:XfQc4Z6rf/tNISgyfGavOGO+9LvBamqupO0PIUR/mCY=\r\n\
+             Content-Length: 0\r\n\
+             \r\n\
+             --batch_1234\r\n\
+             Content-Type: application/http\r\n\
+             Content-Transfer-Encoding: binary\r\n\
+             Content-ID: 1\r\n\
+             \r\n\
+             DELETE /logs/2018/07.txt HTTP/1.1\r\n\
+             x-ms-date: Thu, 14 Jun 2018 16:46:54 GMT\r\n\
+             Authorization: SharedKey mindflavor:7Lqwi4IscPG6q9i0gP7HtUvYqcJ6Ue8w+rsDbbDoI+M=\r\n\
+             Content-Length: 0\r\n\
+             \r\n\
+             --batch_1234--\r\n"
+        );
+
+        let body = BlobBatch::new()
+            .set_tier("logs", "2018/06.txt", AccessTier::Archive)
+            .to_multipart("https://mindflavor.blob.core.windows.net/", "ZHVtbXk=", "batch_1234", DATE)
+            .unwrap();
+        assert!(body.contains("PUT /logs/2018/06.txt?comp=tier HTTP/1.1\r\n"));
+        assert!(body.contains("x-ms-access-tier: Archive\r\n"));
+    }
+
+    #[test]
+    fn invalid_batches() {
+        let too_many = (0..=MAX_BATCH_SUB_REQUESTS).fold(BlobBatch::new(), |batch, i| batch.delete("logs", i.to_string()));
+        let mixed = BlobBatch::new().delete("logs", "a").set_tier("logs", "b", AccessTier::Cool);
+
+        for batch in &[BlobBatch::new(), too_many, mixed] {
+            match batch.validate() {
+                Err(AzureError::InputParametersError(_)) => {}
+                r => panic!("unexpected result {:?} for {} operations", r, batch.len()),
+            }
+        }
+    }
+
+    #[test]
+    fn mixed_status_response() {
+        let body = "--batchresponse_66925647\r\n\
+                    Content-Type: application/http\r\n\
+                    Content-ID: 0\r\n\
+                    \r\n\
+                    HTTP/1.1 202 Accepted\r\n\
+                    x-ms-delete-type-permanent: true\r\n\
+                    x-ms-request-id: 778fdc83-801e-0000-62ff-0334671e284f\r\n\
+                    x-ms-version: 2018-11-09\r\n\
+                    \r\n\
+                    --batchresponse_66925647\r\n\
+                    Content-Type: application/http\r\n\
+                    Content-ID: 1\r\n\
+                    \r\n\
+                    HTTP/1.1 404 The specified blob does not exist.\r\n\
+                    x-ms-error-code: BlobNotFound\r\n\
+                    x-ms-request-id: 778fdc83-801e-0000-62ff-0334671e2852\r\n\
+                    x-ms-version: 2018-11-09\r\n\
+                    Content-Length: 216\r\n\
+                    Content-Type: application/xml\r\n\
+                    \r\n\
+                    <?xml version=\"1.0\" encoding=\"utf-8\"?>\r\n\
+                    <Error><Code>BlobNotFound</Code><Message>The specified blob does not exist.</Message></Error>\r\n\
+                    --batchresponse_66925647--";
+
+        let responses = batch_responses_from_multipart("multipart/mixed; boundary=batchresponse_66925647", body).unwrap();
+        assert_eq!(responses.len(), 2);
+        assert!(responses[0].is_success());
+        assert_eq!(responses[0].status_code, StatusCode::ACCEPTED);
+        assert_eq!(responses[0].error_code(), None);
+        assert_eq!(responses[0].headers.get_as_str("x-ms-delete-type-permanent"), Some("true"));
+        assert_eq!(responses[0].body, "");
+
+        assert!(!responses[1].is_success());
+        assert_eq!(responses[1].status_code, StatusCode::NOT_FOUND);
+        assert_eq!(responses[1].error_code(), Some("BlobNotFound"));
+        assert_eq!(
+            responses[1].body,
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\r\n\
+             <Error><Code>BlobNotFound</Code><Message>The specified blob does not exist.</Message></Error>"
+        );
+
+        match batch_responses_from_multipart("multipart/mixed", body) {
+            Err(AzureError::GenericErrorWithText(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
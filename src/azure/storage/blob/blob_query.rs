@@ -0,0 +1,517 @@
+use azure::core::errors::AzureError;
+use serde_json;
+
+/// Version of the API used by the queries on the contents of a blob.
+pub(crate) const BLOB_QUERY_VERSION: &str = "2019-12-12";
+
+/// The format of the records read from the blob, or of the ones returned by
+/// the query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuerySerialization {
+    Csv(CsvSerialization),
+    Json(JsonSerialization),
+}
+
+/// Delimited text. By default fields are separated by commas, records by
+/// new lines and quoted with `"`, without an escape character nor a header
+/// line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvSerialization {
+    pub field_separator: char,
+    pub record_separator: char,
+    pub quote_char: char,
+    pub escape_char: Option<char>,
+    pub has_headers: bool,
+}
+
+impl Default for CsvSerialization {
+    fn default() -> CsvSerialization {
+        CsvSerialization {
+            field_separator: ',',
+            record_separator: '\n',
+            quote_char: '"',
+            escape_char: None,
+            has_headers: false,
+        }
+    }
+}
+
+/// JSON objects, one every record separator (a new line by default).
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonSerialization {
+    pub record_separator: char,
+}
+
+impl Default for JsonSerialization {
+    fn default() -> JsonSerialization {
+        JsonSerialization { record_separator: '\n' }
+    }
+}
+
+/// An error found by the service while running the query. Unless `fatal`
+/// the query goes on and more records follow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlobQueryError {
+    pub fatal: bool,
+    pub name: String,
+    pub description: String,
+    pub position: u64,
+}
+
+/// A record of the results of a query: `Data` holds the records matching
+/// the query, serialized in the output format. The results are complete only
+/// once `End` is received.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlobQueryRecord {
+    Data(Vec<u8>),
+    Progress { bytes_scanned: u64, total_bytes: u64 },
+    Error(BlobQueryError),
+    End { total_bytes: u64 },
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn serialization_to_xml(s: &mut String, element: &str, serialization: &QuerySerialization) {
+    s.push_str(&format!("\t<{}>\n\t\t<Format>\n", element));
+    match serialization {
+        QuerySerialization::Csv(csv) => {
+            s.push_str("\t\t\t<Type>delimited</Type>\n\t\t\t<DelimitedTextConfiguration>\n");
+            s.push_str(&format!(
+                "\t\t\t\t<ColumnSeparator>{}</ColumnSeparator>\n",
+                escape_xml(&csv.field_separator.to_string())
+            ));
+            s.push_str(&format!(
+                "\t\t\t\t<FieldQuote>{}</FieldQuote>\n",
+                escape_xml(&csv.quote_char.to_string())
+            ));
+            s.push_str(&format!(
+                "\t\t\t\t<RecordSeparator>{}</RecordSeparator>\n",
+                escape_xml(&csv.record_separator.to_string())
+            ));
+            s.push_str(&format!(
+                "\t\t\t\t<EscapeChar>{}</EscapeChar>\n",
+                csv.escape_char.map(|c| escape_xml(&c.to_string())).unwrap_or_default()
+            ));
+            s.push_str(&format!("\t\t\t\t<HasHeaders>{}</HasHeaders>\n", csv.has_headers));
+            s.push_str("\t\t\t</DelimitedTextConfiguration>\n");
+        }
+        QuerySerialization::Json(json) => {
+            s.push_str("\t\t\t<Type>json</Type>\n\t\t\t<JsonTextConfiguration>\n");
+            s.push_str(&format!(
+                "\t\t\t\t<RecordSeparator>{}</RecordSeparator>\n",
+                escape_xml(&json.record_separator.to_string())
+            ));
+            s.push_str("\t\t\t</JsonTextConfiguration>\n");
+        }
+    }
+    s.push_str(&format!("\t\t</Format>\n\t</{}>\n", element));
+}
+
+// Without an input serialization the service decides the format of the blob
+// from its content, without one for the output the input one is used.
+pub(crate) fn blob_query_to_xml(expression: &str, input: Option<&QuerySerialization>, output: Option<&QuerySerialization>) -> String {
+    let mut s = String::new();
+    s.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<QueryRequest>\n\t<QueryType>SQL</QueryType>\n");
+    s.push_str(&format!("\t<Expression>{}</Expression>\n", escape_xml(expression)));
+    if let Some(input) = input {
+        serialization_to_xml(&mut s, "InputSerialization", input);
+    }
+    if let Some(output) = output {
+        serialization_to_xml(&mut s, "OutputSerialization", output);
+    }
+    s.push_str("</QueryRequest>");
+    s
+}
+
+const AVRO_MAGIC: &[u8] = b"Obj\x01";
+const SYNC_MARKER_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RecordKind {
+    ResultData,
+    Progress,
+    Error,
+    End,
+}
+
+#[derive(Debug)]
+enum DecodeError {
+    Incomplete,
+    Invalid(String),
+}
+
+type DecodeResult<T> = Result<T, DecodeError>;
+
+impl From<DecodeError> for AzureError {
+    fn from(error: DecodeError) -> AzureError {
+        match error {
+            DecodeError::Incomplete => AzureError::GenericErrorWithText("truncated query result record".to_owned()),
+            DecodeError::Invalid(text) => AzureError::GenericErrorWithText(format!("invalid query result: {}", text)),
+        }
+    }
+}
+
+struct Cursor<'b> {
+    buf: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> Cursor<'b> {
+    fn new(buf: &'b [u8]) -> Cursor<'b> {
+        Cursor { buf, pos: 0 }
+    }
+
+    fn fixed(&mut self, length: usize) -> DecodeResult<&'b [u8]> {
+        if self.buf.len() - self.pos < length {
+            return Err(DecodeError::Incomplete);
+        }
+        let bytes = &self.buf[self.pos..self.pos + length];
+        self.pos += length;
+        Ok(bytes)
+    }
+
+    // zig-zag encoded variable length integer
+    fn long(&mut self) -> DecodeResult<i64> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.fixed(1)?[0];
+            if shift > 63 {
+                return Err(DecodeError::Invalid("integer too long".to_owned()));
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+            }
+            shift += 7;
+        }
+    }
+
+    fn length(&mut self) -> DecodeResult<usize> {
+        let length = self.long()?;
+        if length < 0 {
+            return Err(DecodeError::Invalid(format!("negative length {}", length)));
+        }
+        Ok(length as usize)
+    }
+
+    fn unsigned(&mut self) -> DecodeResult<u64> {
+        let value = self.long()?;
+        if value < 0 {
+            return Err(DecodeError::Invalid(format!("negative value {}", value)));
+        }
+        Ok(value as u64)
+    }
+
+    fn bytes(&mut self) -> DecodeResult<&'b [u8]> {
+        let length = self.length()?;
+        self.fixed(length)
+    }
+
+    fn string(&mut self) -> DecodeResult<String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|e| DecodeError::Invalid(e.to_string()))
+    }
+
+    fn boolean(&mut self) -> DecodeResult<bool> {
+        match self.fixed(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(DecodeError::Invalid(format!("invalid boolean {}", b))),
+        }
+    }
+}
+
+struct Header {
+    kinds: Vec<RecordKind>,
+    sync_marker: Vec<u8>,
+}
+
+// The schema is a union of the record types: only their names are needed to
+// tell them apart, the fields are always in the documented order.
+fn record_kinds(schema: &[u8]) -> DecodeResult<Vec<RecordKind>> {
+    let schema: serde_json::Value = serde_json::from_slice(schema).map_err(|e| DecodeError::Invalid(e.to_string()))?;
+    let union = schema
+        .as_array()
+        .ok_or_else(|| DecodeError::Invalid("the schema is not a union".to_owned()))?;
+
+    union
+        .iter()
+        .map(|record| {
+            let name = record["name"].as_str().unwrap_or_default();
+            match name.rsplit('.').next().unwrap_or_default() {
+                "resultData" => Ok(RecordKind::ResultData),
+                "progress" => Ok(RecordKind::Progress),
+                "error" => Ok(RecordKind::Error),
+                "end" => Ok(RecordKind::End),
+                _ => Err(DecodeError::Invalid(format!("unknown record {:?}", name))),
+            }
+        })
+        .collect()
+}
+
+fn header(cursor: &mut Cursor) -> DecodeResult<Header> {
+    if cursor.fixed(AVRO_MAGIC.len())? != AVRO_MAGIC {
+        return Err(DecodeError::Invalid("not an avro object container".to_owned()));
+    }
+
+    let mut kinds = None;
+    loop {
+        let mut count = cursor.long()?;
+        if count == 0 {
+            break;
+        }
+        if count < 0 {
+            // a negative count is followed by the size of the block
+            count = -count;
+            cursor.long()?;
+        }
+        for _ in 0..count {
+            let key = cursor.string()?;
+            let value = cursor.bytes()?;
+            match key.as_str() {
+                "avro.schema" => kinds = Some(record_kinds(value)?),
+                "avro.codec" if value != b"null" => {
+                    return Err(DecodeError::Invalid(format!(
+                        "unsupported codec {:?}",
+                        String::from_utf8_lossy(value)
+                    )))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(Header {
+        kinds: kinds.ok_or_else(|| DecodeError::Invalid("missing schema".to_owned()))?,
+        sync_marker: cursor.fixed(SYNC_MARKER_LENGTH)?.to_vec(),
+    })
+}
+
+fn record(cursor: &mut Cursor, kinds: &[RecordKind]) -> DecodeResult<BlobQueryRecord> {
+    let index = cursor.long()?;
+    let kind = if index >= 0 { kinds.get(index as usize) } else { None };
+
+    Ok(match kind {
+        Some(RecordKind::ResultData) => BlobQueryRecord::Data(cursor.bytes()?.to_vec()),
+        Some(RecordKind::Progress) => BlobQueryRecord::Progress {
+            bytes_scanned: cursor.unsigned()?,
+            total_bytes: cursor.unsigned()?,
+        },
+        Some(RecordKind::Error) => BlobQueryRecord::Error(BlobQueryError {
+            fatal: cursor.boolean()?,
+            name: cursor.string()?,
+            description: cursor.string()?,
+            position: cursor.unsigned()?,
+        }),
+        Some(RecordKind::End) => BlobQueryRecord::End {
+            total_bytes: cursor.unsigned()?,
+        },
+        None => return Err(DecodeError::Invalid(format!("unknown record type {}", index))),
+    })
+}
+
+/// Decodes the results of a query, an avro object container, as its chunks
+/// arrive. The records of a block are returned once the whole block has
+/// been received.
+#[derive(Default)]
+pub(crate) struct BlobQueryDecoder {
+    buffer: Vec<u8>,
+    header: Option<Header>,
+}
+
+impl BlobQueryDecoder {
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Result<Vec<BlobQueryRecord>, AzureError> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut records = Vec::new();
+        loop {
+            let (consumed, header) = {
+                let mut cursor = Cursor::new(&self.buffer);
+                let result = match self.header {
+                    None => header(&mut cursor).map(Some),
+                    Some(ref header) => block(&mut cursor, header, &mut records).map(|_| None),
+                };
+                match result {
+                    Ok(header) => (cursor.pos, header),
+                    Err(DecodeError::Incomplete) => return Ok(records),
+                    Err(error) => return Err(error.into()),
+                }
+            };
+            if header.is_some() {
+                self.header = header;
+            }
+            self.buffer.drain(..consumed);
+        }
+    }
+}
+
+fn block(cursor: &mut Cursor, header: &Header, records: &mut Vec<BlobQueryRecord>) -> DecodeResult<()> {
+    let count = cursor.length()?;
+    let size = cursor.length()?;
+    let data = cursor.fixed(size)?;
+    if cursor.fixed(SYNC_MARKER_LENGTH)? != &header.sync_marker[..] {
+        return Err(DecodeError::Invalid("sync marker mismatch".to_owned()));
+    }
+
+    // the block is complete: running out of data now means it is malformed
+    let mut block = Cursor::new(data);
+    for _ in 0..count {
+        match record(&mut block, &header.kinds) {
+            Err(DecodeError::Incomplete) => return Err(DecodeError::Invalid("truncated block".to_owned())),
+            r => records.push(r?),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCHEMA: &str = "[{\"type\":\"record\",\"name\":\"com.microsoft.azure.storage.queryBlobContents.resultData\",\
+                          \"fields\":[{\"name\":\"data\",\"type\":\"bytes\"}]},\
+                          {\"type\":\"record\",\"name\":\"com.microsoft.azure.storage.queryBlobContents.error\",\
+                          \"fields\":[{\"name\":\"fatal\",\"type\":\"boolean\"},{\"name\":\"name\",\"type\":\"string\"},\
+                          {\"name\":\"description\",\"type\":\"string\"},{\"name\":\"position\",\"type\":\"long\"}]},\
+                          {\"type\":\"record\",\"name\":\"com.microsoft.azure.storage.queryBlobContents.progress\",\
+                          \"fields\":[{\"name\":\"bytesScanned\",\"type\":\"long\"},{\"name\":\"totalBytes\",\"type\":\"long\"}]},\
+                          {\"type\":\"record\",\"name\":\"com.microsoft.azure.storage.queryBlobContents.end\",\
+                          \"fields\":[{\"name\":\"totalBytes\",\"type\":\"long\"}]}]";
+
+    fn long(v: &mut Vec<u8>, n: i64) {
+        let mut n = ((n << 1) ^ (n >> 63)) as u64;
+        while n >= 0x80 {
+            v.push((n as u8 & 0x7f) | 0x80);
+            n >>= 7;
+        }
+        v.push(n as u8);
+    }
+
+    fn bytes(v: &mut Vec<u8>, b: &[u8]) {
+        long(v, b.len() as i64);
+        v.extend_from_slice(b);
+    }
+
+    fn block(v: &mut Vec<u8>, count: i64, data: &[u8]) {
+        long(v, count);
+        bytes(v, data);
+        v.extend_from_slice(&[7; SYNC_MARKER_LENGTH]);
+    }
+
+    fn query_results() -> Vec<u8> {
+        let mut v = AVRO_MAGIC.to_vec();
+        long(&mut v, 2);
+        bytes(&mut v, b"avro.codec");
+        bytes(&mut v, b"null");
+        bytes(&mut v, b"avro.schema");
+        bytes(&mut v, SCHEMA.as_bytes());
+        long(&mut v, 0);
+        v.extend_from_slice(&[7; SYNC_MARKER_LENGTH]);
+
+        let mut data = Vec::new();
+        long(&mut data, 0);
+        bytes(&mut data, b"1,Rome\n2,Paris\n");
+        long(&mut data, 2);
+        long(&mut data, 300);
+        long(&mut data, 1024);
+        block(&mut v, 2, &data);
+
+        let mut data = Vec::new();
+        long(&mut data, 1);
+        data.push(0);
+        bytes(&mut data, b"InvalidColumnOrdinal");
+        bytes(&mut data, b"Column ordinal is out of range");
+        long(&mut data, 512);
+        long(&mut data, 3);
+        long(&mut data, 1024);
+        block(&mut v, 2, &data);
+        v
+    }
+
+    #[test]
+    fn query_request_xml() {
+        let input = QuerySerialization::Csv(CsvSerialization {
+            has_headers: true,
+            escape_char: Some('\\'),
+            ..CsvSerialization::default()
+        });
+        let output = QuerySerialization::Json(JsonSerialization::default());
+
+        let xml = blob_query_to_xml(
+            "SELECT _2 FROM BlobStorage WHERE _1 < 10 AND _3 = 'A&B'",
+            Some(&input),
+            Some(&output),
+        );
+        assert_eq!(
+            xml,
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<QueryRequest>
+\t<QueryType>SQL</QueryType>
+\t<Expression>SELECT _2 FROM BlobStorage WHERE _1 &lt; 10 AND _3 = 'A&amp;B'</Expression>
+\t<InputSerialization>
+\t\t<Format>
+\t\t\t<Type>delimited</Type>
+\t\t\t<DelimitedTextConfiguration>
+\t\t\t\t<ColumnSeparator>,</ColumnSeparator>
+\t\t\t\t<FieldQuote>\"</FieldQuote>
+\t\t\t\t<RecordSeparator>\n</RecordSeparator>
+\t\t\t\t<EscapeChar>\\</EscapeChar>
+\t\t\t\t<HasHeaders>true</HasHeaders>
+\t\t\t</DelimitedTextConfiguration>
+\t\t</Format>
+\t</InputSerialization>
+\t<OutputSerialization>
+\t\t<Format>
+\t\t\t<Type>json</Type>
+\t\t\t<JsonTextConfiguration>
+\t\t\t\t<RecordSeparator>\n</RecordSeparator>
+\t\t\t</JsonTextConfiguration>
+\t\t</Format>
+\t</OutputSerialization>
+</QueryRequest>"
+        );
+
+        let xml = blob_query_to_xml("SELECT * FROM BlobStorage", None, None);
+        assert!(!xml.contains("Serialization"));
+    }
+
+    #[test]
+    fn decode_query_results() {
+        let results = query_results();
+
+        // fed a byte at a time the records come out as their blocks complete
+        let mut decoder = BlobQueryDecoder::default();
+        let mut records = Vec::new();
+        for b in &results {
+            records.extend(decoder.feed(&[*b]).unwrap());
+        }
+        assert_eq!(
+            records,
+            vec![
+                BlobQueryRecord::Data(b"1,Rome\n2,Paris\n".to_vec()),
+                BlobQueryRecord::Progress {
+                    bytes_scanned: 300,
+                    total_bytes: 1024
+                },
+                BlobQueryRecord::Error(BlobQueryError {
+                    fatal: false,
+                    name: "InvalidColumnOrdinal".to_owned(),
+                    description: "Column ordinal is out of range".to_owned(),
+                    position: 512,
+                }),
+                BlobQueryRecord::End { total_bytes: 1024 },
+            ]
+        );
+
+        let mut decoder = BlobQueryDecoder::default();
+        assert_eq!(decoder.feed(&results).unwrap(), records);
+
+        let mut corrupted = results.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] = 0;
+        assert!(BlobQueryDecoder::default().feed(&corrupted).is_err());
+        assert!(BlobQueryDecoder::default().feed(b"PAR1....").is_err());
+    }
+}
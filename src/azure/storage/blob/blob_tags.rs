@@ -0,0 +1,258 @@
+use azure::core::errors::AzureError;
+use azure::core::incompletevector::IncompleteVector;
+use serde_xml_rs::deserialize;
+use std::collections::HashMap;
+
+/// Version of the API used by the blob tags operations. Tags were introduced
+/// with 2019-12-12 but only from this one on finding blobs by tags returns
+/// every tag of the matching blobs.
+pub(crate) const BLOB_TAGS_VERSION: &str = "2020-04-08";
+
+const MAX_TAGS: usize = 10;
+const MAX_KEY_LENGTH: usize = 128;
+const MAX_VALUE_LENGTH: usize = 256;
+
+#[derive(Debug, Deserialize)]
+struct Text {
+    #[serde(rename = "$value", default)]
+    pub value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tag {
+    #[serde(rename = "Key")]
+    pub key: Text,
+    #[serde(rename = "Value")]
+    pub value: Text,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagSet {
+    #[serde(rename = "Tag")]
+    pub tag: Option<Vec<Tag>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tags {
+    #[serde(rename = "TagSet")]
+    pub tag_set: TagSet,
+}
+
+#[derive(Debug, Deserialize)]
+struct FoundBlob {
+    #[serde(rename = "Name")]
+    pub name: Text,
+    #[serde(rename = "ContainerName")]
+    pub container_name: Text,
+    #[serde(rename = "Tags")]
+    pub tags: Option<Tags>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FoundBlobs {
+    #[serde(rename = "Blob")]
+    pub blob: Option<Vec<FoundBlob>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnumerationResults {
+    #[serde(rename = "Blobs")]
+    pub blobs: FoundBlobs,
+    #[serde(rename = "NextMarker")]
+    pub next_marker: Option<Text>,
+}
+
+/// A blob matching a tag filter, along with its tags.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaggedBlob {
+    pub container_name: String,
+    pub name: String,
+    pub tags: HashMap<String, String>,
+}
+
+impl Tags {
+    fn into_map(self) -> HashMap<String, String> {
+        self.tag_set
+            .tag
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tag| (tag.key.value, tag.value.value))
+            .collect()
+    }
+}
+
+// Besides alphanumerics only space, `+ - . / : = _` are allowed in tags.
+fn is_valid_tag_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || " +-./:=_".contains(c)
+}
+
+/// Checks the tags against the limits of the service: at most 10 tags, keys
+/// between 1 and 128 characters, values up to 256 characters, all made of
+/// alphanumerics, space and `+ - . / : = _`.
+pub fn validate_blob_tags(tags: &HashMap<String, String>) -> Result<(), AzureError> {
+    if tags.len() > MAX_TAGS {
+        return Err(AzureError::InputParametersError(format!(
+            "a blob can have at most {} tags, {} given",
+            MAX_TAGS,
+            tags.len()
+        )));
+    }
+
+    for (key, value) in tags {
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+            return Err(AzureError::InputParametersError(format!(
+                "the tag key {:?} must be between 1 and {} characters long",
+                key, MAX_KEY_LENGTH
+            )));
+        }
+        if value.len() > MAX_VALUE_LENGTH {
+            return Err(AzureError::InputParametersError(format!(
+                "the value of the tag {:?} cannot be longer than {} characters",
+                key, MAX_VALUE_LENGTH
+            )));
+        }
+        if !key.chars().chain(value.chars()).all(is_valid_tag_char) {
+            return Err(AzureError::InputParametersError(format!(
+                "the tag {:?} = {:?} contains characters other than alphanumerics, space and '+ - . / : = _'",
+                key, value
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// The tags must be validated first: the allowed characters never need to be
+// escaped in xml. Keys are sorted to keep the body stable.
+pub(crate) fn blob_tags_to_xml(tags: &HashMap<String, String>) -> String {
+    let mut keys: Vec<&String> = tags.keys().collect();
+    keys.sort();
+
+    let mut s = String::new();
+    s.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<Tags>\n\t<TagSet>\n");
+    for key in keys {
+        s.push_str(&format!("\t\t<Tag><Key>{}</Key><Value>{}</Value></Tag>\n", key, tags[key]));
+    }
+    s.push_str("\t</TagSet>\n</Tags>");
+    s
+}
+
+pub(crate) fn blob_tags_from_xml(xml: &str) -> Result<HashMap<String, String>, AzureError> {
+    // the service prepends the utf-8 BOM to the body
+    let xml = xml.trim_start_matches('\u{feff}');
+    let tags: Tags = deserialize(xml.as_bytes())?;
+    debug!("tags == {:?}", tags);
+
+    Ok(tags.into_map())
+}
+
+pub(crate) fn tagged_blobs_from_xml(xml: &str) -> Result<IncompleteVector<TaggedBlob>, AzureError> {
+    let xml = xml.trim_start_matches('\u{feff}');
+    let results: EnumerationResults = deserialize(xml.as_bytes())?;
+    debug!("results == {:?}", results);
+
+    let blobs = results
+        .blobs
+        .blob
+        .unwrap_or_default()
+        .into_iter()
+        .map(|blob| TaggedBlob {
+            container_name: blob.container_name.value,
+            name: blob.name.value,
+            tags: blob.tags.map(Tags::into_map).unwrap_or_default(),
+        })
+        .collect();
+
+    let next_marker = match results.next_marker {
+        Some(ref nm) if nm.value.is_empty() => None,
+        Some(nm) => Some(nm.value),
+        None => None,
+    };
+
+    Ok(IncompleteVector::new(next_marker, blobs))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tags() -> HashMap<String, String> {
+        let mut tags = HashMap::new();
+        tags.insert("project".to_owned(), "azure-sdk".to_owned());
+        tags.insert("release".to_owned(), "2019-12-12 v1.0".to_owned());
+        tags.insert("path".to_owned(), "a/b:c=d+e_f".to_owned());
+        tags.insert("empty".to_owned(), "".to_owned());
+        tags
+    }
+
+    #[test]
+    fn xml_round_trip() {
+        let tags = tags();
+        validate_blob_tags(&tags).unwrap();
+
+        let xml = blob_tags_to_xml(&tags);
+        assert!(xml.contains("<Tag><Key>empty</Key><Value></Value></Tag>"));
+        assert_eq!(blob_tags_from_xml(&xml).unwrap(), tags);
+    }
+
+    #[test]
+    fn parse_service_response() {
+        let xml = "\u{feff}<?xml version=\"1.0\" encoding=\"utf-8\"?><Tags><TagSet>\
+                   <Tag><Key>project</Key><Value>azure-sdk</Value></Tag>\
+                   <Tag><Key>release</Key><Value>v1.0</Value></Tag></TagSet></Tags>";
+        let tags = blob_tags_from_xml(xml).unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags["release"], "v1.0");
+
+        let empty = "<?xml version=\"1.0\" encoding=\"utf-8\"?><Tags><TagSet/></Tags>";
+        assert!(blob_tags_from_xml(empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_tagged_blobs() {
+        let xml = "\u{feff}<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+                   <EnumerationResults ServiceEndpoint=\"https://mindflavor.blob.core.windows.net/\">\
+                   <Where>\"project\"='azure-sdk'</Where><Blobs>\
+                   <Blob><Name>index.html</Name><ContainerName>assets</ContainerName><Tags><TagSet>\
+                   <Tag><Key>project</Key><Value>azure-sdk</Value></Tag>\
+                   <Tag><Key>release</Key><Value>v1.0</Value></Tag></TagSet></Tags></Blob>\
+                   <Blob><Name>logs/2020.txt</Name><ContainerName>logs</ContainerName><Tags><TagSet>\
+                   <Tag><Key>project</Key><Value>azure-sdk</Value></Tag></TagSet></Tags></Blob>\
+                   </Blobs><NextMarker>2!100!MDAwMDIx</NextMarker></EnumerationResults>";
+
+        let blobs = tagged_blobs_from_xml(xml).unwrap();
+        assert_eq!(blobs.token(), Some("2!100!MDAwMDIx"));
+        assert_eq!(blobs.len(), 2);
+        assert_eq!(blobs[0].container_name, "assets");
+        assert_eq!(blobs[0].name, "index.html");
+        assert_eq!(blobs[0].tags["release"], "v1.0");
+        assert_eq!(blobs[1].container_name, "logs");
+        assert_eq!(blobs[1].tags.len(), 1);
+
+        let last_page = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+                         <EnumerationResults ServiceEndpoint=\"https://mindflavor.blob.core.windows.net/\">\
+                         <Where>\"project\"='none'</Where><Blobs /><NextMarker /></EnumerationResults>";
+        let blobs = tagged_blobs_from_xml(last_page).unwrap();
+        assert!(blobs.is_empty());
+        assert!(blobs.is_complete());
+    }
+
+    #[test]
+    fn invalid_tags() {
+        let mut invalid = Vec::new();
+        invalid.push(vec![("".to_owned(), "value".to_owned())]);
+        invalid.push(vec![("k".repeat(129), "value".to_owned())]);
+        invalid.push(vec![("key".to_owned(), "v".repeat(257))]);
+        invalid.push(vec![("key&".to_owned(), "value".to_owned())]);
+        invalid.push(vec![("key".to_owned(), "<value>".to_owned())]);
+        invalid.push((0..11).map(|i| (format!("key{}", i), "value".to_owned())).collect());
+
+        for tags in invalid {
+            let tags: HashMap<String, String> = tags.into_iter().collect();
+            match validate_blob_tags(&tags) {
+                Err(AzureError::InputParametersError(_)) => {}
+                r => panic!("unexpected result {:?} for {:?}", r, tags),
+            }
+        }
+    }
+}
@@ -3,6 +3,8 @@ pub use self::lease_blob_options::{LeaseBlobOptions, LEASE_BLOB_OPTIONS_DEFAULT}
 mod blob_block_type;
 mod blob_stream;
 pub use self::blob_block_type::BlobBlockType;
+mod upload_file;
+pub use self::upload_file::{MAX_BLOCK_ATTEMPTS, MAX_BLOCK_SIZE, MAX_SINGLE_PUT_SIZE};
 mod block_list_type;
 pub use self::block_list_type::BlockListType;
 mod blob_block_with_size;
@@ -11,6 +13,18 @@ mod block_with_size_list;
 pub use self::block_with_size_list::BlockWithSizeList;
 mod block_list;
 pub use self::block_list::BlockList;
+mod account_info;
+pub(crate) use self::account_info::ACCOUNT_INFO_VERSION;
+pub use self::account_info::{AccountInfo, AccountKind, SkuName};
+mod blob_query;
+pub(crate) use self::blob_query::{blob_query_to_xml, BlobQueryDecoder, BLOB_QUERY_VERSION};
+pub use self::blob_query::{BlobQueryError, BlobQueryRecord, CsvSerialization, JsonSerialization, QuerySerialization};
+mod blob_batch;
+pub(crate) use self::blob_batch::{batch_responses_from_multipart, BLOB_BATCH_VERSION};
+pub use self::blob_batch::{AccessTier, BatchSubResponse, BlobBatch, MAX_BATCH_SUB_REQUESTS};
+mod blob_tags;
+pub(crate) use self::blob_tags::{blob_tags_from_xml, blob_tags_to_xml, tagged_blobs_from_xml, BLOB_TAGS_VERSION};
+pub use self::blob_tags::{validate_blob_tags, TaggedBlob};
 pub mod requests;
 pub mod responses;
 use azure::core::headers::{
@@ -18,10 +32,12 @@ use azure::core::headers::{
     COPY_STATUS, COPY_STATUS_DESCRIPTION, CREATION_TIME, LEASE_ACTION, LEASE_BREAK_PERIOD, LEASE_DURATION, LEASE_ID, LEASE_STATE,
     LEASE_STATUS, PROPOSED_LEASE_ID, SERVER_ENCRYPTED,
 };
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use futures::{future::*, prelude::*};
 use hyper::{header, Method, StatusCode};
 use std::collections::HashMap;
+use std::path::Path;
 use std::{fmt, str::FromStr};
 use uuid::Uuid;
 use xml::Element;
@@ -32,7 +48,7 @@ use azure::core::{
     errors::{check_status_extract_body, check_status_extract_headers_and_body, AzureError, TraversingError},
     incompletevector::IncompleteVector,
     lease::{LeaseAction, LeaseDuration, LeaseId, LeaseState, LeaseStatus},
-    parsing::{cast_must, cast_optional, from_azure_time, inner_text, traverse, FromStringOptional},
+    parsing::{cast_must, cast_optional, from_azure_snapshot_time, from_azure_time, inner_text, to_azure_snapshot_time, traverse, FromStringOptional},
     range::Range,
     util::{HeaderMapExt, RequestBuilderExt},
 };
@@ -86,15 +102,21 @@ pub struct Blob {
     pub server_encrypted: bool,
     pub access_tier_inferred: Option<bool>,
     pub access_tier_change_time: Option<DateTime<Utc>>,
+    pub deleted: bool,
     pub deleted_time: Option<DateTime<Utc>>,
     pub remaining_retention_days: Option<u64>,
     pub metadata: HashMap<String, String>,
 }
 
+impl_etag!(Blob, etag, optional);
+
 impl Blob {
     pub fn parse(elem: &Element, container_name: &str) -> Result<Blob, AzureError> {
         let name = cast_must::<String>(elem, &["Name"])?;
-        let snapshot_time = cast_optional::<DateTime<Utc>>(elem, &["Snapshot"])?;
+        let snapshot_time = match cast_optional::<String>(elem, &["Snapshot"])? {
+            Some(ref snapshot) => Some(from_azure_snapshot_time(snapshot)?),
+            None => None,
+        };
         let creation_time = cast_must::<DateTime<Utc>>(elem, &["Properties", "Creation-Time"])?;
         let last_modified = cast_optional::<DateTime<Utc>>(elem, &["Properties", "Last-Modified"])?;
         let etag = cast_optional::<String>(elem, &["Properties", "Etag"])?;
@@ -133,6 +155,8 @@ impl Blob {
         let access_tier_inferred = cast_optional::<bool>(elem, &["Properties", "AccessTierInferred"])?;
 
         let access_tier_change_time = cast_optional::<DateTime<Utc>>(elem, &["Properties", "AccessTierChangeTime"])?;
+        // only listed when soft-deleted blobs are included
+        let deleted = cast_optional::<bool>(elem, &["Deleted"])?.unwrap_or(false);
         let deleted_time = cast_optional::<DateTime<Utc>>(elem, &["Properties", "DeletedTime"])?;
         let remaining_retention_days = cast_optional::<u64>(elem, &["Properties", "RemainingRetentionDays"])?;
 
@@ -191,6 +215,7 @@ impl Blob {
             server_encrypted,
             access_tier_inferred: access_tier_inferred,
             access_tier_change_time,
+            deleted,
             deleted_time,
             remaining_retention_days,
             metadata,
@@ -327,6 +352,7 @@ impl Blob {
             server_encrypted,
             access_tier_inferred: None,     // TODO: Not present
             access_tier_change_time: None,  // TODO: Not present
+            deleted: false,
             deleted_time: None,             // TODO
             remaining_retention_days: None, // TODO: Not present or documentation bug?
             metadata: HashMap::new(),       // TODO: Not present or documentation bug?
@@ -345,10 +371,88 @@ impl Blob {
         blob_stream::BlobStream::new(c, container_name, blob_name, snapshot, range, lease_id, increment)
     }
 
+    /// Uploads the file at `path` as a block blob. Files up to
+    /// `MAX_SINGLE_PUT_SIZE` bytes are sent with a single Put Blob, larger
+    /// ones are staged in blocks of `block_size` bytes then committed.
+    /// `progress` is called with the bytes uploaded so far and the total
+    /// after every request. The upload fails if the file changes size in
+    /// the meantime. Returns the number of bytes uploaded.
+    ///
+    /// The file is opened and read with blocking `std::fs` calls from
+    /// within the future, up to `MAX_SINGLE_PUT_SIZE` bytes at once, so run
+    /// it off the event loop thread, on a thread of its own or a blocking
+    /// pool, if the loop serves anything else meanwhile.
+    pub fn upload_file<'a, P, F>(
+        c: &'a Client,
+        container_name: &'a str,
+        blob_name: &'a str,
+        path: P,
+        block_size: u64,
+        progress: F,
+    ) -> impl Future<Item = u64, Error = AzureError> + 'a
+    where
+        P: AsRef<Path>,
+        F: FnMut(u64, u64) + 'a,
+    {
+        upload_file::upload_file(c, container_name, blob_name, path.as_ref(), block_size, MAX_SINGLE_PUT_SIZE, 1, progress)
+    }
+
+    /// Uploads the file at `path` as `upload_file` does, staging up to
+    /// `concurrency` blocks at once. A block failing with a retryable
+    /// error is sent again on its own, up to `MAX_BLOCK_ATTEMPTS` times,
+    /// and the blocks are committed in the order of the file whatever the
+    /// order they were staged in. The file is read with blocking calls, as
+    /// in `upload_file`.
+    pub fn upload_file_parallel<'a, P, F>(
+        c: &'a Client,
+        container_name: &'a str,
+        blob_name: &'a str,
+        path: P,
+        block_size: u64,
+        concurrency: usize,
+        progress: F,
+    ) -> impl Future<Item = u64, Error = AzureError> + 'a
+    where
+        P: AsRef<Path>,
+        F: FnMut(u64, u64) + 'a,
+    {
+        upload_file::upload_file(
+            c,
+            container_name,
+            blob_name,
+            path.as_ref(),
+            block_size,
+            MAX_SINGLE_PUT_SIZE,
+            concurrency,
+            progress,
+        )
+    }
+
+    /// Uploads `body` as a block blob, staging it in blocks of `block_size`
+    /// bytes as it arrives, up to `concurrency` blocks at once, and
+    /// committing them once it ends. The body is read no faster than the
+    /// blocks are staged. Nothing is committed if the body fails, the blob
+    /// is left as it was. Returns the number of bytes uploaded.
+    pub fn upload_stream<'a, B, E>(
+        c: &'a Client,
+        container_name: &'a str,
+        blob_name: &'a str,
+        body: B,
+        block_size: u64,
+        concurrency: usize,
+    ) -> impl Future<Item = u64, Error = AzureError> + 'a
+    where
+        B: Stream<Item = Bytes, Error = E> + 'a,
+        E: Into<AzureError> + 'a,
+    {
+        upload_file::upload_stream(c, container_name, blob_name, body, block_size, concurrency)
+    }
+
     pub fn lease(&self, c: &Client, la: LeaseAction, lbo: &LeaseBlobOptions) -> impl Future<Item = LeaseId, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}/{}?comp=lease",
+            "https://{}.blob.{}/{}/{}?comp=lease",
             c.account(),
+            c.endpoint_suffix(),
             self.container_name,
             self.name
         );
@@ -408,10 +512,33 @@ impl Blob {
         blob_name: &str,
         lease_id: Option<&LeaseId>,
     ) -> impl Future<Item = (), Error = AzureError> {
-        let uri = format!("https://{}.blob.core.windows.net/{}/{}", c.account(), container_name, blob_name);
+        let uri = format!("https://{}.blob.{}/{}/{}", c.account(), c.endpoint_suffix(), container_name, blob_name);
+        Blob::delete_uri(c, &uri, lease_id)
+    }
+
+    /// Deletes a single snapshot of the blob, leaving the base blob untouched.
+    pub fn delete_snapshot(
+        c: &Client,
+        container_name: &str,
+        blob_name: &str,
+        snapshot: &DateTime<Utc>,
+        lease_id: Option<&LeaseId>,
+    ) -> impl Future<Item = (), Error = AzureError> {
+        let uri = format!(
+            "https://{}.blob.{}/{}/{}?snapshot={}",
+            c.account(),
+            c.endpoint_suffix(),
+            container_name,
+            blob_name,
+            to_azure_snapshot_time(snapshot)
+        );
+        Blob::delete_uri(c, &uri, lease_id)
+    }
+
+    fn delete_uri(c: &Client, uri: &str, lease_id: Option<&LeaseId>) -> impl Future<Item = (), Error = AzureError> {
 
         let req = c.perform_request(
-            &uri,
+            uri,
             Method::DELETE,
             |ref mut request| {
                 if let Some(lease_id) = lease_id {
@@ -451,3 +578,61 @@ pub(crate) fn incomplete_vector_from_response(body: &str, container_name: &str)
 
     Ok(IncompleteVector::<Blob>::new(next_marker, v))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use azure::core::enumerations::ParsingError;
+    use chrono::TimeZone;
+
+    #[test]
+    fn blob_type_parse() {
+        for (s, blob_type) in &[
+            ("BlockBlob", BlobType::BlockBlob),
+            ("PageBlob", BlobType::PageBlob),
+            ("AppendBlob", BlobType::AppendBlob),
+        ] {
+            assert_eq!(s.parse::<BlobType>().unwrap(), *blob_type);
+            assert_eq!(blob_type.to_string(), *s);
+            assert_eq!(blob_type.as_ref(), *s);
+        }
+
+        match "blockblob".parse::<BlobType>() {
+            Err(ParsingError::ElementNotFound(s)) => assert_eq!(s, "blockblob"),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn parse_deleted_blobs() {
+        let body = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+                    <EnumerationResults ServiceEndpoint=\"https://mindflavor.blob.core.windows.net/\" ContainerName=\"logs\">\
+                    <Blobs><Blob><Name>deleted.txt</Name><Deleted>true</Deleted><Properties>\
+                    <Creation-Time>Mon, 06 Jan 2020 10:00:00 GMT</Creation-Time>\
+                    <Last-Modified>Mon, 06 Jan 2020 10:00:00 GMT</Last-Modified>\
+                    <Etag>0x8D792A6F0C6C4A1</Etag><Content-Length>12</Content-Length>\
+                    <Content-Type>text/plain</Content-Type><BlobType>BlockBlob</BlobType>\
+                    <LeaseState>available</LeaseState><ServerEncrypted>true</ServerEncrypted>\
+                    <DeletedTime>Tue, 07 Jan 2020 08:30:00 GMT</DeletedTime>\
+                    <RemainingRetentionDays>6</RemainingRetentionDays></Properties></Blob>\
+                    <Blob><Name>live.txt</Name><Properties>\
+                    <Creation-Time>Mon, 06 Jan 2020 10:00:00 GMT</Creation-Time>\
+                    <Content-Length>0</Content-Length><Content-Type>text/plain</Content-Type>\
+                    <BlobType>BlockBlob</BlobType><LeaseState>available</LeaseState>\
+                    <ServerEncrypted>true</ServerEncrypted></Properties></Blob></Blobs>\
+                    <NextMarker /></EnumerationResults>";
+
+        let blobs = incomplete_vector_from_response(body, "logs").unwrap();
+        assert!(blobs.is_complete());
+        assert_eq!(blobs.len(), 2);
+
+        assert_eq!(blobs[0].name, "deleted.txt");
+        assert!(blobs[0].deleted);
+        assert_eq!(blobs[0].deleted_time, Some(Utc.ymd(2020, 1, 7).and_hms(8, 30, 0)));
+        assert_eq!(blobs[0].remaining_retention_days, Some(6));
+
+        assert!(!blobs[1].deleted);
+        assert_eq!(blobs[1].deleted_time, None);
+        assert_eq!(blobs[1].remaining_retention_days, None);
+    }
+}
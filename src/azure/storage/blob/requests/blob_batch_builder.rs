@@ -0,0 +1,179 @@
+use azure::core::errors::{check_status_extract_headers_and_body_as_string, AzureError};
+use azure::core::util::RequestBuilderExt;
+use azure::core::{
+    BlobBatchRequired, BlobBatchSupport, ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, No, TimeoutOption, TimeoutSupport,
+    ToAssign, Yes,
+};
+use azure::storage::blob::responses::BlobBatchResponse;
+use azure::storage::blob::{BlobBatch, BLOB_BATCH_VERSION};
+use azure::storage::client::Client;
+use azure::storage::rest_client::{request_date, ServiceType, HEADER_VERSION};
+use futures::future::done;
+use futures::prelude::*;
+use hyper::{header, Method, StatusCode};
+use std::marker::PhantomData;
+use uuid::Uuid;
+
+/// Sends the operations of a `BlobBatch` in a single request. The request
+/// succeeds as a whole even when some of the operations fail, their
+/// outcomes are in `BlobBatchResponse::sub_responses`.
+#[derive(Debug, Clone)]
+pub struct BlobBatchBuilder<'a, BlobBatchSet>
+where
+    BlobBatchSet: ToAssign,
+{
+    client: &'a Client,
+    p_blob_batch: PhantomData<BlobBatchSet>,
+    blob_batch: Option<&'a BlobBatch>,
+    timeout: Option<u64>,
+    client_request_id: Option<&'a str>,
+}
+
+impl<'a> BlobBatchBuilder<'a, No> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> BlobBatchBuilder<'a, No> {
+        BlobBatchBuilder {
+            client,
+            p_blob_batch: PhantomData {},
+            blob_batch: None,
+            timeout: None,
+            client_request_id: None,
+        }
+    }
+}
+
+impl<'a, BlobBatchSet> ClientRequired<'a> for BlobBatchBuilder<'a, BlobBatchSet>
+where
+    BlobBatchSet: ToAssign,
+{
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a> BlobBatchRequired<'a> for BlobBatchBuilder<'a, Yes> {
+    #[inline]
+    fn blob_batch(&self) -> &'a BlobBatch {
+        self.blob_batch.unwrap()
+    }
+}
+
+impl<'a, BlobBatchSet> TimeoutOption for BlobBatchBuilder<'a, BlobBatchSet>
+where
+    BlobBatchSet: ToAssign,
+{
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a, BlobBatchSet> ClientRequestIdOption<'a> for BlobBatchBuilder<'a, BlobBatchSet>
+where
+    BlobBatchSet: ToAssign,
+{
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a, BlobBatchSet> BlobBatchSupport<'a> for BlobBatchBuilder<'a, BlobBatchSet>
+where
+    BlobBatchSet: ToAssign,
+{
+    type O = BlobBatchBuilder<'a, Yes>;
+
+    #[inline]
+    fn with_blob_batch(self, blob_batch: &'a BlobBatch) -> Self::O {
+        BlobBatchBuilder {
+            client: self.client,
+            p_blob_batch: PhantomData {},
+            blob_batch: Some(blob_batch),
+            timeout: self.timeout,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, BlobBatchSet> TimeoutSupport for BlobBatchBuilder<'a, BlobBatchSet>
+where
+    BlobBatchSet: ToAssign,
+{
+    type O = BlobBatchBuilder<'a, BlobBatchSet>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        BlobBatchBuilder {
+            client: self.client,
+            p_blob_batch: PhantomData {},
+            blob_batch: self.blob_batch,
+            timeout: Some(timeout),
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, BlobBatchSet> ClientRequestIdSupport<'a> for BlobBatchBuilder<'a, BlobBatchSet>
+where
+    BlobBatchSet: ToAssign,
+{
+    type O = BlobBatchBuilder<'a, BlobBatchSet>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        BlobBatchBuilder {
+            client: self.client,
+            p_blob_batch: PhantomData {},
+            blob_batch: self.blob_batch,
+            timeout: self.timeout,
+            client_request_id: Some(client_request_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a, BlobBatchSet> BlobBatchBuilder<'a, BlobBatchSet> where BlobBatchSet: ToAssign {}
+
+impl<'a> BlobBatchBuilder<'a, Yes> {
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = BlobBatchResponse, Error = AzureError> {
+        let mut uri = format!(
+            "https://{}.blob.{}/?comp=batch",
+            self.client().account(),
+            self.client().endpoint_suffix()
+        );
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, timeout);
+        }
+
+        trace!("uri == {:?}", uri);
+
+        let boundary = format!("batch_{}", Uuid::new_v4());
+        let req = self.blob_batch().validate().and_then(|_| {
+            let body = self.blob_batch().to_multipart(
+                &self.client().get_uri_prefix(&ServiceType::Blob),
+                self.client().key(),
+                &boundary,
+                &request_date(),
+            )?;
+
+            self.client().perform_request(
+                &uri,
+                Method::POST,
+                |ref mut request| {
+                    request.header(HEADER_VERSION, BLOB_BATCH_VERSION);
+                    request.header_formatted(header::CONTENT_TYPE, format!("multipart/mixed; boundary={}", boundary));
+                    ClientRequestIdOption::add_header(&self, request);
+                },
+                Some(body.as_bytes()),
+            )
+        });
+
+        done(req).from_err().and_then(move |response| {
+            check_status_extract_headers_and_body_as_string(response, StatusCode::ACCEPTED)
+                .and_then(move |(headers, body)| done(BlobBatchResponse::from_response(&headers, &body)))
+        })
+    }
+}
@@ -0,0 +1,361 @@
+use azure::core::errors::{extract_status_and_body, AzureError, UnexpectedHTTPResult};
+use azure::core::lease::LeaseId;
+use azure::core::{
+    BlobNameRequired, BlobNameSupport, ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, ContainerNameRequired,
+    ContainerNameSupport, LeaseIdOption, LeaseIdSupport, No, SnapshotOption, SnapshotSupport, TimeoutOption, TimeoutSupport, ToAssign, Yes,
+};
+use azure::storage::client::Client;
+use chrono::{DateTime, Utc};
+use futures::future::done;
+use futures::prelude::*;
+use hyper::{Method, StatusCode};
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone)]
+pub struct BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    client: &'a Client,
+    p_container_name: PhantomData<ContainerNameSet>,
+    p_blob_name: PhantomData<BlobNameSet>,
+    container_name: Option<&'a str>,
+    blob_name: Option<&'a str>,
+    snapshot: Option<DateTime<Utc>>,
+    timeout: Option<u64>,
+    lease_id: Option<&'a LeaseId>,
+    client_request_id: Option<&'a str>,
+}
+
+impl<'a> BlobExistsBuilder<'a, No, No> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> BlobExistsBuilder<'a, No, No> {
+        BlobExistsBuilder {
+            client,
+            p_container_name: PhantomData {},
+            container_name: None,
+            p_blob_name: PhantomData {},
+            blob_name: None,
+            snapshot: None,
+            timeout: None,
+            lease_id: None,
+            client_request_id: None,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequired<'a> for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a, BlobNameSet> ContainerNameRequired<'a> for BlobExistsBuilder<'a, Yes, BlobNameSet>
+where
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn container_name(&self) -> &'a str {
+        self.container_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet> BlobNameRequired<'a> for BlobExistsBuilder<'a, ContainerNameSet, Yes>
+where
+    ContainerNameSet: ToAssign,
+{
+    #[inline]
+    fn blob_name(&self) -> &'a str {
+        self.blob_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> SnapshotOption for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn snapshot(&self) -> Option<DateTime<Utc>> {
+        self.snapshot
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TimeoutOption for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> LeaseIdOption<'a> for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn lease_id(&self) -> Option<&'a LeaseId> {
+        self.lease_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdOption<'a> for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContainerNameSupport<'a> for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = BlobExistsBuilder<'a, Yes, BlobNameSet>;
+
+    #[inline]
+    fn with_container_name(self, container_name: &'a str) -> Self::O {
+        BlobExistsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: Some(container_name),
+            blob_name: self.blob_name,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> BlobNameSupport<'a> for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = BlobExistsBuilder<'a, ContainerNameSet, Yes>;
+
+    #[inline]
+    fn with_blob_name(self, blob_name: &'a str) -> Self::O {
+        BlobExistsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: Some(blob_name),
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> SnapshotSupport for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_snapshot(self, snapshot: DateTime<Utc>) -> Self::O {
+        BlobExistsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            snapshot: Some(snapshot),
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TimeoutSupport for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        BlobExistsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            snapshot: self.snapshot,
+            timeout: Some(timeout),
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> LeaseIdSupport<'a> for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_lease_id(self, lease_id: &'a LeaseId) -> Self::O {
+        BlobExistsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: Some(lease_id),
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdSupport<'a> for BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        BlobExistsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: Some(client_request_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a, ContainerNameSet, BlobNameSet> BlobExistsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{}
+
+impl<'a> BlobExistsBuilder<'a, Yes, Yes> {
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = bool, Error = AzureError> {
+        let mut uri = format!(
+            "https://{}.blob.{}/{}/{}",
+            self.client().account(),
+            self.client().endpoint_suffix(),
+            self.container_name(),
+            self.blob_name()
+        );
+
+        let mut f_first = true;
+        if let Some(snapshot) = SnapshotOption::to_uri_parameter(&self) {
+            uri = format!("{}?{}", uri, snapshot);
+            f_first = false;
+        }
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(&self) {
+            uri = format!("{}{}{}", uri, if f_first { "?" } else { "&" }, timeout);
+        }
+
+        trace!("uri == {:?}", uri);
+
+        let req = self.client().perform_request(
+            &uri,
+            Method::HEAD,
+            |ref mut request| {
+                LeaseIdOption::add_header(&self, request);
+                ClientRequestIdOption::add_header(&self, request);
+            },
+            None,
+        );
+
+        done(req)
+            .from_err()
+            .and_then(extract_status_and_body)
+            .and_then(move |(status, body)| done(exists_from_status(status, &body)))
+    }
+}
+
+// HEAD responses carry no body so 404 is the only way to tell a missing
+// blob apart.
+fn exists_from_status(status: StatusCode, body: &str) -> Result<bool, AzureError> {
+    match status {
+        StatusCode::OK => Ok(true),
+        StatusCode::NOT_FOUND => Ok(false),
+        _ => Err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(
+            StatusCode::OK,
+            status,
+            body,
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exists_true() {
+        assert!(exists_from_status(StatusCode::OK, "").unwrap());
+    }
+
+    #[test]
+    fn exists_false() {
+        assert!(!exists_from_status(StatusCode::NOT_FOUND, "").unwrap());
+        assert!(exists_from_status(StatusCode::FORBIDDEN, "").is_err());
+    }
+
+    fn found(_: &str) -> (&'static str, String, String) {
+        ("200 OK", String::new(), String::new())
+    }
+
+    #[test]
+    fn signed_head_request() {
+        use azure::core::test_server::serve_recorded;
+        use azure::storage::client::Blob;
+        use tokio_core::reactor::Core;
+
+        let (endpoint, requests) = serve_recorded(found);
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap().with_blob_endpoint(endpoint);
+        let exists = client.blob_exists().with_container_name("logs").with_blob_name("2020/01.txt");
+        let exists = exists.finalize();
+        assert!(Core::new().unwrap().run(exists).unwrap());
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let mut lines = requests[0].lines();
+        assert!(lines.next().unwrap().starts_with("HEAD /logs/2020/01.txt "));
+        let headers: Vec<String> = lines.map(|line| line.to_lowercase()).collect();
+        let has = |name: &str| headers.iter().any(|header| header.starts_with(name));
+        assert!(has("authorization: sharedkey mindflavor:"));
+        assert!(has("x-ms-version: "));
+    }
+}
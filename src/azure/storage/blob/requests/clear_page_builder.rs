@@ -454,8 +454,9 @@ impl<'a> ClearPageBuilder<'a, Yes, Yes, Yes> {
     #[inline]
     pub fn finalize(self) -> impl Future<Item = ClearPageResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}/{}?comp=page",
+            "https://{}.blob.{}/{}/{}?comp=page",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name(),
             self.blob_name()
         );
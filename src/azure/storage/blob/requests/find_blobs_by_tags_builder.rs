@@ -0,0 +1,262 @@
+use azure::core::errors::{check_status_extract_headers_and_body_as_string, AzureError};
+use azure::core::{
+    ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, MaxResultsOption, MaxResultsSupport, NextMarkerOption,
+    NextMarkerSupport, No, TagFilterRequired, TagFilterSupport, TimeoutOption, TimeoutSupport, ToAssign, Yes,
+};
+use azure::storage::blob::responses::FindBlobsByTagsResponse;
+use azure::storage::blob::BLOB_TAGS_VERSION;
+use azure::storage::client::Client;
+use azure::storage::rest_client::HEADER_VERSION;
+use futures::future::done;
+use futures::prelude::*;
+use hyper::{Method, StatusCode};
+use std::marker::PhantomData;
+
+/// Finds the blobs of every container of the account whose tags match the
+/// filter, for example `"project" = 'azure-sdk' AND "release" >= 'v1'`.
+/// The filter is url encoded here so it must be passed as is.
+#[derive(Debug, Clone)]
+pub struct FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    client: &'a Client,
+    p_tag_filter: PhantomData<TagFilterSet>,
+    tag_filter: Option<&'a str>,
+    next_marker: Option<&'a str>,
+    max_results: Option<u32>,
+    timeout: Option<u64>,
+    client_request_id: Option<&'a str>,
+}
+
+impl<'a> FindBlobsByTagsBuilder<'a, No> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> FindBlobsByTagsBuilder<'a, No> {
+        FindBlobsByTagsBuilder {
+            client,
+            p_tag_filter: PhantomData {},
+            tag_filter: None,
+            next_marker: None,
+            max_results: None,
+            timeout: None,
+            client_request_id: None,
+        }
+    }
+}
+
+impl<'a, TagFilterSet> ClientRequired<'a> for FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a> TagFilterRequired<'a> for FindBlobsByTagsBuilder<'a, Yes> {
+    #[inline]
+    fn tag_filter(&self) -> &'a str {
+        self.tag_filter.unwrap()
+    }
+}
+
+impl<'a, TagFilterSet> NextMarkerOption<'a> for FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    #[inline]
+    fn next_marker(&self) -> Option<&'a str> {
+        self.next_marker
+    }
+}
+
+impl<'a, TagFilterSet> MaxResultsOption for FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    #[inline]
+    fn max_results(&self) -> Option<u32> {
+        self.max_results
+    }
+}
+
+impl<'a, TagFilterSet> TimeoutOption for FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a, TagFilterSet> ClientRequestIdOption<'a> for FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a, TagFilterSet> TagFilterSupport<'a> for FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    type O = FindBlobsByTagsBuilder<'a, Yes>;
+
+    #[inline]
+    fn with_tag_filter(self, tag_filter: &'a str) -> Self::O {
+        FindBlobsByTagsBuilder {
+            client: self.client,
+            p_tag_filter: PhantomData {},
+            tag_filter: Some(tag_filter),
+            next_marker: self.next_marker,
+            max_results: self.max_results,
+            timeout: self.timeout,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, TagFilterSet> NextMarkerSupport<'a> for FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    type O = FindBlobsByTagsBuilder<'a, TagFilterSet>;
+
+    #[inline]
+    fn with_next_marker(self, next_marker: &'a str) -> Self::O {
+        FindBlobsByTagsBuilder {
+            client: self.client,
+            p_tag_filter: PhantomData {},
+            tag_filter: self.tag_filter,
+            next_marker: Some(next_marker),
+            max_results: self.max_results,
+            timeout: self.timeout,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, TagFilterSet> MaxResultsSupport for FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    type O = FindBlobsByTagsBuilder<'a, TagFilterSet>;
+
+    #[inline]
+    fn with_max_results(self, max_results: u32) -> Self::O {
+        FindBlobsByTagsBuilder {
+            client: self.client,
+            p_tag_filter: PhantomData {},
+            tag_filter: self.tag_filter,
+            next_marker: self.next_marker,
+            max_results: Some(max_results),
+            timeout: self.timeout,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, TagFilterSet> TimeoutSupport for FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    type O = FindBlobsByTagsBuilder<'a, TagFilterSet>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        FindBlobsByTagsBuilder {
+            client: self.client,
+            p_tag_filter: PhantomData {},
+            tag_filter: self.tag_filter,
+            next_marker: self.next_marker,
+            max_results: self.max_results,
+            timeout: Some(timeout),
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, TagFilterSet> ClientRequestIdSupport<'a> for FindBlobsByTagsBuilder<'a, TagFilterSet>
+where
+    TagFilterSet: ToAssign,
+{
+    type O = FindBlobsByTagsBuilder<'a, TagFilterSet>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        FindBlobsByTagsBuilder {
+            client: self.client,
+            p_tag_filter: PhantomData {},
+            tag_filter: self.tag_filter,
+            next_marker: self.next_marker,
+            max_results: self.max_results,
+            timeout: self.timeout,
+            client_request_id: Some(client_request_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a, TagFilterSet> FindBlobsByTagsBuilder<'a, TagFilterSet> where TagFilterSet: ToAssign {}
+
+impl<'a> FindBlobsByTagsBuilder<'a, Yes> {
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = FindBlobsByTagsResponse, Error = AzureError> {
+        let mut uri = format!(
+            "https://{}.blob.{}/?comp=blobs&{}",
+            self.client().account(),
+            self.client().endpoint_suffix(),
+            TagFilterRequired::to_uri_parameter(&self)
+        );
+        if let Some(nm) = NextMarkerOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, nm);
+        }
+        if let Some(mr) = MaxResultsOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, mr);
+        }
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, timeout);
+        }
+
+        trace!("uri == {:?}", uri);
+
+        let req = self.client().perform_request(
+            &uri,
+            Method::GET,
+            |ref mut request| {
+                request.header(HEADER_VERSION, BLOB_TAGS_VERSION);
+                ClientRequestIdOption::add_header(&self, request);
+            },
+            None,
+        );
+
+        done(req).from_err().and_then(move |future_response| {
+            check_status_extract_headers_and_body_as_string(future_response, StatusCode::OK)
+                .and_then(move |(headers, body)| done(FindBlobsByTagsResponse::from_response(&headers, &body)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use azure::storage::client::Blob;
+
+    #[test]
+    fn tag_filter_is_encoded() {
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap();
+        let builder = client
+            .find_blobs_by_tags()
+            .with_tag_filter("\"project\" = 'azure-sdk' AND \"build\" >= 'v1+2'");
+
+        assert_eq!(
+            TagFilterRequired::to_uri_parameter(&builder),
+            "where=%22project%22%20%3D%20'azure%2Dsdk'%20AND%20%22build%22%20%3E%3D%20'v1%2B2'"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use azure::core::errors::{check_status_extract_headers_and_body, AzureError};
+use azure::core::{ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, TimeoutOption, TimeoutSupport};
+use azure::storage::blob::responses::GetAccountInformationResponse;
+use azure::storage::blob::ACCOUNT_INFO_VERSION;
+use azure::storage::client::Client;
+use azure::storage::rest_client::HEADER_VERSION;
+use futures::future::done;
+use futures::prelude::*;
+use hyper::{Method, StatusCode};
+
+/// Reads the SKU and the kind of the storage account, to find out which
+/// features it supports.
+#[derive(Debug, Clone)]
+pub struct GetAccountInformationBuilder<'a> {
+    client: &'a Client,
+    timeout: Option<u64>,
+    client_request_id: Option<&'a str>,
+}
+
+impl<'a> GetAccountInformationBuilder<'a> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> GetAccountInformationBuilder<'a> {
+        GetAccountInformationBuilder {
+            client,
+            timeout: None,
+            client_request_id: None,
+        }
+    }
+}
+
+impl<'a> ClientRequired<'a> for GetAccountInformationBuilder<'a> {
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a> TimeoutOption for GetAccountInformationBuilder<'a> {
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a> ClientRequestIdOption<'a> for GetAccountInformationBuilder<'a> {
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a> TimeoutSupport for GetAccountInformationBuilder<'a> {
+    type O = GetAccountInformationBuilder<'a>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        GetAccountInformationBuilder {
+            client: self.client,
+            timeout: Some(timeout),
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a> ClientRequestIdSupport<'a> for GetAccountInformationBuilder<'a> {
+    type O = GetAccountInformationBuilder<'a>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        GetAccountInformationBuilder {
+            client: self.client,
+            timeout: self.timeout,
+            client_request_id: Some(client_request_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a> GetAccountInformationBuilder<'a> {}
+
+impl<'a> GetAccountInformationBuilder<'a> {
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = GetAccountInformationResponse, Error = AzureError> {
+        let mut uri = format!(
+            "https://{}.blob.{}/?restype=account&comp=properties",
+            self.client().account(),
+            self.client().endpoint_suffix()
+        );
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, timeout);
+        }
+
+        trace!("uri == {:?}", uri);
+
+        let req = self.client().perform_request(
+            &uri,
+            Method::GET,
+            |ref mut request| {
+                request.header(HEADER_VERSION, ACCOUNT_INFO_VERSION);
+                ClientRequestIdOption::add_header(&self, request);
+            },
+            None,
+        );
+
+        done(req)
+            .from_err()
+            .and_then(move |response| check_status_extract_headers_and_body(response, StatusCode::OK))
+            .and_then(move |(headers, _body)| done(GetAccountInformationResponse::from_headers(&headers)))
+    }
+}
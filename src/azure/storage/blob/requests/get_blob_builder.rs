@@ -1,21 +1,33 @@
-use azure::core::errors::{check_status_extract_headers_and_body, AzureError};
+use azure::core::errors::{check_status_extract_headers_and_body, conditional_error, AzureError, UnexpectedHTTPResult};
 use azure::core::headers::RANGE_GET_CONTENT_MD5;
 use azure::core::lease::LeaseId;
+use azure::core::modify_conditions::IfSinceCondition;
 use azure::core::range::Range;
 use azure::core::util::RequestBuilderExt;
 use azure::core::{
     BlobNameRequired, BlobNameSupport, ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, ContainerNameRequired,
-    ContainerNameSupport, LeaseIdOption, LeaseIdSupport, No, RangeOption, RangeSupport, SnapshotOption, SnapshotSupport, TimeoutOption,
-    TimeoutSupport, ToAssign, Yes,
+    ContainerNameSupport, IfSinceConditionOption, IfSinceConditionSupport, LeaseIdOption, LeaseIdSupport, No, RangeContentMD5Option,
+    RangeContentMD5Support, RangeHeaderOption, RangeHeaderSupport, RangeOption, RangeSupport, SnapshotOption, SnapshotSupport,
+    TimeoutOption, TimeoutSupport, ToAssign, Yes,
 };
 use azure::storage::blob::responses::GetBlobResponse;
 use azure::storage::blob::Blob;
 use azure::storage::client::Client;
+use azure::storage::rest_client::CorrelatedResponseFuture;
+use base64;
 use chrono::{DateTime, Utc};
-use futures::future::done;
+use futures::future::{done, Either};
 use futures::prelude::*;
+use http::request::Builder;
 use hyper::{Method, StatusCode};
+use md5;
 use std::marker::PhantomData;
+use std::path::Path;
+use tokio_fs::File;
+use tokio_io::io::{flush, write_all};
+
+/// Longest range the service computes the MD5 of.
+pub const MAX_RANGE_CONTENT_MD5_LENGTH: u64 = 4 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>
@@ -31,7 +43,10 @@ where
     snapshot: Option<DateTime<Utc>>,
     timeout: Option<u64>,
     range: Option<&'a Range>,
+    range_header: Option<&'a str>,
+    range_content_md5: Option<bool>,
     lease_id: Option<&'a LeaseId>,
+    if_since_condition: Option<IfSinceCondition>,
     client_request_id: Option<&'a str>,
 }
 
@@ -47,7 +62,10 @@ impl<'a> GetBlobBuilder<'a, No, No> {
             snapshot: None,
             timeout: None,
             range: None,
+            range_header: None,
+            range_content_md5: None,
             lease_id: None,
+            if_since_condition: None,
             client_request_id: None,
         }
     }
@@ -117,6 +135,28 @@ where
     }
 }
 
+impl<'a, ContainerNameSet, BlobNameSet> RangeHeaderOption<'a> for GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn range_header(&self) -> Option<&'a str> {
+        self.range_header
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> RangeContentMD5Option for GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn range_content_md5(&self) -> Option<bool> {
+        self.range_content_md5
+    }
+}
+
 impl<'a, ContainerNameSet, BlobNameSet> LeaseIdOption<'a> for GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>
 where
     ContainerNameSet: ToAssign,
@@ -128,6 +168,17 @@ where
     }
 }
 
+impl<'a, ContainerNameSet, BlobNameSet> IfSinceConditionOption for GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn if_since_condition(&self) -> Option<IfSinceCondition> {
+        self.if_since_condition.clone()
+    }
+}
+
 impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdOption<'a> for GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>
 where
     ContainerNameSet: ToAssign,
@@ -157,7 +208,10 @@ where
             snapshot: self.snapshot,
             timeout: self.timeout,
             range: self.range,
+            range_header: self.range_header,
+            range_content_md5: self.range_content_md5,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -181,7 +235,10 @@ where
             snapshot: self.snapshot,
             timeout: self.timeout,
             range: self.range,
+            range_header: self.range_header,
+            range_content_md5: self.range_content_md5,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -205,7 +262,10 @@ where
             snapshot: Some(snapshot),
             timeout: self.timeout,
             range: self.range,
+            range_header: self.range_header,
+            range_content_md5: self.range_content_md5,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -229,7 +289,10 @@ where
             snapshot: self.snapshot,
             timeout: Some(timeout),
             range: self.range,
+            range_header: self.range_header,
+            range_content_md5: self.range_content_md5,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -253,7 +316,64 @@ where
             snapshot: self.snapshot,
             timeout: self.timeout,
             range: Some(range),
+            range_header: self.range_header,
+            range_content_md5: self.range_content_md5,
+            lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> RangeHeaderSupport<'a> for GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_range_header(self, range_header: &'a str) -> Self::O {
+        GetBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            range: self.range,
+            range_header: Some(range_header),
+            range_content_md5: self.range_content_md5,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> RangeContentMD5Support for GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_range_content_md5(self, range_content_md5: bool) -> Self::O {
+        GetBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            range: self.range,
+            range_header: self.range_header,
+            range_content_md5: Some(range_content_md5),
+            lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -277,7 +397,37 @@ where
             snapshot: self.snapshot,
             timeout: self.timeout,
             range: self.range,
+            range_header: self.range_header,
+            range_content_md5: self.range_content_md5,
             lease_id: Some(lease_id),
+            if_since_condition: self.if_since_condition,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> IfSinceConditionSupport for GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = GetBlobBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_if_since_condition(self, if_since_condition: IfSinceCondition) -> Self::O {
+        GetBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            range: self.range,
+            range_header: self.range_header,
+            range_content_md5: self.range_content_md5,
+            lease_id: self.lease_id,
+            if_since_condition: Some(if_since_condition),
             client_request_id: self.client_request_id,
         }
     }
@@ -301,7 +451,10 @@ where
             snapshot: self.snapshot,
             timeout: self.timeout,
             range: self.range,
+            range_header: self.range_header,
+            range_content_md5: self.range_content_md5,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: Some(client_request_id),
         }
     }
@@ -315,58 +468,440 @@ where
 {}
 
 impl<'a> GetBlobBuilder<'a, Yes, Yes> {
-    #[inline]
-    pub fn finalize(self) -> impl Future<Item = GetBlobResponse, Error = AzureError> {
-        let container_name = self.container_name().to_owned();
-        let blob_name = self.blob_name().to_owned();
-        let snapshot_time = self.snapshot();
-
+    fn perform(&self) -> Result<CorrelatedResponseFuture, AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}/{}",
+            "https://{}.blob.{}/{}/{}",
             self.client().account(),
-            &container_name,
-            &blob_name
+            self.client().endpoint_suffix(),
+            self.container_name(),
+            self.blob_name()
         );
 
         let mut f_first = true;
-        if let Some(snapshot) = SnapshotOption::to_uri_parameter(&self) {
+        if let Some(snapshot) = SnapshotOption::to_uri_parameter(self) {
             uri = format!("{}?{}", uri, snapshot);
             f_first = false;
         }
-        if let Some(timeout) = TimeoutOption::to_uri_parameter(&self) {
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(self) {
             uri = format!("{}{}{}", uri, if f_first { "?" } else { "&" }, timeout);
         }
 
         trace!("uri == {:?}", uri);
 
-        let req = self.client().perform_request(
+        self.check_ranges()?;
+        let range_content_md5 = self.send_range_content_md5()?;
+
+        self.client().perform_request(
             &uri,
             Method::GET,
-            |ref mut request| {
-                if let Some(r) = self.range() {
-                    LeaseIdOption::add_header(&self, request);
-                    RangeOption::add_header(&self, request);
-
-                    if r.len() <= 4 * 1024 * 1024 {
-                        request.header_static(RANGE_GET_CONTENT_MD5, "true");
-                    }
-                }
-            },
+            |ref mut request| self.add_headers(request, range_content_md5),
             None,
-        );
+        )
+    }
+
+    fn add_headers(&self, request: &mut Builder, range_content_md5: bool) {
+        IfSinceConditionOption::add_header(self, request);
+        if self.ranged() {
+            LeaseIdOption::add_header(self, request);
+            RangeOption::add_header(self, request);
+            RangeHeaderOption::add_header(self, request);
+
+            if range_content_md5 {
+                request.header_static(RANGE_GET_CONTENT_MD5, "true");
+            }
+        }
+    }
+
+    fn ranged(&self) -> bool {
+        self.range().is_some() || self.range_header().is_some()
+    }
+
+    // The ranges are checked before sending: the service answers a range it
+    // does not understand with the whole blob.
+    fn check_ranges(&self) -> Result<(), AzureError> {
+        if let Some(range) = self.range() {
+            if range.end < range.start {
+                return Err(AzureError::InputParametersError(format!(
+                    "the range {} ends before it starts",
+                    range
+                )));
+            }
+        }
+
+        match (self.range(), self.range_header()) {
+            (Some(_), Some(_)) => Err(AzureError::InputParametersError(
+                "a range and a range header cannot be both specified".to_owned(),
+            )),
+            (None, Some(range_header)) => parse_range_header(range_header).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
 
-        let expected_status_code = if self.range().is_some() {
+    // Unless told otherwise the MD5 of the range is asked for whenever the
+    // service can compute it. Asking for it explicitly on a range it cannot
+    // hash is an error rather than a silent no-op.
+    fn send_range_content_md5(&self) -> Result<bool, AzureError> {
+        // both ends of the range are inclusive
+        let length = match (self.range(), self.range_header()) {
+            (Some(range), _) => Some(range.end - range.start + 1),
+            (None, Some(range_header)) => match parse_range_header(range_header)? {
+                (start, Some(end)) => Some(end - start + 1),
+                (_, None) => None,
+            },
+            (None, None) => None,
+        };
+
+        match (self.range_content_md5(), length) {
+            (Some(true), None) => Err(AzureError::InputParametersError(
+                "the content MD5 of a range can only be requested along with a range that has an end".to_owned(),
+            )),
+            (Some(true), Some(length)) if length > MAX_RANGE_CONTENT_MD5_LENGTH => Err(AzureError::InputParametersError(format!(
+                "the content MD5 can only be requested for ranges up to {} bytes, {} requested",
+                MAX_RANGE_CONTENT_MD5_LENGTH, length
+            ))),
+            (Some(send), _) => Ok(send),
+            (None, Some(length)) => Ok(length <= MAX_RANGE_CONTENT_MD5_LENGTH),
+            (None, None) => Ok(false),
+        }
+    }
+
+    fn expected_status_code(&self) -> StatusCode {
+        if self.ranged() {
             StatusCode::PARTIAL_CONTENT
         } else {
             StatusCode::OK
-        };
+        }
+    }
+
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = GetBlobResponse, Error = AzureError> {
+        let container_name = self.container_name().to_owned();
+        let blob_name = self.blob_name().to_owned();
+        let snapshot_time = self.snapshot();
+        let expected_status_code = self.expected_status_code();
+        let ranged = self.ranged();
 
-        done(req)
+        done(self.perform())
             .from_err()
             .and_then(move |future_response| check_status_extract_headers_and_body(future_response, expected_status_code))
+            .map_err(conditional_error)
             .and_then(move |(headers, body)| {
                 done(Blob::from_headers(&blob_name, &container_name, snapshot_time, &headers))
-                    .and_then(move |blob| done(GetBlobResponse::from_response(&headers, blob, &body)))
+                    .and_then(move |blob| done(GetBlobResponse::from_response(&headers, blob, &body, ranged)))
             })
     }
+
+    /// Streams the blob (or its range) into the file at `path`, created or
+    /// truncated, without holding it in memory. The length written is
+    /// checked against `Content-Length` and, when the service returns one,
+    /// its MD5 is checked against `Content-MD5`. Returns the properties of
+    /// the blob.
+    ///
+    /// The file is written with tokio-fs, so the future must run on the
+    /// tokio threadpool runtime.
+    pub fn download_to_file<P: AsRef<Path>>(self, path: P) -> impl Future<Item = Blob, Error = AzureError> {
+        let container_name = self.container_name().to_owned();
+        let blob_name = self.blob_name().to_owned();
+        let snapshot_time = self.snapshot();
+        let expected_status_code = self.expected_status_code();
+        let file = File::create(path.as_ref().to_owned()).from_err();
+
+        done(self.perform()).join(file).and_then(move |(future_response, file)| {
+            future_response.from_err().and_then(move |response| {
+                if response.status() != expected_status_code {
+                    let status = response.status();
+                    let error = response.into_body().concat2().from_err().and_then(move |body| {
+                        Err(conditional_error(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(
+                            expected_status_code,
+                            status,
+                            &String::from_utf8_lossy(&body),
+                        ))))
+                    });
+                    return Either::A(error);
+                }
+
+                let (head, body) = response.into_parts();
+                Either::B(
+                    done(Blob::from_headers(&blob_name, &container_name, snapshot_time, &head.headers)).and_then(move |blob| {
+                        let expected_md5 = blob.content_md5.clone();
+                        write_body_to_file(body.from_err(), file, blob.content_length, expected_md5).map(move |_| blob)
+                    }),
+                )
+            })
+        })
+    }
+}
+
+// The start and the end, if any, of a range header as browsers send it:
+// `bytes=1024-2047` or `bytes=1024-`. The service supports neither suffix
+// ranges nor several ranges at once.
+fn parse_range_header(range_header: &str) -> Result<(u64, Option<u64>), AzureError> {
+    let invalid = || {
+        AzureError::InputParametersError(format!(
+            "invalid range header {:?}, expected bytes=<start>-<end> or bytes=<start>-",
+            range_header
+        ))
+    };
+
+    let mut unit = range_header.splitn(2, '=');
+    let range = match (unit.next(), unit.next()) {
+        (Some("bytes"), Some(range)) => range,
+        _ => return Err(invalid()),
+    };
+    let mut bounds = range.splitn(2, '-');
+    let start = bounds.next().unwrap_or("").parse::<u64>().map_err(|_| invalid())?;
+    let end = match bounds.next() {
+        Some("") => None,
+        Some(end) => Some(end.parse::<u64>().map_err(|_| invalid())?),
+        None => return Err(invalid()),
+    };
+
+    match end {
+        Some(end) if end < start => Err(invalid()),
+        end => Ok((start, end)),
+    }
+}
+
+// Writes the chunks of `body` to `file` as they arrive, each write going
+// through the blocking section of the tokio threadpool.
+fn write_body_to_file<S>(
+    body: S,
+    file: File,
+    expected_length: u64,
+    expected_md5: Option<String>,
+) -> impl Future<Item = u64, Error = AzureError>
+where
+    S: Stream<Error = AzureError>,
+    S::Item: AsRef<[u8]>,
+{
+    body.fold((file, 0, md5::Context::new()), |(file, length, mut md5), chunk| {
+        md5.consume(chunk.as_ref());
+        let length = length + chunk.as_ref().len() as u64;
+        write_all(file, chunk)
+            .map_err(AzureError::from)
+            .map(move |(file, _)| (file, length, md5))
+    })
+    .and_then(|(file, length, md5)| flush(file).from_err().map(move |_| (length, md5)))
+    .and_then(move |(length, md5)| {
+        if length != expected_length {
+            return Err(AzureError::GenericErrorWithText(format!(
+                "downloaded {} bytes instead of the {} of Content-Length",
+                length, expected_length
+            )));
+        }
+        if let Some(expected_md5) = expected_md5 {
+            let md5 = base64::encode(&md5.compute()[..]);
+            if md5 != expected_md5 {
+                return Err(AzureError::GenericErrorWithText(format!(
+                    "the MD5 of the downloaded data is {} instead of the {} of Content-MD5",
+                    md5, expected_md5
+                )));
+            }
+        }
+        Ok(length)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use azure::core::test_server::serve;
+    use azure::storage::client::Blob as BlobTrait;
+    use futures::stream;
+    use std::env;
+    use std::fs;
+    use tokio::runtime::Runtime;
+
+    fn range_content_md5_header(range: &Range, range_content_md5: Option<bool>) -> Result<Option<String>, AzureError> {
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap();
+        let mut builder = client
+            .get_blob()
+            .with_container_name("logs")
+            .with_blob_name("2020/01.txt")
+            .with_range(range);
+        if let Some(range_content_md5) = range_content_md5 {
+            builder = builder.with_range_content_md5(range_content_md5);
+        }
+
+        let send = builder.send_range_content_md5()?;
+        let mut request = ::http::Request::builder();
+        builder.add_headers(&mut request, send);
+        let request = request.body(()).unwrap();
+        Ok(request
+            .headers()
+            .get(RANGE_GET_CONTENT_MD5)
+            .map(|value| value.to_str().unwrap().to_owned()))
+    }
+
+    #[test]
+    fn range_content_md5() {
+        let small = Range::new(0, 1023);
+        let largest = Range::new(0, MAX_RANGE_CONTENT_MD5_LENGTH - 1);
+        let large = Range::new(0, MAX_RANGE_CONTENT_MD5_LENGTH);
+
+        assert_eq!(range_content_md5_header(&small, None).unwrap(), Some("true".to_owned()));
+        assert_eq!(range_content_md5_header(&small, Some(true)).unwrap(), Some("true".to_owned()));
+        assert_eq!(range_content_md5_header(&largest, Some(true)).unwrap(), Some("true".to_owned()));
+        assert_eq!(range_content_md5_header(&small, Some(false)).unwrap(), None);
+        assert_eq!(range_content_md5_header(&large, None).unwrap(), None);
+
+        match range_content_md5_header(&large, Some(true)) {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap();
+        let without_range = client
+            .get_blob()
+            .with_container_name("logs")
+            .with_blob_name("2020/01.txt")
+            .with_range_content_md5(true);
+        match without_range.send_range_content_md5() {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn download_small_blob_to_file() {
+        let path = env::temp_dir().join(format!("azure_sdk_download_{}.txt", ::uuid::Uuid::new_v4()));
+        let chunks = vec![&b"hello "[..], &b"blob "[..], &b"world"[..]];
+        let md5 = base64::encode(&md5::compute(b"hello blob world")[..]);
+        let mut runtime = Runtime::new().unwrap();
+        let write = |expected_length, expected_md5| {
+            let chunks = chunks.clone();
+            File::create(path.clone())
+                .from_err()
+                .and_then(move |file| write_body_to_file(stream::iter_ok(chunks), file, expected_length, expected_md5))
+        };
+
+        assert_eq!(runtime.block_on(write(16, Some(md5))).unwrap(), 16);
+        assert_eq!(fs::read(&path).unwrap(), b"hello blob world");
+
+        match runtime.block_on(write(20, None)) {
+            Err(AzureError::GenericErrorWithText(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        let corrupt = base64::encode(&md5::compute(b"hello")[..]);
+        match runtime.block_on(write(16, Some(corrupt))) {
+            Err(AzureError::GenericErrorWithText(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    fn blob(request: &str) -> (&'static str, String, String) {
+        assert!(
+            request.starts_with("GET /logs/2020/01.txt HTTP/1.1\r\n"),
+            "unexpected request {}",
+            request
+        );
+        let body = "hello blob world";
+        let headers = format!(
+            "Content-Type: text/plain\r\nContent-MD5: {}\r\nETag: \"0x8D7\"\r\nLast-Modified: Mon, 06 Jan 2020 10:00:00 GMT\r\n\
+             x-ms-blob-type: BlockBlob\r\nx-ms-creation-time: Mon, 06 Jan 2020 09:00:00 GMT\r\nx-ms-lease-status: unlocked\r\n\
+             x-ms-lease-state: available\r\nx-ms-server-encrypted: true\r\n",
+            base64::encode(&md5::compute(body)[..])
+        );
+        ("200 OK", headers, body.to_owned())
+    }
+
+    #[test]
+    fn download_to_file() {
+        let path = env::temp_dir().join(format!("azure_sdk_download_{}.txt", ::uuid::Uuid::new_v4()));
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap().with_blob_endpoint(serve(blob));
+        let download = client
+            .get_blob()
+            .with_container_name("logs")
+            .with_blob_name("2020/01.txt")
+            .download_to_file(path.clone());
+
+        let blob = Runtime::new().unwrap().block_on(download).unwrap();
+        assert_eq!(blob.name, "2020/01.txt");
+        assert_eq!(blob.content_length, 16);
+        assert_eq!(fs::read(&path).unwrap(), b"hello blob world");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn range_header() {
+        assert_eq!(parse_range_header("bytes=0-1023").unwrap(), (0, Some(1023)));
+        assert_eq!(parse_range_header("bytes=1024-").unwrap(), (1024, None));
+        for invalid in &["bytes=-500", "bytes=0-99,200-299", "bytes=100-99", "items=0-99", "bytes=0"] {
+            match parse_range_header(invalid) {
+                Err(AzureError::InputParametersError(_)) => {}
+                r => panic!("unexpected result {:?} for {}", r, invalid),
+            }
+        }
+
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap();
+        let builder = client
+            .get_blob()
+            .with_container_name("videos")
+            .with_blob_name("intro.mp4")
+            .with_range_header("bytes=1024-");
+        builder.check_ranges().unwrap();
+        assert_eq!(builder.expected_status_code(), StatusCode::PARTIAL_CONTENT);
+        let send = builder.send_range_content_md5().unwrap();
+        assert!(!send);
+        let mut request = ::http::Request::builder();
+        builder.add_headers(&mut request, send);
+        let request = request.body(()).unwrap();
+        assert_eq!(request.headers()["range"], "bytes=1024-");
+
+        let range = Range::new(0, 1023);
+        match builder.with_range(&range).check_ranges() {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        let backwards = Range::new(1023, 0);
+        let builder = client
+            .get_blob()
+            .with_container_name("videos")
+            .with_blob_name("intro.mp4")
+            .with_range(&backwards);
+        match builder.check_ranges() {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn partial_download() {
+        let mut headers = ::http::HeaderMap::new();
+        for (name, value) in &[
+            ("x-ms-creation-time", "Mon, 06 Jan 2020 10:00:00 GMT"),
+            ("last-modified", "Mon, 06 Jan 2020 10:00:00 GMT"),
+            ("etag", "\"0x8D7929F9D2F1C4B\""),
+            ("content-length", "1024"),
+            ("content-type", "video/mp4"),
+            ("content-range", "bytes 1024-2047/1048576"),
+            ("content-disposition", "inline; filename=\"intro.mp4\""),
+            ("x-ms-blob-type", "BlockBlob"),
+            ("x-ms-lease-status", "unlocked"),
+            ("x-ms-lease-state", "available"),
+            ("x-ms-server-encrypted", "true"),
+            ("x-ms-request-id", "f4b8b1e8-601e-0036-7a62-c4e7fa000000"),
+            ("date", "Mon, 06 Jan 2020 10:00:01 GMT"),
+        ] {
+            headers.insert(*name, value.parse().unwrap());
+        }
+
+        let blob = Blob::from_headers("intro.mp4", "videos", None, &headers).unwrap();
+        let response = GetBlobResponse::from_response(&headers, blob, &[0; 1024], true).unwrap();
+        assert!(response.partial);
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_range = response.content_range.unwrap();
+        assert_eq!((content_range.start, content_range.end), (1024, 2047));
+        assert_eq!(content_range.total_length, Some(1_048_576));
+
+        let browser_headers = response.browser_headers().unwrap();
+        assert_eq!(browser_headers["content-range"], "bytes 1024-2047/1048576");
+        assert_eq!(browser_headers["content-length"], "1024");
+        assert_eq!(browser_headers["accept-ranges"], "bytes");
+        assert_eq!(browser_headers["content-disposition"], "inline; filename=\"intro.mp4\"");
+        assert_eq!(browser_headers["last-modified"], "Mon, 06 Jan 2020 10:00:00 GMT");
+    }
 }
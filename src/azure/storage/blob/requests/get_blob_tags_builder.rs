@@ -0,0 +1,262 @@
+use azure::core::errors::{check_status_extract_headers_and_body, AzureError};
+use azure::core::lease::LeaseId;
+use azure::core::{
+    BlobNameRequired, BlobNameSupport, ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, ContainerNameRequired,
+    ContainerNameSupport, LeaseIdOption, LeaseIdSupport, No, TimeoutOption, TimeoutSupport, ToAssign, Yes,
+};
+use azure::storage::blob::responses::GetBlobTagsResponse;
+use azure::storage::blob::BLOB_TAGS_VERSION;
+use azure::storage::client::Client;
+use azure::storage::rest_client::HEADER_VERSION;
+use futures::future::done;
+use futures::prelude::*;
+use hyper::{Method, StatusCode};
+use std::marker::PhantomData;
+
+/// Reads the index tags of a blob.
+#[derive(Debug, Clone)]
+pub struct GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    client: &'a Client,
+    p_container_name: PhantomData<ContainerNameSet>,
+    p_blob_name: PhantomData<BlobNameSet>,
+    container_name: Option<&'a str>,
+    blob_name: Option<&'a str>,
+    timeout: Option<u64>,
+    lease_id: Option<&'a LeaseId>,
+    client_request_id: Option<&'a str>,
+}
+
+impl<'a> GetBlobTagsBuilder<'a, No, No> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> GetBlobTagsBuilder<'a, No, No> {
+        GetBlobTagsBuilder {
+            client,
+            p_container_name: PhantomData {},
+            container_name: None,
+            p_blob_name: PhantomData {},
+            blob_name: None,
+            timeout: None,
+            lease_id: None,
+            client_request_id: None,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequired<'a> for GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a, BlobNameSet> ContainerNameRequired<'a> for GetBlobTagsBuilder<'a, Yes, BlobNameSet>
+where
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn container_name(&self) -> &'a str {
+        self.container_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet> BlobNameRequired<'a> for GetBlobTagsBuilder<'a, ContainerNameSet, Yes>
+where
+    ContainerNameSet: ToAssign,
+{
+    #[inline]
+    fn blob_name(&self) -> &'a str {
+        self.blob_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TimeoutOption for GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> LeaseIdOption<'a> for GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn lease_id(&self) -> Option<&'a LeaseId> {
+        self.lease_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdOption<'a> for GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContainerNameSupport<'a> for GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = GetBlobTagsBuilder<'a, Yes, BlobNameSet>;
+
+    #[inline]
+    fn with_container_name(self, container_name: &'a str) -> Self::O {
+        GetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: Some(container_name),
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> BlobNameSupport<'a> for GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = GetBlobTagsBuilder<'a, ContainerNameSet, Yes>;
+
+    #[inline]
+    fn with_blob_name(self, blob_name: &'a str) -> Self::O {
+        GetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: Some(blob_name),
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TimeoutSupport for GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        GetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: Some(timeout),
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> LeaseIdSupport<'a> for GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_lease_id(self, lease_id: &'a LeaseId) -> Self::O {
+        GetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            lease_id: Some(lease_id),
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdSupport<'a> for GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        GetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: Some(client_request_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a, ContainerNameSet, BlobNameSet> GetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{}
+
+impl<'a> GetBlobTagsBuilder<'a, Yes, Yes> {
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = GetBlobTagsResponse, Error = AzureError> {
+        let mut uri = format!(
+            "https://{}.blob.{}/{}/{}?comp=tags",
+            self.client().account(),
+            self.client().endpoint_suffix(),
+            self.container_name(),
+            self.blob_name()
+        );
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, timeout);
+        }
+
+        trace!("uri == {:?}", uri);
+
+        let req = self.client().perform_request(
+            &uri,
+            Method::GET,
+            |ref mut request| {
+                request.header(HEADER_VERSION, BLOB_TAGS_VERSION);
+                LeaseIdOption::add_header(&self, request);
+                ClientRequestIdOption::add_header(&self, request);
+            },
+            None,
+        );
+
+        done(req)
+            .from_err()
+            .and_then(move |response| check_status_extract_headers_and_body(response, StatusCode::OK))
+            .and_then(move |(headers, body)| done(GetBlobTagsResponse::from_response(&headers, &body)))
+    }
+}
@@ -303,8 +303,9 @@ impl<'a> GetBlockListBuilder<'a, Yes, Yes, Yes> {
     #[inline]
     pub fn finalize(self) -> impl Future<Item = GetBlockListResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}/{}?comp=blocklist",
+            "https://{}.blob.{}/{}/{}?comp=blocklist",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name(),
             self.blob_name()
         );
@@ -515,35 +515,39 @@ impl<'a, ContainerNameSet> ListBlobBuilder<'a, ContainerNameSet> where Container
 impl<'a> IncludeListOptions for ListBlobBuilder<'a, Yes> {}
 
 impl<'a> ListBlobBuilder<'a, Yes> {
-    #[inline]
-    pub fn finalize(self) -> impl Future<Item = ListBlobsResponse, Error = AzureError> {
-        // we create a copy to move into the future's closure.
-        // We need to do this since the closure only accepts
-        // 'static lifetimes.
-        let container_name = self.container_name().to_owned();
-
+    pub(crate) fn uri(&self) -> String {
         let mut uri = format!(
-            "https://{}.blob.core.windows.\
-             net/{}?restype=container&comp=list",
+            "https://{}.blob.{}/{}?restype=container&comp=list",
             self.client().account(),
-            container_name
+            self.client().endpoint_suffix(),
+            self.container_name()
         );
 
-        if let Some(mr) = MaxResultsOption::to_uri_parameter(&self) {
+        if let Some(mr) = MaxResultsOption::to_uri_parameter(self) {
             uri = format!("{}&{}", uri, mr);
         }
-        if let Some(mr) = NextMarkerOption::to_uri_parameter(&self) {
+        if let Some(mr) = NextMarkerOption::to_uri_parameter(self) {
             uri = format!("{}&{}", uri, mr);
         }
-        if let Some(mr) = TimeoutOption::to_uri_parameter(&self) {
+        if let Some(mr) = TimeoutOption::to_uri_parameter(self) {
             uri = format!("{}&{}", uri, mr);
         }
-        if let Some(mr) = PrefixOption::to_uri_parameter(&self) {
+        if let Some(mr) = PrefixOption::to_uri_parameter(self) {
             uri = format!("{}&{}", uri, mr);
         }
-        if let Some(mr) = IncludeListOptions::to_uri_parameter(&self) {
+        if let Some(mr) = IncludeListOptions::to_uri_parameter(self) {
             uri = format!("{}&{}", uri, mr);
         }
+        uri
+    }
+
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = ListBlobsResponse, Error = AzureError> {
+        // we create a copy to move into the future's closure.
+        // We need to do this since the closure only accepts
+        // 'static lifetimes.
+        let container_name = self.container_name().to_owned();
+        let uri = self.uri();
 
         let req = self.client().perform_request(&uri, Method::GET, |_| {}, None);
 
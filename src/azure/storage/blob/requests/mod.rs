@@ -1,5 +1,10 @@
+mod blob_batch_builder;
+mod blob_exists_builder;
 mod clear_page_builder;
+mod find_blobs_by_tags_builder;
+mod get_account_information_builder;
 mod get_blob_builder;
+mod get_blob_tags_builder;
 mod get_block_list_builder;
 mod list_blobs_builder;
 mod put_append_blob_builder;
@@ -7,9 +12,19 @@ mod put_block_blob_builder;
 mod put_block_builder;
 mod put_block_list_builder;
 mod put_page_blob_builder;
+mod query_blob_builder;
+mod set_blob_properties_builder;
+mod set_blob_tags_builder;
+mod snapshot_blob_builder;
+mod undelete_blob_builder;
 mod update_page_builder;
+pub use self::blob_batch_builder::BlobBatchBuilder;
+pub use self::blob_exists_builder::BlobExistsBuilder;
 pub use self::clear_page_builder::ClearPageBuilder;
+pub use self::find_blobs_by_tags_builder::FindBlobsByTagsBuilder;
+pub use self::get_account_information_builder::GetAccountInformationBuilder;
 pub use self::get_blob_builder::GetBlobBuilder;
+pub use self::get_blob_tags_builder::GetBlobTagsBuilder;
 pub use self::get_block_list_builder::GetBlockListBuilder;
 pub use self::list_blobs_builder::ListBlobBuilder;
 pub use self::put_append_blob_builder::PutAppendBlobBuilder;
@@ -17,4 +32,9 @@ pub use self::put_block_blob_builder::PutBlockBlobBuilder;
 pub use self::put_block_builder::PutBlockBuilder;
 pub use self::put_block_list_builder::PutBlockListBuilder;
 pub use self::put_page_blob_builder::PutPageBlobBuilder;
+pub use self::query_blob_builder::QueryBlobBuilder;
+pub use self::set_blob_properties_builder::SetBlobPropertiesBuilder;
+pub use self::set_blob_tags_builder::SetBlobTagsBuilder;
+pub use self::snapshot_blob_builder::SnapshotBlobBuilder;
+pub use self::undelete_blob_builder::UndeleteBlobBuilder;
 pub use self::update_page_builder::UpdatePageBuilder;
@@ -8,6 +8,7 @@ use azure::core::{
     LeaseIdOption, LeaseIdSupport, MetadataOption, MetadataSupport, No, TimeoutOption, TimeoutSupport, ToAssign, Yes,
 };
 use azure::storage::blob::responses::PutBlobResponse;
+use azure::storage::blob::BlobType;
 use azure::storage::client::Client;
 use futures::future::{done, ok};
 use futures::prelude::*;
@@ -508,8 +509,9 @@ impl<'a> PutAppendBlobBuilder<'a, Yes, Yes> {
     #[inline]
     pub fn finalize(self) -> impl Future<Item = PutBlobResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}/{}",
+            "https://{}.blob.{}/{}/{}",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name(),
             self.blob_name()
         );
@@ -529,7 +531,7 @@ impl<'a> PutAppendBlobBuilder<'a, Yes, Yes> {
                 CacheControlOption::add_header(&self, request);
                 ContentDispositionOption::add_header(&self, request);
                 MetadataOption::add_header(&self, request);
-                request.header(BLOB_TYPE, "AppendBlob");
+                request.header(BLOB_TYPE, BlobType::AppendBlob.as_ref());
                 LeaseIdOption::add_header(&self, request);
                 ClientRequestIdOption::add_header(&self, request);
             },
@@ -1,14 +1,16 @@
-use azure::core::errors::{check_status_extract_headers_and_body, AzureError};
+use azure::core::errors::{check_status_extract_headers_and_body, conditional_error, AzureError};
 use azure::core::headers::BLOB_TYPE;
 use azure::core::lease::LeaseId;
+use azure::core::modify_conditions::IfSinceCondition;
 use azure::core::{
     BlobNameRequired, BlobNameSupport, BodyRequired, BodySupport, CacheControlOption, CacheControlSupport, ClientRequestIdOption,
     ClientRequestIdSupport, ClientRequired, ContainerNameRequired, ContainerNameSupport, ContentDispositionOption,
     ContentDispositionSupport, ContentEncodingOption, ContentEncodingSupport, ContentLanguageOption, ContentLanguageSupport,
-    ContentMD5Option, ContentMD5Support, ContentTypeOption, ContentTypeSupport, LeaseIdOption, LeaseIdSupport, MetadataOption,
-    MetadataSupport, No, TimeoutOption, TimeoutSupport, ToAssign, Yes,
+    ContentMD5Option, ContentMD5Support, ContentTypeOption, ContentTypeSupport, IfSinceConditionOption, IfSinceConditionSupport,
+    LeaseIdOption, LeaseIdSupport, MetadataOption, MetadataSupport, No, TimeoutOption, TimeoutSupport, ToAssign, Yes,
 };
 use azure::storage::blob::responses::PutBlockBlobResponse;
+use azure::storage::blob::BlobType;
 use azure::storage::client::Client;
 use futures::future::{done, ok};
 use futures::prelude::*;
@@ -39,6 +41,7 @@ where
     content_disposition: Option<&'a str>,
     metadata: Option<&'a HashMap<&'a str, &'a str>>,
     lease_id: Option<&'a LeaseId>,
+    if_since_condition: Option<IfSinceCondition>,
     client_request_id: Option<&'a str>,
 }
 
@@ -62,6 +65,7 @@ impl<'a> PutBlockBlobBuilder<'a, No, No, No> {
             content_disposition: None,
             metadata: None,
             lease_id: None,
+            if_since_condition: None,
             client_request_id: None,
         }
     }
@@ -223,6 +227,18 @@ where
     }
 }
 
+impl<'a, ContainerNameSet, BlobNameSet, BodySet> IfSinceConditionOption for PutBlockBlobBuilder<'a, ContainerNameSet, BlobNameSet, BodySet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    BodySet: ToAssign,
+{
+    #[inline]
+    fn if_since_condition(&self) -> Option<IfSinceCondition> {
+        self.if_since_condition.clone()
+    }
+}
+
 impl<'a, ContainerNameSet, BlobNameSet, BodySet> ClientRequestIdOption<'a>
     for PutBlockBlobBuilder<'a, ContainerNameSet, BlobNameSet, BodySet>
 where
@@ -264,6 +280,7 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -296,6 +313,7 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -328,6 +346,7 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -360,6 +379,7 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -392,6 +412,7 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -425,6 +446,7 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -458,6 +480,7 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -490,6 +513,7 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -522,6 +546,7 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -555,6 +580,7 @@ where
             content_disposition: Some(content_disposition),
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -587,6 +613,7 @@ where
             content_disposition: self.content_disposition,
             metadata: Some(metadata),
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: self.client_request_id,
         }
     }
@@ -619,6 +646,40 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: Some(lease_id),
+            if_since_condition: self.if_since_condition,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, BodySet> IfSinceConditionSupport for PutBlockBlobBuilder<'a, ContainerNameSet, BlobNameSet, BodySet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    BodySet: ToAssign,
+{
+    type O = PutBlockBlobBuilder<'a, ContainerNameSet, BlobNameSet, BodySet>;
+
+    #[inline]
+    fn with_if_since_condition(self, if_since_condition: IfSinceCondition) -> Self::O {
+        PutBlockBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_body: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            body: self.body,
+            timeout: self.timeout,
+            content_type: self.content_type,
+            content_encoding: self.content_encoding,
+            content_language: self.content_language,
+            cache_control: self.cache_control,
+            content_md5: self.content_md5,
+            content_disposition: self.content_disposition,
+            metadata: self.metadata,
+            lease_id: self.lease_id,
+            if_since_condition: Some(if_since_condition),
             client_request_id: self.client_request_id,
         }
     }
@@ -652,6 +713,7 @@ where
             content_disposition: self.content_disposition,
             metadata: self.metadata,
             lease_id: self.lease_id,
+            if_since_condition: self.if_since_condition,
             client_request_id: Some(client_request_id),
         }
     }
@@ -669,8 +731,9 @@ impl<'a> PutBlockBlobBuilder<'a, Yes, Yes, Yes> {
     #[inline]
     pub fn finalize(self) -> impl Future<Item = PutBlockBlobResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}/{}",
+            "https://{}.blob.{}/{}/{}",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name(),
             self.blob_name()
         );
@@ -691,8 +754,9 @@ impl<'a> PutBlockBlobBuilder<'a, Yes, Yes, Yes> {
                 CacheControlOption::add_header(&self, request);
                 ContentDispositionOption::add_header(&self, request);
                 MetadataOption::add_header(&self, request);
-                request.header(BLOB_TYPE, "BlockBlob");
+                request.header(BLOB_TYPE, BlobType::BlockBlob.as_ref());
                 LeaseIdOption::add_header(&self, request);
+                IfSinceConditionOption::add_header(&self, request);
                 ClientRequestIdOption::add_header(&self, request);
             },
             Some(self.body()),
@@ -701,6 +765,7 @@ impl<'a> PutBlockBlobBuilder<'a, Yes, Yes, Yes> {
         done(req)
             .from_err()
             .and_then(move |response| check_status_extract_headers_and_body(response, StatusCode::CREATED))
+            .map_err(conditional_error)
             .and_then(move |(headers, _body)| done(PutBlockBlobResponse::from_headers(&headers)).and_then(|pbbr| ok(pbbr)))
     }
 }
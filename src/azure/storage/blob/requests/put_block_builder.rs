@@ -427,8 +427,9 @@ impl<'a> PutBlockBuilder<'a, Yes, Yes, Yes, Yes> {
     #[inline]
     pub fn finalize(self) -> impl Future<Item = PutBlockResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}/{}?comp=block",
+            "https://{}.blob.{}/{}/{}?comp=block",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name(),
             self.blob_name()
         );
@@ -659,8 +659,9 @@ where
     #[inline]
     pub fn finalize(self) -> impl Future<Item = PutBlockListResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}/{}?comp=blocklist",
+            "https://{}.blob.{}/{}/{}?comp=blocklist",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name(),
             self.blob_name()
         );
@@ -9,6 +9,7 @@ use azure::core::{
     PageBlobLengthSupport, SequenceNumberOption, SequenceNumberSupport, TimeoutOption, TimeoutSupport, ToAssign, Yes,
 };
 use azure::storage::blob::responses::PutBlobResponse;
+use azure::storage::blob::BlobType;
 use azure::storage::client::Client;
 use futures::future::{done, ok};
 use futures::prelude::*;
@@ -751,8 +752,9 @@ impl<'a> PutPageBlobBuilder<'a, Yes, Yes, Yes> {
     #[inline]
     pub fn finalize(self) -> impl Future<Item = PutBlobResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}/{}",
+            "https://{}.blob.{}/{}/{}",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name(),
             self.blob_name()
         );
@@ -775,7 +777,7 @@ impl<'a> PutPageBlobBuilder<'a, Yes, Yes, Yes> {
                 CacheControlOption::add_header(&self, request);
                 ContentDispositionOption::add_header(&self, request);
                 MetadataOption::add_header(&self, request);
-                request.header(BLOB_TYPE, "PageBlob");
+                request.header(BLOB_TYPE, BlobType::PageBlob.as_ref());
                 LeaseIdOption::add_header(&self, request);
                 ClientRequestIdOption::add_header(&self, request);
             },
@@ -0,0 +1,519 @@
+use azure::core::errors::{AzureError, UnexpectedHTTPResult};
+use azure::core::lease::LeaseId;
+use azure::core::{
+    BlobNameRequired, BlobNameSupport, ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, ContainerNameRequired,
+    ContainerNameSupport, InputSerializationOption, InputSerializationSupport, LeaseIdOption, LeaseIdSupport, No,
+    OutputSerializationOption, OutputSerializationSupport, QueryExpressionRequired, QueryExpressionSupport, SnapshotOption,
+    SnapshotSupport, TimeoutOption, TimeoutSupport, ToAssign, Yes,
+};
+use azure::storage::blob::{blob_query_to_xml, BlobQueryDecoder, BlobQueryRecord, QuerySerialization, BLOB_QUERY_VERSION};
+use azure::storage::client::Client;
+use azure::storage::rest_client::HEADER_VERSION;
+use chrono::{DateTime, Utc};
+use futures::future::{done, Either};
+use futures::prelude::*;
+use futures::stream;
+use hyper::{Method, StatusCode};
+use std::marker::PhantomData;
+
+/// Runs a SQL expression over the contents of a CSV or JSON blob, so that
+/// only the matching records are sent back.
+#[derive(Debug, Clone)]
+pub struct QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    client: &'a Client,
+    p_container_name: PhantomData<ContainerNameSet>,
+    p_blob_name: PhantomData<BlobNameSet>,
+    p_query_expression: PhantomData<QueryExpressionSet>,
+    container_name: Option<&'a str>,
+    blob_name: Option<&'a str>,
+    query_expression: Option<&'a str>,
+    input_serialization: Option<&'a QuerySerialization>,
+    output_serialization: Option<&'a QuerySerialization>,
+    snapshot: Option<DateTime<Utc>>,
+    timeout: Option<u64>,
+    lease_id: Option<&'a LeaseId>,
+    client_request_id: Option<&'a str>,
+}
+
+impl<'a> QueryBlobBuilder<'a, No, No, No> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> QueryBlobBuilder<'a, No, No, No> {
+        QueryBlobBuilder {
+            client,
+            p_container_name: PhantomData {},
+            container_name: None,
+            p_blob_name: PhantomData {},
+            blob_name: None,
+            p_query_expression: PhantomData {},
+            query_expression: None,
+            input_serialization: None,
+            output_serialization: None,
+            snapshot: None,
+            timeout: None,
+            lease_id: None,
+            client_request_id: None,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> ClientRequired<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a, BlobNameSet, QueryExpressionSet> ContainerNameRequired<'a> for QueryBlobBuilder<'a, Yes, BlobNameSet, QueryExpressionSet>
+where
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    #[inline]
+    fn container_name(&self) -> &'a str {
+        self.container_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, QueryExpressionSet> BlobNameRequired<'a> for QueryBlobBuilder<'a, ContainerNameSet, Yes, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    #[inline]
+    fn blob_name(&self) -> &'a str {
+        self.blob_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> QueryExpressionRequired<'a> for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, Yes>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn query_expression(&self) -> &'a str {
+        self.query_expression.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> InputSerializationOption<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    #[inline]
+    fn input_serialization(&self) -> Option<&'a QuerySerialization> {
+        self.input_serialization
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> OutputSerializationOption<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    #[inline]
+    fn output_serialization(&self) -> Option<&'a QuerySerialization> {
+        self.output_serialization
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> SnapshotOption
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    #[inline]
+    fn snapshot(&self) -> Option<DateTime<Utc>> {
+        self.snapshot
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> TimeoutOption
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> LeaseIdOption<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    #[inline]
+    fn lease_id(&self) -> Option<&'a LeaseId> {
+        self.lease_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> ClientRequestIdOption<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> ContainerNameSupport<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    type O = QueryBlobBuilder<'a, Yes, BlobNameSet, QueryExpressionSet>;
+
+    #[inline]
+    fn with_container_name(self, container_name: &'a str) -> Self::O {
+        QueryBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_query_expression: PhantomData {},
+            container_name: Some(container_name),
+            blob_name: self.blob_name,
+            query_expression: self.query_expression,
+            input_serialization: self.input_serialization,
+            output_serialization: self.output_serialization,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> BlobNameSupport<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    type O = QueryBlobBuilder<'a, ContainerNameSet, Yes, QueryExpressionSet>;
+
+    #[inline]
+    fn with_blob_name(self, blob_name: &'a str) -> Self::O {
+        QueryBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_query_expression: PhantomData {},
+            container_name: self.container_name,
+            blob_name: Some(blob_name),
+            query_expression: self.query_expression,
+            input_serialization: self.input_serialization,
+            output_serialization: self.output_serialization,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> QueryExpressionSupport<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    type O = QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, Yes>;
+
+    #[inline]
+    fn with_query_expression(self, query_expression: &'a str) -> Self::O {
+        QueryBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_query_expression: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            query_expression: Some(query_expression),
+            input_serialization: self.input_serialization,
+            output_serialization: self.output_serialization,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> InputSerializationSupport<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    type O = QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>;
+
+    #[inline]
+    fn with_input_serialization(self, input_serialization: &'a QuerySerialization) -> Self::O {
+        QueryBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_query_expression: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            query_expression: self.query_expression,
+            input_serialization: Some(input_serialization),
+            output_serialization: self.output_serialization,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> OutputSerializationSupport<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    type O = QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>;
+
+    #[inline]
+    fn with_output_serialization(self, output_serialization: &'a QuerySerialization) -> Self::O {
+        QueryBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_query_expression: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            query_expression: self.query_expression,
+            input_serialization: self.input_serialization,
+            output_serialization: Some(output_serialization),
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> SnapshotSupport
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    type O = QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>;
+
+    #[inline]
+    fn with_snapshot(self, snapshot: DateTime<Utc>) -> Self::O {
+        QueryBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_query_expression: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            query_expression: self.query_expression,
+            input_serialization: self.input_serialization,
+            output_serialization: self.output_serialization,
+            snapshot: Some(snapshot),
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> TimeoutSupport
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    type O = QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        QueryBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_query_expression: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            query_expression: self.query_expression,
+            input_serialization: self.input_serialization,
+            output_serialization: self.output_serialization,
+            snapshot: self.snapshot,
+            timeout: Some(timeout),
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> LeaseIdSupport<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    type O = QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>;
+
+    #[inline]
+    fn with_lease_id(self, lease_id: &'a LeaseId) -> Self::O {
+        QueryBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_query_expression: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            query_expression: self.query_expression,
+            input_serialization: self.input_serialization,
+            output_serialization: self.output_serialization,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: Some(lease_id),
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> ClientRequestIdSupport<'a>
+    for QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{
+    type O = QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        QueryBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_query_expression: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            query_expression: self.query_expression,
+            input_serialization: self.input_serialization,
+            output_serialization: self.output_serialization,
+            snapshot: self.snapshot,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: Some(client_request_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet> QueryBlobBuilder<'a, ContainerNameSet, BlobNameSet, QueryExpressionSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    QueryExpressionSet: ToAssign,
+{}
+
+impl<'a> QueryBlobBuilder<'a, Yes, Yes, Yes> {
+    /// Streams the records of the results as they arrive. A fatal
+    /// `BlobQueryRecord::Error` ends the query; the results are complete
+    /// once `BlobQueryRecord::End` is received.
+    pub fn finalize(self) -> impl Stream<Item = BlobQueryRecord, Error = AzureError> {
+        let mut uri = format!(
+            "https://{}.blob.{}/{}/{}?comp=query",
+            self.client().account(),
+            self.client().endpoint_suffix(),
+            self.container_name(),
+            self.blob_name()
+        );
+        if let Some(snapshot) = SnapshotOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, snapshot);
+        }
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, timeout);
+        }
+
+        trace!("uri == {:?}", uri);
+
+        let body = blob_query_to_xml(self.query_expression(), self.input_serialization(), self.output_serialization());
+        debug!("body == {}", body);
+
+        let req = self.client().perform_request(
+            &uri,
+            Method::POST,
+            |ref mut request| {
+                request.header(HEADER_VERSION, BLOB_QUERY_VERSION);
+                LeaseIdOption::add_header(&self, request);
+                ClientRequestIdOption::add_header(&self, request);
+            },
+            Some(body.as_bytes()),
+        );
+
+        done(req)
+            .from_err()
+            .and_then(move |future_response| {
+                future_response.from_err().and_then(move |response| {
+                    if response.status() != StatusCode::OK {
+                        let status = response.status();
+                        let error = response.into_body().concat2().from_err().and_then(move |body| {
+                            Err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(
+                                StatusCode::OK,
+                                status,
+                                &String::from_utf8_lossy(&body),
+                            )))
+                        });
+                        return Either::A(error);
+                    }
+                    Either::B(Ok(response.into_body()).into_future())
+                })
+            })
+            .map(|body| {
+                let mut decoder = BlobQueryDecoder::default();
+                body.from_err()
+                    .and_then(move |chunk| decoder.feed(&chunk))
+                    .map(stream::iter_ok::<_, AzureError>)
+                    .flatten()
+            })
+            .flatten_stream()
+    }
+}
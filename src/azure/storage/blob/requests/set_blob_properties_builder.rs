@@ -0,0 +1,547 @@
+use azure::core::errors::{check_status_extract_headers_and_body, AzureError};
+use azure::core::headers::{BLOB_CACHE_CONTROL, BLOB_CONTENT_DISPOSITION, BLOB_CONTENT_ENCODING, BLOB_CONTENT_LANGUAGE, BLOB_CONTENT_TYPE};
+use azure::core::lease::LeaseId;
+use azure::core::{
+    BlobNameRequired, BlobNameSupport, CacheControlOption, CacheControlSupport, ClientRequestIdOption, ClientRequestIdSupport,
+    ClientRequired, ContainerNameRequired, ContainerNameSupport, ContentDispositionOption, ContentDispositionSupport,
+    ContentEncodingOption, ContentEncodingSupport, ContentLanguageOption, ContentLanguageSupport, ContentTypeOption, ContentTypeSupport,
+    LeaseIdOption, LeaseIdSupport, No, TimeoutOption, TimeoutSupport, ToAssign, Yes,
+};
+use azure::storage::blob::responses::SetBlobPropertiesResponse;
+use azure::storage::client::Client;
+use futures::future::done;
+use futures::prelude::*;
+use http::request::Builder;
+use hyper::{Method, StatusCode};
+use std::marker::PhantomData;
+
+/// Updates the content headers of a blob without uploading it again.
+///
+/// Beware: the service clears every property not sent with the request, so
+/// to change only one of them the current values (as returned by a
+/// `get_blob` or `blob_exists` call) must be passed along with the new one.
+#[derive(Debug, Clone)]
+pub struct SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    client: &'a Client,
+    p_container_name: PhantomData<ContainerNameSet>,
+    p_blob_name: PhantomData<BlobNameSet>,
+    container_name: Option<&'a str>,
+    blob_name: Option<&'a str>,
+    timeout: Option<u64>,
+    content_type: Option<&'a str>,
+    content_encoding: Option<&'a str>,
+    content_language: Option<&'a str>,
+    cache_control: Option<&'a str>,
+    content_disposition: Option<&'a str>,
+    lease_id: Option<&'a LeaseId>,
+    client_request_id: Option<&'a str>,
+}
+
+impl<'a> SetBlobPropertiesBuilder<'a, No, No> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> SetBlobPropertiesBuilder<'a, No, No> {
+        SetBlobPropertiesBuilder {
+            client,
+            p_container_name: PhantomData {},
+            container_name: None,
+            p_blob_name: PhantomData {},
+            blob_name: None,
+            timeout: None,
+            content_type: None,
+            content_encoding: None,
+            content_language: None,
+            cache_control: None,
+            content_disposition: None,
+            lease_id: None,
+            client_request_id: None,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequired<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a, BlobNameSet> ContainerNameRequired<'a> for SetBlobPropertiesBuilder<'a, Yes, BlobNameSet>
+where
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn container_name(&self) -> &'a str {
+        self.container_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet> BlobNameRequired<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, Yes>
+where
+    ContainerNameSet: ToAssign,
+{
+    #[inline]
+    fn blob_name(&self) -> &'a str {
+        self.blob_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TimeoutOption for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContentTypeOption<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn content_type(&self) -> Option<&'a str> {
+        self.content_type
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContentEncodingOption<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn content_encoding(&self) -> Option<&'a str> {
+        self.content_encoding
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContentLanguageOption<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn content_language(&self) -> Option<&'a str> {
+        self.content_language
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> CacheControlOption<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn cache_control(&self) -> Option<&'a str> {
+        self.cache_control
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContentDispositionOption<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn content_disposition(&self) -> Option<&'a str> {
+        self.content_disposition
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> LeaseIdOption<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn lease_id(&self) -> Option<&'a LeaseId> {
+        self.lease_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdOption<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContainerNameSupport<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SetBlobPropertiesBuilder<'a, Yes, BlobNameSet>;
+
+    #[inline]
+    fn with_container_name(self, container_name: &'a str) -> Self::O {
+        SetBlobPropertiesBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: Some(container_name),
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            content_type: self.content_type,
+            content_encoding: self.content_encoding,
+            content_language: self.content_language,
+            cache_control: self.cache_control,
+            content_disposition: self.content_disposition,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> BlobNameSupport<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SetBlobPropertiesBuilder<'a, ContainerNameSet, Yes>;
+
+    #[inline]
+    fn with_blob_name(self, blob_name: &'a str) -> Self::O {
+        SetBlobPropertiesBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: Some(blob_name),
+            timeout: self.timeout,
+            content_type: self.content_type,
+            content_encoding: self.content_encoding,
+            content_language: self.content_language,
+            cache_control: self.cache_control,
+            content_disposition: self.content_disposition,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TimeoutSupport for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        SetBlobPropertiesBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: Some(timeout),
+            content_type: self.content_type,
+            content_encoding: self.content_encoding,
+            content_language: self.content_language,
+            cache_control: self.cache_control,
+            content_disposition: self.content_disposition,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContentTypeSupport<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_content_type(self, content_type: &'a str) -> Self::O {
+        SetBlobPropertiesBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            content_type: Some(content_type),
+            content_encoding: self.content_encoding,
+            content_language: self.content_language,
+            cache_control: self.cache_control,
+            content_disposition: self.content_disposition,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContentEncodingSupport<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_content_encoding(self, content_encoding: &'a str) -> Self::O {
+        SetBlobPropertiesBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            content_type: self.content_type,
+            content_encoding: Some(content_encoding),
+            content_language: self.content_language,
+            cache_control: self.cache_control,
+            content_disposition: self.content_disposition,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContentLanguageSupport<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_content_language(self, content_language: &'a str) -> Self::O {
+        SetBlobPropertiesBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            content_type: self.content_type,
+            content_encoding: self.content_encoding,
+            content_language: Some(content_language),
+            cache_control: self.cache_control,
+            content_disposition: self.content_disposition,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> CacheControlSupport<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_cache_control(self, cache_control: &'a str) -> Self::O {
+        SetBlobPropertiesBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            content_type: self.content_type,
+            content_encoding: self.content_encoding,
+            content_language: self.content_language,
+            cache_control: Some(cache_control),
+            content_disposition: self.content_disposition,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContentDispositionSupport<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_content_disposition(self, content_disposition: &'a str) -> Self::O {
+        SetBlobPropertiesBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            content_type: self.content_type,
+            content_encoding: self.content_encoding,
+            content_language: self.content_language,
+            cache_control: self.cache_control,
+            content_disposition: Some(content_disposition),
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> LeaseIdSupport<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_lease_id(self, lease_id: &'a LeaseId) -> Self::O {
+        SetBlobPropertiesBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            content_type: self.content_type,
+            content_encoding: self.content_encoding,
+            content_language: self.content_language,
+            cache_control: self.cache_control,
+            content_disposition: self.content_disposition,
+            lease_id: Some(lease_id),
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdSupport<'a> for SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        SetBlobPropertiesBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            content_type: self.content_type,
+            content_encoding: self.content_encoding,
+            content_language: self.content_language,
+            cache_control: self.cache_control,
+            content_disposition: self.content_disposition,
+            lease_id: self.lease_id,
+            client_request_id: Some(client_request_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a, ContainerNameSet, BlobNameSet> SetBlobPropertiesBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{}
+
+impl<'a> SetBlobPropertiesBuilder<'a, Yes, Yes> {
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = SetBlobPropertiesResponse, Error = AzureError> {
+        let mut uri = format!(
+            "https://{}.blob.{}/{}/{}?comp=properties",
+            self.client().account(),
+            self.client().endpoint_suffix(),
+            self.container_name(),
+            self.blob_name()
+        );
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, timeout);
+        }
+
+        trace!("uri == {:?}", uri);
+
+        let req = self
+            .client()
+            .perform_request(&uri, Method::PUT, |ref mut request| self.add_headers(request), None);
+
+        done(req)
+            .from_err()
+            .and_then(move |response| check_status_extract_headers_and_body(response, StatusCode::OK))
+            .and_then(move |(headers, _body)| done(SetBlobPropertiesResponse::from_headers(&headers)))
+    }
+
+    // The properties travel in the x-ms-blob-* headers: the plain content
+    // headers would describe the (empty) request body instead.
+    fn add_headers(&self, request: &mut Builder) {
+        if let Some(content_type) = self.content_type() {
+            request.header(BLOB_CONTENT_TYPE, content_type);
+        }
+        if let Some(content_encoding) = self.content_encoding() {
+            request.header(BLOB_CONTENT_ENCODING, content_encoding);
+        }
+        if let Some(content_language) = self.content_language() {
+            request.header(BLOB_CONTENT_LANGUAGE, content_language);
+        }
+        if let Some(cache_control) = self.cache_control() {
+            request.header(BLOB_CACHE_CONTROL, cache_control);
+        }
+        if let Some(content_disposition) = self.content_disposition() {
+            request.header(BLOB_CONTENT_DISPOSITION, content_disposition);
+        }
+        LeaseIdOption::add_header(self, request);
+        ClientRequestIdOption::add_header(self, request);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use azure::storage::client::Blob;
+    use azure::storage::rest_client::{prepare_request, ServiceType};
+    use hyper::header;
+
+    #[test]
+    fn properties_headers() {
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap();
+        let builder = client
+            .set_blob_properties()
+            .with_container_name("assets")
+            .with_blob_name("index.html")
+            .with_content_type("text/html; charset=utf-8")
+            .with_cache_control("public, max-age=3600")
+            .with_content_disposition("inline");
+
+        let request = prepare_request(
+            "https://mindflavor.blob.core.windows.net/assets/index.html?comp=properties",
+            Method::PUT,
+            client.key(),
+            |request| builder.add_headers(request),
+            None,
+            ServiceType::Blob,
+        )
+        .unwrap();
+
+        let headers = request.headers();
+        assert_eq!(headers[BLOB_CONTENT_TYPE], "text/html; charset=utf-8");
+        assert_eq!(headers[BLOB_CACHE_CONTROL], "public, max-age=3600");
+        assert_eq!(headers[BLOB_CONTENT_DISPOSITION], "inline");
+        assert!(!headers.contains_key(BLOB_CONTENT_ENCODING));
+        assert!(!headers.contains_key(BLOB_CONTENT_LANGUAGE));
+        assert!(!headers.contains_key(header::CONTENT_TYPE));
+        assert!(!headers.contains_key(header::CACHE_CONTROL));
+    }
+}
@@ -0,0 +1,334 @@
+use azure::core::errors::{check_status_extract_headers_and_body, AzureError};
+use azure::core::lease::LeaseId;
+use azure::core::{
+    BlobNameRequired, BlobNameSupport, ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, ContainerNameRequired,
+    ContainerNameSupport, LeaseIdOption, LeaseIdSupport, No, TagsRequired, TagsSupport, TimeoutOption, TimeoutSupport, ToAssign, Yes,
+};
+use azure::storage::blob::responses::SetBlobTagsResponse;
+use azure::storage::blob::{blob_tags_to_xml, validate_blob_tags, BLOB_TAGS_VERSION};
+use azure::storage::client::Client;
+use azure::storage::rest_client::HEADER_VERSION;
+use futures::future::done;
+use futures::prelude::*;
+use hyper::{Method, StatusCode};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Replaces the index tags of a blob with the given ones: an empty map
+/// removes them all. The tags are validated before the request is sent.
+#[derive(Debug, Clone)]
+pub struct SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    client: &'a Client,
+    p_container_name: PhantomData<ContainerNameSet>,
+    p_blob_name: PhantomData<BlobNameSet>,
+    p_tags: PhantomData<TagsSet>,
+    container_name: Option<&'a str>,
+    blob_name: Option<&'a str>,
+    tags: Option<&'a HashMap<String, String>>,
+    timeout: Option<u64>,
+    lease_id: Option<&'a LeaseId>,
+    client_request_id: Option<&'a str>,
+}
+
+impl<'a> SetBlobTagsBuilder<'a, No, No, No> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> SetBlobTagsBuilder<'a, No, No, No> {
+        SetBlobTagsBuilder {
+            client,
+            p_container_name: PhantomData {},
+            container_name: None,
+            p_blob_name: PhantomData {},
+            blob_name: None,
+            p_tags: PhantomData {},
+            tags: None,
+            timeout: None,
+            lease_id: None,
+            client_request_id: None,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> ClientRequired<'a> for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a, BlobNameSet, TagsSet> ContainerNameRequired<'a> for SetBlobTagsBuilder<'a, Yes, BlobNameSet, TagsSet>
+where
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    #[inline]
+    fn container_name(&self) -> &'a str {
+        self.container_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, TagsSet> BlobNameRequired<'a> for SetBlobTagsBuilder<'a, ContainerNameSet, Yes, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    #[inline]
+    fn blob_name(&self) -> &'a str {
+        self.blob_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TagsRequired<'a> for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, Yes>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn tags(&self) -> &'a HashMap<String, String> {
+        self.tags.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> TimeoutOption for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> LeaseIdOption<'a> for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    #[inline]
+    fn lease_id(&self) -> Option<&'a LeaseId> {
+        self.lease_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> ClientRequestIdOption<'a>
+    for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> ContainerNameSupport<'a> for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    type O = SetBlobTagsBuilder<'a, Yes, BlobNameSet, TagsSet>;
+
+    #[inline]
+    fn with_container_name(self, container_name: &'a str) -> Self::O {
+        SetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_tags: PhantomData {},
+            container_name: Some(container_name),
+            blob_name: self.blob_name,
+            tags: self.tags,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> BlobNameSupport<'a> for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    type O = SetBlobTagsBuilder<'a, ContainerNameSet, Yes, TagsSet>;
+
+    #[inline]
+    fn with_blob_name(self, blob_name: &'a str) -> Self::O {
+        SetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_tags: PhantomData {},
+            container_name: self.container_name,
+            blob_name: Some(blob_name),
+            tags: self.tags,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> TagsSupport<'a> for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    type O = SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, Yes>;
+
+    #[inline]
+    fn with_tags(self, tags: &'a HashMap<String, String>) -> Self::O {
+        SetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_tags: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            tags: Some(tags),
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> TimeoutSupport for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    type O = SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        SetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_tags: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            tags: self.tags,
+            timeout: Some(timeout),
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> LeaseIdSupport<'a> for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    type O = SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>;
+
+    #[inline]
+    fn with_lease_id(self, lease_id: &'a LeaseId) -> Self::O {
+        SetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_tags: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            tags: self.tags,
+            timeout: self.timeout,
+            lease_id: Some(lease_id),
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> ClientRequestIdSupport<'a>
+    for SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{
+    type O = SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        SetBlobTagsBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            p_tags: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            tags: self.tags,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: Some(client_request_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a, ContainerNameSet, BlobNameSet, TagsSet> SetBlobTagsBuilder<'a, ContainerNameSet, BlobNameSet, TagsSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+    TagsSet: ToAssign,
+{}
+
+impl<'a> SetBlobTagsBuilder<'a, Yes, Yes, Yes> {
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = SetBlobTagsResponse, Error = AzureError> {
+        let mut uri = format!(
+            "https://{}.blob.{}/{}/{}?comp=tags",
+            self.client().account(),
+            self.client().endpoint_suffix(),
+            self.container_name(),
+            self.blob_name()
+        );
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, timeout);
+        }
+
+        trace!("uri == {:?}", uri);
+
+        let req = validate_blob_tags(self.tags()).and_then(|_| {
+            let body = blob_tags_to_xml(self.tags());
+            debug!("body == {}", body);
+
+            self.client().perform_request(
+                &uri,
+                Method::PUT,
+                |ref mut request| {
+                    request.header(HEADER_VERSION, BLOB_TAGS_VERSION);
+                    LeaseIdOption::add_header(&self, request);
+                    ClientRequestIdOption::add_header(&self, request);
+                },
+                Some(body.as_bytes()),
+            )
+        });
+
+        done(req)
+            .from_err()
+            .and_then(move |response| check_status_extract_headers_and_body(response, StatusCode::NO_CONTENT))
+            .and_then(move |(headers, _body)| done(SetBlobTagsResponse::from_headers(&headers)))
+    }
+}
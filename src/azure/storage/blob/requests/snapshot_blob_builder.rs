@@ -0,0 +1,301 @@
+use azure::core::errors::{check_status_extract_headers_and_body, AzureError};
+use azure::core::lease::LeaseId;
+use azure::core::{
+    BlobNameRequired, BlobNameSupport, ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, ContainerNameRequired,
+    ContainerNameSupport, LeaseIdOption, LeaseIdSupport, No, TimeoutOption, TimeoutSupport, ToAssign, Yes,
+};
+use azure::storage::blob::responses::SnapshotBlobResponse;
+use azure::storage::client::Client;
+use futures::future::done;
+use futures::prelude::*;
+use hyper::{Method, StatusCode};
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone)]
+pub struct SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    client: &'a Client,
+    p_container_name: PhantomData<ContainerNameSet>,
+    p_blob_name: PhantomData<BlobNameSet>,
+    container_name: Option<&'a str>,
+    blob_name: Option<&'a str>,
+    timeout: Option<u64>,
+    lease_id: Option<&'a LeaseId>,
+    client_request_id: Option<&'a str>,
+}
+
+impl<'a> SnapshotBlobBuilder<'a, No, No> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> SnapshotBlobBuilder<'a, No, No> {
+        SnapshotBlobBuilder {
+            client,
+            p_container_name: PhantomData {},
+            container_name: None,
+            p_blob_name: PhantomData {},
+            blob_name: None,
+            timeout: None,
+            lease_id: None,
+            client_request_id: None,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequired<'a> for SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a, BlobNameSet> ContainerNameRequired<'a> for SnapshotBlobBuilder<'a, Yes, BlobNameSet>
+where
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn container_name(&self) -> &'a str {
+        self.container_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet> BlobNameRequired<'a> for SnapshotBlobBuilder<'a, ContainerNameSet, Yes>
+where
+    ContainerNameSet: ToAssign,
+{
+    #[inline]
+    fn blob_name(&self) -> &'a str {
+        self.blob_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TimeoutOption for SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> LeaseIdOption<'a> for SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn lease_id(&self) -> Option<&'a LeaseId> {
+        self.lease_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdOption<'a> for SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContainerNameSupport<'a> for SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SnapshotBlobBuilder<'a, Yes, BlobNameSet>;
+
+    #[inline]
+    fn with_container_name(self, container_name: &'a str) -> Self::O {
+        SnapshotBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: Some(container_name),
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> BlobNameSupport<'a> for SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SnapshotBlobBuilder<'a, ContainerNameSet, Yes>;
+
+    #[inline]
+    fn with_blob_name(self, blob_name: &'a str) -> Self::O {
+        SnapshotBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: Some(blob_name),
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TimeoutSupport for SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        SnapshotBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: Some(timeout),
+            lease_id: self.lease_id,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> LeaseIdSupport<'a> for SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_lease_id(self, lease_id: &'a LeaseId) -> Self::O {
+        SnapshotBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            lease_id: Some(lease_id),
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdSupport<'a> for SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        SnapshotBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+            client_request_id: Some(client_request_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a, ContainerNameSet, BlobNameSet> SnapshotBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{}
+
+impl<'a> SnapshotBlobBuilder<'a, Yes, Yes> {
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = SnapshotBlobResponse, Error = AzureError> {
+        let mut uri = format!(
+            "https://{}.blob.{}/{}/{}?comp=snapshot",
+            self.client().account(),
+            self.client().endpoint_suffix(),
+            self.container_name(),
+            self.blob_name()
+        );
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(&self) {
+            uri = format!("{}&{}", uri, timeout);
+        }
+
+        trace!("uri == {:?}", uri);
+
+        let req = self.client().perform_request(
+            &uri,
+            Method::PUT,
+            |ref mut request| {
+                LeaseIdOption::add_header(&self, request);
+                ClientRequestIdOption::add_header(&self, request);
+            },
+            None,
+        );
+
+        done(req)
+            .from_err()
+            .and_then(move |response| check_status_extract_headers_and_body(response, StatusCode::CREATED))
+            .and_then(move |(headers, _body)| done(SnapshotBlobResponse::from_headers(&headers)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use azure::core::headers::{REQUEST_ID, SNAPSHOT};
+    use azure::core::{SnapshotOption, SnapshotSupport};
+    use azure::storage::client::Blob;
+    use http::HeaderMap;
+    use hyper::header::{HeaderValue, DATE, ETAG, LAST_MODIFIED};
+
+    #[test]
+    fn snapshot_response() {
+        let mut headers = HeaderMap::new();
+        headers.insert(SNAPSHOT, HeaderValue::from_static("2011-03-09T01:42:34.9360000Z"));
+        headers.insert(ETAG, HeaderValue::from_static("\"0x8CEB669D794AFE2\""));
+        headers.insert(LAST_MODIFIED, HeaderValue::from_static("Wed, 09 Mar 2011 01:42:34 GMT"));
+        headers.insert(REQUEST_ID, HeaderValue::from_static("ed4bc710-0c2e-4d5d-9b6c-0ccdd5ef1bb2"));
+        headers.insert(DATE, HeaderValue::from_static("Wed, 09 Mar 2011 01:42:34 GMT"));
+
+        let response = SnapshotBlobResponse::from_headers(&headers).unwrap();
+        assert_eq!(response.snapshot.timestamp(), 1_299_634_954);
+        assert_eq!(response.snapshot.timestamp_subsec_millis(), 936);
+        assert_eq!(response.etag, "\"0x8CEB669D794AFE2\"");
+    }
+
+    #[test]
+    fn snapshot_scoped_read() {
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap();
+        let snapshot = ::azure::core::parsing::from_azure_snapshot_time("2011-03-09T01:42:34.9360000Z").unwrap();
+
+        let request = client
+            .get_blob()
+            .with_container_name("container")
+            .with_blob_name("blob")
+            .with_snapshot(snapshot);
+
+        // the service matches the identifier verbatim
+        assert_eq!(
+            SnapshotOption::to_uri_parameter(&request),
+            Some("snapshot=2011-03-09T01:42:34.9360000Z".to_owned())
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use azure::core::errors::{check_status_extract_headers_and_body, AzureError};
+use azure::core::{
+    BlobNameRequired, BlobNameSupport, ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, ContainerNameRequired,
+    ContainerNameSupport, No, TimeoutOption, TimeoutSupport, ToAssign, Yes,
+};
+use azure::storage::blob::responses::UndeleteBlobResponse;
+use azure::storage::client::Client;
+use futures::future::done;
+use futures::prelude::*;
+use hyper::{Method, StatusCode};
+use std::marker::PhantomData;
+
+/// Restores a soft-deleted blob along with its soft-deleted snapshots.
+/// Fails unless soft delete is enabled on the account and the retention
+/// period of the blob has not expired yet.
+#[derive(Debug, Clone)]
+pub struct UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    client: &'a Client,
+    p_container_name: PhantomData<ContainerNameSet>,
+    p_blob_name: PhantomData<BlobNameSet>,
+    container_name: Option<&'a str>,
+    blob_name: Option<&'a str>,
+    timeout: Option<u64>,
+    client_request_id: Option<&'a str>,
+}
+
+impl<'a> UndeleteBlobBuilder<'a, No, No> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> UndeleteBlobBuilder<'a, No, No> {
+        UndeleteBlobBuilder {
+            client,
+            p_container_name: PhantomData {},
+            container_name: None,
+            p_blob_name: PhantomData {},
+            blob_name: None,
+            timeout: None,
+            client_request_id: None,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequired<'a> for UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a, BlobNameSet> ContainerNameRequired<'a> for UndeleteBlobBuilder<'a, Yes, BlobNameSet>
+where
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn container_name(&self) -> &'a str {
+        self.container_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet> BlobNameRequired<'a> for UndeleteBlobBuilder<'a, ContainerNameSet, Yes>
+where
+    ContainerNameSet: ToAssign,
+{
+    #[inline]
+    fn blob_name(&self) -> &'a str {
+        self.blob_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TimeoutOption for UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdOption<'a> for UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ContainerNameSupport<'a> for UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = UndeleteBlobBuilder<'a, Yes, BlobNameSet>;
+
+    #[inline]
+    fn with_container_name(self, container_name: &'a str) -> Self::O {
+        UndeleteBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: Some(container_name),
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> BlobNameSupport<'a> for UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = UndeleteBlobBuilder<'a, ContainerNameSet, Yes>;
+
+    #[inline]
+    fn with_blob_name(self, blob_name: &'a str) -> Self::O {
+        UndeleteBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: Some(blob_name),
+            timeout: self.timeout,
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> TimeoutSupport for UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        UndeleteBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: Some(timeout),
+            client_request_id: self.client_request_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, BlobNameSet> ClientRequestIdSupport<'a> for UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{
+    type O = UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        UndeleteBlobBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_blob_name: PhantomData {},
+            container_name: self.container_name,
+            blob_name: self.blob_name,
+            timeout: self.timeout,
+            client_request_id: Some(client_request_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a, ContainerNameSet, BlobNameSet> UndeleteBlobBuilder<'a, ContainerNameSet, BlobNameSet>
+where
+    ContainerNameSet: ToAssign,
+    BlobNameSet: ToAssign,
+{}
+
+impl<'a> UndeleteBlobBuilder<'a, Yes, Yes> {
+    fn uri(&self) -> String {
+        let mut uri = format!(
+            "https://{}.blob.{}/{}/{}?comp=undelete",
+            self.client().account(),
+            self.client().endpoint_suffix(),
+            self.container_name(),
+            self.blob_name()
+        );
+        if let Some(timeout) = TimeoutOption::to_uri_parameter(self) {
+            uri = format!("{}&{}", uri, timeout);
+        }
+        uri
+    }
+
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = UndeleteBlobResponse, Error = AzureError> {
+        let uri = self.uri();
+        trace!("uri == {:?}", uri);
+
+        let req = self.client().perform_request(
+            &uri,
+            Method::PUT,
+            |ref mut request| {
+                ClientRequestIdOption::add_header(&self, request);
+            },
+            None,
+        );
+
+        done(req)
+            .from_err()
+            .and_then(move |response| check_status_extract_headers_and_body(response, StatusCode::OK))
+            .and_then(move |(headers, _body)| done(UndeleteBlobResponse::from_headers(&headers)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use azure::storage::client::Blob;
+
+    #[test]
+    fn undelete_uri() {
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap();
+        let builder = client
+            .undelete_blob()
+            .with_container_name("logs")
+            .with_blob_name("2020/01.txt")
+            .with_timeout(30);
+
+        assert_eq!(
+            builder.uri(),
+            "https://mindflavor.blob.core.windows.net/logs/2020/01.txt?comp=undelete&timeout=30"
+        );
+    }
+}
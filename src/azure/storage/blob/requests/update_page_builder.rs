@@ -604,8 +604,9 @@ where
 impl<'a> UpdatePageBuilder<'a, Yes, Yes, Yes, Yes> {
     pub fn finalize(self) -> impl Future<Item = UpdatePageResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}/{}?comp=page",
+            "https://{}.blob.{}/{}/{}?comp=page",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name(),
             self.blob_name()
         );
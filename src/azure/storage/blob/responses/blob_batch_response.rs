@@ -0,0 +1,35 @@
+use azure::core::errors::AzureError;
+use azure::core::util::HeaderMapExt;
+use azure::core::{date_from_headers, request_id_from_headers, RequestId};
+use azure::storage::blob::{batch_responses_from_multipart, BatchSubResponse};
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+use hyper::header;
+
+#[derive(Debug, Clone)]
+pub struct BlobBatchResponse {
+    /// The outcome of every operation, in the order they were added to the
+    /// batch.
+    pub sub_responses: Vec<BatchSubResponse>,
+    pub request_id: RequestId,
+    pub date: DateTime<Utc>,
+    headers: HeaderMap,
+}
+
+impl_headers!(BlobBatchResponse);
+
+impl BlobBatchResponse {
+    pub(crate) fn from_response(headers: &HeaderMap, body: &str) -> Result<BlobBatchResponse, AzureError> {
+        let content_type = headers.get_as_str(header::CONTENT_TYPE).unwrap_or_default();
+        let sub_responses = batch_responses_from_multipart(content_type, body)?;
+        let request_id = request_id_from_headers(headers)?;
+        let date = date_from_headers(headers)?;
+
+        Ok(BlobBatchResponse {
+            sub_responses,
+            request_id,
+            date,
+            headers: headers.clone(),
+        })
+    }
+}
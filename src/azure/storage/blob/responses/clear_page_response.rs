@@ -9,3 +9,5 @@ response_from_headers!(ClearPageResponse,
 		       request_id_from_headers -> request_id: RequestId,
 		       date_from_headers -> date: DateTime<Utc>
 );
+
+impl_etag!(ClearPageResponse, etag);
@@ -0,0 +1,31 @@
+use azure::core::errors::AzureError;
+use azure::core::incompletevector::IncompleteVector;
+use azure::core::{date_from_headers, request_id_from_headers, RequestId};
+use azure::storage::blob::{tagged_blobs_from_xml, TaggedBlob};
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FindBlobsByTagsResponse {
+    pub incomplete_vector: IncompleteVector<TaggedBlob>,
+    pub request_id: RequestId,
+    pub date: DateTime<Utc>,
+    headers: HeaderMap,
+}
+
+impl_headers!(FindBlobsByTagsResponse);
+
+impl FindBlobsByTagsResponse {
+    pub(crate) fn from_response(headers: &HeaderMap, body: &str) -> Result<FindBlobsByTagsResponse, AzureError> {
+        let incomplete_vector = tagged_blobs_from_xml(body)?;
+        let request_id = request_id_from_headers(headers)?;
+        let date = date_from_headers(headers)?;
+
+        Ok(FindBlobsByTagsResponse {
+            incomplete_vector,
+            request_id,
+            date,
+            headers: headers.clone(),
+        })
+    }
+}
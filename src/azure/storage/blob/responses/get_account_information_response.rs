@@ -0,0 +1,30 @@
+use azure::core::errors::AzureError;
+use azure::core::{date_from_headers, request_id_from_headers, RequestId};
+use azure::storage::blob::AccountInfo;
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetAccountInformationResponse {
+    pub account_info: AccountInfo,
+    pub request_id: RequestId,
+    pub date: DateTime<Utc>,
+    headers: HeaderMap,
+}
+
+impl_headers!(GetAccountInformationResponse);
+
+impl GetAccountInformationResponse {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Result<GetAccountInformationResponse, AzureError> {
+        let account_info = AccountInfo::from_headers(headers)?;
+        let request_id = request_id_from_headers(headers)?;
+        let date = date_from_headers(headers)?;
+
+        Ok(GetAccountInformationResponse {
+            account_info,
+            request_id,
+            date,
+            headers: headers.clone(),
+        })
+    }
+}
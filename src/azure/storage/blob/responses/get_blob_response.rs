@@ -1,27 +1,101 @@
 use azure::core::errors::AzureError;
+use azure::core::parsing::to_rfc1123;
+use azure::core::range::ContentRange;
+use azure::core::util::HeaderMapExt;
 use azure::core::{date_from_headers, request_id_from_headers, RequestId};
 use azure::storage::blob::Blob;
 use chrono::{DateTime, Utc};
-use http::HeaderMap;
+use http;
+use http::header::{self, HeaderMap, HeaderValue};
+use hyper::StatusCode;
 
 #[derive(Debug, Clone)]
 pub struct GetBlobResponse {
     pub blob: Blob,
     pub request_id: RequestId,
     pub data: Vec<u8>,
+    /// The base64 MD5 of `data` for ranged downloads when the service
+    /// computed one, see `with_range_content_md5`.
+    pub range_content_md5: Option<String>,
+    /// Whether only a range of the blob was returned, with a 206.
+    pub partial: bool,
+    /// The range returned, along with the length of the whole blob, for
+    /// ranged downloads.
+    pub content_range: Option<ContentRange>,
     pub date: DateTime<Utc>,
+    headers: HeaderMap,
 }
 
+impl_headers!(GetBlobResponse);
+
 impl GetBlobResponse {
-    pub(crate) fn from_response(headers: &HeaderMap, blob: Blob, body: &[u8]) -> Result<GetBlobResponse, AzureError> {
+    pub(crate) fn from_response(headers: &HeaderMap, blob: Blob, body: &[u8], ranged: bool) -> Result<GetBlobResponse, AzureError> {
         let request_id = request_id_from_headers(headers)?;
         let date = date_from_headers(headers)?;
+        let range_content_md5 = if ranged { blob.content_md5.clone() } else { None };
+        let content_range = content_range_from_headers(headers)?;
 
         Ok(GetBlobResponse {
             blob,
             request_id,
             data: body.to_vec(),
+            range_content_md5,
+            partial: ranged,
+            content_range,
             date,
+            headers: headers.clone(),
         })
     }
+
+    /// The status to answer a browser with: 206 for a ranged download.
+    pub fn status(&self) -> StatusCode {
+        if self.partial {
+            StatusCode::PARTIAL_CONTENT
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    /// The headers to answer a browser with when serving `data`, so that it
+    /// can seek through the blob with range requests: `Content-Type`,
+    /// `Content-Length`, `Accept-Ranges`, `Content-Range` for ranged
+    /// downloads, and the `Content-Disposition`, `ETag` and `Last-Modified`
+    /// of the blob when it has them.
+    pub fn browser_headers(&self) -> Result<HeaderMap, AzureError> {
+        let mut headers = HeaderMap::new();
+        let content_type = self.blob.content_type.as_deref().unwrap_or("application/octet-stream");
+        headers.insert(header::CONTENT_TYPE, header_value(content_type)?);
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(self.data.len() as u64));
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        if let Some(ref content_range) = self.content_range {
+            headers.insert(header::CONTENT_RANGE, header_value(&content_range.to_string())?);
+        }
+        if let Some(ref content_disposition) = self.blob.content_disposition {
+            headers.insert(header::CONTENT_DISPOSITION, header_value(content_disposition)?);
+        }
+        if let Some(ref etag) = self.blob.etag {
+            headers.insert(header::ETAG, header_value(etag)?);
+        }
+        if let Some(last_modified) = self.blob.last_modified {
+            headers.insert(header::LAST_MODIFIED, header_value(&to_rfc1123(&last_modified))?);
+        }
+        Ok(headers)
+    }
+}
+
+fn content_range_from_headers(headers: &HeaderMap) -> Result<Option<ContentRange>, AzureError> {
+    match headers.get_as_str(header::CONTENT_RANGE) {
+        Some(content_range) => match content_range.parse::<ContentRange>() {
+            Ok(content_range) => Ok(Some(content_range)),
+            Err(error) => Err(AzureError::GenericErrorWithText(format!(
+                "invalid Content-Range {:?}: {:?}",
+                content_range, error
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, AzureError> {
+    Ok(HeaderValue::from_str(value).map_err(http::Error::from)?)
 }
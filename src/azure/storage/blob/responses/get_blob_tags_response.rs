@@ -0,0 +1,32 @@
+use azure::core::errors::AzureError;
+use azure::core::{date_from_headers, request_id_from_headers, RequestId};
+use azure::storage::blob::blob_tags_from_xml;
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+use std::collections::HashMap;
+use std::str::from_utf8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetBlobTagsResponse {
+    pub request_id: RequestId,
+    pub date: DateTime<Utc>,
+    pub tags: HashMap<String, String>,
+    headers: HeaderMap,
+}
+
+impl_headers!(GetBlobTagsResponse);
+
+impl GetBlobTagsResponse {
+    pub(crate) fn from_response(headers: &HeaderMap, body: &[u8]) -> Result<GetBlobTagsResponse, AzureError> {
+        let request_id = request_id_from_headers(headers)?;
+        let date = date_from_headers(headers)?;
+        let tags = blob_tags_from_xml(from_utf8(body)?)?;
+
+        Ok(GetBlobTagsResponse {
+            request_id,
+            date,
+            tags,
+            headers: headers.clone(),
+        })
+    }
+}
@@ -12,8 +12,12 @@ pub struct GetBlockListResponse {
     pub request_id: RequestId,
     pub date: DateTime<Utc>,
     pub block_with_size_list: BlockWithSizeList<Vec<u8>>,
+    headers: HeaderMap,
 }
 
+impl_etag!(GetBlockListResponse, etag, optional);
+impl_headers!(GetBlockListResponse);
+
 impl GetBlockListResponse {
     pub(crate) fn from_response(headers: &HeaderMap, body: &[u8]) -> Result<GetBlockListResponse, AzureError> {
         let etag = etag_from_headers_optional(headers)?;
@@ -30,6 +34,7 @@ impl GetBlockListResponse {
             request_id,
             date,
             block_with_size_list,
+            headers: headers.clone(),
         })
     }
 }
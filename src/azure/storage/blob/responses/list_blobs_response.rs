@@ -10,8 +10,11 @@ pub struct ListBlobsResponse {
     pub incomplete_vector: IncompleteVector<Blob>,
     pub request_id: RequestId,
     pub date: DateTime<Utc>,
+    headers: HeaderMap,
 }
 
+impl_headers!(ListBlobsResponse);
+
 impl ListBlobsResponse {
     pub(crate) fn from_response(container_name: &str, headers: &HeaderMap, body: &str) -> Result<ListBlobsResponse, AzureError> {
         let incomplete_vector = incomplete_vector_from_response(body, container_name)?;
@@ -22,6 +25,7 @@ impl ListBlobsResponse {
             incomplete_vector,
             request_id,
             date,
+            headers: headers.clone(),
         })
     }
 }
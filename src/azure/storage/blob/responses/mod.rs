@@ -9,8 +9,11 @@ macro_rules! response_from_headers {
         #[derive(Debug, Clone, PartialEq)]
         pub struct $cn {
             $($na: $typ),+,
+            headers: HeaderMap,
         }
 
+        impl_headers!($cn);
+
         impl $cn {
             pub(crate) fn from_headers(headers: &HeaderMap) -> Result<$cn, AzureError> {
                $(
@@ -19,6 +22,7 @@ macro_rules! response_from_headers {
 
                 Ok($cn {
                     $($na,)+
+                    headers: headers.clone(),
                 })
             }
 
@@ -44,3 +48,19 @@ mod put_blob_response;
 pub use self::put_blob_response::PutBlobResponse;
 mod update_page_response;
 pub use self::update_page_response::UpdatePageResponse;
+mod set_blob_properties_response;
+pub use self::set_blob_properties_response::SetBlobPropertiesResponse;
+mod snapshot_blob_response;
+pub use self::snapshot_blob_response::SnapshotBlobResponse;
+mod get_blob_tags_response;
+pub use self::get_blob_tags_response::GetBlobTagsResponse;
+mod set_blob_tags_response;
+pub use self::set_blob_tags_response::SetBlobTagsResponse;
+mod find_blobs_by_tags_response;
+pub use self::find_blobs_by_tags_response::FindBlobsByTagsResponse;
+mod undelete_blob_response;
+pub use self::undelete_blob_response::UndeleteBlobResponse;
+mod get_account_information_response;
+pub use self::get_account_information_response::GetAccountInformationResponse;
+mod blob_batch_response;
+pub use self::blob_batch_response::BlobBatchResponse;
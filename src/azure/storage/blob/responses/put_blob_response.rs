@@ -13,8 +13,12 @@ pub struct PutBlobResponse {
     pub request_id: RequestId,
     pub date: DateTime<Utc>,
     pub request_server_encrypted: bool,
+    headers: HeaderMap,
 }
 
+impl_etag!(PutBlobResponse, etag);
+impl_headers!(PutBlobResponse);
+
 impl PutBlobResponse {
     pub fn from_headers(headers: &HeaderMap) -> Result<PutBlobResponse, AzureError> {
         let etag = etag_from_headers(headers)?;
@@ -29,6 +33,7 @@ impl PutBlobResponse {
             request_id,
             date,
             request_server_encrypted,
+            headers: headers.clone(),
         })
     }
 }
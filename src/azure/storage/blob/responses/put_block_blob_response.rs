@@ -14,8 +14,12 @@ pub struct PutBlockBlobResponse {
     pub request_id: RequestId,
     pub date: DateTime<Utc>,
     pub request_server_encrypted: bool,
+    headers: HeaderMap,
 }
 
+impl_etag!(PutBlockBlobResponse, etag);
+impl_headers!(PutBlockBlobResponse);
+
 impl PutBlockBlobResponse {
     pub fn from_headers(headers: &HeaderMap) -> Result<PutBlockBlobResponse, AzureError> {
         let etag = etag_from_headers(headers)?;
@@ -32,6 +36,7 @@ impl PutBlockBlobResponse {
             request_id,
             date,
             request_server_encrypted,
+            headers: headers.clone(),
         })
     }
 }
@@ -14,8 +14,12 @@ pub struct PutBlockListResponse {
     pub request_id: RequestId,
     pub date: DateTime<Utc>,
     pub request_server_encrypted: bool,
+    headers: HeaderMap,
 }
 
+impl_etag!(PutBlockListResponse, etag);
+impl_headers!(PutBlockListResponse);
+
 impl PutBlockListResponse {
     pub(crate) fn from_headers(headers: &HeaderMap) -> Result<PutBlockListResponse, AzureError> {
         let etag = etag_from_headers(headers)?;
@@ -32,6 +36,7 @@ impl PutBlockListResponse {
             request_id,
             date,
             request_server_encrypted,
+            headers: headers.clone(),
         })
     }
 }
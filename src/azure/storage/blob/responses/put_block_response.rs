@@ -9,8 +9,11 @@ pub struct PutBlockResponse {
     pub request_id: RequestId,
     pub date: DateTime<Utc>,
     pub request_server_encrypted: bool,
+    headers: HeaderMap,
 }
 
+impl_headers!(PutBlockResponse);
+
 impl PutBlockResponse {
     pub(crate) fn from_headers(headers: &HeaderMap) -> Result<PutBlockResponse, AzureError> {
         let content_md5 = content_md5_from_headers(headers)?;
@@ -23,6 +26,7 @@ impl PutBlockResponse {
             request_id,
             date,
             request_server_encrypted,
+            headers: headers.clone(),
         })
     }
 }
@@ -0,0 +1,26 @@
+use azure::core::errors::AzureError;
+use azure::core::{date_from_headers, request_id_from_headers, RequestId};
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetBlobTagsResponse {
+    pub request_id: RequestId,
+    pub date: DateTime<Utc>,
+    headers: HeaderMap,
+}
+
+impl_headers!(SetBlobTagsResponse);
+
+impl SetBlobTagsResponse {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Result<SetBlobTagsResponse, AzureError> {
+        let request_id = request_id_from_headers(headers)?;
+        let date = date_from_headers(headers)?;
+
+        Ok(SetBlobTagsResponse {
+            request_id,
+            date,
+            headers: headers.clone(),
+        })
+    }
+}
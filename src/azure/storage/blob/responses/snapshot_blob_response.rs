@@ -0,0 +1,38 @@
+use azure::core::errors::AzureError;
+use azure::core::{
+    date_from_headers, etag_from_headers, last_modified_from_headers, request_id_from_headers, snapshot_from_headers, RequestId,
+};
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+
+#[derive(Debug, Clone)]
+pub struct SnapshotBlobResponse {
+    pub snapshot: DateTime<Utc>,
+    pub etag: String,
+    pub last_modified: DateTime<Utc>,
+    pub request_id: RequestId,
+    pub date: DateTime<Utc>,
+    headers: HeaderMap,
+}
+
+impl_etag!(SnapshotBlobResponse, etag);
+impl_headers!(SnapshotBlobResponse);
+
+impl SnapshotBlobResponse {
+    pub fn from_headers(headers: &HeaderMap) -> Result<SnapshotBlobResponse, AzureError> {
+        let snapshot = snapshot_from_headers(headers)?;
+        let etag = etag_from_headers(headers)?;
+        let last_modified = last_modified_from_headers(headers)?;
+        let request_id = request_id_from_headers(headers)?;
+        let date = date_from_headers(headers)?;
+
+        Ok(SnapshotBlobResponse {
+            snapshot,
+            etag,
+            last_modified,
+            request_id,
+            date,
+            headers: headers.clone(),
+        })
+    }
+}
@@ -0,0 +1,26 @@
+use azure::core::errors::AzureError;
+use azure::core::{date_from_headers, request_id_from_headers, RequestId};
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndeleteBlobResponse {
+    pub request_id: RequestId,
+    pub date: DateTime<Utc>,
+    headers: HeaderMap,
+}
+
+impl_headers!(UndeleteBlobResponse);
+
+impl UndeleteBlobResponse {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Result<UndeleteBlobResponse, AzureError> {
+        let request_id = request_id_from_headers(headers)?;
+        let date = date_from_headers(headers)?;
+
+        Ok(UndeleteBlobResponse {
+            request_id,
+            date,
+            headers: headers.clone(),
+        })
+    }
+}
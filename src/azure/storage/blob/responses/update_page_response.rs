@@ -15,8 +15,12 @@ pub struct UpdatePageResponse {
     pub request_id: RequestId,
     pub date: DateTime<Utc>,
     pub request_server_encrypted: bool,
+    headers: HeaderMap,
 }
 
+impl_etag!(UpdatePageResponse, etag);
+impl_headers!(UpdatePageResponse);
+
 impl UpdatePageResponse {
     pub(crate) fn from_headers(headers: &HeaderMap) -> Result<UpdatePageResponse, AzureError> {
         let etag = etag_from_headers(headers)?;
@@ -35,6 +39,7 @@ impl UpdatePageResponse {
             request_id,
             date,
             request_server_encrypted,
+            headers: headers.clone(),
         })
     }
 }
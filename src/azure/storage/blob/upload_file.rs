@@ -0,0 +1,531 @@
+use azure::core::errors::AzureError;
+use azure::core::util::delay;
+use azure::core::{BlobNameSupport, BlockIdSupport, BlockListSupport, BodySupport, ContainerNameSupport};
+use azure::storage::blob::{BlobBlockType, BlockList};
+use azure::storage::client::{Blob as BlobTrait, Client};
+use bytes::{Bytes, BytesMut};
+use futures::future::{done, loop_fn, Either, Loop};
+use futures::prelude::*;
+use futures::stream;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Largest blob `upload_file` sends with a single Put Blob.
+pub const MAX_SINGLE_PUT_SIZE: u64 = 256 * 1024 * 1024;
+/// Largest block the service accepts.
+pub const MAX_BLOCK_SIZE: u64 = 100 * 1024 * 1024;
+const MAX_BLOCKS: u64 = 50_000;
+/// Attempts of a block failing with a retryable error, see
+/// `AzureError::is_retryable`.
+pub const MAX_BLOCK_ATTEMPTS: u32 = 3;
+const BLOCK_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+// All the block ids of a blob must have the same length.
+fn block_id(index: u64) -> Vec<u8> {
+    format!("block-{:06}", index).into_bytes()
+}
+
+fn check_block_size(block_size: u64) -> Result<(), AzureError> {
+    if block_size == 0 || block_size > MAX_BLOCK_SIZE {
+        return Err(AzureError::InputParametersError(format!(
+            "the block size must be between 1 and {} bytes, {} given",
+            MAX_BLOCK_SIZE, block_size
+        )));
+    }
+    Ok(())
+}
+
+fn block_count(length: u64, block_size: u64) -> Result<u64, AzureError> {
+    check_block_size(block_size)?;
+
+    let count = if length == 0 { 0 } else { (length - 1) / block_size + 1 };
+    if count > MAX_BLOCKS {
+        return Err(AzureError::InputParametersError(format!(
+            "{} bytes would take {} blocks of {} bytes, more than the {} allowed",
+            length, count, block_size, MAX_BLOCKS
+        )));
+    }
+    Ok(count)
+}
+
+// Reads exactly `length` bytes, failing if the file got shorter since it
+// was measured.
+fn read_block(file: &mut File, length: u64) -> Result<Vec<u8>, AzureError> {
+    let mut block = vec![0; length as usize];
+    file.read_exact(&mut block).map_err(|error| match error.kind() {
+        io::ErrorKind::UnexpectedEof => AzureError::GenericErrorWithText("the file shrank while being uploaded".to_owned()),
+        _ => AzureError::from(error),
+    })?;
+    Ok(block)
+}
+
+fn check_unchanged(file: &File, length: u64) -> Result<(), AzureError> {
+    let current = file.metadata()?.len();
+    if current != length {
+        return Err(AzureError::GenericErrorWithText(format!(
+            "the file changed size while being uploaded ({} bytes, then {})",
+            length, current
+        )));
+    }
+    Ok(())
+}
+
+// The file is opened, measured and read with blocking `std::fs` calls,
+// see `Blob::upload_file`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn upload_file<'a, F>(
+    c: &'a Client,
+    container_name: &'a str,
+    blob_name: &'a str,
+    path: &Path,
+    block_size: u64,
+    max_single_put_size: u64,
+    concurrency: usize,
+    mut progress: F,
+) -> impl Future<Item = u64, Error = AzureError> + 'a
+where
+    F: FnMut(u64, u64) + 'a,
+{
+    let file = File::open(path).map_err(AzureError::from);
+
+    done(file.and_then(|file| Ok((file.metadata()?.len(), file)))).and_then(move |(length, mut file)| {
+        if length <= max_single_put_size {
+            let single_put = done(read_block(&mut file, length).and_then(|body| check_unchanged(&file, length).map(|_| body)))
+                .and_then(move |body| {
+                    c.put_block_blob()
+                        .with_container_name(container_name)
+                        .with_blob_name(blob_name)
+                        .with_body(&body)
+                        .finalize()
+                })
+                .map(move |_| {
+                    progress(length, length);
+                    length
+                });
+            return Either::A(single_put);
+        }
+
+        let stage = move |id: &[u8], block: &[u8]| {
+            c.put_block()
+                .with_container_name(container_name)
+                .with_blob_name(blob_name)
+                .with_block_id(id)
+                .with_body(block)
+                .finalize()
+                .map(|_| ())
+        };
+
+        Either::B(
+            stage_blocks(file, length, block_size, concurrency, stage, progress)
+                .and_then(move |block_list| {
+                    c.put_block_list()
+                        .with_container_name(container_name)
+                        .with_blob_name(blob_name)
+                        .with_block_list(&block_list)
+                        .finalize()
+                })
+                .map(move |_| length),
+        )
+    })
+}
+
+// Stages the blocks of `file` with `stage`, at most `concurrency` of them
+// at once. The blocks are read in order as the previous ones are staged so
+// only `concurrency` of them are in memory, and each one is retried on its
+// own. The returned block list follows the order of the file, whatever
+// the order the blocks were staged in.
+fn stage_blocks<'a, S, R, F>(
+    file: File,
+    length: u64,
+    block_size: u64,
+    concurrency: usize,
+    stage: S,
+    mut progress: F,
+) -> impl Future<Item = BlockList<Vec<u8>>, Error = AzureError> + 'a
+where
+    S: Fn(&[u8], &[u8]) -> R + 'a,
+    R: Future<Item = (), Error = AzureError> + 'a,
+    F: FnMut(u64, u64) + 'a,
+{
+    let stage = Rc::new(stage);
+
+    done(block_count(length, block_size).and_then(|count| Ok((count, file.try_clone()?)))).and_then(move |(count, checked)| {
+        let mut file = file;
+        stream::iter_ok(0..count)
+            .and_then(move |index| {
+                let block_length = block_size.min(length - index * block_size);
+                read_block(&mut file, block_length).map(|block| (index, block))
+            })
+            .map(move |(index, block)| {
+                let stage = stage.clone();
+                let id = block_id(index);
+                let block_length = block.len() as u64;
+                stage_with_retries(move || stage(&id, &block)).map(move |_| (index, block_length))
+            })
+            .buffer_unordered(::std::cmp::max(concurrency, 1))
+            .fold((0, Vec::new()), move |(uploaded, mut staged), (index, block_length)| {
+                let uploaded = uploaded + block_length;
+                staged.push(index);
+                progress(uploaded, length);
+                Ok::<_, AzureError>((uploaded, staged))
+            })
+            .and_then(move |(_, mut staged)| {
+                check_unchanged(&checked, length)?;
+                staged.sort();
+                Ok(BlockList {
+                    blocks: staged
+                        .into_iter()
+                        .map(|index| BlobBlockType::Uncommitted(block_id(index)))
+                        .collect(),
+                })
+            })
+    })
+}
+
+pub(crate) fn upload_stream<'a, B, E>(
+    c: &'a Client,
+    container_name: &'a str,
+    blob_name: &'a str,
+    body: B,
+    block_size: u64,
+    concurrency: usize,
+) -> impl Future<Item = u64, Error = AzureError> + 'a
+where
+    B: Stream<Item = Bytes, Error = E> + 'a,
+    E: Into<AzureError> + 'a,
+{
+    let stage = move |id: &[u8], block: &[u8]| {
+        c.put_block()
+            .with_container_name(container_name)
+            .with_blob_name(blob_name)
+            .with_block_id(id)
+            .with_body(block)
+            .finalize()
+            .map(|_| ())
+    };
+
+    stage_stream(body, block_size, concurrency, stage).and_then(move |(length, block_list)| {
+        c.put_block_list()
+            .with_container_name(container_name)
+            .with_blob_name(blob_name)
+            .with_block_list(&block_list)
+            .finalize()
+            .map(move |_| length)
+    })
+}
+
+// Stages `body` with `stage` in blocks of `block_size` bytes, whatever the
+// size of the chunks it is made of, at most `concurrency` blocks at once.
+// The body is only read when a block can be staged, so at most
+// `concurrency` blocks are in memory however fast it comes. An error of the
+// body stops the upload before anything is committed, the blocks already
+// staged being discarded by the service. Resolves to the length of the body
+// with the block list in its order.
+fn stage_stream<'a, B, E, S, R>(
+    body: B,
+    block_size: u64,
+    concurrency: usize,
+    stage: S,
+) -> impl Future<Item = (u64, BlockList<Vec<u8>>), Error = AzureError> + 'a
+where
+    B: Stream<Item = Bytes, Error = E> + 'a,
+    E: Into<AzureError> + 'a,
+    S: Fn(&[u8], &[u8]) -> R + 'a,
+    R: Future<Item = (), Error = AzureError> + 'a,
+{
+    let stage = Rc::new(stage);
+
+    done(check_block_size(block_size)).and_then(move |_| {
+        Blocks {
+            body: body.map_err(Into::into),
+            block_size: block_size as usize,
+            buffer: BytesMut::new(),
+            ended: false,
+        }
+        .zip(stream::iter_ok(0..))
+        .and_then(move |(block, index)| {
+            if index < MAX_BLOCKS {
+                Ok((index, block))
+            } else {
+                Err(AzureError::InputParametersError(format!(
+                    "the stream would take more than the {} blocks of {} bytes allowed",
+                    MAX_BLOCKS, block_size
+                )))
+            }
+        })
+        .map(move |(index, block)| {
+            let stage = stage.clone();
+            let id = block_id(index);
+            let block_length = block.len() as u64;
+            stage_with_retries(move || stage(&id, &block)).map(move |_| (index, block_length))
+        })
+        .buffer_unordered(::std::cmp::max(concurrency, 1))
+        .fold((0, Vec::new()), |(length, mut staged), (index, block_length)| {
+            staged.push(index);
+            Ok::<_, AzureError>((length + block_length, staged))
+        })
+        .map(|(length, mut staged)| {
+            staged.sort();
+            let block_list = BlockList {
+                blocks: staged
+                    .into_iter()
+                    .map(|index| BlobBlockType::Uncommitted(block_id(index)))
+                    .collect(),
+            };
+            (length, block_list)
+        })
+    })
+}
+
+// The chunks of `body` gathered in blocks of `block_size` bytes, the last one
+// holding what is left.
+struct Blocks<B> {
+    body: B,
+    block_size: usize,
+    buffer: BytesMut,
+    ended: bool,
+}
+
+impl<B> Stream for Blocks<B>
+where
+    B: Stream<Item = Bytes, Error = AzureError>,
+{
+    type Item = Bytes;
+    type Error = AzureError;
+
+    fn poll(&mut self) -> Poll<Option<Bytes>, AzureError> {
+        loop {
+            if self.buffer.len() >= self.block_size {
+                return Ok(Async::Ready(Some(self.buffer.split_to(self.block_size).freeze())));
+            }
+            if self.ended {
+                if self.buffer.is_empty() {
+                    return Ok(Async::Ready(None));
+                }
+                return Ok(Async::Ready(Some(self.buffer.take().freeze())));
+            }
+            match self.body.poll()? {
+                Async::Ready(Some(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Async::Ready(None) => self.ended = true,
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+// Sends the block again, after a growing backoff, as long as it fails
+// with a retryable error, at most `MAX_BLOCK_ATTEMPTS` times.
+fn stage_with_retries<S, R>(stage: S) -> impl Future<Item = (), Error = AzureError>
+where
+    S: Fn() -> R,
+    R: Future<Item = (), Error = AzureError>,
+{
+    loop_fn(1, move |attempt: u32| {
+        stage().then(move |result| match result {
+            Err(ref error) if error.is_retryable() && attempt < MAX_BLOCK_ATTEMPTS => {
+                debug!("staging the block failed ({}), sending it again", error);
+                Either::A(delay(BLOCK_RETRY_BACKOFF * attempt).map(move |_| Loop::Continue(attempt + 1)))
+            }
+            result => Either::B(done(result.map(Loop::Break))),
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn blocks_of_a_file_over_the_threshold() {
+        let path = env::temp_dir().join(format!("azure_sdk_upload_{}.txt", ::uuid::Uuid::new_v4()));
+        fs::write(&path, b"0123456789").unwrap();
+
+        let block_size = 3;
+        let mut file = File::open(&path).unwrap();
+        let length = file.metadata().unwrap().len();
+        assert_eq!(block_count(length, block_size).unwrap(), 4);
+
+        let mut blocks = Vec::new();
+        let mut uploaded = 0;
+        while uploaded < length {
+            let block_length = block_size.min(length - uploaded);
+            blocks.push(read_block(&mut file, block_length).unwrap());
+            uploaded += block_length;
+        }
+        assert_eq!(blocks, vec![b"012".to_vec(), b"345".to_vec(), b"678".to_vec(), b"9".to_vec()]);
+        check_unchanged(&file, length).unwrap();
+        assert_eq!(block_id(7).len(), block_id(49_999).len());
+
+        // the file grows while being uploaded
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"!").unwrap();
+        match check_unchanged(&file, length) {
+            Err(AzureError::GenericErrorWithText(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        // and then shrinks
+        fs::write(&path, b"01").unwrap();
+        let mut file = File::open(&path).unwrap();
+        match read_block(&mut file, 3) {
+            Err(AzureError::GenericErrorWithText(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        assert!(block_count(MAX_BLOCKS * 2, 1).is_err());
+        assert!(block_count(10, 0).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    fn created(_: &str) -> (&'static str, String, String) {
+        let headers = format!(
+            "ETag: \"0x8D7C1\"\r\nLast-Modified: Tue, 14 Jan 2020 10:00:00 GMT\r\nContent-MD5: {}\r\n\
+             x-ms-request-id: {}\r\nDate: Tue, 14 Jan 2020 10:00:00 GMT\r\nx-ms-request-server-encrypted: true\r\n",
+            ::base64::encode(&::md5::compute(b"0123456789")[..]),
+            ::uuid::Uuid::new_v4()
+        );
+        ("201 Created", headers, String::new())
+    }
+
+    #[test]
+    fn small_files_are_put_whole_and_large_ones_in_blocks() {
+        use azure::core::test_server::serve_recorded;
+        use tokio_core::reactor::Core;
+
+        let path = env::temp_dir().join(format!("azure_sdk_upload_{}.txt", ::uuid::Uuid::new_v4()));
+        fs::write(&path, b"0123456789").unwrap();
+        let mut core = Core::new().unwrap();
+        let mut upload = |max_single_put_size| {
+            let (endpoint, requests) = serve_recorded(created);
+            let client = Client::new("mindflavor", "ZHVtbXk=").unwrap().with_blob_endpoint(endpoint);
+            let mut progress = Vec::new();
+            let record = |uploaded, total| progress.push((uploaded, total));
+            let upload = upload_file(&client, "logs", "data.txt", &path, 3, max_single_put_size, 1, record);
+            let uploaded = core.run(upload).unwrap();
+            assert_eq!(uploaded, 10);
+            assert_eq!(progress.last(), Some(&(10, 10)));
+            let request_lines: Vec<String> = requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|request| request.lines().next().unwrap().to_owned())
+                .collect();
+            request_lines
+        };
+
+        let under = upload(10);
+        assert_eq!(under.len(), 1);
+        assert!(under[0].starts_with("PUT /logs/data.txt "), "{}", under[0]);
+
+        let over = upload(9);
+        assert_eq!(over.len(), 5);
+        let staged = |line: &&String| line.starts_with("PUT ") && line.contains("comp=block&");
+        assert_eq!(over[..4].iter().filter(staged).count(), 4);
+        assert!(over[4].starts_with("PUT ") && over[4].contains("comp=blocklist"), "{}", over[4]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn blocks_staged_in_parallel_are_committed_in_order() {
+        use azure::core::errors::UnexpectedHTTPResult;
+        use futures::future;
+        use hyper::StatusCode;
+        use std::cell::RefCell;
+
+        let path = env::temp_dir().join(format!("azure_sdk_upload_{}.txt", ::uuid::Uuid::new_v4()));
+        fs::write(&path, b"0123456789").unwrap();
+
+        // the third block fails once, as if the service were busy
+        let staged = RefCell::new(Vec::new());
+        let stage = |id: &[u8], block: &[u8]| {
+            let mut staged = staged.borrow_mut();
+            let attempt = staged.iter().filter(|(staged_id, _)| staged_id == id).count();
+            staged.push((id.to_vec(), block.to_vec()));
+            if id == &block_id(2)[..] && attempt == 0 {
+                future::err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(
+                    StatusCode::CREATED,
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "ServerBusy",
+                )))
+            } else {
+                future::ok(())
+            }
+        };
+        let mut progress = Vec::new();
+
+        let stage_all = stage_blocks(File::open(&path).unwrap(), 10, 3, 3, stage, |uploaded, total| {
+            progress.push((uploaded, total))
+        });
+        let block_list = ::tokio_core::reactor::Core::new().unwrap().run(stage_all).unwrap();
+
+        let ids: Vec<Vec<u8>> = (0..4).map(block_id).collect();
+        assert_eq!(
+            block_list.blocks,
+            ids.iter().cloned().map(BlobBlockType::Uncommitted).collect::<Vec<_>>()
+        );
+        let staged = staged.into_inner();
+        assert_eq!(staged.len(), 5);
+        let third: Vec<&Vec<u8>> = staged.iter().filter(|(id, _)| id == &ids[2]).map(|(_, block)| block).collect();
+        assert_eq!(third, vec![&b"678".to_vec(), &b"678".to_vec()]);
+        assert_eq!(progress.len(), 4);
+        assert_eq!(progress.last(), Some(&(10, 10)));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn chunks_of_a_stream_are_staged_in_blocks() {
+        use futures::future;
+        use std::cell::RefCell;
+
+        let chunks: Vec<Result<Bytes, AzureError>> = vec![b"01".to_vec(), b"2345".to_vec(), b"".to_vec(), b"6".to_vec(), b"789".to_vec()]
+            .into_iter()
+            .map(|chunk| Ok(Bytes::from(chunk)))
+            .collect();
+        let staged = RefCell::new(Vec::new());
+        let stage = |id: &[u8], block: &[u8]| {
+            staged.borrow_mut().push((id.to_vec(), block.to_vec()));
+            future::ok(())
+        };
+
+        let (length, block_list) = stage_stream(stream::iter_result(chunks), 3, 2, &stage).wait().unwrap();
+        assert_eq!(length, 10);
+        let ids: Vec<Vec<u8>> = (0..4).map(block_id).collect();
+        assert_eq!(
+            block_list.blocks,
+            ids.iter().cloned().map(BlobBlockType::Uncommitted).collect::<Vec<_>>()
+        );
+        let mut blocks = staged.borrow().clone();
+        blocks.sort();
+        assert_eq!(
+            blocks,
+            ids.into_iter()
+                .zip(vec![b"012".to_vec(), b"345".to_vec(), b"678".to_vec(), b"9".to_vec()])
+                .collect::<Vec<_>>()
+        );
+
+        // the stream fails after its first block
+        staged.borrow_mut().clear();
+        let failing: Vec<Result<Bytes, AzureError>> = vec![
+            Ok(Bytes::from(&b"0123"[..])),
+            Err(AzureError::GenericErrorWithText("connection reset".to_owned())),
+            Ok(Bytes::from(&b"45"[..])),
+        ];
+        match stage_stream(stream::iter_result(failing), 3, 1, &stage).wait() {
+            Err(AzureError::GenericErrorWithText(message)) => assert_eq!(message, "connection reset"),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(staged.borrow().clone(), vec![(block_id(0), b"012".to_vec())]);
+
+        let (length, block_list) = stage_stream(stream::empty::<Bytes, AzureError>(), 3, 1, &stage).wait().unwrap();
+        assert_eq!(length, 0);
+        assert!(block_list.blocks.is_empty());
+        assert!(stage_stream(stream::empty::<Bytes, AzureError>(), 0, 1, &stage).wait().is_err());
+    }
+}
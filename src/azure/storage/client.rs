@@ -1,18 +1,19 @@
-use super::rest_client::{perform_request, ServiceType};
+use super::rest_client::{perform_request, CorrelatedResponseFuture, ServiceType};
+#[cfg(test)]
+use super::rest_client::{prepare_request, send_request};
+use azure::core::client_builder::{ClientBuilder, HyperClient};
 use azure::core::errors::AzureError;
+use azure::core::util::lookup_var;
 use azure::core::No;
 use azure::storage::{blob, container};
-use hyper::{self, Method};
-use hyper_tls;
+use hyper::Method;
 use std::borrow::Borrow;
-
-// Can be variant for different cloud environment
-const SERVICE_SUFFIX_BLOB: &str = ".blob.core.windows.net";
-const SERVICE_SUFFIX_TABLE: &str = ".table.core.windows.net";
+use std::env;
 
 pub trait Blob {
     fn list_blobs<'a>(&'a self) -> blob::requests::ListBlobBuilder<'a, No>;
     fn get_blob<'a>(&'a self) -> blob::requests::GetBlobBuilder<'a, No, No>;
+    fn blob_exists<'a>(&'a self) -> blob::requests::BlobExistsBuilder<'a, No, No>;
     fn put_block_blob<'a>(&'a self) -> blob::requests::PutBlockBlobBuilder<'a, No, No, No>;
     fn put_page_blob<'a>(&'a self) -> blob::requests::PutPageBlobBuilder<'a, No, No, No>;
     fn put_append_blob<'a>(&'a self) -> blob::requests::PutAppendBlobBuilder<'a, No, No>;
@@ -21,6 +22,15 @@ pub trait Blob {
     fn put_block<'a>(&'a self) -> blob::requests::PutBlockBuilder<'a, No, No, No, No>;
     fn get_block_list<'a>(&'a self) -> blob::requests::GetBlockListBuilder<'a, No, No, No>;
     fn put_block_list<'a, T: Borrow<[u8]> + 'a>(&'a self) -> blob::requests::PutBlockListBuilder<'a, T, No, No, No>;
+    fn snapshot_blob<'a>(&'a self) -> blob::requests::SnapshotBlobBuilder<'a, No, No>;
+    fn set_blob_properties<'a>(&'a self) -> blob::requests::SetBlobPropertiesBuilder<'a, No, No>;
+    fn get_blob_tags<'a>(&'a self) -> blob::requests::GetBlobTagsBuilder<'a, No, No>;
+    fn set_blob_tags<'a>(&'a self) -> blob::requests::SetBlobTagsBuilder<'a, No, No, No>;
+    fn find_blobs_by_tags<'a>(&'a self) -> blob::requests::FindBlobsByTagsBuilder<'a, No>;
+    fn query_blob<'a>(&'a self) -> blob::requests::QueryBlobBuilder<'a, No, No, No>;
+    fn undelete_blob<'a>(&'a self) -> blob::requests::UndeleteBlobBuilder<'a, No, No>;
+    fn get_account_information<'a>(&'a self) -> blob::requests::GetAccountInformationBuilder<'a>;
+    fn blob_batch<'a>(&'a self) -> blob::requests::BlobBatchBuilder<'a, No>;
 }
 
 pub trait Container {
@@ -29,6 +39,7 @@ pub trait Container {
     fn list_containers<'a>(&'a self) -> container::requests::ListBuilder<'a>;
     fn get_container_acl<'a>(&'a self) -> container::requests::GetACLBuilder<'a, No>;
     fn set_container_acl<'a>(&'a self) -> container::requests::SetACLBuilder<'a, No, No>;
+    fn set_container_public_access<'a>(&'a self) -> container::requests::SetPublicAccessBuilder<'a, No, No>;
     fn get_container_properties<'a>(&'a self) -> container::requests::GetPropertiesBuilder<'a, No>;
     fn acquire_container_lease<'a>(&'a self) -> container::requests::AcquireLeaseBuilder<'a, No, No>;
     fn renew_container_lease<'a>(&'a self) -> container::requests::RenewLeaseBuilder<'a, No, No>;
@@ -36,11 +47,14 @@ pub trait Container {
     fn break_container_lease<'a>(&'a self) -> container::requests::BreakLeaseBuilder<'a, No>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Client {
     account: String,
     key: String,
-    hc: hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>,
+    endpoint_suffix: String,
+    hc: HyperClient,
+    #[cfg(test)]
+    blob_endpoint: Option<String>,
 }
 
 impl Blob for Client {
@@ -52,6 +66,10 @@ impl Blob for Client {
         blob::requests::GetBlobBuilder::new(self)
     }
 
+    fn blob_exists<'a>(&'a self) -> blob::requests::BlobExistsBuilder<'a, No, No> {
+        blob::requests::BlobExistsBuilder::new(self)
+    }
+
     fn put_block_blob<'a>(&'a self) -> blob::requests::PutBlockBlobBuilder<'a, No, No, No> {
         blob::requests::PutBlockBlobBuilder::new(self)
     }
@@ -83,6 +101,42 @@ impl Blob for Client {
     fn put_block_list<'a, T: Borrow<[u8]> + 'a>(&'a self) -> blob::requests::PutBlockListBuilder<'a, T, No, No, No> {
         blob::requests::PutBlockListBuilder::new(self)
     }
+
+    fn snapshot_blob<'a>(&'a self) -> blob::requests::SnapshotBlobBuilder<'a, No, No> {
+        blob::requests::SnapshotBlobBuilder::new(self)
+    }
+
+    fn set_blob_properties<'a>(&'a self) -> blob::requests::SetBlobPropertiesBuilder<'a, No, No> {
+        blob::requests::SetBlobPropertiesBuilder::new(self)
+    }
+
+    fn get_blob_tags<'a>(&'a self) -> blob::requests::GetBlobTagsBuilder<'a, No, No> {
+        blob::requests::GetBlobTagsBuilder::new(self)
+    }
+
+    fn set_blob_tags<'a>(&'a self) -> blob::requests::SetBlobTagsBuilder<'a, No, No, No> {
+        blob::requests::SetBlobTagsBuilder::new(self)
+    }
+
+    fn find_blobs_by_tags<'a>(&'a self) -> blob::requests::FindBlobsByTagsBuilder<'a, No> {
+        blob::requests::FindBlobsByTagsBuilder::new(self)
+    }
+
+    fn query_blob<'a>(&'a self) -> blob::requests::QueryBlobBuilder<'a, No, No, No> {
+        blob::requests::QueryBlobBuilder::new(self)
+    }
+
+    fn undelete_blob<'a>(&'a self) -> blob::requests::UndeleteBlobBuilder<'a, No, No> {
+        blob::requests::UndeleteBlobBuilder::new(self)
+    }
+
+    fn get_account_information<'a>(&'a self) -> blob::requests::GetAccountInformationBuilder<'a> {
+        blob::requests::GetAccountInformationBuilder::new(self)
+    }
+
+    fn blob_batch<'a>(&'a self) -> blob::requests::BlobBatchBuilder<'a, No> {
+        blob::requests::BlobBatchBuilder::new(self)
+    }
 }
 
 impl Container for Client {
@@ -106,6 +160,10 @@ impl Container for Client {
         container::requests::SetACLBuilder::new(self)
     }
 
+    fn set_container_public_access<'a>(&'a self) -> container::requests::SetPublicAccessBuilder<'a, No, No> {
+        container::requests::SetPublicAccessBuilder::new(self)
+    }
+
     fn get_container_properties<'a>(&'a self) -> container::requests::GetPropertiesBuilder<'a, No> {
         container::requests::GetPropertiesBuilder::new(self)
     }
@@ -129,15 +187,43 @@ impl Container for Client {
 
 impl Client {
     pub fn new(account: &str, key: &str) -> Result<Client, AzureError> {
-        use hyper;
+        Client::with_builder(account, key, &ClientBuilder::default())
+    }
 
-        let client = hyper::Client::builder().build(hyper_tls::HttpsConnector::new(4)?);
+    pub fn with_builder(account: &str, key: &str, builder: &ClientBuilder) -> Result<Client, AzureError> {
+        let client = builder.build_hyper_client()?;
+        Ok(Client::with_hyper_client(account, key, builder, client))
+    }
 
-        Ok(Client {
+    pub(crate) fn with_hyper_client(account: &str, key: &str, builder: &ClientBuilder, hc: HyperClient) -> Client {
+        Client {
             account: account.to_owned(),
             key: key.to_owned(),
-            hc: client,
-        })
+            endpoint_suffix: builder.endpoint_suffix().to_owned(),
+            hc,
+            #[cfg(test)]
+            blob_endpoint: None,
+        }
+    }
+
+    // Sends the blob requests to `endpoint`, a local test server, instead.
+    #[cfg(test)]
+    pub(crate) fn with_blob_endpoint(mut self, endpoint: String) -> Client {
+        self.blob_endpoint = Some(endpoint);
+        self
+    }
+
+    /// Builds a client reading the account and its key from
+    /// `STORAGE_ACCOUNT` and `STORAGE_MASTER_KEY`.
+    pub fn from_env() -> Result<Client, AzureError> {
+        Client::from_lookup(|name| env::var(name).ok())
+    }
+
+    // `from_env` reading the variables with `lookup`, see `lookup_var`.
+    fn from_lookup<L: Fn(&str) -> Option<String>>(lookup: L) -> Result<Client, AzureError> {
+        let account = lookup_var(&lookup, "STORAGE_ACCOUNT")?;
+        let key = lookup_var(&lookup, "STORAGE_MASTER_KEY")?;
+        Client::new(&account, &key)
     }
 
     pub fn account(&self) -> &str {
@@ -148,16 +234,32 @@ impl Client {
         &self.key
     }
 
+    /// Host suffix of the storage services, `core.windows.net` in the
+    /// public cloud.
+    pub fn endpoint_suffix(&self) -> &str {
+        &self.endpoint_suffix
+    }
+
     pub fn perform_request<F>(
         &self,
         uri: &str,
         method: Method,
         headers_func: F,
         request_body: Option<&[u8]>,
-    ) -> Result<hyper::client::ResponseFuture, AzureError>
+    ) -> Result<CorrelatedResponseFuture, AzureError>
     where
         F: FnOnce(&mut ::http::request::Builder),
     {
+        // the request is signed for the account before being redirected
+        #[cfg(test)]
+        {
+            if let Some(ref endpoint) = self.blob_endpoint {
+                let mut request = prepare_request(uri, method, &self.key, headers_func, request_body, ServiceType::Blob)?;
+                *request.uri_mut() = uri.replacen(&self.get_uri_prefix(&ServiceType::Blob), endpoint, 1).parse().unwrap();
+                return Ok(send_request(&self.hc, request));
+            }
+        }
+
         perform_request(&self.hc, uri, method, &self.key, headers_func, request_body, ServiceType::Blob)
     }
 
@@ -167,7 +269,7 @@ impl Client {
         method: Method,
         headers_func: F,
         request_str: Option<&[u8]>,
-    ) -> Result<hyper::client::ResponseFuture, AzureError>
+    ) -> Result<CorrelatedResponseFuture, AzureError>
     where
         F: FnOnce(&mut ::http::request::Builder),
     {
@@ -185,12 +287,60 @@ impl Client {
 
     /// Uri scheme + authority e.g. http://myaccount.table.core.windows.net/
     pub fn get_uri_prefix(&self, service_type: &ServiceType) -> String {
-        "https://".to_owned()
-            + self.account()
-            + match *service_type {
-                ServiceType::Blob => SERVICE_SUFFIX_BLOB,
-                ServiceType::Table => SERVICE_SUFFIX_TABLE,
-            }
-            + "/"
+        let service = match *service_type {
+            ServiceType::Blob => "blob",
+            ServiceType::Table => "table",
+        };
+        format!("https://{}.{}.{}/", self.account(), service, self.endpoint_suffix())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use azure::core::client_builder::AzureCloud;
+    use azure::core::ContainerNameSupport;
+
+    #[test]
+    fn cloud_uri_prefix() {
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap();
+        assert_eq!(
+            client.get_uri_prefix(&ServiceType::Blob),
+            "https://mindflavor.blob.core.windows.net/"
+        );
+
+        let builder = ClientBuilder::new().with_cloud(AzureCloud::Germany);
+        let client = Client::with_builder("mindflavor", "ZHVtbXk=", &builder).unwrap();
+        assert_eq!(
+            client.get_uri_prefix(&ServiceType::Table),
+            "https://mindflavor.table.core.cloudapi.de/"
+        );
+
+        let builder = builder.with_endpoint_suffix("local.azurestack.external");
+        let client = Client::with_builder("mindflavor", "ZHVtbXk=", &builder).unwrap();
+        assert_eq!(
+            client.get_uri_prefix(&ServiceType::Blob),
+            "https://mindflavor.blob.local.azurestack.external/"
+        );
+        assert_eq!(
+            client.list_blobs().with_container_name("logs").uri(),
+            "https://mindflavor.blob.local.azurestack.external/logs?restype=container&comp=list"
+        );
+    }
+
+    #[test]
+    fn from_env() {
+        let lookup = |name: &str| match name {
+            "STORAGE_ACCOUNT" => Some("mindflavor".to_owned()),
+            _ => None,
+        };
+        match Client::from_lookup(lookup) {
+            Err(AzureError::InputParametersError(msg)) => assert!(msg.contains("STORAGE_MASTER_KEY")),
+            r => panic!("unexpected result {:?}", r.map(|c| c.account().to_owned())),
+        }
+
+        let lookup = |name: &str| Some(if name == "STORAGE_ACCOUNT" { "mindflavor" } else { "ZHVtbXk=" }.to_owned());
+        let client = Client::from_lookup(lookup).unwrap();
+        assert_eq!(client.account(), "mindflavor");
     }
 }
@@ -4,8 +4,8 @@ pub mod responses;
 use azure::core::{
     enumerations,
     errors::{AzureError, TraversingError},
-    headers::{BLOB_PUBLIC_ACCESS, HAS_IMMUTABILITY_POLICY, HAS_LEGAL_HOLD, LEASE_DURATION, LEASE_STATE, LEASE_STATUS, META_PREFIX},
-    lease::{LeaseDuration, LeaseState, LeaseStatus},
+    headers::{BLOB_PUBLIC_ACCESS, HAS_IMMUTABILITY_POLICY, HAS_LEGAL_HOLD, META_PREFIX},
+    lease::{LeaseDuration, LeaseInfo, LeaseState, LeaseStatus},
     parsing::{cast_must, cast_optional, traverse, FromStringOptional},
 };
 use chrono::{DateTime, Utc};
@@ -55,6 +55,8 @@ pub struct Container {
     pub metadata: HashMap<String, String>,
 }
 
+impl_etag!(Container, e_tag);
+
 impl AsRef<str> for Container {
     fn as_ref(&self) -> &str {
         &self.name
@@ -90,22 +92,7 @@ impl Container {
             None => return Err(AzureError::MissingHeaderError(header::ETAG.as_str().to_owned())),
         };
 
-        let lease_status = match headers.get(LEASE_STATUS) {
-            Some(lease_status) => lease_status.to_str()?,
-            None => return Err(AzureError::MissingHeaderError(LEASE_STATUS.to_owned())),
-        };
-        let lease_status = LeaseStatus::from_str(lease_status)?;
-
-        let lease_state = match headers.get(LEASE_STATE) {
-            Some(lease_state) => lease_state.to_str()?,
-            None => return Err(AzureError::MissingHeaderError(LEASE_STATE.to_owned())),
-        };
-        let lease_state = LeaseState::from_str(lease_state)?;
-
-        let lease_duration = match headers.get(LEASE_DURATION) {
-            Some(lease_duration) => Some(LeaseDuration::from_str(lease_duration.to_str()?)?),
-            None => None,
-        };
+        let lease_info = LeaseInfo::from_headers(headers)?;
 
         let public_access = public_access_from_header(&headers)?;
 
@@ -130,9 +117,9 @@ impl Container {
             name,
             last_modified,
             e_tag,
-            lease_status,
-            lease_state,
-            lease_duration,
+            lease_status: lease_info.status,
+            lease_state: lease_info.state,
+            lease_duration: lease_info.duration,
             public_access,
             has_immutability_policy,
             has_legal_hold,
@@ -192,8 +192,9 @@ impl<'a, ContainerNameSet> BreakLeaseBuilder<'a, ContainerNameSet> where Contain
 impl<'a> BreakLeaseBuilder<'a, Yes> {
     pub fn finalize(self) -> impl Future<Item = BreakLeaseResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}?comp=lease&restype=container",
+            "https://{}.blob.{}/{}?comp=lease&restype=container",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name()
         );
 
@@ -215,8 +215,9 @@ impl<'a> CreateBuilder<'a, No, No> {
 impl<'a> CreateBuilder<'a, Yes, Yes> {
     pub fn finalize(self) -> impl Future<Item = (), Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}?restype=container",
+            "https://{}.blob.{}/{}?restype=container",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name()
         );
 
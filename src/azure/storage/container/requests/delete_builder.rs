@@ -147,8 +147,9 @@ impl<'a> DeleteBuilder<'a, No> {
 impl<'a> DeleteBuilder<'a, Yes> {
     pub fn finalize(self) -> impl Future<Item = (), Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}?restype=container",
+            "https://{}.blob.{}/{}?restype=container",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name()
         );
 
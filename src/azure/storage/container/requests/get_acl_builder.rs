@@ -73,8 +73,9 @@ where
 impl<'a> GetACLBuilder<'a, Yes> {
     pub fn finalize(self) -> impl Future<Item = GetACLResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}?restype=container&comp=acl",
+            "https://{}.blob.{}/{}?restype=container&comp=acl",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name()
         );
 
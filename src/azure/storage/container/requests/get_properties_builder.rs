@@ -73,8 +73,9 @@ where
 impl<'a> GetPropertiesBuilder<'a, Yes> {
     pub fn finalize(self) -> impl Future<Item = GetPropertiesResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}?restype=container",
+            "https://{}.blob.{}/{}?restype=container",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name()
         );
 
@@ -79,8 +79,9 @@ impl<'a> ListBuilder<'a> {
 
     pub fn finalize(self) -> impl Future<Item = ListContainersResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net?comp=list&maxresults={}",
+            "https://{}.blob.{}?comp=list&maxresults={}",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.max_results()
         );
 
@@ -112,9 +113,10 @@ impl<'a> ListBuilder<'a> {
         done(req).from_err().and_then(move |future_response| {
             check_status_extract_headers_and_body_as_string(future_response, StatusCode::OK).and_then(move |(headers, body)| {
                 done(incomplete_vector_from_response(&body)).and_then(move |incomplete_vector| {
-                    done(request_id_from_headers(&headers)).map(|request_id| ListContainersResponse {
+                    done(request_id_from_headers(&headers)).map(move |request_id| ListContainersResponse {
                         incomplete_vector,
                         request_id,
+                        headers,
                     })
                 })
             })
@@ -8,6 +8,7 @@ mod list_builder;
 mod release_lease_builder;
 mod renew_lease_builder;
 mod set_acl_builder;
+mod set_public_access_builder;
 pub use self::acquire_lease_builder::AcquireLeaseBuilder;
 pub use self::break_lease_builder::BreakLeaseBuilder;
 pub use self::create_builder::CreateBuilder;
@@ -18,3 +19,4 @@ pub use self::list_builder::ListBuilder;
 pub use self::release_lease_builder::ReleaseLeaseBuilder;
 pub use self::renew_lease_builder::RenewLeaseBuilder;
 pub use self::set_acl_builder::SetACLBuilder;
+pub use self::set_public_access_builder::SetPublicAccessBuilder;
@@ -179,8 +179,9 @@ where
 impl<'a> ReleaseLeaseBuilder<'a, Yes, Yes> {
     pub fn finalize(self) -> impl Future<Item = ReleaseLeaseResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}?comp=lease&restype=container",
+            "https://{}.blob.{}/{}?comp=lease&restype=container",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name()
         );
 
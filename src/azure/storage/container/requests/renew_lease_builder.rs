@@ -179,8 +179,9 @@ where
 impl<'a> RenewLeaseBuilder<'a, Yes, Yes> {
     pub fn finalize(self) -> impl Future<Item = RenewLeaseResponse, Error = AzureError> {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}?comp=lease&restype=container",
+            "https://{}.blob.{}/{}?comp=lease&restype=container",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name()
         );
 
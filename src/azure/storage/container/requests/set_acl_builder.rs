@@ -7,7 +7,9 @@ use azure::core::{
 use azure::core::{No, StoredAccessPolicyList, ToAssign, Yes};
 use azure::storage::client::Client;
 use azure::storage::container::{public_access_from_header, PublicAccess, PublicAccessRequired, PublicAccessSupport};
+use azure::storage::rest_client::CorrelatedResponseFuture;
 use futures::future::{done, Future};
+use http::request::Builder;
 use hyper::{Method, StatusCode};
 use std::marker::PhantomData;
 
@@ -111,39 +113,49 @@ where
 }
 
 impl<'a> SetACLBuilder<'a, Yes, Yes> {
-    pub fn finalize(self) -> impl Future<Item = PublicAccess, Error = AzureError> {
+    pub(crate) fn uri(&self) -> String {
         let mut uri = format!(
-            "https://{}.blob.core.windows.net/{}?restype=container&comp=acl",
+            "https://{}.blob.{}/{}?restype=container&comp=acl",
             self.client().account(),
+            self.client().endpoint_suffix(),
             self.container_name()
         );
 
-        if let Some(nm) = TimeoutOption::to_uri_parameter(&self) {
+        if let Some(nm) = TimeoutOption::to_uri_parameter(self) {
             uri = format!("{}&{}", uri, nm);
         }
+        uri
+    }
 
-        let xml = if let Some(sapl) = self.stored_access_policy_list {
-            let xml = sapl.to_xml();
-            Some(xml)
-        } else {
-            None
-        };
+    pub(crate) fn add_headers(&self, request: &mut Builder) {
+        ClientRequestIdOption::add_header(self, request);
+        LeaseIdOption::add_header(self, request);
+        PublicAccessRequired::add_header(self, request);
+    }
 
-        let req = self.client().perform_request(
+    // Without a list, no access policy is sent.
+    pub(crate) fn body(&self) -> Result<String, AzureError> {
+        match self.stored_access_policy_list {
+            Some(sapl) => sapl.validate().map(|_| sapl.to_xml()),
+            None => Ok(String::new()),
+        }
+    }
+
+    // Sends the request. The response does not borrow the builder, so the
+    // policies can be dropped as soon as it is sent.
+    pub(crate) fn perform(&self) -> Result<CorrelatedResponseFuture, AzureError> {
+        let uri = self.uri();
+        let body = self.body()?;
+        self.client().perform_request(
             &uri,
             Method::PUT,
-            |ref mut request| {
-                ClientRequestIdOption::add_header(&self, request);
-                LeaseIdOption::add_header(&self, request);
-                PublicAccessRequired::add_header(&self, request);
-            },
-            match xml {
-                Some(ref x) => Some(x.as_bytes()),
-                None => Some(&[]),
-            },
-        );
+            |ref mut request| self.add_headers(request),
+            Some(body.as_bytes()),
+        )
+    }
 
-        done(req)
+    pub fn finalize(self) -> impl Future<Item = PublicAccess, Error = AzureError> {
+        done(self.perform())
             .from_err()
             .and_then(move |future_response| check_status_extract_headers_and_body(future_response, StatusCode::OK))
             .and_then(|(headers, _body)| done(public_access_from_header(&headers)))
@@ -0,0 +1,338 @@
+use azure::core::errors::{check_status_extract_headers_and_body, AzureError};
+use azure::core::lease::LeaseId;
+use azure::core::{
+    ClientRequestIdOption, ClientRequestIdSupport, ClientRequired, ContainerNameRequired, ContainerNameSupport, LeaseIdOption,
+    LeaseIdSupport, No, StoredAccessPolicyList, TimeoutOption, TimeoutSupport, ToAssign, Yes,
+};
+use azure::storage::client::{Client, Container};
+use azure::storage::container::requests::{GetACLBuilder, SetACLBuilder};
+use azure::storage::container::{public_access_from_header, PublicAccess, PublicAccessRequired, PublicAccessSupport};
+use futures::future::{done, Future};
+use hyper::StatusCode;
+use std::marker::PhantomData;
+
+/// Changes the public access level of an existing container: `Blob` lets
+/// anyone read the blobs, `Container` also lets anyone list them and
+/// `None` makes the container private again. Setting the access level
+/// replaces the whole ACL, so the stored access policies of the container
+/// are read first and sent back with it. A policy changed in between by
+/// someone else is overwritten.
+#[derive(Debug, Clone)]
+pub struct SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{
+    client: &'a Client,
+    p_container_name: PhantomData<ContainerNameSet>,
+    p_public_access: PhantomData<PublicAccessSet>,
+    container_name: Option<&'a str>,
+    public_access: PublicAccess,
+    client_request_id: Option<&'a str>,
+    timeout: Option<u64>,
+    lease_id: Option<&'a LeaseId>,
+}
+
+impl<'a> SetPublicAccessBuilder<'a, No, No> {
+    #[inline]
+    pub(crate) fn new(client: &'a Client) -> SetPublicAccessBuilder<'a, No, No> {
+        SetPublicAccessBuilder {
+            client,
+            p_container_name: PhantomData {},
+            container_name: None,
+            p_public_access: PhantomData {},
+            public_access: PublicAccess::None,
+            client_request_id: None,
+            timeout: None,
+            lease_id: None,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, PublicAccessSet> ClientRequired<'a> for SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{
+    #[inline]
+    fn client(&self) -> &'a Client {
+        self.client
+    }
+}
+
+impl<'a, PublicAccessSet> ContainerNameRequired<'a> for SetPublicAccessBuilder<'a, Yes, PublicAccessSet>
+where
+    PublicAccessSet: ToAssign,
+{
+    #[inline]
+    fn container_name(&self) -> &'a str {
+        self.container_name.unwrap()
+    }
+}
+
+impl<'a, ContainerNameSet> PublicAccessRequired for SetPublicAccessBuilder<'a, ContainerNameSet, Yes>
+where
+    ContainerNameSet: ToAssign,
+{
+    #[inline]
+    fn public_access(&self) -> PublicAccess {
+        self.public_access
+    }
+}
+
+impl<'a, ContainerNameSet, PublicAccessSet> ClientRequestIdOption<'a> for SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{
+    #[inline]
+    fn client_request_id(&self) -> Option<&'a str> {
+        self.client_request_id
+    }
+}
+
+impl<'a, ContainerNameSet, PublicAccessSet> TimeoutOption for SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{
+    #[inline]
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+}
+
+impl<'a, ContainerNameSet, PublicAccessSet> LeaseIdOption<'a> for SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{
+    #[inline]
+    fn lease_id(&self) -> Option<&'a LeaseId> {
+        self.lease_id
+    }
+}
+
+impl<'a, ContainerNameSet, PublicAccessSet> ContainerNameSupport<'a> for SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{
+    type O = SetPublicAccessBuilder<'a, Yes, PublicAccessSet>;
+
+    #[inline]
+    fn with_container_name(self, container_name: &'a str) -> Self::O {
+        SetPublicAccessBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_public_access: PhantomData {},
+            container_name: Some(container_name),
+            public_access: self.public_access,
+            client_request_id: self.client_request_id,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, PublicAccessSet> PublicAccessSupport for SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{
+    type O = SetPublicAccessBuilder<'a, ContainerNameSet, Yes>;
+
+    #[inline]
+    fn with_public_access(self, public_access: PublicAccess) -> Self::O {
+        SetPublicAccessBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_public_access: PhantomData {},
+            container_name: self.container_name,
+            public_access,
+            client_request_id: self.client_request_id,
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, PublicAccessSet> ClientRequestIdSupport<'a> for SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{
+    type O = SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>;
+
+    #[inline]
+    fn with_client_request_id(self, client_request_id: &'a str) -> Self::O {
+        SetPublicAccessBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_public_access: PhantomData {},
+            container_name: self.container_name,
+            public_access: self.public_access,
+            client_request_id: Some(client_request_id),
+            timeout: self.timeout,
+            lease_id: self.lease_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, PublicAccessSet> TimeoutSupport for SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{
+    type O = SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>;
+
+    #[inline]
+    fn with_timeout(self, timeout: u64) -> Self::O {
+        SetPublicAccessBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_public_access: PhantomData {},
+            container_name: self.container_name,
+            public_access: self.public_access,
+            client_request_id: self.client_request_id,
+            timeout: Some(timeout),
+            lease_id: self.lease_id,
+        }
+    }
+}
+
+impl<'a, ContainerNameSet, PublicAccessSet> LeaseIdSupport<'a> for SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{
+    type O = SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>;
+
+    #[inline]
+    fn with_lease_id(self, lease_id: &'a LeaseId) -> Self::O {
+        SetPublicAccessBuilder {
+            client: self.client,
+            p_container_name: PhantomData {},
+            p_public_access: PhantomData {},
+            container_name: self.container_name,
+            public_access: self.public_access,
+            client_request_id: self.client_request_id,
+            timeout: self.timeout,
+            lease_id: Some(lease_id),
+        }
+    }
+}
+
+// methods callable regardless
+impl<'a, ContainerNameSet, PublicAccessSet> SetPublicAccessBuilder<'a, ContainerNameSet, PublicAccessSet>
+where
+    ContainerNameSet: ToAssign,
+    PublicAccessSet: ToAssign,
+{}
+
+impl<'a> SetPublicAccessBuilder<'a, Yes, Yes> {
+    fn get_acl(&self) -> GetACLBuilder<'a, Yes> {
+        let mut builder = self.client().get_container_acl().with_container_name(self.container_name());
+        if let Some(timeout) = self.timeout() {
+            builder = builder.with_timeout(timeout);
+        }
+        if let Some(client_request_id) = self.client_request_id() {
+            builder = builder.with_client_request_id(client_request_id);
+        }
+        if let Some(lease_id) = self.lease_id() {
+            builder = builder.with_lease_id(lease_id);
+        }
+        builder
+    }
+
+    // The access policies are those read from the container.
+    fn set_acl<'b>(&self, stored_access_policy_list: &'b StoredAccessPolicyList) -> SetACLBuilder<'b, Yes, Yes>
+    where
+        'a: 'b,
+    {
+        let mut builder = self
+            .client()
+            .set_container_acl()
+            .with_container_name(self.container_name())
+            .with_public_access(self.public_access())
+            .with_stored_access_policy_list(stored_access_policy_list);
+        if let Some(timeout) = self.timeout() {
+            builder = builder.with_timeout(timeout);
+        }
+        if let Some(client_request_id) = self.client_request_id() {
+            builder = builder.with_client_request_id(client_request_id);
+        }
+        if let Some(lease_id) = self.lease_id() {
+            builder = builder.with_lease_id(lease_id);
+        }
+        builder
+    }
+
+    #[inline]
+    pub fn finalize(self) -> impl Future<Item = PublicAccess, Error = AzureError> + 'a {
+        self.get_acl().finalize().and_then(move |acl| {
+            done(self.set_acl(&acl.stored_access_policy_list).perform())
+                .from_err()
+                .and_then(move |response| check_status_extract_headers_and_body(response, StatusCode::OK))
+                .and_then(|(headers, _body)| done(public_access_from_header(&headers)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use azure::core::headers::BLOB_PUBLIC_ACCESS;
+    use azure::core::StoredAccessPolicy;
+    use azure::storage::rest_client::{prepare_request, ServiceType};
+    use chrono::DateTime;
+    use hyper::Method;
+
+    #[test]
+    fn stored_access_policies_are_sent_back() {
+        let client = Client::new("mindflavor", "ZHVtbXk=").unwrap();
+        let mut policies = StoredAccessPolicyList::new();
+        policies.stored_access.push(StoredAccessPolicy::new(
+            "readers",
+            DateTime::parse_from_rfc3339("2018-01-01T00:00:00Z").unwrap(),
+            DateTime::parse_from_rfc3339("2019-01-01T00:00:00Z").unwrap(),
+            "r",
+        ));
+        let expected = [
+            (PublicAccess::Blob, Some("blob")),
+            (PublicAccess::Container, Some("container")),
+            (PublicAccess::None, None),
+        ];
+
+        for &(public_access, header) in expected.iter() {
+            let builder = client
+                .set_container_public_access()
+                .with_container_name("assets")
+                .with_public_access(public_access)
+                .with_timeout(30);
+            let get_acl = builder.get_acl();
+            assert_eq!((get_acl.container_name(), get_acl.timeout()), ("assets", Some(30)));
+
+            let set_acl = builder.set_acl(&policies);
+            assert_eq!(
+                set_acl.uri(),
+                "https://mindflavor.blob.core.windows.net/assets?restype=container&comp=acl&timeout=30"
+            );
+            assert_eq!(set_acl.body().unwrap(), policies.to_xml());
+
+            let request = prepare_request(
+                &set_acl.uri(),
+                Method::PUT,
+                client.key(),
+                |request| set_acl.add_headers(request),
+                None,
+                ServiceType::Blob,
+            )
+            .unwrap();
+            assert_eq!(
+                request.headers().get(BLOB_PUBLIC_ACCESS).map(|value| value.to_str().unwrap()),
+                header
+            );
+        }
+    }
+}
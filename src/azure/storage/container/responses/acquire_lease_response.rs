@@ -14,8 +14,12 @@ pub struct AcquireLeaseResponse {
     pub lease_id: LeaseId,
     pub request_id: RequestId,
     pub date: DateTime<FixedOffset>,
+    headers: HeaderMap,
 }
 
+impl_etag!(AcquireLeaseResponse, etag);
+impl_headers!(AcquireLeaseResponse);
+
 impl AcquireLeaseResponse {
     pub(crate) fn from_response(headers: &HeaderMap) -> Result<AcquireLeaseResponse, AzureError> {
         let etag = match headers.get(header::ETAG) {
@@ -53,6 +57,7 @@ impl AcquireLeaseResponse {
             lease_id,
             request_id,
             date,
+            headers: headers.clone(),
         })
     }
 }
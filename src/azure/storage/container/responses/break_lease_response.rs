@@ -13,8 +13,12 @@ pub struct BreakLeaseResponse {
     pub request_id: RequestId,
     pub lease_time: u8,
     pub date: DateTime<FixedOffset>,
+    headers: HeaderMap,
 }
 
+impl_etag!(BreakLeaseResponse, etag);
+impl_headers!(BreakLeaseResponse);
+
 impl BreakLeaseResponse {
     pub(crate) fn from_response(headers: &HeaderMap) -> Result<BreakLeaseResponse, AzureError> {
         let etag = match headers.get(header::ETAG) {
@@ -51,6 +55,7 @@ impl BreakLeaseResponse {
             request_id,
             lease_time,
             date,
+            headers: headers.clone(),
         })
     }
 }
@@ -16,8 +16,12 @@ pub struct GetACLResponse {
     pub request_id: RequestId,
     pub date: DateTime<FixedOffset>,
     pub stored_access_policy_list: StoredAccessPolicyList,
+    headers: HeaderMap,
 }
 
+impl_etag!(GetACLResponse, etag);
+impl_headers!(GetACLResponse);
+
 impl GetACLResponse {
     // this should be named into and be consuming
     pub(crate) fn from_response(body: &Chunk, headers: &HeaderMap) -> Result<GetACLResponse, AzureError> {
@@ -55,6 +59,7 @@ impl GetACLResponse {
             request_id: Uuid::parse_str(request_id)?,
             date,
             stored_access_policy_list,
+            headers: headers.clone(),
         })
     }
 }
@@ -1,5 +1,6 @@
 use azure::core::errors::AzureError;
 use azure::core::headers::REQUEST_ID;
+use azure::core::lease::LeaseInfo;
 use azure::core::RequestId;
 use azure::storage::container::Container;
 use chrono::{DateTime, FixedOffset};
@@ -10,10 +11,14 @@ use uuid::Uuid;
 #[derive(Debug, Clone)]
 pub struct GetPropertiesResponse {
     pub container: Container,
+    pub lease_info: LeaseInfo,
     pub request_id: RequestId,
     pub date: DateTime<FixedOffset>,
+    headers: HeaderMap,
 }
 
+impl_headers!(GetPropertiesResponse);
+
 impl GetPropertiesResponse {
     pub(crate) fn from_response(container_name: String, headers: &HeaderMap) -> Result<GetPropertiesResponse, AzureError> {
         let request_id = match headers.get(REQUEST_ID) {
@@ -27,11 +32,14 @@ impl GetPropertiesResponse {
         };
 
         let container = Container::from_response(container_name, headers)?;
+        let lease_info = LeaseInfo::from_headers(headers)?;
 
         Ok(GetPropertiesResponse {
             container,
+            lease_info,
             request_id,
             date,
+            headers: headers.clone(),
         })
     }
 }
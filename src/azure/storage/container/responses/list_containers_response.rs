@@ -1,13 +1,17 @@
 use azure::core::incompletevector::IncompleteVector;
 use azure::core::RequestId;
 use azure::storage::container::Container;
+use http::HeaderMap;
 
 #[derive(Debug, Clone)]
 pub struct ListContainersResponse {
     pub incomplete_vector: IncompleteVector<Container>,
     pub request_id: RequestId,
+    pub(crate) headers: HeaderMap,
 }
 
+impl_headers!(ListContainersResponse);
+
 impl ListContainersResponse {
     pub fn is_complete(&self) -> bool {
         self.incomplete_vector.is_complete()
@@ -12,8 +12,12 @@ pub struct ReleaseLeaseResponse {
     pub last_modified: DateTime<FixedOffset>,
     pub request_id: RequestId,
     pub date: DateTime<FixedOffset>,
+    headers: HeaderMap,
 }
 
+impl_etag!(ReleaseLeaseResponse, etag);
+impl_headers!(ReleaseLeaseResponse);
+
 impl ReleaseLeaseResponse {
     pub(crate) fn from_response(headers: &HeaderMap) -> Result<ReleaseLeaseResponse, AzureError> {
         let etag = match headers.get(header::ETAG) {
@@ -44,6 +48,7 @@ impl ReleaseLeaseResponse {
             last_modified,
             request_id,
             date,
+            headers: headers.clone(),
         })
     }
 }
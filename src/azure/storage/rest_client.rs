@@ -1,15 +1,17 @@
 use azure::core::{
+    client_builder::HyperClient,
     errors::AzureError,
     headers,
     util::{format_header_value, HeaderMapExt, RequestBuilderExt},
 };
 use base64;
 use chrono;
+use futures::{Async, Future, Poll};
 use hyper::{self, header, HeaderMap, Method};
-use hyper_tls;
 use ring::{digest::SHA256, hmac};
 use std::fmt::Write;
 use url;
+use uuid::Uuid;
 
 pub enum ServiceType {
     Blob,
@@ -34,6 +36,24 @@ fn generate_authorization(h: &HeaderMap, u: &url::Url, method: Method, hmac_key:
     format!("SharedKey {}:{}", get_account(u), auth)
 }
 
+// The Authorization header of a request carried in the body of another one,
+// such as the sub-requests of a blob batch.
+pub(crate) fn authorization(
+    h: &HeaderMap,
+    uri: &str,
+    http_method: Method,
+    azure_key: &str,
+    service_type: ServiceType,
+) -> Result<String, AzureError> {
+    let url = url::Url::parse(uri)?;
+    Ok(generate_authorization(h, &url, http_method, azure_key, service_type))
+}
+
+// The value of x-ms-date for a request sent now.
+pub(crate) fn request_date() -> String {
+    format!("{}", chrono::Utc::now().format("%a, %d %h %Y %T GMT"))
+}
+
 fn encode_str_to_sign(str_to_sign: &str, hmac_key: &str) -> String {
     let key = hmac::SigningKey::new(&SHA256, &base64::decode(hmac_key).unwrap());
     let sig = hmac::sign(&key, str_to_sign.as_bytes());
@@ -228,19 +248,88 @@ fn lexy_sort(vec: &url::form_urlencoded::Parse, query_param: &str) -> Vec<(Strin
 #[allow(unknown_lints)]
 #[allow(too_many_arguments)]
 pub fn perform_request<F>(
-    client: &hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>,
+    client: &HyperClient,
     uri: &str,
     http_method: Method,
     azure_key: &str,
     headers_func: F,
     request_body: Option<&[u8]>,
     service_type: ServiceType,
-) -> Result<hyper::client::ResponseFuture, AzureError>
+) -> Result<CorrelatedResponseFuture, AzureError>
 where
     F: FnOnce(&mut ::http::request::Builder),
 {
-    let dt = chrono::Utc::now();
-    let time = format!("{}", dt.format("%a, %d %h %Y %T GMT"));
+    let request = prepare_request(uri, http_method, azure_key, headers_func, request_body, service_type)?;
+    Ok(send_request(client, request))
+}
+
+pub(crate) fn send_request(client: &HyperClient, request: hyper::Request<hyper::Body>) -> CorrelatedResponseFuture {
+    let client_request_id = request
+        .headers()
+        .get_as_str(headers::CLIENT_REQUEST_ID)
+        .unwrap_or_default()
+        .to_owned();
+
+    CorrelatedResponseFuture {
+        inner: client.request(request),
+        client_request_id,
+    }
+}
+
+/// Response of a storage request. Once the response arrives the
+/// `x-ms-client-request-id` echoed by the service is compared with the
+/// one sent and a warning is logged if they differ.
+pub struct CorrelatedResponseFuture {
+    inner: hyper::client::ResponseFuture,
+    client_request_id: String,
+}
+
+impl CorrelatedResponseFuture {
+    pub fn client_request_id(&self) -> &str {
+        &self.client_request_id
+    }
+}
+
+impl Future for CorrelatedResponseFuture {
+    type Item = hyper::Response<hyper::Body>;
+    type Error = hyper::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.inner.poll()? {
+            Async::Ready(response) => {
+                check_client_request_id(&self.client_request_id, response.headers());
+                Ok(Async::Ready(response))
+            }
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+fn check_client_request_id(sent: &str, h: &HeaderMap) -> bool {
+    match h.get_as_str(headers::CLIENT_REQUEST_ID) {
+        Some(received) if received != sent => {
+            warn!(
+                "x-ms-client-request-id mismatch (sent == {:?}, received == {:?})",
+                sent, received
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+pub(crate) fn prepare_request<F>(
+    uri: &str,
+    http_method: Method,
+    azure_key: &str,
+    headers_func: F,
+    request_body: Option<&[u8]>,
+    service_type: ServiceType,
+) -> Result<hyper::Request<hyper::Body>, AzureError>
+where
+    F: FnOnce(&mut ::http::request::Builder),
+{
+    let time = request_date();
 
     let url = url::Url::parse(uri)?;
 
@@ -262,7 +351,23 @@ where
     // a Cow with 'static lifetime...
     headers_func(&mut request);
 
-    request.header_bytes(HEADER_DATE, time).header_static(HEADER_VERSION, AZURE_VERSION);
+    // every request gets a client request id so it can be traced
+    // through Azure support, even if the caller did not supply one.
+    let has_client_request_id = request
+        .headers_ref()
+        .map(|h| h.contains_key(headers::CLIENT_REQUEST_ID))
+        .unwrap_or(false);
+    if !has_client_request_id {
+        request.header_formatted(headers::CLIENT_REQUEST_ID, Uuid::new_v4());
+    }
+
+    // newer operations (such as blob tags) ask for a later version of the api.
+    let has_version = request.headers_ref().map(|h| h.contains_key(HEADER_VERSION)).unwrap_or(false);
+    if !has_version {
+        request.header_static(HEADER_VERSION, AZURE_VERSION);
+    }
+
+    request.header_bytes(HEADER_DATE, time);
 
     let b = request_body.map(|v| Vec::from(v).into()).unwrap_or_else(hyper::Body::empty);
     let mut request = request.body(b)?;
@@ -270,7 +375,7 @@ where
     let auth = generate_authorization(request.headers(), &url, http_method, azure_key, service_type);
     request.headers_mut().insert(header::AUTHORIZATION, format_header_value(auth)?);
 
-    Ok(client.request(request))
+    Ok(request)
 }
 
 mod test {
@@ -408,4 +513,72 @@ Wed, 03 May 2017 14:04:56 GMT
             "YuKoXELO9M9HXeeGaSXBr4Nk+CgPAEQhcwJ6tVtBRCw=".to_owned()
         );
     }
+
+    #[test]
+    fn prepare_head_request_signed() {
+        use super::*;
+
+        let request = prepare_request(
+            "https://mindflavor.blob.core.windows.net/container/blob",
+            Method::HEAD,
+            "ZHVtbXk=",
+            |_| {},
+            None,
+            ServiceType::Blob,
+        ).unwrap();
+
+        assert_eq!(request.method(), Method::HEAD);
+        assert!(request.headers().contains_key(header::AUTHORIZATION));
+        assert!(request.headers().contains_key(HEADER_DATE));
+        assert_eq!(request.headers()[HEADER_VERSION], AZURE_VERSION);
+        assert_eq!(request.headers()[header::CONTENT_LENGTH], "0");
+    }
+
+    #[test]
+    fn client_request_id_round_trip() {
+        use super::*;
+
+        let uri = "https://mindflavor.blob.core.windows.net/container/blob";
+        let request = prepare_request(
+            uri,
+            Method::GET,
+            "ZHVtbXk=",
+            |request| {
+                request.header(headers::CLIENT_REQUEST_ID, "my-request-id");
+            },
+            None,
+            ServiceType::Blob,
+        ).unwrap();
+        assert_eq!(request.headers()[headers::CLIENT_REQUEST_ID], "my-request-id");
+
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert(headers::CLIENT_REQUEST_ID, header::HeaderValue::from_static("my-request-id"));
+        assert!(check_client_request_id("my-request-id", &response_headers));
+        assert!(!check_client_request_id("another-request-id", &response_headers));
+        // the service does not always echo it back
+        assert!(check_client_request_id("my-request-id", &HeaderMap::new()));
+
+        let request = prepare_request(uri, Method::GET, "ZHVtbXk=", |_| {}, None, ServiceType::Blob).unwrap();
+        let generated = request.headers()[headers::CLIENT_REQUEST_ID].to_str().unwrap();
+        assert!(Uuid::parse_str(generated).is_ok());
+    }
+
+    #[test]
+    fn version_override() {
+        use super::*;
+
+        let request = prepare_request(
+            "https://mindflavor.blob.core.windows.net/container/blob?comp=tags",
+            Method::GET,
+            "ZHVtbXk=",
+            |request| {
+                request.header(HEADER_VERSION, "2019-12-12");
+            },
+            None,
+            ServiceType::Blob,
+        ).unwrap();
+
+        let versions: Vec<_> = request.headers().get_all(HEADER_VERSION).iter().collect();
+        assert_eq!(versions, vec!["2019-12-12"]);
+    }
 }
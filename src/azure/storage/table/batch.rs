@@ -1,57 +1,228 @@
-/* Table batch support
-* Current limitation:
-1. Only support single changeset in a batch request
-2. Only allow PUT and GET in changeset
-*/
-use super::entity_path;
+use super::{entity_path, OdataMetadata};
+use azure::core::errors::AzureError;
+use hyper::StatusCode;
 use serde::Serialize;
 use serde_json;
-
-const BATCH_BEGIN: &str = r#"--batch_a1e9d677-b28b-435e-a89e-87e6a768a431
-Content-Type: multipart/mixed; boundary=changeset_8a28b620-b4bb-458c-a177-0959fb14c977
-
-"#;
-const BATCH_END: &str = "--batch_a1e9d677-b28b-435e-a89e-87e6a768a431\n";
-const CHANGESET_BEGIN: &str = r#"--changeset_8a28b620-b4bb-458c-a177-0959fb14c977
-Content-Type: application/http
-Content-Transfer-Encoding: binary
-
-"#;
-const CHANGESET_END: &str = "--changeset_8a28b620-b4bb-458c-a177-0959fb14c977--\n";
-const UPDATE_HEADER: &str = "Content-Type: application/json\n";
-const ACCEPT_HEADER: &str = "Accept: application/json;odata=nometadata\n";
-const IF_MATCH_HEADER: &str = "If-Match: *\n";
+use std::str::FromStr;
+use uuid::Uuid;
 
 // RowKey, Payload. Payload None for deletion
+#[deprecated(note = "use TableBatch::insert_or_replace and TableBatch::delete")]
 pub struct BatchItem<T: Serialize>(String, Option<T>);
 
+#[allow(deprecated)]
 impl<T: Serialize> BatchItem<T> {
     pub fn new(row_key: String, value: Option<T>) -> Self {
         BatchItem(row_key, value)
     }
 }
 
-pub fn generate_batch_payload<T: Serialize>(uri_prefix: &str, table: &str, primary_key: &str, items: &[BatchItem<T>]) -> String {
-    let mut payload: String = BATCH_BEGIN.to_owned();
+// The items of `TableService::batch`: the ones with a payload insert or
+// replace their entity, the others delete it.
+#[allow(deprecated)]
+pub(crate) fn batch_of_items<T: Serialize>(
+    table_name: &str,
+    partition_key: &str,
+    items: &[BatchItem<T>],
+) -> Result<TableBatch, AzureError> {
+    let mut batch = TableBatch::new(table_name, partition_key);
     for item in items {
-        payload.push_str(CHANGESET_BEGIN);
-        payload.push_str(if item.1.is_some() { "PUT" } else { "DELETE" });
-        payload.push_str(" ");
-        payload.push_str(uri_prefix);
-        payload.push_str(entity_path(table, primary_key, item.0.as_str()).as_str());
-        payload.push_str(" HTTP/1.1\n");
-        payload.push_str(ACCEPT_HEADER);
-        if let Some(ref v) = item.1 {
-            payload.push_str(UPDATE_HEADER);
-            payload.push_str("\n");
-            payload.push_str(serde_json::to_string(v).unwrap().as_str());
-        } else {
-            payload.push_str(IF_MATCH_HEADER);
+        match item.1 {
+            Some(ref entity) => batch.insert_or_replace(&item.0, entity)?,
+            None => batch.delete(&item.0)?,
+        };
+    }
+    Ok(batch)
+}
+
+/// Most operations a batch can hold.
+pub const MAX_BATCH_OPERATIONS: usize = 100;
+
+const CRLF: &str = "\r\n";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BatchOperationKind {
+    Insert,
+    InsertOrReplace,
+    Replace,
+    Merge,
+    Delete,
+}
+
+#[derive(Debug, Clone)]
+struct BatchOperation {
+    kind: BatchOperationKind,
+    row_key: String,
+    body: Option<String>,
+}
+
+/// An entity group transaction: operations on the entities of a single
+/// partition of a table, applied all together or not at all. Replace,
+/// merge and delete apply whatever the current ETag of the entity.
+#[derive(Debug, Clone)]
+pub struct TableBatch {
+    table_name: String,
+    partition_key: String,
+    operations: Vec<BatchOperation>,
+    batch_id: Uuid,
+    changeset_id: Uuid,
+}
+
+impl TableBatch {
+    pub fn new<S: Into<String>, P: Into<String>>(table_name: S, partition_key: P) -> TableBatch {
+        TableBatch {
+            table_name: table_name.into(),
+            partition_key: partition_key.into(),
+            operations: Vec::new(),
+            batch_id: Uuid::new_v4(),
+            changeset_id: Uuid::new_v4(),
+        }
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    pub fn partition_key(&self) -> &str {
+        &self.partition_key
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Inserts `entity`, which must carry the `PartitionKey` of the batch
+    /// and its own `RowKey`.
+    pub fn insert<T: Serialize>(&mut self, entity: &T) -> Result<&mut TableBatch, AzureError> {
+        let body = self.serialize_entity(entity)?;
+        let row_key = match serde_json::from_str::<serde_json::Value>(&body)?.get("RowKey") {
+            Some(serde_json::Value::String(row_key)) => row_key.to_owned(),
+            _ => {
+                return Err(AzureError::InputParametersError(
+                    "the entities inserted in a batch must have a RowKey".to_owned(),
+                ))
+            }
+        };
+        self.push(BatchOperationKind::Insert, row_key, Some(body))
+    }
+
+    pub fn replace<T: Serialize>(&mut self, row_key: &str, entity: &T) -> Result<&mut TableBatch, AzureError> {
+        let body = self.serialize_entity(entity)?;
+        self.push(BatchOperationKind::Replace, row_key.to_owned(), Some(body))
+    }
+
+    /// Inserts `entity`, replacing the entity with the same `RowKey` if
+    /// there is one.
+    pub fn insert_or_replace<T: Serialize>(&mut self, row_key: &str, entity: &T) -> Result<&mut TableBatch, AzureError> {
+        let body = self.serialize_entity(entity)?;
+        self.push(BatchOperationKind::InsertOrReplace, row_key.to_owned(), Some(body))
+    }
+
+    /// Updates the properties of the entity found in `entity`, leaving the
+    /// others untouched.
+    pub fn merge<T: Serialize>(&mut self, row_key: &str, entity: &T) -> Result<&mut TableBatch, AzureError> {
+        let body = self.serialize_entity(entity)?;
+        self.push(BatchOperationKind::Merge, row_key.to_owned(), Some(body))
+    }
+
+    pub fn delete(&mut self, row_key: &str) -> Result<&mut TableBatch, AzureError> {
+        self.push(BatchOperationKind::Delete, row_key.to_owned(), None)
+    }
+
+    // An entity of another partition would fail the whole batch: better
+    // to tell before sending it.
+    fn serialize_entity<T: Serialize>(&self, entity: &T) -> Result<String, AzureError> {
+        let value = serde_json::to_value(entity)?;
+        match value.get("PartitionKey") {
+            None => {}
+            Some(serde_json::Value::String(ref partition_key)) if *partition_key == self.partition_key => {}
+            Some(partition_key) => {
+                return Err(AzureError::InputParametersError(format!(
+                    "the entity has the PartitionKey {}, the batch is on {:?}",
+                    partition_key, self.partition_key
+                )))
+            }
         }
+        Ok(serde_json::to_string(&value)?)
+    }
+
+    fn push(&mut self, kind: BatchOperationKind, row_key: String, body: Option<String>) -> Result<&mut TableBatch, AzureError> {
+        if self.operations.len() >= MAX_BATCH_OPERATIONS {
+            return Err(AzureError::InputParametersError(format!(
+                "a batch can hold at most {} operations",
+                MAX_BATCH_OPERATIONS
+            )));
+        }
+        self.operations.push(BatchOperation { kind, row_key, body });
+        Ok(self)
+    }
+
+    pub(crate) fn content_type(&self) -> String {
+        format!("multipart/mixed; boundary=batch_{}", self.batch_id)
+    }
+
+    pub(crate) fn payload(&self, uri_prefix: &str, metadata_level: OdataMetadata) -> String {
+        let mut payload = String::new();
+        let mut line = |s: &str| {
+            payload.push_str(s);
+            payload.push_str(CRLF);
+        };
+
+        line(&format!("--batch_{}", self.batch_id));
+        line(&format!("Content-Type: multipart/mixed; boundary=changeset_{}", self.changeset_id));
+        line("");
+
+        for operation in &self.operations {
+            line(&format!("--changeset_{}", self.changeset_id));
+            line("Content-Type: application/http");
+            line("Content-Transfer-Encoding: binary");
+            line("");
 
-        payload.push_str("\n");
+            let (method, path) = match operation.kind {
+                BatchOperationKind::Insert => ("POST", self.table_name.clone()),
+                BatchOperationKind::InsertOrReplace | BatchOperationKind::Replace => {
+                    ("PUT", entity_path(&self.table_name, &self.partition_key, &operation.row_key))
+                }
+                BatchOperationKind::Merge => ("MERGE", entity_path(&self.table_name, &self.partition_key, &operation.row_key)),
+                BatchOperationKind::Delete => ("DELETE", entity_path(&self.table_name, &self.partition_key, &operation.row_key)),
+            };
+            line(&format!("{} {}{} HTTP/1.1", method, uri_prefix, path));
+            line(&format!("Accept: {}", metadata_level.accept()));
+            line("DataServiceVersion: 3.0;");
+            match operation.kind {
+                BatchOperationKind::Insert => line("Prefer: return-no-content"),
+                BatchOperationKind::InsertOrReplace => {}
+                _ => line("If-Match: *"),
+            }
+
+            match operation.body {
+                Some(ref body) => {
+                    line("Content-Type: application/json");
+                    line("");
+                    line(body);
+                }
+                None => line(""),
+            }
+        }
+
+        line(&format!("--changeset_{}--", self.changeset_id));
+        line(&format!("--batch_{}--", self.batch_id));
+        payload
     }
-    payload + CHANGESET_END + BATCH_END
+}
+
+// The batch is answered with 202 even when it fails: the status of the
+// changeset is in the body, as the one of the operation that failed.
+pub(crate) fn batch_response_status(body: &str) -> Option<StatusCode> {
+    body.lines()
+        .filter(|line| line.starts_with("HTTP/1.1 "))
+        .filter_map(|line| line["HTTP/1.1 ".len()..].split(' ').next())
+        .filter_map(|code| StatusCode::from_str(code).ok())
+        .find(|status| status.is_client_error() || status.is_server_error())
 }
 
 #[cfg(test)]
@@ -68,61 +239,138 @@ mod test {
     }
 
     #[test]
-    fn verify_batch_payload() {
-        let expected = r#"--batch_a1e9d677-b28b-435e-a89e-87e6a768a431
-Content-Type: multipart/mixed; boundary=changeset_8a28b620-b4bb-458c-a177-0959fb14c977
-
---changeset_8a28b620-b4bb-458c-a177-0959fb14c977
-Content-Type: application/http
-Content-Transfer-Encoding: binary
-
-PUT https://myaccount.table.core.windows.net/Blogs(PartitionKey='Channel_17',RowKey='3') HTTP/1.1
-Accept: application/json;odata=nometadata
-Content-Type: application/json
-
-{"PartitionKey":"Channel_17","RowKey":"3","Rating":9,"Text":".NET..."}
---changeset_8a28b620-b4bb-458c-a177-0959fb14c977
-Content-Type: application/http
-Content-Transfer-Encoding: binary
-
-PUT https://myaccount.table.core.windows.net/Blogs(PartitionKey='Channel_17',RowKey='3') HTTP/1.1
-Accept: application/json;odata=nometadata
-Content-Type: application/json
-
-{"PartitionKey":"Channel_17","RowKey":"3","Rating":9,"Text":"PDC 2008..."}
---changeset_8a28b620-b4bb-458c-a177-0959fb14c977
-Content-Type: application/http
-Content-Transfer-Encoding: binary
-
-DELETE https://myaccount.table.core.windows.net/Blogs(PartitionKey='Channel_17',RowKey='3') HTTP/1.1
-Accept: application/json;odata=nometadata
-If-Match: *
-
---changeset_8a28b620-b4bb-458c-a177-0959fb14c977--
---batch_a1e9d677-b28b-435e-a89e-87e6a768a431
-"#;
-
-        let items = vec![
-            bupdate("Channel_17", "3", 9, ".NET..."),
-            bupdate("Channel_17", "3", 9, "PDC 2008..."),
-            bdelete("3"),
+    #[allow(deprecated)]
+    fn batch_items() {
+        let items = vec![bupdate("Channel_17", "3", 9, ".NET..."), bdelete("4")];
+        let mut batch = batch_of_items("Blogs", "Channel_17", items.as_slice()).unwrap();
+        batch.batch_id = Uuid::parse_str("a1e9d677-b28b-435e-a89e-87e6a768a431").unwrap();
+        batch.changeset_id = Uuid::parse_str("8a28b620-b4bb-458c-a177-0959fb14c977").unwrap();
+
+        let expected = [
+            "--batch_a1e9d677-b28b-435e-a89e-87e6a768a431",
+            "Content-Type: multipart/mixed; boundary=changeset_8a28b620-b4bb-458c-a177-0959fb14c977",
+            "",
+            "--changeset_8a28b620-b4bb-458c-a177-0959fb14c977",
+            "Content-Type: application/http",
+            "Content-Transfer-Encoding: binary",
+            "",
+            "PUT https://myaccount.table.core.windows.net/Blogs(PartitionKey='Channel_17',RowKey='3') HTTP/1.1",
+            "Accept: application/json; odata=minimalmetadata",
+            "DataServiceVersion: 3.0;",
+            "Content-Type: application/json",
+            "",
+            r#"{"PartitionKey":"Channel_17","Rating":9,"RowKey":"3","Text":".NET..."}"#,
+            "--changeset_8a28b620-b4bb-458c-a177-0959fb14c977",
+            "Content-Type: application/http",
+            "Content-Transfer-Encoding: binary",
+            "",
+            "DELETE https://myaccount.table.core.windows.net/Blogs(PartitionKey='Channel_17',RowKey='4') HTTP/1.1",
+            "Accept: application/json; odata=minimalmetadata",
+            "DataServiceVersion: 3.0;",
+            "If-Match: *",
+            "",
+            "--changeset_8a28b620-b4bb-458c-a177-0959fb14c977--",
+            "--batch_a1e9d677-b28b-435e-a89e-87e6a768a431--",
+            "",
         ];
-        let actual = generate_batch_payload("https://myaccount.table.core.windows.net/", "Blogs", "Channel_17", items.as_slice());
-        assert_eq!(expected, actual);
+        let payload = batch.payload("https://myaccount.table.core.windows.net/", OdataMetadata::MinimalMetadata);
+        assert_eq!(payload, expected.join("\r\n"));
+    }
+
+    #[test]
+    fn table_batch_payload() {
+        let mut batch = TableBatch::new("Blogs", "Channel_17");
+        batch.batch_id = Uuid::parse_str("a1e9d677-b28b-435e-a89e-87e6a768a431").unwrap();
+        batch.changeset_id = Uuid::parse_str("8a28b620-b4bb-458c-a177-0959fb14c977").unwrap();
+
+        let entity = entity("Channel_17", "3", 9, ".NET...");
+        batch.insert(&entity).unwrap().delete("4").unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(
+            batch.content_type(),
+            "multipart/mixed; boundary=batch_a1e9d677-b28b-435e-a89e-87e6a768a431"
+        );
+
+        let expected = [
+            "--batch_a1e9d677-b28b-435e-a89e-87e6a768a431",
+            "Content-Type: multipart/mixed; boundary=changeset_8a28b620-b4bb-458c-a177-0959fb14c977",
+            "",
+            "--changeset_8a28b620-b4bb-458c-a177-0959fb14c977",
+            "Content-Type: application/http",
+            "Content-Transfer-Encoding: binary",
+            "",
+            "POST https://myaccount.table.core.windows.net/Blogs HTTP/1.1",
+            "Accept: application/json; odata=nometadata",
+            "DataServiceVersion: 3.0;",
+            "Prefer: return-no-content",
+            "Content-Type: application/json",
+            "",
+            r#"{"PartitionKey":"Channel_17","Rating":9,"RowKey":"3","Text":".NET..."}"#,
+            "--changeset_8a28b620-b4bb-458c-a177-0959fb14c977",
+            "Content-Type: application/http",
+            "Content-Transfer-Encoding: binary",
+            "",
+            "DELETE https://myaccount.table.core.windows.net/Blogs(PartitionKey='Channel_17',RowKey='4') HTTP/1.1",
+            "Accept: application/json; odata=nometadata",
+            "DataServiceVersion: 3.0;",
+            "If-Match: *",
+            "",
+            "--changeset_8a28b620-b4bb-458c-a177-0959fb14c977--",
+            "--batch_a1e9d677-b28b-435e-a89e-87e6a768a431--",
+            "",
+        ];
+        let payload = batch.payload("https://myaccount.table.core.windows.net/", OdataMetadata::NoMetadata);
+        assert_eq!(payload, expected.join("\r\n"));
+    }
+
+    #[test]
+    fn table_batch_limits() {
+        let mut batch = TableBatch::new("Blogs", "Channel_17");
+        let other_partition = entity("Channel_18", "3", 9, ".NET...");
+        match batch.insert(&other_partition) {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r.map(|b| b.len())),
+        }
+        assert!(batch.merge("3", &other_partition).is_err());
+        assert!(batch.is_empty());
+
+        for i in 0..MAX_BATCH_OPERATIONS {
+            batch.delete(&i.to_string()).unwrap();
+        }
+        match batch.delete("100") {
+            Err(AzureError::InputParametersError(_)) => {}
+            r => panic!("unexpected result {:?}", r.map(|b| b.len())),
+        }
+        assert_eq!(batch.len(), MAX_BATCH_OPERATIONS);
+    }
+
+    #[test]
+    fn failed_batch_response() {
+        let failed = "--batchresponse_1\r\nContent-Type: multipart/mixed; boundary=changesetresponse_2\r\n\r\n\
+                      --changesetresponse_2\r\nContent-Type: application/http\r\n\r\n\
+                      HTTP/1.1 409 Conflict\r\nContent-Type: application/json\r\n\r\n\
+                      {\"odata.error\":{\"code\":\"EntityAlreadyExists\"}}\r\n--changesetresponse_2--\r\n--batchresponse_1--\r\n";
+        assert_eq!(batch_response_status(failed), Some(StatusCode::CONFLICT));
+
+        let succeeded = "--changesetresponse_2\r\nHTTP/1.1 204 No Content\r\n\r\n--changesetresponse_2\r\nHTTP/1.1 204 No Content\r\n";
+        assert_eq!(batch_response_status(succeeded), None);
+    }
+
+    fn entity(pk: &str, rk: &str, rating: i32, text: &str) -> Entity {
+        Entity {
+            PartitionKey: pk.to_owned(),
+            RowKey: rk.to_owned(),
+            Rating: rating,
+            Text: text.to_owned(),
+        }
     }
 
+    #[allow(deprecated)]
     fn bupdate(pk: &str, rk: &str, rating: i32, text: &str) -> BatchItem<Entity> {
-        BatchItem(
-            rk.to_owned(),
-            Some(Entity {
-                PartitionKey: pk.to_owned(),
-                RowKey: rk.to_owned(),
-                Rating: rating,
-                Text: text.to_owned(),
-            }),
-        )
+        BatchItem(rk.to_owned(), Some(entity(pk, rk, rating, text)))
     }
 
+    #[allow(deprecated)]
     fn bdelete(rk: &str) -> BatchItem<Entity> {
         BatchItem(rk.to_owned(), None)
     }
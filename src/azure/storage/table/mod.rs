@@ -1,13 +1,14 @@
 mod batch;
 
+#[allow(deprecated)]
 pub use self::batch::BatchItem;
+pub use self::batch::{TableBatch, MAX_BATCH_OPERATIONS};
 
-use self::batch::generate_batch_payload;
+use self::batch::{batch_of_items, batch_response_status};
 use azure::core::errors::{check_status_extract_body, extract_status_and_body, AzureError, UnexpectedHTTPResult};
 use azure::storage::client::Client;
-use azure::storage::rest_client::ServiceType;
+use azure::storage::rest_client::{CorrelatedResponseFuture as ResponseFuture, ServiceType};
 use hyper::{
-    client::ResponseFuture,
     header::{self, HeaderValue},
     Method, StatusCode,
 };
@@ -19,13 +20,53 @@ use futures::future::*;
 
 const TABLE_TABLES: &str = "TABLES";
 
+/// How much OData metadata the service sends along with the entities.
+/// `NoMetadata` gives the smallest responses but drops the type of the
+/// properties JSON cannot tell apart, such as binary, dates or 64 bit
+/// integers, which then come back as plain strings. `MinimalMetadata`
+/// keeps these types and `FullMetadata` adds the type of every property
+/// and the links of the entities. Tables use `MinimalMetadata` unless
+/// told otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OdataMetadata {
+    NoMetadata,
+    MinimalMetadata,
+    FullMetadata,
+}
+
+impl OdataMetadata {
+    /// Value of the `Accept` header asking for this level.
+    pub fn accept(self) -> &'static str {
+        match self {
+            OdataMetadata::NoMetadata => get_json_mime_nometadata(),
+            OdataMetadata::MinimalMetadata => "application/json; odata=minimalmetadata",
+            OdataMetadata::FullMetadata => "application/json; odata=fullmetadata",
+        }
+    }
+}
+
 pub struct TableService {
     client: Client,
+    metadata_level: OdataMetadata,
 }
 
 impl TableService {
     pub fn new(client: Client) -> Self {
-        TableService { client }
+        TableService {
+            client,
+            metadata_level: OdataMetadata::MinimalMetadata,
+        }
+    }
+
+    /// Sets the metadata level of the entities read by the following
+    /// operations.
+    pub fn with_metadata_level(mut self, metadata_level: OdataMetadata) -> Self {
+        self.metadata_level = metadata_level;
+        self
+    }
+
+    pub fn metadata_level(&self) -> OdataMetadata {
+        self.metadata_level
     }
 
     pub fn list_tables(&self) -> impl Future<Item = Vec<String>, Error = AzureError> {
@@ -144,9 +185,10 @@ impl TableService {
 
     pub fn delete_entity(&self, table_name: &str, partition_key: &str, row_key: &str) -> impl Future<Item = (), Error = AzureError> {
         let path = &entity_path(table_name, partition_key, row_key);
+        let metadata_level = self.metadata_level;
 
         let req = self.request(path, Method::DELETE, None, |ref mut request| {
-            request.header(header::ACCEPT, HeaderValue::from_static(get_json_mime_nometadata()));
+            request.header(header::ACCEPT, HeaderValue::from_static(metadata_level.accept()));
             request.header(header::IF_MATCH, header::HeaderValue::from_static("*"));
         });
         done(req)
@@ -154,41 +196,61 @@ impl TableService {
             .and_then(move |future_response| check_status_extract_body(future_response, StatusCode::NO_CONTENT).and_then(move |_| ok(())))
     }
 
+    /// Inserts or replaces the entities of the items with a payload and
+    /// deletes the others, in a single transaction.
+    #[deprecated(note = "use TableBatch and TableService::execute_batch")]
+    #[allow(deprecated)]
     pub fn batch<T: Serialize>(
         &self,
         table_name: &str,
         partition_key: &str,
         batch_items: &[BatchItem<T>],
     ) -> impl Future<Item = (), Error = AzureError> {
-        let payload = &generate_batch_payload(
-            self.client.get_uri_prefix(&ServiceType::Table).as_str(),
-            table_name,
-            partition_key,
-            batch_items,
-        );
-
-        let req = self.request("$batch", Method::POST, Some(payload), |ref mut request| {
-            request.header(header::CONTENT_TYPE, header::HeaderValue::from_static(get_batch_mime()));
-        });
-        done(req).from_err().and_then(move |future_response| {
-            check_status_extract_body(future_response, StatusCode::ACCEPTED).and_then(move |_| {
-                // TODO deal with body response, handle batch failure.
-                // let ref body = get_response_body(&mut response)?;
-                // info!("{}", body);
-                ok(())
+        match batch_of_items(table_name, partition_key, batch_items) {
+            Ok(batch) => Either::A(self.execute_batch(&batch)),
+            Err(error) => Either::B(err(error)),
+        }
+    }
+
+    /// Sends the operations of `batch` as a single transaction. If one of
+    /// them fails, none is applied and its status is returned in an
+    /// `UnexpectedHTTPResult`.
+    pub fn execute_batch(&self, batch: &TableBatch) -> impl Future<Item = (), Error = AzureError> {
+        let req = if batch.is_empty() {
+            Err(AzureError::InputParametersError("the batch has no operations".to_owned()))
+        } else {
+            let payload = batch.payload(self.client.get_uri_prefix(&ServiceType::Table).as_str(), self.metadata_level);
+            let content_type = batch.content_type();
+            self.request("$batch", Method::POST, Some(&payload), |ref mut request| {
+                request.header(header::CONTENT_TYPE, &content_type as &str);
+            })
+        };
+
+        done(req).and_then(move |future_response| {
+            check_status_extract_body(future_response, StatusCode::ACCEPTED).and_then(|body| match batch_response_status(&body) {
+                Some(status) => Err(AzureError::UnexpectedHTTPResult(UnexpectedHTTPResult::new(
+                    StatusCode::ACCEPTED,
+                    status,
+                    &body,
+                ))),
+                None => Ok(()),
             })
         })
     }
 
     fn request_with_default_header(&self, segment: &str, method: Method, request_str: Option<&str>) -> Result<ResponseFuture, AzureError> {
         self.request(segment, method, request_str, |ref mut request| {
-            request.header(header::ACCEPT, HeaderValue::from_static(get_json_mime_nometadata()));
-            if request_str.is_some() {
-                request.header(header::CONTENT_TYPE, HeaderValue::from_static(get_default_json_mime()));
-            }
+            self.add_default_headers(request, request_str.is_some())
         })
     }
 
+    fn add_default_headers(&self, request: &mut ::http::request::Builder, has_body: bool) {
+        request.header(header::ACCEPT, HeaderValue::from_static(self.metadata_level.accept()));
+        if has_body {
+            request.header(header::CONTENT_TYPE, HeaderValue::from_static(get_default_json_mime()));
+        }
+    }
+
     fn request<F>(&self, segment: &str, method: Method, request_str: Option<&str>, headers_func: F) -> Result<ResponseFuture, AzureError>
     where
         F: FnOnce(&mut ::http::request::Builder),
@@ -234,6 +296,31 @@ pub fn get_json_mime_nometadata() -> &'static str {
 }
 
 #[inline]
+#[deprecated(note = "the boundaries of a TableBatch are random")]
 pub fn get_batch_mime() -> &'static str {
     "multipart/mixed; boundary=batch_a1e9d677-b28b-435e-a89e-87e6a768a431"
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn accept_header(table_service: &TableService) -> String {
+        let mut request = ::http::Request::builder();
+        table_service.add_default_headers(&mut request, false);
+        request.body(()).unwrap().headers()[header::ACCEPT].to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn metadata_level_accept_header() {
+        let table_service = TableService::new(Client::new("mindflavor", "ZHVtbXk=").unwrap());
+        assert_eq!(table_service.metadata_level(), OdataMetadata::MinimalMetadata);
+        assert_eq!(accept_header(&table_service), "application/json; odata=minimalmetadata");
+
+        let table_service = table_service.with_metadata_level(OdataMetadata::NoMetadata);
+        assert_eq!(accept_header(&table_service), "application/json; odata=nometadata");
+
+        let table_service = table_service.with_metadata_level(OdataMetadata::FullMetadata);
+        assert_eq!(accept_header(&table_service), "application/json; odata=fullmetadata");
+    }
+}
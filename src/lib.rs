@@ -1,12 +1,14 @@
-#![recursion_limit = "128"]
+#![recursion_limit = "256"]
 
 extern crate base64;
 extern crate chrono;
+extern crate flate2;
 extern crate futures;
 extern crate http;
 extern crate hyper;
 extern crate hyper_tls;
 extern crate md5;
+extern crate native_tls;
 extern crate ring;
 extern crate time;
 #[macro_use]
@@ -24,6 +26,13 @@ extern crate bytes;
 extern crate serde_json;
 extern crate serde_xml_rs;
 extern crate smallvec;
+#[cfg(test)]
+extern crate tokio;
+#[cfg(test)]
+extern crate tokio_core;
+extern crate tokio_fs;
+extern crate tokio_io;
+extern crate tokio_timer;
 
 #[macro_use]
 mod azure;
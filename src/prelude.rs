@@ -7,13 +7,18 @@ pub use azure::core::{
     ContentLengthSupport, ContentMD5Option, ContentMD5Support, ContentTypeOption, ContentTypeSupport, DelimiterOption, DelimiterSupport,
     IfMatchConditionOption, IfMatchConditionSupport, IfSinceConditionOption, IfSinceConditionSupport, IncludeCopyOption,
     IncludeCopySupport, IncludeDeletedOption, IncludeDeletedSupport, IncludeListOptions, IncludeMetadataOption, IncludeMetadataSupport,
-    IncludeSnapshotsOption, IncludeSnapshotsSupport, IncludeUncommittedBlobsOption, IncludeUncommittedBlobsSupport, LeaseBreakPeriodOption,
-    LeaseBreakPeriodSupport, LeaseDurationRequired, LeaseDurationSupport, LeaseIdOption, LeaseIdRequired, LeaseIdSupport, MaxResultsOption,
-    MaxResultsSupport, MetadataOption, MetadataSupport, NextMarkerOption, NextMarkerSupport, PageBlobLengthRequired, PageBlobLengthSupport,
-    PrefixOption, PrefixSupport, ProposedLeaseIdOption, ProposedLeaseIdRequired, ProposedLeaseIdSupport, RangeOption, RangeSupport,
+    IncludeSnapshotsOption, IncludeSnapshotsSupport, IncludeUncommittedBlobsOption, IncludeUncommittedBlobsSupport,
+    InputSerializationOption, InputSerializationSupport, LeaseBreakPeriodOption, LeaseBreakPeriodSupport, LeaseDurationRequired,
+    LeaseDurationSupport, LeaseIdOption, LeaseIdRequired, LeaseIdSupport, MaxResultsOption, MaxResultsSupport, MetadataOption,
+    MetadataSupport, NextMarkerOption, NextMarkerSupport, OutputSerializationOption, OutputSerializationSupport, PageBlobLengthRequired,
+    PageBlobLengthSupport, PrefixOption, PrefixSupport, ProposedLeaseIdOption, ProposedLeaseIdRequired, ProposedLeaseIdSupport,
+    QueryExpressionRequired, QueryExpressionSupport, RangeContentMD5Option, RangeContentMD5Support, RangeOption, RangeSupport,
     SequenceNumberConditionOption, SequenceNumberConditionSupport, SequenceNumberOption, SequenceNumberSupport, SnapshotOption,
-    SnapshotSupport, StoredAccessPolicy, StoredAccessPolicyList, TimeoutOption, TimeoutSupport,
+    SnapshotSupport, StoredAccessPolicy, StoredAccessPolicyList, TagFilterRequired, TagFilterSupport, TagsRequired, TagsSupport,
+    TimeoutOption, TimeoutSupport,
 };
 pub use azure::storage::container::PublicAccessSupport;
 
 pub use azure::storage::client::{Blob as BlobTrait, Client, Container as ContainerTrait};
+
+pub use azure::service_client::ServiceClient;